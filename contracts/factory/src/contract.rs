@@ -1,15 +1,25 @@
 use cosmwasm_std::{
-    entry_point, Addr, Binary, ContractResult, Deps, Reply, ReplyOn, StdError, StdResult,
+    entry_point, Addr, Binary, ContractResult, Deps, Order, Reply, ReplyOn, StdError, StdResult,
     SubMsgExecutionResponse, Uint128,
 };
 use cosmwasm_std::{to_binary, CosmosMsg, DepsMut, Env, MessageInfo, Response, SubMsg, WasmMsg};
+use cw2::{set_contract_version, CONTRACT};
+use cw_storage_plus::Bound;
 
 use crate::errors::FactoryError;
-use crate::state::CONFIG;
-use localterra_protocol::factory::{Config, ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::state::{arbitrators, contracts, ARBITRATOR_CURSORS, CONFIG, IBC_DENOMS};
+use localterra_protocol::constants::DEFAULT_FEE_BPS;
+use localterra_protocol::currencies::FiatCurrency;
+use localterra_protocol::factory::{
+    Arbitrator, ArbitratorsResponse, Config, ContractKind, ContractRecord, ContractsResponse,
+    ExecuteMsg, IbcDenom, IbcDenomsResponse, InstantiateMsg, MigrateMsg, QueryMsg, SudoMsg,
+};
+use localterra_protocol::factory_util::instantiated_contract_addr;
 use localterra_protocol::fee_collector::InstantiateMsg as FeeCollectorInstantiate;
 use localterra_protocol::governance::InstantiateMsg as GovernanceInstantiateMsg;
+use localterra_protocol::migration::is_valid_migration;
 use localterra_protocol::offer::InstantiateMsg as OfferInstantiate;
+use localterra_protocol::pagination::{page_limit, page_next};
 use localterra_protocol::trading_incentives::InstantiateMsg as TradingIncentivesInstantiateMsg;
 use terraswap::token::InstantiateMsg as TokenInstantiateMsg;
 
@@ -19,14 +29,18 @@ pub const OFFER_REPLY_ID: u64 = 2;
 pub const TRADING_INCENTIVES_REPLY_ID: u64 = 3;
 pub const CW20_TOKEN_REPLY_ID: u64 = 4;
 
+const CONTRACT_NAME: &str = concat!("crates.io:", env!("CARGO_PKG_NAME"));
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, FactoryError> {
     let cfg = Config {
+        admin: info.sender,
         trade_code_id: msg.trade_code_id,
         token_addr: Addr::unchecked(""),
         local_ust_pool_addr: deps.api.addr_validate(&msg.local_ust_pool_addr).unwrap(),
@@ -34,13 +48,19 @@ pub fn instantiate(
         offers_addr: Addr::unchecked(""),
         fee_collector_addr: Addr::unchecked(""),
         trading_incentives_addr: Addr::unchecked(""),
+        maker_fee_bps: DEFAULT_FEE_BPS,
+        taker_fee_bps: 0,
+        fee_burn_bps: 0,
+        treasury_addr: deps.api.addr_validate(&msg.treasury_addr)?,
+        pending_treasury_addr: None,
     };
     CONFIG.save(deps.storage, &cfg).unwrap();
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     let token_msg = instantiate_token_msg(msg.cw20_code_id);
     let fee_collector_msg =
         instantiate_fee_collector_msg(msg.fee_collector_code_id, msg.fee_collector_threshold);
-    let offer_msg = instantiate_offer_msg(msg.offer_code_id);
+    let offer_msg = instantiate_offer_msg(msg.offer_code_id, msg.max_offers_per_owner);
     let gov_msg = instantiate_gov_msg(msg.gov_contract_code_id);
     let trading_incentives_msg = instantiate_trading_incentives_msg(msg.trading_incentives_code_id);
 
@@ -55,22 +75,74 @@ pub fn instantiate(
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
-    _deps: DepsMut,
-    _env: Env,
-    _info: MessageInfo,
-    _msg: ExecuteMsg,
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
 ) -> Result<Response, FactoryError> {
-    Ok(Response::default())
+    match msg {
+        ExecuteMsg::UpdateConfig {
+            maker_fee_bps,
+            taker_fee_bps,
+            fee_burn_bps,
+            fee_collector_addr,
+        } => {
+            assert_admin(deps.as_ref(), &info.sender)?;
+            update_config(
+                deps,
+                maker_fee_bps,
+                taker_fee_bps,
+                fee_burn_bps,
+                fee_collector_addr,
+            )
+        }
+        ExecuteMsg::ProposeTreasury { treasury_addr } => {
+            propose_treasury(deps, info, treasury_addr)
+        }
+        ExecuteMsg::CancelTreasuryProposal {} => cancel_treasury_proposal(deps, info),
+        ExecuteMsg::AcceptTreasury {} => accept_treasury(deps, info),
+        ExecuteMsg::UpdateTradeCodeId { code_id } => update_trade_code_id(deps, info, code_id),
+        ExecuteMsg::AddArbitrator { addr, fiat } => add_arbitrator(deps, info, addr, fiat),
+        ExecuteMsg::RemoveArbitrator { addr, fiat } => remove_arbitrator(deps, info, addr, fiat),
+        ExecuteMsg::RegisterTrade { addr, arbitrator } => {
+            register_trade(deps, env, info, addr, arbitrator)
+        }
+        ExecuteMsg::AddIbcDenom {
+            channel,
+            base_denom,
+        } => add_ibc_denom(deps, info, channel, base_denom),
+        ExecuteMsg::RemoveIbcDenom { denom } => remove_ibc_denom(deps, info, denom),
+    }
+}
+
+/// Chain governance interventions, the same changes the admin can make
+/// through `execute`.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, FactoryError> {
+    match msg {
+        SudoMsg::UpdateConfig {
+            maker_fee_bps,
+            taker_fee_bps,
+            fee_burn_bps,
+            fee_collector_addr,
+        } => update_config(
+            deps,
+            maker_fee_bps,
+            taker_fee_bps,
+            fee_burn_bps,
+            fee_collector_addr,
+        ),
+    }
 }
 
 #[entry_point]
-pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, FactoryError> {
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, FactoryError> {
     match msg.id {
-        GOV_REPLY_ID => instantiate_gov_reply(deps, msg.result),
-        FEE_COLLECTOR_REPLY_ID => instantiate_fee_collector_reply(deps, msg.result),
-        OFFER_REPLY_ID => instantiate_offer_reply(deps, msg.result),
-        TRADING_INCENTIVES_REPLY_ID => instantiate_trading_incentives_reply(deps, msg.result),
-        CW20_TOKEN_REPLY_ID => instantiate_token_reply(deps, msg.result),
+        GOV_REPLY_ID => instantiate_gov_reply(deps, env, msg.result),
+        FEE_COLLECTOR_REPLY_ID => instantiate_fee_collector_reply(deps, env, msg.result),
+        OFFER_REPLY_ID => instantiate_offer_reply(deps, env, msg.result),
+        TRADING_INCENTIVES_REPLY_ID => instantiate_trading_incentives_reply(deps, env, msg.result),
+        CW20_TOKEN_REPLY_ID => instantiate_token_reply(deps, env, msg.result),
         _ => Err(FactoryError::Std(StdError::generic_err(
             "Unknown reply id.",
         ))),
@@ -81,7 +153,417 @@ pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, FactoryEr
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&CONFIG.load(deps.storage).unwrap()),
+        QueryMsg::Arbitrators {
+            fiat_currency,
+            last_value,
+            limit,
+        } => to_binary(&query_arbitrators(deps, fiat_currency, last_value, limit)?),
+        QueryMsg::NextArbitrator { fiat_currency } => {
+            to_binary(&query_next_arbitrator(deps, fiat_currency)?)
+        }
+        QueryMsg::Contracts {
+            kind,
+            last_value,
+            limit,
+        } => to_binary(&query_contracts(deps, kind, last_value, limit)?),
+        QueryMsg::IbcDenom { denom } => to_binary(&IBC_DENOMS.may_load(deps.storage, &denom)?),
+        QueryMsg::IbcDenoms { last_value, limit } => {
+            to_binary(&query_ibc_denoms(deps, last_value, limit)?)
+        }
+    }
+}
+
+/// The contracts registry is kept as it is, configs stored before the admin
+/// or the treasury existed get `msg.admin` and `msg.treasury_addr`.
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, FactoryError> {
+    if let Some(stored) = CONTRACT.may_load(deps.storage)? {
+        if !is_valid_migration(&stored, CONTRACT_NAME, CONTRACT_VERSION) {
+            return Err(FactoryError::InvalidMigration {
+                contract: stored.contract,
+                version: stored.version,
+            });
+        }
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let mut cfg = CONFIG.load(deps.storage)?;
+    if cfg.admin.as_str().is_empty() {
+        let admin = msg.admin.ok_or(FactoryError::MissingAdmin {})?;
+        cfg.admin = deps.api.addr_validate(&admin)?;
+    }
+    if cfg.treasury_addr.as_str().is_empty() {
+        let treasury_addr = msg.treasury_addr.ok_or(FactoryError::MissingTreasury {})?;
+        cfg.treasury_addr = deps.api.addr_validate(&treasury_addr)?;
+    }
+    CONFIG.save(deps.storage, &cfg)?;
+    Ok(Response::default())
+}
+
+fn assert_admin(deps: Deps, caller: &Addr) -> Result<(), FactoryError> {
+    if CONFIG.load(deps.storage)?.admin != *caller {
+        return Err(FactoryError::Unauthorized {});
+    }
+    Ok(())
+}
+
+fn update_config(
+    deps: DepsMut,
+    maker_fee_bps: Option<u64>,
+    taker_fee_bps: Option<u64>,
+    fee_burn_bps: Option<u64>,
+    fee_collector_addr: Option<String>,
+) -> Result<Response, FactoryError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    if let Some(maker_fee_bps) = maker_fee_bps {
+        cfg.maker_fee_bps = maker_fee_bps;
+    }
+    if let Some(taker_fee_bps) = taker_fee_bps {
+        cfg.taker_fee_bps = taker_fee_bps;
+    }
+    if let Some(fee_burn_bps) = fee_burn_bps {
+        cfg.fee_burn_bps = fee_burn_bps;
+    }
+    if let Some(fee_collector_addr) = fee_collector_addr {
+        cfg.fee_collector_addr = deps.api.addr_validate(fee_collector_addr.as_str())?;
+    }
+
+    //Buy offers pay both fees out of the escrow
+    let total_fee_bps = cfg.maker_fee_bps.checked_add(cfg.taker_fee_bps);
+    if total_fee_bps.is_none_or(|total_fee_bps| total_fee_bps > 10_000) {
+        return Err(FactoryError::InvalidFeeBps {
+            maker_fee_bps: cfg.maker_fee_bps,
+            taker_fee_bps: cfg.taker_fee_bps,
+        });
     }
+    if cfg.fee_burn_bps > 10_000 {
+        return Err(FactoryError::InvalidFeeBurnBps {
+            fee_burn_bps: cfg.fee_burn_bps,
+        });
+    }
+    CONFIG.save(deps.storage, &cfg)?;
+
+    let res = Response::new()
+        .add_attribute("action", "update_config")
+        .add_attribute("maker_fee_bps", cfg.maker_fee_bps.to_string())
+        .add_attribute("taker_fee_bps", cfg.taker_fee_bps.to_string())
+        .add_attribute("fee_burn_bps", cfg.fee_burn_bps.to_string())
+        .add_attribute("fee_collector_addr", cfg.fee_collector_addr);
+    Ok(res)
+}
+
+fn propose_treasury(
+    deps: DepsMut,
+    info: MessageInfo,
+    treasury_addr: String,
+) -> Result<Response, FactoryError> {
+    assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut cfg = CONFIG.load(deps.storage)?;
+    let pending_treasury_addr = deps.api.addr_validate(treasury_addr.as_str())?;
+    cfg.pending_treasury_addr = Some(pending_treasury_addr.clone());
+    CONFIG.save(deps.storage, &cfg)?;
+
+    let res = Response::new()
+        .add_attribute("action", "propose_treasury")
+        .add_attribute("treasury_addr", cfg.treasury_addr)
+        .add_attribute("pending_treasury_addr", pending_treasury_addr);
+    Ok(res)
+}
+
+fn cancel_treasury_proposal(deps: DepsMut, info: MessageInfo) -> Result<Response, FactoryError> {
+    assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut cfg = CONFIG.load(deps.storage)?;
+    let pending_treasury_addr = cfg
+        .pending_treasury_addr
+        .take()
+        .ok_or(FactoryError::NoPendingTreasury {})?;
+    CONFIG.save(deps.storage, &cfg)?;
+
+    let res = Response::new()
+        .add_attribute("action", "cancel_treasury_proposal")
+        .add_attribute("pending_treasury_addr", pending_treasury_addr);
+    Ok(res)
+}
+
+/// The admin alone can't redirect fees, the new treasury has to accept.
+fn accept_treasury(deps: DepsMut, info: MessageInfo) -> Result<Response, FactoryError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    let pending_treasury_addr = cfg
+        .pending_treasury_addr
+        .take()
+        .ok_or(FactoryError::NoPendingTreasury {})?;
+    if info.sender != pending_treasury_addr {
+        return Err(FactoryError::Unauthorized {});
+    }
+
+    let old_treasury_addr = std::mem::replace(&mut cfg.treasury_addr, pending_treasury_addr);
+    CONFIG.save(deps.storage, &cfg)?;
+
+    let res = Response::new()
+        .add_attribute("action", "accept_treasury")
+        .add_attribute("old_treasury_addr", old_treasury_addr)
+        .add_attribute("treasury_addr", cfg.treasury_addr);
+    Ok(res)
+}
+
+fn update_trade_code_id(
+    deps: DepsMut,
+    info: MessageInfo,
+    code_id: u64,
+) -> Result<Response, FactoryError> {
+    assert_admin(deps.as_ref(), &info.sender)?;
+    if code_id == 0 {
+        return Err(FactoryError::InvalidCodeId {});
+    }
+
+    let mut cfg = CONFIG.load(deps.storage)?;
+    let old_code_id = cfg.trade_code_id;
+    cfg.trade_code_id = code_id;
+    CONFIG.save(deps.storage, &cfg)?;
+
+    let res = Response::new()
+        .add_attribute("action", "update_trade_code_id")
+        .add_attribute("old_code_id", old_code_id.to_string())
+        .add_attribute("code_id", code_id.to_string());
+    Ok(res)
+}
+
+fn add_arbitrator(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+    fiat: FiatCurrency,
+) -> Result<Response, FactoryError> {
+    assert_admin(deps.as_ref(), &info.sender)?;
+
+    let addr = deps.api.addr_validate(addr.as_str())?;
+    arbitrators().save(
+        deps.storage,
+        (fiat.to_string().as_str(), &addr),
+        &Arbitrator {
+            addr: addr.clone(),
+            fiat: fiat.clone(),
+        },
+    )?;
+
+    let res = Response::new()
+        .add_attribute("action", "add_arbitrator")
+        .add_attribute("addr", addr)
+        .add_attribute("fiat", fiat.to_string());
+    Ok(res)
+}
+
+/// Trades only fall back to the offer contract's arbitrator when a currency
+/// never had one, removing the last one is refused instead of silently
+/// changing who settles disputes.
+fn remove_arbitrator(
+    deps: DepsMut,
+    info: MessageInfo,
+    addr: String,
+    fiat: FiatCurrency,
+) -> Result<Response, FactoryError> {
+    assert_admin(deps.as_ref(), &info.sender)?;
+
+    let addr = deps.api.addr_validate(addr.as_str())?;
+    let fiat_key = fiat.to_string();
+    if arbitrators()
+        .may_load(deps.storage, (fiat_key.as_str(), &addr))?
+        .is_none()
+    {
+        return Err(FactoryError::ArbitratorNotFound { addr, fiat });
+    }
+    let remaining = query_arbitrators(deps.as_ref(), fiat.clone(), None, 2)?;
+    if remaining.arbitrators.len() < 2 {
+        return Err(FactoryError::LastArbitrator { fiat });
+    }
+    arbitrators().remove(deps.storage, (fiat_key.as_str(), &addr));
+
+    let res = Response::new()
+        .add_attribute("action", "remove_arbitrator")
+        .add_attribute("addr", addr)
+        .add_attribute("fiat", fiat_key);
+    Ok(res)
+}
+
+fn query_arbitrators(
+    deps: Deps,
+    fiat_currency: FiatCurrency,
+    last_value: Option<String>,
+    limit: u32,
+) -> StdResult<ArbitratorsResponse> {
+    let limit = page_limit(limit);
+    let min = last_value.map(|addr| Bound::exclusive(addr.as_str()));
+    let arbitrators = arbitrators()
+        .prefix(fiat_currency.to_string().as_str())
+        .range(deps.storage, min, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, arbitrator)| arbitrator))
+        .collect::<StdResult<Vec<Arbitrator>>>()?;
+
+    Ok(ArbitratorsResponse {
+        next: page_next(&arbitrators, limit, |arbitrator| arbitrator.addr.clone()),
+        arbitrators,
+        limit: limit as u32,
+    })
+}
+
+/// Walks the currency's arbitrators in address order from the cursor, so
+/// every registered one gets trades in turn.
+fn query_next_arbitrator(deps: Deps, fiat_currency: FiatCurrency) -> StdResult<Option<Arbitrator>> {
+    let fiat_key = fiat_currency.to_string();
+    let cursor = ARBITRATOR_CURSORS.may_load(deps.storage, fiat_key.as_str())?;
+    let first_from = |min: Option<Bound>| {
+        arbitrators()
+            .prefix(fiat_key.as_str())
+            .range(deps.storage, min, None, Order::Ascending)
+            .next()
+            .transpose()
+            .map(|item| item.map(|(_, arbitrator)| arbitrator))
+    };
+    match first_from(cursor.map(|addr| Bound::exclusive(addr.as_str())))? {
+        Some(arbitrator) => Ok(Some(arbitrator)),
+        None => first_from(None),
+    }
+}
+
+/// Only vouchers received straight from the counterparty chain's transfer
+/// channel are registered, the voucher denom is derived here so a look-alike
+/// denom can't be registered under a trusted channel's name.
+fn add_ibc_denom(
+    deps: DepsMut,
+    info: MessageInfo,
+    channel: String,
+    base_denom: String,
+) -> Result<Response, FactoryError> {
+    assert_admin(deps.as_ref(), &info.sender)?;
+    let channel_id = channel.strip_prefix("channel-").unwrap_or_default();
+    if channel_id.is_empty()
+        || !channel_id.chars().all(|c| c.is_ascii_digit())
+        || base_denom.is_empty()
+    {
+        return Err(FactoryError::InvalidIbcDenom {
+            channel,
+            base_denom,
+        });
+    }
+
+    let ibc_denom = IbcDenom::new(&channel, &base_denom);
+    IBC_DENOMS.save(deps.storage, &ibc_denom.denom, &ibc_denom)?;
+
+    let res = Response::new()
+        .add_attribute("action", "add_ibc_denom")
+        .add_attribute("denom", ibc_denom.denom)
+        .add_attribute("channel", channel)
+        .add_attribute("base_denom", base_denom);
+    Ok(res)
+}
+
+fn remove_ibc_denom(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+) -> Result<Response, FactoryError> {
+    assert_admin(deps.as_ref(), &info.sender)?;
+    if IBC_DENOMS.may_load(deps.storage, &denom)?.is_none() {
+        return Err(FactoryError::IbcDenomNotFound { denom });
+    }
+    IBC_DENOMS.remove(deps.storage, &denom);
+
+    let res = Response::new()
+        .add_attribute("action", "remove_ibc_denom")
+        .add_attribute("denom", denom);
+    Ok(res)
+}
+
+fn query_ibc_denoms(
+    deps: Deps,
+    last_value: Option<String>,
+    limit: u32,
+) -> StdResult<IbcDenomsResponse> {
+    let limit = page_limit(limit);
+    let min = last_value.map(|denom| Bound::exclusive(denom.as_str()));
+    let ibc_denoms = IBC_DENOMS
+        .range(deps.storage, min, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, ibc_denom)| ibc_denom))
+        .collect::<StdResult<Vec<IbcDenom>>>()?;
+
+    Ok(IbcDenomsResponse {
+        next: page_next(&ibc_denoms, limit, |ibc_denom| ibc_denom.denom.clone()),
+        ibc_denoms,
+        limit: limit as u32,
+    })
+}
+
+fn register_trade(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    addr: String,
+    arbitrator: Option<Arbitrator>,
+) -> Result<Response, FactoryError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if info.sender != cfg.offers_addr {
+        return Err(FactoryError::Unauthorized {});
+    }
+
+    //Arbitrators of the offer contract config don't advance the registry's turns
+    if let Some(arbitrator) = arbitrator {
+        let fiat_key = arbitrator.fiat.to_string();
+        if arbitrators().has(deps.storage, (fiat_key.as_str(), &arbitrator.addr)) {
+            ARBITRATOR_CURSORS.save(deps.storage, fiat_key.as_str(), &arbitrator.addr)?;
+        }
+    }
+    let addr = deps.api.addr_validate(addr.as_str())?;
+    record_contract(deps, &env, ContractKind::Trade, &addr)?;
+
+    let res = Response::new()
+        .add_attribute("action", "register_trade")
+        .add_attribute("addr", addr);
+    Ok(res)
+}
+
+fn record_contract(deps: DepsMut, env: &Env, kind: ContractKind, addr: &Addr) -> StdResult<()> {
+    contracts().save(
+        deps.storage,
+        addr.as_str(),
+        &ContractRecord {
+            kind,
+            addr: addr.clone(),
+            created_at: env.block.height,
+        },
+    )
+}
+
+fn query_contracts(
+    deps: Deps,
+    kind: Option<ContractKind>,
+    last_value: Option<String>,
+    limit: u32,
+) -> StdResult<ContractsResponse> {
+    let limit = page_limit(limit);
+    let min = last_value.map(|addr| Bound::exclusive(addr.as_str()));
+    let range: Box<dyn Iterator<Item = StdResult<(Vec<u8>, ContractRecord)>> + '_> = match kind {
+        Some(kind) => contracts().idx.kind.prefix(kind.to_string()).range(
+            deps.storage,
+            min,
+            None,
+            Order::Ascending,
+        ),
+        None => contracts().range(deps.storage, min, None, Order::Ascending),
+    };
+    let contracts = range
+        .take(limit)
+        .map(|item| item.map(|(_, record)| record))
+        .collect::<StdResult<Vec<ContractRecord>>>()?;
+
+    Ok(ContractsResponse {
+        next: page_next(&contracts, limit, |record| record.addr.clone()),
+        contracts,
+        limit: limit as u32,
+    })
 }
 
 fn instantiate_gov_msg(code_id: u64) -> SubMsg {
@@ -94,7 +576,8 @@ fn instantiate_gov_msg(code_id: u64) -> SubMsg {
 }
 
 fn instantiate_gov_reply(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     result: ContractResult<SubMsgExecutionResponse>,
 ) -> Result<Response, FactoryError> {
     if result.is_err() {
@@ -104,6 +587,7 @@ fn instantiate_gov_reply(
     }
 
     let gov_contract_addr = get_contract_address_from_reply(deps.as_ref(), result);
+    record_contract(deps.branch(), &env, ContractKind::Gov, &gov_contract_addr)?;
     let mut cfg = CONFIG.load(deps.storage).unwrap();
     cfg.gov_addr = gov_contract_addr.clone();
     CONFIG.save(deps.storage, &cfg).unwrap();
@@ -124,7 +608,8 @@ fn instantiate_fee_collector_msg(code_id: u64, ust_conversion_threshold: Uint128
 }
 
 fn instantiate_fee_collector_reply(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     result: ContractResult<SubMsgExecutionResponse>,
 ) -> Result<Response, FactoryError> {
     if result.is_err() {
@@ -134,6 +619,12 @@ fn instantiate_fee_collector_reply(
     }
 
     let fee_collector_addr = get_contract_address_from_reply(deps.as_ref(), result);
+    record_contract(
+        deps.branch(),
+        &env,
+        ContractKind::FeeCollector,
+        &fee_collector_addr,
+    )?;
     let mut cfg = CONFIG.load(deps.storage).unwrap();
     cfg.fee_collector_addr = fee_collector_addr;
     CONFIG.save(deps.storage, &cfg).unwrap();
@@ -141,17 +632,22 @@ fn instantiate_fee_collector_reply(
     Ok(res)
 }
 
-fn instantiate_offer_msg(code_id: u64) -> SubMsg {
+fn instantiate_offer_msg(code_id: u64, max_offers_per_owner: u32) -> SubMsg {
     create_instantiate_msg(
         code_id,
-        to_binary(&OfferInstantiate {}).unwrap(),
+        to_binary(&OfferInstantiate {
+            max_offers_per_owner,
+            max_trade_amount: Uint128::zero(),
+        })
+        .unwrap(),
         OFFER_REPLY_ID,
         "offer".to_string(),
     )
 }
 
 fn instantiate_offer_reply(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     result: ContractResult<SubMsgExecutionResponse>,
 ) -> Result<Response, FactoryError> {
     if result.is_err() {
@@ -161,6 +657,7 @@ fn instantiate_offer_reply(
     }
 
     let offer_addr = get_contract_address_from_reply(deps.as_ref(), result);
+    record_contract(deps.branch(), &env, ContractKind::Offer, &offer_addr)?;
     let mut cfg = CONFIG.load(deps.storage).unwrap();
     cfg.offers_addr = offer_addr;
     CONFIG.save(deps.storage, &cfg).unwrap();
@@ -185,7 +682,8 @@ fn instantiate_token_msg(cw20_code_id: u64) -> SubMsg {
 }
 
 fn instantiate_token_reply(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     result: ContractResult<SubMsgExecutionResponse>,
 ) -> Result<Response, FactoryError> {
     if result.is_err() {
@@ -196,6 +694,7 @@ fn instantiate_token_reply(
 
     let mut cfg = CONFIG.load(deps.storage).unwrap();
     cfg.token_addr = get_contract_address_from_reply(deps.as_ref(), result);
+    record_contract(deps.branch(), &env, ContractKind::Token, &cfg.token_addr)?;
     CONFIG.save(deps.storage, &cfg).unwrap();
     let res = Response::new().add_attribute("instantiate_contract", "token");
     Ok(res)
@@ -211,7 +710,8 @@ fn instantiate_trading_incentives_msg(trading_incentives_code_id: u64) -> SubMsg
 }
 
 fn instantiate_trading_incentives_reply(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     result: ContractResult<SubMsgExecutionResponse>,
 ) -> Result<Response, FactoryError> {
     if result.is_err() {
@@ -222,6 +722,12 @@ fn instantiate_trading_incentives_reply(
 
     let mut cfg = CONFIG.load(deps.storage).unwrap();
     cfg.trading_incentives_addr = get_contract_address_from_reply(deps.as_ref(), result);
+    record_contract(
+        deps.branch(),
+        &env,
+        ContractKind::TradingIncentives,
+        &cfg.trading_incentives_addr,
+    )?;
     CONFIG.save(deps.storage, &cfg).unwrap();
     let res = Response::new().add_attribute("instantiate_contract", "incentives");
     Ok(res)
@@ -231,17 +737,8 @@ fn get_contract_address_from_reply(
     deps: Deps,
     result: ContractResult<SubMsgExecutionResponse>,
 ) -> Addr {
-    result
-        .unwrap()
-        .events
-        .into_iter()
-        .find(|e| e.ty == "instantiate_contract")
-        .and_then(|ev| {
-            ev.attributes
-                .into_iter()
-                .find(|attr| attr.key == "contract_address")
-        })
-        .map(|attr| deps.api.addr_validate(attr.value.as_str()).unwrap())
+    instantiated_contract_addr(&result.unwrap())
+        .map(|addr| deps.api.addr_validate(addr.as_str()).unwrap())
         .unwrap()
 }
 
@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Addr, StdError};
+use localterra_protocol::currencies::FiatCurrency;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -8,4 +9,40 @@ pub enum FactoryError {
 
     #[error("Unauthorized")]
     Unauthorized {},
+
+    #[error("{addr} is not an arbitrator for {fiat}.")]
+    ArbitratorNotFound { addr: Addr, fiat: FiatCurrency },
+
+    #[error("{fiat} needs at least one arbitrator.")]
+    LastArbitrator { fiat: FiatCurrency },
+
+    #[error("Code id can't be 0.")]
+    InvalidCodeId {},
+
+    #[error("Maker and taker fees can't be more than 10000 basis points together.")]
+    InvalidFeeBps {
+        maker_fee_bps: u64,
+        taker_fee_bps: u64,
+    },
+
+    #[error("Fee burn share can't be more than 10000 basis points.")]
+    InvalidFeeBurnBps { fee_burn_bps: u64 },
+
+    #[error("IBC vouchers need a channel-<number> channel and a base denom.")]
+    InvalidIbcDenom { channel: String, base_denom: String },
+
+    #[error("{denom} is not a trusted IBC denom.")]
+    IbcDenomNotFound { denom: String },
+
+    #[error("No treasury handover is pending.")]
+    NoPendingTreasury {},
+
+    #[error("Configs without a treasury need a treasury_addr to migrate.")]
+    MissingTreasury {},
+
+    #[error("Configs without an admin need an admin to migrate.")]
+    MissingAdmin {},
+
+    #[error("Cannot migrate from {contract} {version}.")]
+    InvalidMigration { contract: String, version: String },
 }
@@ -1,3 +1,7 @@
 pub mod contract;
 pub mod errors;
 mod state;
+#[cfg(test)]
+mod tests;
+#[cfg(test)]
+mod multitest;
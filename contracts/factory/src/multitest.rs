@@ -0,0 +1,571 @@
+#![cfg(test)]
+//! The factory deploying the whole protocol on a `cw_multi_test::App`, trades
+//! go through the offer and trade contracts like they do on chain. Native
+//! trades escrow `uluna`, untaxed, the app can't answer Terra's tax queries.
+use cosmwasm_std::{coins, to_binary, Addr, Empty, Uint128};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
+use cw20_base::msg::InstantiateMsg as TokenInstantiateMsg;
+use cw_multi_test::{App, AppBuilder, AppResponse, Contract, ContractWrapper, Executor};
+
+use localterra_protocol::currencies::FiatCurrency;
+use localterra_protocol::errors::{OfferError, TradeError};
+use localterra_protocol::factory::{Config, ExecuteMsg, InstantiateMsg, QueryMsg, SudoMsg};
+use localterra_protocol::offer::{
+    ConfigUpdate, ExecuteMsg as OfferExecuteMsg, Offer, OfferAsset, OfferMsg, OfferState,
+    OfferType, SudoMsg as OfferSudoMsg,
+};
+use localterra_protocol::trade::{
+    Cw20HookMsg, ExecuteMsg as TradeExecuteMsg, InstantiateMsg as TradeInstantiateMsg,
+    QueryMsg as TradeQueryMsg, TradeData, TradeState,
+};
+
+const ADMIN: &str = "admin";
+const MAKER: &str = "maker";
+const TAKER: &str = "taker";
+const DENOM: &str = "uluna";
+
+fn factory_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        crate::contract::execute,
+        crate::contract::instantiate,
+        crate::contract::query,
+    )
+    .with_reply(crate::contract::reply)
+    .with_sudo(crate::contract::sudo)
+    .with_migrate(crate::contract::migrate);
+    Box::new(contract)
+}
+
+fn offer_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        offer::contract::execute,
+        offer::contract::instantiate,
+        offer::contract::query,
+    )
+    .with_reply(offer::contract::reply)
+    .with_sudo(offer::contract::sudo)
+    .with_migrate(offer::contract::migrate);
+    Box::new(contract)
+}
+
+fn trade_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        trade::contract::execute,
+        trade::contract::instantiate,
+        trade::contract::query,
+    )
+    .with_migrate(trade::contract::migrate);
+    Box::new(contract)
+}
+
+fn token_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    ))
+}
+
+fn gov_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        governance::contract::execute,
+        governance::contract::instantiate,
+        governance::contract::query,
+    ))
+}
+
+fn fee_collector_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        fee_collector::contract::execute,
+        fee_collector::contract::instantiate,
+        fee_collector::contract::query,
+    )
+    .with_reply(fee_collector::contract::reply);
+    Box::new(contract)
+}
+
+fn trading_incentives_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        trading_incentives::contract::execute,
+        trading_incentives::contract::instantiate,
+        trading_incentives::contract::query,
+    ))
+}
+
+struct Suite {
+    app: App,
+    factory: Addr,
+    offers: Addr,
+}
+
+impl Suite {
+    /// Deploys the protocol from the factory, the offer contract takes
+    /// `uluna` and BRL trades are settled by the registered `arbitrator`.
+    fn new() -> Suite {
+        let mut app = AppBuilder::new().build();
+        let msg = InstantiateMsg {
+            cw20_code_id: app.store_code(token_contract()),
+            gov_contract_code_id: app.store_code(gov_contract()),
+            fee_collector_code_id: app.store_code(fee_collector_contract()),
+            trading_incentives_code_id: app.store_code(trading_incentives_contract()),
+            offer_code_id: app.store_code(offer_contract()),
+            trade_code_id: app.store_code(trade_contract()),
+            fee_collector_threshold: Uint128::new(1),
+            local_ust_pool_addr: "local-ust".to_string(),
+            max_offers_per_owner: 0,
+            treasury_addr: "treasury".to_string(),
+        };
+        let factory_code_id = app.store_code(factory_contract());
+        let factory = app
+            .instantiate_contract(
+                factory_code_id,
+                Addr::unchecked(ADMIN),
+                &msg,
+                &[],
+                "factory",
+                None,
+            )
+            .unwrap();
+        let offers = app
+            .wrap()
+            .query_wasm_smart::<Config>(&factory, &QueryMsg::Config {})
+            .unwrap()
+            .offers_addr;
+
+        let update = ConfigUpdate {
+            denoms: Some(vec![DENOM.to_string()]),
+            ..Default::default()
+        };
+        app.sudo(offers.clone(), &OfferSudoMsg::UpdateConfig(update))
+            .unwrap();
+        let msg = ExecuteMsg::AddArbitrator {
+            addr: "arbitrator".to_string(),
+            fiat: FiatCurrency::unchecked("BRL"),
+        };
+        app.execute_contract(Addr::unchecked(ADMIN), factory.clone(), &msg, &[])
+            .unwrap();
+        for addr in [MAKER, TAKER].iter() {
+            app.init_bank_balance(&Addr::unchecked(*addr), coins(1_000_000_000, DENOM))
+                .unwrap();
+        }
+
+        Suite {
+            app,
+            factory,
+            offers,
+        }
+    }
+
+    fn factory_config(&self) -> Config {
+        self.app
+            .wrap()
+            .query_wasm_smart(&self.factory, &QueryMsg::Config {})
+            .unwrap()
+    }
+
+    fn execute_factory(&mut self, sender: &str, msg: &ExecuteMsg) -> anyhow::Result<AppResponse> {
+        self.app
+            .execute_contract(Addr::unchecked(sender), self.factory.clone(), msg, &[])
+    }
+
+    /// BRL offer of `MAKER` escrowing `uluna`, for 1 to 1_000_000 micro units.
+    fn create_offer(&mut self, offer_type: OfferType) -> u64 {
+        let asset = OfferAsset::Native {
+            denom: DENOM.to_string(),
+        };
+        self.create_offer_of(offer_type, asset)
+    }
+
+    fn create_offer_of(&mut self, offer_type: OfferType, asset: OfferAsset) -> u64 {
+        let offer = OfferMsg {
+            offer_type,
+            fiat_currency: FiatCurrency::unchecked("BRL"),
+            min_amount: Uint128::new(1),
+            max_amount: Uint128::new(1_000_000),
+            maker_contact: Some("maker-contact".to_string()),
+            expires_at: None,
+            price_margin_bps: None,
+            payment_methods: vec![],
+            description: None,
+            asset,
+            min_trades_completed: None,
+            total_available: Uint128::zero(),
+            activate_at: None,
+            pause_at: None,
+            amounts_in_fiat: false,
+            available_from_hour: None,
+            available_to_hour: None,
+        };
+        let res = self
+            .app
+            .execute_contract(
+                Addr::unchecked(MAKER),
+                self.offers.clone(),
+                &OfferExecuteMsg::Create { offer },
+                &[],
+            )
+            .unwrap();
+        cosmwasm_std::from_binary::<Offer>(&res.data.unwrap())
+            .unwrap()
+            .id
+    }
+
+    /// Trade `TAKER` opens on `offer_id`.
+    fn new_trade(&mut self, offer_id: u64, amount: u128) -> Addr {
+        let res = self.try_new_trade(offer_id, amount).unwrap();
+        let trade_addr = res
+            .events
+            .iter()
+            .flat_map(|event| event.attributes.iter())
+            .find(|attr| attr.key == "trade_addr")
+            .map(|attr| attr.value.clone())
+            .unwrap();
+        Addr::unchecked(trade_addr)
+    }
+
+    fn execute_trade(
+        &mut self,
+        sender: &str,
+        trade: &Addr,
+        msg: &TradeExecuteMsg,
+    ) -> anyhow::Result<AppResponse> {
+        self.app
+            .execute_contract(Addr::unchecked(sender), trade.clone(), msg, &[])
+    }
+
+    fn try_new_trade(&mut self, offer_id: u64, amount: u128) -> anyhow::Result<AppResponse> {
+        let msg = OfferExecuteMsg::NewTrade {
+            offer_id,
+            amount: Uint128::new(amount),
+            taker: TAKER.to_string(),
+            taker_contact: "taker-contact".to_string(),
+            multisig_release: false,
+        };
+        self.app
+            .execute_contract(Addr::unchecked(TAKER), self.offers.clone(), &msg, &[])
+    }
+
+    fn fund(&mut self, sender: &str, trade: &Addr, amount: u128) -> anyhow::Result<AppResponse> {
+        self.app.execute_contract(
+            Addr::unchecked(sender),
+            trade.clone(),
+            &TradeExecuteMsg::FundEscrow {},
+            &coins(amount, DENOM),
+        )
+    }
+
+    fn trade(&self, trade: &Addr) -> TradeData {
+        self.app
+            .wrap()
+            .query_wasm_smart(trade, &TradeQueryMsg::State {})
+            .unwrap()
+    }
+
+    fn balance(&self, addr: &str) -> u128 {
+        self.app
+            .wrap()
+            .query_balance(addr, DENOM)
+            .unwrap()
+            .amount
+            .u128()
+    }
+
+    /// cw20-base token with `balance` minted to `TAKER`.
+    fn create_token(&mut self, symbol: &str, balance: u128) -> Addr {
+        let msg = TokenInstantiateMsg {
+            name: format!("{} token", symbol),
+            symbol: symbol.to_string(),
+            decimals: 6,
+            initial_balances: vec![Cw20Coin {
+                address: TAKER.to_string(),
+                amount: Uint128::new(balance),
+            }],
+            mint: None,
+            marketing: None,
+        };
+        let code_id = self.app.store_code(token_contract());
+        self.app
+            .instantiate_contract(code_id, Addr::unchecked(ADMIN), &msg, &[], symbol, None)
+            .unwrap()
+    }
+
+    fn token_balance(&self, token: &Addr, addr: &str) -> u128 {
+        let res: BalanceResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                token,
+                &Cw20QueryMsg::Balance {
+                    address: addr.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance.u128()
+    }
+
+    fn code_id(&self, contract: &Addr) -> u64 {
+        self.app.contract_data(contract).unwrap().code_id as u64
+    }
+}
+
+#[test]
+fn new_trade_code_id_test() {
+    let mut suite = Suite::new();
+    let offer_id = suite.create_offer(OfferType::Buy);
+    let old_code_id = suite.factory_config().trade_code_id;
+    let old_trade = suite.new_trade(offer_id, 1_000);
+    suite.fund(TAKER, &old_trade, 1_000).unwrap();
+    assert_eq!(suite.code_id(&old_trade), old_code_id);
+
+    //Only the admin points new trades at another code
+    let new_code_id = suite.app.store_code(trade_contract());
+    let update = ExecuteMsg::UpdateTradeCodeId {
+        code_id: new_code_id,
+    };
+    assert!(suite.execute_factory(MAKER, &update).is_err());
+    suite.execute_factory(ADMIN, &update).unwrap();
+
+    //Trades opened afterwards run on the new code, the open one keeps its code and settles
+    let new_trade = suite.new_trade(offer_id, 1_000);
+    assert_eq!(suite.code_id(&new_trade), new_code_id);
+    assert_eq!(suite.code_id(&old_trade), old_code_id);
+    suite
+        .execute_trade(TAKER, &old_trade, &TradeExecuteMsg::Release {})
+        .unwrap();
+    assert_eq!(suite.trade(&old_trade).state, TradeState::Released);
+}
+
+#[test]
+fn test_factory_fee_applies_at_release() {
+    let mut suite = Suite::new();
+    let buy_offer = suite.create_offer(OfferType::Buy);
+    let sell_offer = suite.create_offer(OfferType::Sell);
+    let funding_fee_bps = suite.factory_config().maker_fee_bps;
+
+    //The taker sells on the buy offer, the maker escrows the maker fee on the sell offer
+    let buy_trade = suite.new_trade(buy_offer, 10_000);
+    suite.fund(TAKER, &buy_trade, 10_000).unwrap();
+    let sell_trade = suite.new_trade(sell_offer, 10_000);
+    let sell_escrow = 10_000 + 10_000 * funding_fee_bps as u128 / 10_000;
+    suite.fund(MAKER, &sell_trade, sell_escrow).unwrap();
+
+    let update = ExecuteMsg::UpdateConfig {
+        maker_fee_bps: Some(funding_fee_bps + 100),
+        taker_fee_bps: Some(50),
+        fee_burn_bps: Some(0),
+        fee_collector_addr: None,
+    };
+    assert!(suite.execute_factory(MAKER, &update).is_err());
+    suite.execute_factory(ADMIN, &update).unwrap();
+
+    //The buy trade pays both new rates at release
+    let maker_balance = suite.balance(MAKER);
+    suite
+        .execute_trade(TAKER, &buy_trade, &TradeExecuteMsg::Release {})
+        .unwrap();
+    let buy_fees = 10_000 * (funding_fee_bps as u128 + 100) / 10_000 + 50;
+    assert_eq!(suite.balance("treasury"), buy_fees);
+    assert_eq!(suite.balance(MAKER), maker_balance + 10_000 - buy_fees);
+
+    //The sell trade keeps the maker fee it escrowed and pays the new taker fee
+    let taker_balance = suite.balance(TAKER);
+    suite
+        .execute_trade(MAKER, &sell_trade, &TradeExecuteMsg::Release {})
+        .unwrap();
+    assert_eq!(
+        suite.balance("treasury"),
+        buy_fees + sell_escrow - 10_000 + 50
+    );
+    assert_eq!(suite.balance(TAKER), taker_balance + 10_000 - 50);
+    assert_eq!(suite.balance(sell_trade.as_str()), 0);
+}
+
+#[test]
+fn trade_instantiate_checks_offer_test() {
+    let mut suite = Suite::new();
+    let offer_id = suite.create_offer(OfferType::Buy);
+    let trade_code_id = suite.factory_config().trade_code_id;
+    let offers = suite.offers.clone();
+    let trade_msg = |offer_id: u64, amount: u128, denom: &str| TradeInstantiateMsg {
+        offer_id,
+        amount: Uint128::new(amount),
+        asset: OfferAsset::Native {
+            denom: denom.to_string(),
+        },
+        taker: TAKER.to_string(),
+        offers_addr: offers.to_string(),
+        taker_contact: "taker-contact".to_string(),
+        timestamp: 0,
+        fiat_price: None,
+        fiat_amount: None,
+        multisig_release: false,
+    };
+    let mut instantiate = |sender: &Addr, msg: &TradeInstantiateMsg| {
+        suite
+            .app
+            .instantiate_contract(trade_code_id, sender.clone(), msg, &[], "trade", None)
+            .unwrap_err()
+            .downcast::<TradeError>()
+            .unwrap()
+    };
+
+    //Instantiating the trade code directly is refused
+    let err = instantiate(&Addr::unchecked(TAKER), &trade_msg(offer_id, 1_000, DENOM));
+    assert!(matches!(err, TradeError::NotOfferContract { .. }));
+
+    //Even sent as the offer contract, the terms are checked against the stored offer
+    let err = instantiate(&offers, &trade_msg(offer_id + 1, 1_000, DENOM));
+    assert!(matches!(err, TradeError::OfferNotFound { .. }));
+    let err = instantiate(&offers, &trade_msg(offer_id, 1_000_001, DENOM));
+    assert!(matches!(err, TradeError::AmountError { amount, .. } if amount.u128() == 1_000_001));
+    let err = instantiate(&offers, &trade_msg(offer_id, 1_000, "uusd"));
+    assert!(matches!(err, TradeError::InvalidDenom { .. }));
+
+    let pause = OfferExecuteMsg::Pause { id: offer_id };
+    suite
+        .app
+        .execute_contract(Addr::unchecked(MAKER), offers.clone(), &pause, &[])
+        .unwrap();
+    let err = suite
+        .app
+        .instantiate_contract(
+            trade_code_id,
+            offers.clone(),
+            &trade_msg(offer_id, 1_000, DENOM),
+            &[],
+            "trade",
+            None,
+        )
+        .unwrap_err()
+        .downcast::<TradeError>()
+        .unwrap();
+    assert!(matches!(
+        err,
+        TradeError::OfferNotActive {
+            state: OfferState::Paused,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn sudo_test() {
+    let mut suite = Suite::new();
+    let offer_id = suite.create_offer(OfferType::Buy);
+    let offers = suite.offers.clone();
+    let offer_error = |err: anyhow::Error| err.downcast::<OfferError>().unwrap();
+
+    //Governance pauses and resumes the marketplace, senders other than the admin can't
+    suite
+        .app
+        .sudo(offers.clone(), &OfferSudoMsg::SetPause { paused: true })
+        .unwrap();
+    let err = suite.try_new_trade(offer_id, 1_000).unwrap_err();
+    assert!(matches!(offer_error(err), OfferError::ContractPaused {}));
+    let resume = OfferExecuteMsg::SetPause { paused: false };
+    assert!(suite
+        .app
+        .execute_contract(Addr::unchecked(MAKER), offers.clone(), &resume, &[])
+        .is_err());
+    suite
+        .app
+        .sudo(offers.clone(), &OfferSudoMsg::SetPause { paused: false })
+        .unwrap();
+    suite.new_trade(offer_id, 1_000);
+
+    //Governance lifts the admin's block, `RemoveBlacklisted` isn't an execute message
+    let block = OfferExecuteMsg::SetBlacklist {
+        addr: TAKER.to_string(),
+        blocked: true,
+    };
+    suite
+        .app
+        .execute_contract(suite.factory.clone(), offers.clone(), &block, &[])
+        .unwrap();
+    let err = suite.try_new_trade(offer_id, 1_000).unwrap_err();
+    assert!(matches!(offer_error(err), OfferError::Blocked {}));
+    let unblock = OfferSudoMsg::RemoveBlacklisted {
+        addr: TAKER.to_string(),
+    };
+    assert!(suite
+        .app
+        .execute_contract(suite.factory.clone(), offers.clone(), &unblock, &[])
+        .is_err());
+    suite.app.sudo(offers.clone(), &unblock).unwrap();
+    suite.new_trade(offer_id, 1_000);
+
+    //Governance rotates the fee collector without the factory admin
+    let update = SudoMsg::UpdateConfig {
+        maker_fee_bps: None,
+        taker_fee_bps: None,
+        fee_burn_bps: None,
+        fee_collector_addr: Some("new-fee-collector".to_string()),
+    };
+    let factory = suite.factory.clone();
+    assert!(suite
+        .app
+        .execute_contract(Addr::unchecked(MAKER), factory.clone(), &update, &[])
+        .is_err());
+    suite.app.sudo(factory, &update).unwrap();
+    assert_eq!(
+        suite.factory_config().fee_collector_addr,
+        Addr::unchecked("new-fee-collector")
+    );
+}
+
+#[test]
+fn cw20_trade_test() {
+    let mut suite = Suite::new();
+    let token = suite.create_token("TKN", 10_000);
+    let other_token = suite.create_token("OTH", 10_000);
+    let offer_id = suite.create_offer_of(
+        OfferType::Buy,
+        OfferAsset::Cw20 {
+            contract_addr: token.clone(),
+        },
+    );
+    let trade = suite.new_trade(offer_id, 1_000);
+    let send = |amount: u128| Cw20ExecuteMsg::Send {
+        contract: trade.to_string(),
+        amount: Uint128::new(amount),
+        msg: to_binary(&Cw20HookMsg::FundEscrow {}).unwrap(),
+    };
+
+    //Only the trade's token funds it, and only with the exact escrow amount
+    let err = suite
+        .app
+        .execute_contract(
+            Addr::unchecked(TAKER),
+            other_token.clone(),
+            &send(1_000),
+            &[],
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast::<TradeError>().unwrap(),
+        TradeError::InvalidDenom { .. }
+    ));
+    assert!(suite
+        .app
+        .execute_contract(Addr::unchecked(TAKER), token.clone(), &send(999), &[])
+        .is_err());
+    suite
+        .app
+        .execute_contract(Addr::unchecked(TAKER), token.clone(), &send(1_000), &[])
+        .unwrap();
+    assert_eq!(suite.trade(&trade).state, TradeState::EscrowFunded);
+    assert_eq!(suite.token_balance(&token, trade.as_str()), 1_000);
+    assert_eq!(suite.token_balance(&other_token, TAKER), 10_000);
+
+    //Released in the token, the fees go to the treasury or get burned
+    suite
+        .execute_trade(TAKER, &trade, &TradeExecuteMsg::Release {})
+        .unwrap();
+    let cfg = suite.factory_config();
+    let fees = 1_000 * (cfg.maker_fee_bps + cfg.taker_fee_bps) as u128 / 10_000;
+    let burned = fees * cfg.fee_burn_bps as u128 / 10_000;
+    assert_eq!(suite.token_balance(&token, MAKER), 1_000 - fees);
+    assert_eq!(suite.token_balance(&token, "treasury"), fees - burned);
+    assert_eq!(suite.token_balance(&token, trade.as_str()), 0);
+    assert_eq!(suite.token_balance(&token, TAKER), 9_000);
+}
@@ -1,4 +1,38 @@
-use cw_storage_plus::Item;
-use localterra_protocol::factory::Config;
+use cosmwasm_std::Addr;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use localterra_protocol::factory::{Arbitrator, Config, ContractRecord, IbcDenom};
 
 pub const CONFIG: Item<Config> = Item::new("config");
+/// Trusted IBC vouchers keyed by their `ibc/…` denom.
+pub const IBC_DENOMS: Map<&str, IbcDenom> = Map::new("ibc_denoms");
+
+/// Keyed by `(fiat_currency.to_string(), addr)`.
+pub fn arbitrators<'a>() -> Map<'a, (&'a str, &'a Addr), Arbitrator> {
+    Map::new("arbitrators")
+}
+/// Arbitrator last assigned to a trade, keyed by `fiat_currency.to_string()`.
+pub const ARBITRATOR_CURSORS: Map<&str, Addr> = Map::new("arbitrator_cursors");
+
+pub struct ContractIndexes<'a> {
+    // pk goes to second tuple element
+    pub kind: MultiIndex<'a, (String, Vec<u8>), ContractRecord>,
+}
+
+impl<'a> IndexList<ContractRecord> for ContractIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<ContractRecord>> + '_> {
+        let v: Vec<&dyn Index<ContractRecord>> = vec![&self.kind];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Deployed contracts keyed by address.
+pub fn contracts<'a>() -> IndexedMap<'a, &'a str, ContractRecord, ContractIndexes<'a>> {
+    let indexes = ContractIndexes {
+        kind: MultiIndex::new(
+            |d: &ContractRecord, k: Vec<u8>| (d.kind.to_string(), k),
+            "contracts",
+            "contracts__kind",
+        ),
+    };
+    IndexedMap::new("contracts", indexes)
+}
@@ -0,0 +1,671 @@
+#![cfg(test)]
+use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+use cosmwasm_std::{
+    from_binary, to_vec, Addr, ContractResult, Deps, DepsMut, Event, Reply, Storage,
+    SubMsgExecutionResponse, Uint128,
+};
+use cw2::{get_contract_version, set_contract_version};
+use localterra_protocol::constants::{DEFAULT_FEE_BPS, DEFAULT_PAGE_LIMIT};
+use localterra_protocol::currencies::FiatCurrency;
+use localterra_protocol::factory::{
+    ibc_voucher_denom, Arbitrator, ArbitratorsResponse, Config, ContractKind, ContractRecord,
+    ContractsResponse, ExecuteMsg, IbcDenom, IbcDenomsResponse, InstantiateMsg, MigrateMsg,
+    QueryMsg, SudoMsg,
+};
+
+use crate::contract::{
+    execute, instantiate, migrate, query, reply, sudo, FEE_COLLECTOR_REPLY_ID, OFFER_REPLY_ID,
+};
+use crate::errors::FactoryError;
+
+fn do_init(deps: DepsMut) {
+    let msg = InstantiateMsg {
+        cw20_code_id: 0,
+        gov_contract_code_id: 1,
+        fee_collector_code_id: 2,
+        trading_incentives_code_id: 3,
+        offer_code_id: 4,
+        trade_code_id: 5,
+        fee_collector_threshold: Uint128::new(1),
+        local_ust_pool_addr: "local-ust".to_string(),
+        max_offers_per_owner: 0,
+        treasury_addr: "treasury".to_string(),
+    };
+    instantiate(deps, mock_env(), mock_info("admin", &[]), msg).unwrap();
+}
+
+fn add(deps: DepsMut, sender: &str, addr: &str, fiat: FiatCurrency) -> Result<(), FactoryError> {
+    let msg = ExecuteMsg::AddArbitrator {
+        addr: addr.to_string(),
+        fiat,
+    };
+    execute(deps, mock_env(), mock_info(sender, &[]), msg).map(|_| ())
+}
+
+fn remove(deps: DepsMut, addr: &str, fiat: FiatCurrency) -> Result<(), FactoryError> {
+    let msg = ExecuteMsg::RemoveArbitrator {
+        addr: addr.to_string(),
+        fiat,
+    };
+    execute(deps, mock_env(), mock_info("admin", &[]), msg).map(|_| ())
+}
+
+fn query_arbitrators(
+    deps: Deps,
+    fiat_currency: FiatCurrency,
+    last_value: Option<&str>,
+) -> Vec<Addr> {
+    let msg = QueryMsg::Arbitrators {
+        fiat_currency,
+        last_value: last_value.map(String::from),
+        limit: 2,
+    };
+    let page: ArbitratorsResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(page.limit, 2);
+    assert_eq!(
+        page.next.as_ref(),
+        page.arbitrators.get(1).map(|arbitrator| &arbitrator.addr)
+    );
+    page.arbitrators
+        .into_iter()
+        .map(|arbitrator| arbitrator.addr)
+        .collect()
+}
+
+#[test]
+fn arbitrator_registry_test() {
+    let mut deps = mock_dependencies(&[]);
+    do_init(deps.as_mut());
+
+    //Only the admin manages the registry
+    let err = add(
+        deps.as_mut(),
+        "stranger",
+        "arb-1",
+        FiatCurrency::unchecked("BRL"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, FactoryError::Unauthorized {}));
+
+    for addr in ["arb-3", "arb-1", "arb-2"].iter() {
+        add(deps.as_mut(), "admin", addr, FiatCurrency::unchecked("BRL")).unwrap();
+    }
+    add(
+        deps.as_mut(),
+        "admin",
+        "arb-cop",
+        FiatCurrency::unchecked("COP"),
+    )
+    .unwrap();
+
+    //Pages are ordered by address and scoped to the currency
+    let first_page = query_arbitrators(deps.as_ref(), FiatCurrency::unchecked("BRL"), None);
+    assert_eq!(
+        first_page,
+        vec![Addr::unchecked("arb-1"), Addr::unchecked("arb-2")]
+    );
+    let second_page =
+        query_arbitrators(deps.as_ref(), FiatCurrency::unchecked("BRL"), Some("arb-2"));
+    assert_eq!(second_page, vec![Addr::unchecked("arb-3")]);
+    assert_eq!(
+        query_arbitrators(deps.as_ref(), FiatCurrency::unchecked("COP"), None),
+        vec![Addr::unchecked("arb-cop")]
+    );
+    assert!(query_arbitrators(deps.as_ref(), FiatCurrency::unchecked("USD"), None).is_empty());
+
+    //Unknown entries and the last arbitrator of a currency can't be removed
+    let err = remove(deps.as_mut(), "arb-cop", FiatCurrency::unchecked("BRL")).unwrap_err();
+    assert!(matches!(err, FactoryError::ArbitratorNotFound { .. }));
+    let err = remove(deps.as_mut(), "arb-cop", FiatCurrency::unchecked("COP")).unwrap_err();
+    assert!(matches!(err, FactoryError::LastArbitrator { .. }));
+
+    remove(deps.as_mut(), "arb-1", FiatCurrency::unchecked("BRL")).unwrap();
+    assert_eq!(
+        query_arbitrators(deps.as_ref(), FiatCurrency::unchecked("BRL"), None),
+        vec![Addr::unchecked("arb-2"), Addr::unchecked("arb-3")]
+    );
+}
+
+#[test]
+fn next_arbitrator_test() {
+    let mut deps = mock_dependencies(&[]);
+    do_init(deps.as_mut());
+    instantiated(deps.as_mut(), OFFER_REPLY_ID, "offers");
+    let brl = FiatCurrency::unchecked("BRL");
+    let next = |deps: Deps| -> Option<Addr> {
+        let msg = QueryMsg::NextArbitrator {
+            fiat_currency: FiatCurrency::unchecked("BRL"),
+        };
+        let arbitrator: Option<Arbitrator> =
+            from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        arbitrator.map(|arbitrator| arbitrator.addr)
+    };
+    let register = |deps: DepsMut, trade: &str, arbitrator: &str| {
+        let msg = ExecuteMsg::RegisterTrade {
+            addr: trade.to_string(),
+            arbitrator: Some(Arbitrator {
+                addr: Addr::unchecked(arbitrator),
+                fiat: FiatCurrency::unchecked("BRL"),
+            }),
+        };
+        execute(deps, mock_env(), mock_info("offers", &[]), msg).unwrap();
+    };
+    assert_eq!(next(deps.as_ref()), None);
+
+    //Every registered arbitrator gets trades in turn, past any page limit
+    let addrs: Vec<String> = (0..40).map(|i| format!("arb-{:02}", i)).collect();
+    for addr in addrs.iter() {
+        add(deps.as_mut(), "admin", addr, brl.clone()).unwrap();
+    }
+    for (i, addr) in addrs.iter().enumerate() {
+        assert_eq!(next(deps.as_ref()), Some(Addr::unchecked(addr)));
+        register(deps.as_mut(), &format!("trade-{}", i), addr);
+    }
+    assert_eq!(next(deps.as_ref()), Some(Addr::unchecked("arb-00")));
+
+    //Arbitrators outside the registry don't move the turn, removed ones are skipped
+    register(deps.as_mut(), "trade-fallback", "arbitrator");
+    assert_eq!(next(deps.as_ref()), Some(Addr::unchecked("arb-00")));
+    register(deps.as_mut(), "trade-40", "arb-00");
+    remove(deps.as_mut(), "arb-01", brl.clone()).unwrap();
+    assert_eq!(next(deps.as_ref()), Some(Addr::unchecked("arb-02")));
+    remove(deps.as_mut(), "arb-00", brl).unwrap();
+    assert_eq!(next(deps.as_ref()), Some(Addr::unchecked("arb-02")));
+}
+
+#[test]
+fn ibc_denoms_registry_test() {
+    let mut deps = mock_dependencies(&[]);
+    do_init(deps.as_mut());
+    let add = |channel: &str, base_denom: &str| ExecuteMsg::AddIbcDenom {
+        channel: channel.to_string(),
+        base_denom: base_denom.to_string(),
+    };
+    let ibc_denom = |deps: Deps, denom: &str| -> Option<IbcDenom> {
+        let msg = QueryMsg::IbcDenom {
+            denom: denom.to_string(),
+        };
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+    };
+
+    //The voucher denom is the hash of the channel's transfer trace
+    assert_eq!(
+        ibc_voucher_denom("channel-0", "uatom"),
+        "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("stranger", &[]),
+        add("channel-0", "uatom"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, FactoryError::Unauthorized {}));
+    for (channel, base_denom) in [
+        ("channel-", "uatom"),
+        ("transfer/channel-0", "uatom"),
+        ("channel-0", ""),
+    ]
+    .iter()
+    {
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            add(channel, base_denom),
+        )
+        .unwrap_err();
+        assert!(matches!(err, FactoryError::InvalidIbcDenom { .. }));
+    }
+    for (channel, base_denom) in [("channel-0", "uatom"), ("channel-3", "uosmo")].iter() {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            add(channel, base_denom),
+        )
+        .unwrap();
+    }
+
+    //Vouchers of a trusted denom through other channels aren't trusted
+    let atom = ibc_voucher_denom("channel-0", "uatom");
+    assert_eq!(
+        ibc_denom(deps.as_ref(), &atom),
+        Some(IbcDenom::new("channel-0", "uatom"))
+    );
+    assert_eq!(
+        ibc_denom(deps.as_ref(), &ibc_voucher_denom("channel-7", "uatom")),
+        None
+    );
+    let msg = QueryMsg::IbcDenoms {
+        last_value: None,
+        limit: 0,
+    };
+    let all: IbcDenomsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(all.ibc_denoms.len(), 2);
+    assert_eq!((all.next, all.limit), (None, DEFAULT_PAGE_LIMIT));
+
+    let remove = |denom: &str| ExecuteMsg::RemoveIbcDenom {
+        denom: denom.to_string(),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        remove(&atom),
+    )
+    .unwrap();
+    assert_eq!(ibc_denom(deps.as_ref(), &atom), None);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        remove(&atom),
+    )
+    .unwrap_err();
+    assert!(matches!(err, FactoryError::IbcDenomNotFound { .. }));
+}
+
+#[test]
+fn update_fee_config_test() {
+    let mut deps = mock_dependencies(&[]);
+    do_init(deps.as_mut());
+    let update = |maker_fee_bps: Option<u64>,
+                  taker_fee_bps: Option<u64>,
+                  fee_burn_bps: Option<u64>| ExecuteMsg::UpdateConfig {
+        maker_fee_bps,
+        taker_fee_bps,
+        fee_burn_bps,
+        fee_collector_addr: None,
+    };
+    let admin = mock_info("admin", &[]);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("stranger", &[]),
+        update(Some(50), None, None),
+    )
+    .unwrap_err();
+    assert!(matches!(err, FactoryError::Unauthorized {}));
+
+    //Fees are bounded, together too, and so is the burn share
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        admin.clone(),
+        update(Some(10_001), Some(0), None),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        FactoryError::InvalidFeeBps {
+            maker_fee_bps: 10_001,
+            taker_fee_bps: 0
+        }
+    ));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        admin.clone(),
+        update(Some(5_000), Some(5_001), None),
+    )
+    .unwrap_err();
+    assert!(matches!(err, FactoryError::InvalidFeeBps { .. }));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        admin.clone(),
+        update(None, Some(u64::MAX), None),
+    )
+    .unwrap_err();
+    assert!(matches!(err, FactoryError::InvalidFeeBps { .. }));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        admin.clone(),
+        update(None, None, Some(10_001)),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        FactoryError::InvalidFeeBurnBps {
+            fee_burn_bps: 10_001
+        }
+    ));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        admin,
+        update(Some(0), Some(60), Some(2_500)),
+    )
+    .unwrap();
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "taker_fee_bps" && attr.value == "60"));
+    let cfg: Config =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(cfg.maker_fee_bps, 0);
+    assert_eq!(cfg.taker_fee_bps, 60);
+    assert_eq!(cfg.fee_burn_bps, 2_500);
+
+    //Configs stored with a single fee charge it to makers, stored collector shares are ignored
+    let legacy = String::from_utf8(to_vec(&cfg).unwrap())
+        .unwrap()
+        .replace(
+            r#""maker_fee_bps":0,"taker_fee_bps":60,"#,
+            r#""fee_bps":80,"#,
+        )
+        .replace(
+            r#""fee_burn_bps":2500,"#,
+            r#""fee_burn_bps":2500,"fee_collector_bps":7500,"#,
+        );
+    assert!(!legacy.contains("taker_fee_bps"));
+    assert!(legacy.contains("fee_collector_bps"));
+    deps.storage.set(b"config", legacy.as_bytes());
+    let cfg: Config =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!((cfg.maker_fee_bps, cfg.taker_fee_bps), (80, 0));
+    assert_eq!(cfg.fee_burn_bps, 2_500);
+}
+
+#[test]
+fn treasury_handover_test() {
+    let mut deps = mock_dependencies(&[]);
+    do_init(deps.as_mut());
+    let run = |deps: DepsMut, sender: &str, msg: ExecuteMsg| {
+        execute(deps, mock_env(), mock_info(sender, &[]), msg)
+    };
+    let propose = |treasury_addr: &str| ExecuteMsg::ProposeTreasury {
+        treasury_addr: treasury_addr.to_string(),
+    };
+    let config = |deps: Deps| -> Config {
+        from_binary(&query(deps, mock_env(), QueryMsg::Config {}).unwrap()).unwrap()
+    };
+    assert_eq!(
+        config(deps.as_ref()).treasury_addr,
+        Addr::unchecked("treasury")
+    );
+
+    //Only the admin proposes, and the proposal alone redirects nothing
+    let err = run(deps.as_mut(), "stranger", propose("treasury-b")).unwrap_err();
+    assert!(matches!(err, FactoryError::Unauthorized {}));
+    let err = run(deps.as_mut(), "treasury-b", ExecuteMsg::AcceptTreasury {}).unwrap_err();
+    assert!(matches!(err, FactoryError::NoPendingTreasury {}));
+    run(deps.as_mut(), "admin", propose("treasury-b")).unwrap();
+    let cfg = config(deps.as_ref());
+    assert_eq!(cfg.treasury_addr, Addr::unchecked("treasury"));
+    assert_eq!(
+        cfg.pending_treasury_addr,
+        Some(Addr::unchecked("treasury-b"))
+    );
+
+    //Only the proposed treasury accepts, a canceled proposal can't be accepted
+    let err = run(deps.as_mut(), "admin", ExecuteMsg::AcceptTreasury {}).unwrap_err();
+    assert!(matches!(err, FactoryError::Unauthorized {}));
+    run(
+        deps.as_mut(),
+        "admin",
+        ExecuteMsg::CancelTreasuryProposal {},
+    )
+    .unwrap();
+    let err = run(deps.as_mut(), "treasury-b", ExecuteMsg::AcceptTreasury {}).unwrap_err();
+    assert!(matches!(err, FactoryError::NoPendingTreasury {}));
+
+    run(deps.as_mut(), "admin", propose("treasury-b")).unwrap();
+    run(deps.as_mut(), "treasury-b", ExecuteMsg::AcceptTreasury {}).unwrap();
+    let cfg = config(deps.as_ref());
+    assert_eq!(cfg.treasury_addr, Addr::unchecked("treasury-b"));
+    assert_eq!(cfg.pending_treasury_addr, None);
+
+    //Migrations leave a set treasury alone
+    let migrate_msg = |treasury_addr: Option<&str>| MigrateMsg {
+        admin: None,
+        treasury_addr: treasury_addr.map(String::from),
+    };
+    migrate(deps.as_mut(), mock_env(), migrate_msg(Some("treasury-c"))).unwrap();
+    assert_eq!(
+        config(deps.as_ref()).treasury_addr,
+        Addr::unchecked("treasury-b")
+    );
+
+    //Configs stored without a treasury can't be migrated without one
+    let legacy = String::from_utf8(to_vec(&config(deps.as_ref())).unwrap())
+        .unwrap()
+        .replace(r#""treasury_addr":"treasury-b","#, "");
+    assert!(!legacy.contains(r#""treasury_addr""#));
+    deps.storage.set(b"config", legacy.as_bytes());
+    let err = migrate(deps.as_mut(), mock_env(), migrate_msg(None)).unwrap_err();
+    assert!(matches!(err, FactoryError::MissingTreasury {}));
+    migrate(deps.as_mut(), mock_env(), migrate_msg(Some("treasury-c"))).unwrap();
+    assert_eq!(
+        config(deps.as_ref()).treasury_addr,
+        Addr::unchecked("treasury-c")
+    );
+}
+
+#[test]
+fn migrate_baseline_config_test() {
+    let mut deps = mock_dependencies(&[]);
+    let config = |deps: Deps| -> Config {
+        from_binary(&query(deps, mock_env(), QueryMsg::Config {}).unwrap()).unwrap()
+    };
+    let migrate_msg = |admin: Option<&str>, treasury_addr: Option<&str>| MigrateMsg {
+        admin: admin.map(String::from),
+        treasury_addr: treasury_addr.map(String::from),
+    };
+
+    //A config as stored before any field was added, without a contract version
+    deps.storage.set(
+        b"config",
+        br#"{"trade_code_id":5,"token_addr":"local","local_ust_pool_addr":"local-ust","gov_addr":"gov","offers_addr":"offers","fee_collector_addr":"fee-collector","trading_incentives_addr":"trading-incentives"}"#,
+    );
+    let err = migrate(
+        deps.as_mut(),
+        mock_env(),
+        migrate_msg(None, Some("treasury")),
+    )
+    .unwrap_err();
+    assert!(matches!(err, FactoryError::MissingAdmin {}));
+    let err = migrate(deps.as_mut(), mock_env(), migrate_msg(Some("admin"), None)).unwrap_err();
+    assert!(matches!(err, FactoryError::MissingTreasury {}));
+    migrate(
+        deps.as_mut(),
+        mock_env(),
+        migrate_msg(Some("admin"), Some("treasury")),
+    )
+    .unwrap();
+    let cfg = config(deps.as_ref());
+    assert_eq!(cfg.admin, Addr::unchecked("admin"));
+    assert_eq!(cfg.treasury_addr, Addr::unchecked("treasury"));
+    assert_eq!(cfg.trade_code_id, 5);
+    assert_eq!(cfg.offers_addr, Addr::unchecked("offers"));
+    assert_eq!(cfg.maker_fee_bps, DEFAULT_FEE_BPS);
+
+    //The admin can manage the registry afterwards
+    add(
+        deps.as_mut(),
+        "admin",
+        "arbitrator",
+        FiatCurrency::unchecked("BRL"),
+    )
+    .unwrap();
+
+    //Migrations record the version, other contracts and newer versions are refused
+    let stored = get_contract_version(&deps.storage).unwrap();
+    assert_eq!(stored.contract, "crates.io:factory");
+    set_contract_version(&mut deps.storage, "crates.io:offer", &stored.version).unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), migrate_msg(None, None)).unwrap_err();
+    assert!(matches!(err, FactoryError::InvalidMigration { .. }));
+    set_contract_version(&mut deps.storage, &stored.contract, "99.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), migrate_msg(None, None)).unwrap_err();
+    assert!(matches!(err, FactoryError::InvalidMigration { .. }));
+    set_contract_version(&mut deps.storage, &stored.contract, &stored.version).unwrap();
+    migrate(deps.as_mut(), mock_env(), migrate_msg(None, None)).unwrap();
+}
+
+#[test]
+fn sudo_update_config_test() {
+    let mut deps = mock_dependencies(&[]);
+    do_init(deps.as_mut());
+    let update =
+        |maker_fee_bps: Option<u64>, fee_collector_addr: Option<&str>| SudoMsg::UpdateConfig {
+            maker_fee_bps,
+            taker_fee_bps: None,
+            fee_burn_bps: None,
+            fee_collector_addr: fee_collector_addr.map(String::from),
+        };
+
+    //Bounds still apply to governance
+    let err = sudo(deps.as_mut(), mock_env(), update(Some(10_001), None)).unwrap_err();
+    assert!(matches!(
+        err,
+        FactoryError::InvalidFeeBps {
+            maker_fee_bps: 10_001,
+            ..
+        }
+    ));
+
+    //Rotating the fee collector doesn't need the admin key
+    sudo(
+        deps.as_mut(),
+        mock_env(),
+        update(None, Some("new-fee-collector")),
+    )
+    .unwrap();
+    let cfg: Config =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(cfg.fee_collector_addr, Addr::unchecked("new-fee-collector"));
+    assert_eq!(cfg.admin, Addr::unchecked("admin"));
+}
+
+#[test]
+fn update_trade_code_id_test() {
+    let mut deps = mock_dependencies(&[]);
+    do_init(deps.as_mut());
+    let update = |code_id: u64| ExecuteMsg::UpdateTradeCodeId { code_id };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("stranger", &[]),
+        update(6),
+    )
+    .unwrap_err();
+    assert!(matches!(err, FactoryError::Unauthorized {}));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        update(0),
+    )
+    .unwrap_err();
+    assert!(matches!(err, FactoryError::InvalidCodeId {}));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        update(6),
+    )
+    .unwrap();
+    let cfg: Config =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(cfg.trade_code_id, 6);
+}
+
+fn instantiated(deps: DepsMut, id: u64, addr: &str) {
+    let result = ContractResult::Ok(SubMsgExecutionResponse {
+        events: vec![Event::new("instantiate_contract").add_attribute("contract_address", addr)],
+        data: None,
+    });
+    reply(deps, mock_env(), Reply { id, result }).unwrap();
+}
+
+fn query_contracts(
+    deps: Deps,
+    kind: Option<ContractKind>,
+    last_value: Option<&str>,
+) -> Vec<ContractRecord> {
+    let msg = QueryMsg::Contracts {
+        kind,
+        last_value: last_value.map(String::from),
+        limit: 2,
+    };
+    let page: ContractsResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(
+        page.next.as_ref(),
+        page.contracts.get(1).map(|record| &record.addr)
+    );
+    page.contracts
+}
+
+#[test]
+fn contracts_registry_test() {
+    let mut deps = mock_dependencies(&[]);
+    do_init(deps.as_mut());
+    instantiated(deps.as_mut(), OFFER_REPLY_ID, "offers");
+    instantiated(deps.as_mut(), FEE_COLLECTOR_REPLY_ID, "fee-collector");
+
+    //Only the offer contract registers trades
+    let register = |addr: &str| ExecuteMsg::RegisterTrade {
+        addr: addr.to_string(),
+        arbitrator: None,
+    };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("stranger", &[]),
+        register("trade-1"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, FactoryError::Unauthorized {}));
+    for addr in ["trade-2", "trade-1"].iter() {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("offers", &[]),
+            register(addr),
+        )
+        .unwrap();
+    }
+
+    let offers = ContractRecord {
+        kind: ContractKind::Offer,
+        addr: Addr::unchecked("offers"),
+        created_at: mock_env().block.height,
+    };
+    let first_page = query_contracts(deps.as_ref(), None, None);
+    assert_eq!(
+        first_page
+            .iter()
+            .map(|c| c.addr.as_str())
+            .collect::<Vec<_>>(),
+        vec!["fee-collector", "offers"]
+    );
+    assert_eq!(first_page[1], offers);
+    let trades = query_contracts(deps.as_ref(), Some(ContractKind::Trade), None);
+    assert_eq!(
+        trades.iter().map(|c| c.addr.as_str()).collect::<Vec<_>>(),
+        vec!["trade-1", "trade-2"]
+    );
+    let last_page = query_contracts(deps.as_ref(), Some(ContractKind::Trade), Some("trade-1"));
+    assert_eq!(last_page.len(), 1);
+    assert_eq!(last_page[0].addr, Addr::unchecked("trade-2"));
+
+    //Records are kept across migrations
+    let msg = MigrateMsg {
+        admin: None,
+        treasury_addr: None,
+    };
+    migrate(deps.as_mut(), mock_env(), msg).unwrap();
+    assert_eq!(query_contracts(deps.as_ref(), None, None), first_page);
+    assert_eq!(
+        query_contracts(deps.as_ref(), Some(ContractKind::Trade), None),
+        trades
+    );
+}
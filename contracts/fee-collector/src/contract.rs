@@ -77,7 +77,7 @@ fn distribute_fee(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
             info: AssetInfo::NativeToken {
                 denom: "uusd".to_string(),
             },
-            amount: ust_balance.clone(),
+            amount: ust_balance,
         },
         belief_price: None,
         max_spread: None,
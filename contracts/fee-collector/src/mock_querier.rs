@@ -6,13 +6,15 @@ use serde::{Deserialize, Serialize};
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
     from_binary, from_slice, to_binary, Addr, Coin, ContractResult, Decimal, OwnedDeps, Querier,
-    QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
+    QuerierResult, QueryRequest, SystemError, SystemResult, Timestamp, Uint128, WasmQuery,
 };
 use cw20::BalanceResponse;
 use localterra_protocol::currencies::FiatCurrency;
 use localterra_protocol::factory::Config as FactoryConfig;
 use localterra_protocol::governance::Config as GovConfig;
-use localterra_protocol::offer::{Config as OfferConfig, Offer, OfferState, OfferType, TradeInfo};
+use localterra_protocol::offer::{
+    Config as OfferConfig, Offer, OfferAsset, OfferState, OfferType, TradeInfo,
+};
 use localterra_protocol::trade::{TradeData as TradeState, TradeState as TradeTradeState};
 use std::collections::HashMap;
 use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute};
@@ -173,7 +175,7 @@ impl WasmMockQuerier {
                     panic!("DO NOT ENTER HERE")
                 }
             }
-            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => match from_binary(&msg)
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => match from_binary(msg)
                 .unwrap()
             {
                 QueryMsg::Pair { asset_infos } => {
@@ -232,13 +234,32 @@ impl WasmMockQuerier {
                     let offer = self.offer.clone().unwrap_or(Offer {
                         id,
                         owner: Addr::unchecked("offer-owner"),
-                        maker_contact: "LunaQueen".to_string(),
+                        maker_contact: Some("LunaQueen".to_string()),
                         offer_type: OfferType::Buy,
-                        fiat_currency: FiatCurrency::COP,
+                        fiat_currency: FiatCurrency::unchecked("COP"),
                         min_amount: Uint128::new(1_000_000u128),
                         max_amount: Uint128::new(500_000_000u128),
                         state: OfferState::Active,
                         timestamp: 1641329895,
+                        expires_at: None,
+                        price_margin_bps: None,
+                        payment_methods: vec![],
+                        description: None,
+                        asset: OfferAsset::default(),
+                        active_trades: 0,
+                        reserved_amount: Uint128::zero(),
+                        total_available: Uint128::zero(),
+                        remaining: Uint128::zero(),
+                        activate_at: None,
+                        pause_at: None,
+                        amounts_in_fiat: false,
+                        available_from_hour: None,
+                        available_to_hour: None,
+                        effective_state: None,
+                        currently_available: None,
+                        created_at: Timestamp::from_seconds(1641329895),
+                        last_updated: Timestamp::from_seconds(1641329895),
+                        min_trades_completed: None,
                     });
                     SystemResult::Ok(ContractResult::from(to_binary(&offer)))
                 }
@@ -247,6 +268,7 @@ impl WasmMockQuerier {
                 QueryMsg::Config {} => {
                     if contract_addr.contains("factory") {
                         SystemResult::Ok(ContractResult::from(to_binary(&FactoryConfig {
+                            admin: Addr::unchecked("admin"),
                             trade_code_id: 0,
                             token_addr: Addr::unchecked("local"),
                             local_ust_pool_addr: Addr::unchecked("local-ust"),
@@ -254,6 +276,11 @@ impl WasmMockQuerier {
                             offers_addr: Addr::unchecked("offers"),
                             fee_collector_addr: Addr::unchecked("fee-collector"),
                             trading_incentives_addr: Addr::unchecked("trading-incentives"),
+                            maker_fee_bps: 100,
+                            taker_fee_bps: 0,
+                            fee_burn_bps: 0,
+                            treasury_addr: Addr::unchecked("treasury"),
+                            pending_treasury_addr: None,
                         })))
                     } else if contract_addr.contains("gov") {
                         SystemResult::Ok(ContractResult::from(to_binary(&GovConfig {
@@ -262,6 +289,22 @@ impl WasmMockQuerier {
                     } else {
                         let offer_config = OfferConfig {
                             factory_addr: Addr::unchecked("factory"),
+                            admin: Addr::unchecked("factory"),
+                            max_offers_per_owner: 0,
+                            price_oracle_addr: None,
+                            denoms: vec!["uusd".to_string()],
+                            trade_duration: 3600,
+                            arbitrator: None,
+                            pending_admin: None,
+                            max_trade_amount: Uint128::zero(),
+                            fiat_currencies: vec![FiatCurrency::unchecked("COP")],
+                            max_concurrent_trades: 0,
+                            dispute_window: 86400,
+                            new_trade_limit: 0,
+                            new_trade_window: 3600,
+                            allow_delete_traded: false,
+                            max_amount_ratio: 0,
+                            max_price_age: 3600,
                         };
                         SystemResult::Ok(ContractResult::from(to_binary(&offer_config)))
                     }
@@ -271,31 +314,68 @@ impl WasmMockQuerier {
                 }
                 QueryMsg::TradeInfo { trade: _, maker } => {
                     SystemResult::Ok(ContractResult::from(to_binary(&TradeInfo {
+                        trade_addr: Addr::unchecked("trade"),
+                        offer_id: 1,
+                        created_at: 0,
+                        amount: Uint128::new(1_000_000u128),
+                        denom: "uusd".to_string(),
+                        counterparty: Addr::unchecked("taker"),
+                        expires_at: Timestamp::from_seconds(0),
                         trade: TradeState {
                             addr: Addr::unchecked("trade"),
                             factory_addr: Addr::unchecked("factory"),
                             buyer: Addr::unchecked("taker"),
                             arbitrator: Addr::unchecked("arbitrator"),
                             taker_contact: "USTKing".to_string(),
+                            maker_contact: Some("LunaQueen".to_string()),
                             seller: Addr::unchecked(maker),
                             offer_id: 1,
                             offer_contract: Addr::unchecked("offer"),
-                            state: TradeTradeState::Closed,
-                            expire_height: 0,
-                            ust_amount: Uint128::new(1_000_000u128),
+                            state: TradeTradeState::Released,
+                            created_at: Timestamp::from_seconds(0),
+                            expires_at: Timestamp::from_seconds(0),
+                            dispute_window_ends_at: Timestamp::from_seconds(0),
+                            fee_bps: 100,
+                            amount: Uint128::new(1_000_000u128),
+                            asset: OfferAsset::default(),
+                            fiat_price: None,
+                            fiat_amount: None,
+                            multisig_release: false,
+                            release_approvals: vec![],
+                            approvals_conflict: false,
+                            evidence: vec![],
                         },
-                        offer: Offer {
+                        offer: Some(Offer {
                             id: 1,
                             owner: Addr::unchecked("offer-owner"),
-                            maker_contact: "LunaQueen".to_string(),
+                            maker_contact: Some("LunaQueen".to_string()),
 
                             offer_type: OfferType::Buy,
-                            fiat_currency: FiatCurrency::COP,
+                            fiat_currency: FiatCurrency::unchecked("COP"),
                             min_amount: Uint128::new(1_000_000u128),
                             max_amount: Uint128::new(500_000_000u128),
                             state: OfferState::Active,
                             timestamp: 1641329895,
-                        },
+                            expires_at: None,
+                            price_margin_bps: None,
+                            payment_methods: vec![],
+                            description: None,
+                            asset: OfferAsset::default(),
+                            active_trades: 0,
+                            reserved_amount: Uint128::zero(),
+                            total_available: Uint128::zero(),
+                            remaining: Uint128::zero(),
+                            activate_at: None,
+                            pause_at: None,
+                            amounts_in_fiat: false,
+                            available_from_hour: None,
+                            available_to_hour: None,
+                            effective_state: None,
+                            currently_available: None,
+                            created_at: Timestamp::from_seconds(1641329895),
+                            last_updated: Timestamp::from_seconds(1641329895),
+                            min_trades_completed: None,
+                        }),
                         expired: false,
                     })))
                 }
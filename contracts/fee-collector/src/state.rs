@@ -4,10 +4,10 @@ use localterra_protocol::fee_collector::Config;
 
 pub static CONFIG_KEY: &[u8] = b"config";
 
-pub fn config_storage(storage: &mut dyn Storage) -> Singleton<Config> {
+pub fn config_storage(storage: &mut dyn Storage) -> Singleton<'_, Config> {
     singleton(storage, CONFIG_KEY)
 }
 
-pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<Config> {
+pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, Config> {
     singleton_read(storage, CONFIG_KEY)
 }
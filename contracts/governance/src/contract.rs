@@ -227,7 +227,7 @@ fn send_tokens(
     let attributes = vec![
         attr("action", action),
         attr("recipient", recipient.to_string()),
-        attr("amount", &amount.to_string()),
+        attr("amount", amount.to_string()),
     ];
 
     let mut res = Response::new();
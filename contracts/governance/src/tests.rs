@@ -67,7 +67,7 @@ fn test_stake_and_withdraw() {
         deps.as_mut(),
         token_addr.as_str(),
         staker.sender.to_string(),
-        staker_balance.clone(),
+        staker_balance,
         Cw20HookMsg::StakeTokens {},
     );
     assert!(res.is_ok());
@@ -152,7 +152,7 @@ fn test_deposit_rewards() {
         deps.as_mut(),
         token_addr.as_str(),
         staker_a.sender.to_string(),
-        staker_balance.clone(),
+        staker_balance,
         Cw20HookMsg::StakeTokens {},
     );
     assert!(res.is_ok());
@@ -162,7 +162,7 @@ fn test_deposit_rewards() {
         deps.as_mut(),
         token_addr.as_str(),
         factory.sender.to_string(),
-        staker_balance.clone(),
+        staker_balance,
         Cw20HookMsg::DepositRewards {},
     );
     assert!(res.is_ok());
@@ -172,7 +172,7 @@ fn test_deposit_rewards() {
         deps.as_mut(),
         token_addr.as_str(),
         staker_b.sender.to_string(),
-        staker_balance.clone(),
+        staker_balance,
         Cw20HookMsg::StakeTokens {},
     );
     assert!(res.is_ok());
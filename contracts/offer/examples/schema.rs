@@ -0,0 +1,40 @@
+use std::env::current_dir;
+use std::fs::create_dir_all;
+
+use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
+
+use localterra_protocol::offer::{
+    BlacklistResponse, Config, ExecuteMsg, InstantiateMsg, LastTradedPrice, MigrateMsg,
+    NewTradeResponse, Offer, OfferMsg, OffersExport, OffersFilteredResponse, OffersPageResponse,
+    ProfileResponse, QueryMsg, StateResponse, SudoMsg, TradeInfo, TradeSimulation, TradesExport,
+    TradesPageResponse, TradesResponse,
+};
+
+fn main() {
+    let mut out_dir = current_dir().unwrap();
+    out_dir.push("schema");
+    create_dir_all(&out_dir).unwrap();
+    remove_schemas(&out_dir).unwrap();
+
+    export_schema(&schema_for!(InstantiateMsg), &out_dir);
+    export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
+    export_schema(&schema_for!(SudoMsg), &out_dir);
+    export_schema(&schema_for!(OfferMsg), &out_dir);
+    export_schema(&schema_for!(Offer), &out_dir);
+    export_schema(&schema_for!(Config), &out_dir);
+    export_schema(&schema_for!(StateResponse), &out_dir);
+    export_schema(&schema_for!(OffersPageResponse), &out_dir);
+    export_schema(&schema_for!(TradesPageResponse), &out_dir);
+    export_schema(&schema_for!(TradesResponse), &out_dir);
+    export_schema(&schema_for!(BlacklistResponse), &out_dir);
+    export_schema(&schema_for!(ProfileResponse), &out_dir);
+    export_schema(&schema_for!(TradeInfo), &out_dir);
+    export_schema(&schema_for!(TradeSimulation), &out_dir);
+    export_schema(&schema_for!(OffersExport), &out_dir);
+    export_schema(&schema_for!(OffersFilteredResponse), &out_dir);
+    export_schema(&schema_for!(TradesExport), &out_dir);
+    export_schema(&schema_for!(NewTradeResponse), &out_dir);
+    export_schema(&schema_for!(LastTradedPrice), &out_dir);
+}
@@ -1,37 +1,98 @@
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, Binary, ContractResult, CosmosMsg, Deps, DepsMut, Env,
-    MessageInfo, Order, QueryRequest, Reply, ReplyOn, Response, StdResult, Storage, SubMsg,
-    SubMsgExecutionResponse, WasmMsg, WasmQuery,
+    entry_point, to_binary, Addr, Binary, ContractResult, Decimal, Deps, DepsMut, Env, Event,
+    MessageInfo, Order, QueryRequest, Reply, Response, StdError, StdResult, Storage, SubMsg,
+    SubMsgExecutionResponse, Timestamp, Uint128, WasmMsg, WasmQuery,
 };
-use cw_storage_plus::Bound;
+use cw2::{set_contract_version, CONTRACT};
+use cw20::{Cw20QueryMsg, TokenInfoResponse};
+use cw_storage_plus::{Bound, Prefix, U64Key};
 
-use localterra_protocol::factory_util::get_factory_config;
-use localterra_protocol::guards::{assert_min_g_max, assert_ownership};
+use localterra_protocol::constants::{
+    DEFAULT_DISPUTE_WINDOW, DEFAULT_MAX_PRICE_AGE, DEFAULT_NEW_TRADE_WINDOW,
+    DEFAULT_TRADE_DURATION, MAX_BATCH_SIZE, MAX_BULK_STATE_CHANGES, NEW_TRADE_REPLY_ID,
+};
+use localterra_protocol::currencies::{minor_units_to_denom, FiatCurrency, FiatCurrencyInfo};
+use localterra_protocol::events::offer_event;
+use localterra_protocol::factory::{is_ibc_denom, Arbitrator, ExecuteMsg as FactoryExecuteMsg};
+use localterra_protocol::factory_util::{
+    get_factory_config, get_ibc_denom, instantiated_contract_addr,
+};
+use localterra_protocol::fees::{fee_burn_amount, TradeFees};
+use localterra_protocol::guards::{
+    assert_amount_ratio, assert_denom, assert_description, assert_expiration, assert_fiat_currency,
+    assert_maker_contact, assert_min_g_max, assert_not_self_trade, assert_payment_methods,
+    assert_price_margin, assert_schedule, assert_sender_is, assert_trading_hours, validate_addr,
+};
+use localterra_protocol::migration::is_valid_migration;
 use localterra_protocol::offer::{
-    offers, Config, ExecuteMsg, InstantiateMsg, Offer, OfferModel, OfferMsg, OfferState, QueryMsg,
-    State, TradeAddr, TradeInfo, TradesIndex,
+    default_denom, default_fiat_currencies, next_offer_id, offer_fiat_currencies, offers,
+    BlacklistResponse, Config, ConfigUpdate, ExecuteMsg, InstantiateMsg, LastTradedPrice,
+    MigrateMsg, NewTradeResponse, Offer, OfferAsset, OfferFilter, OfferModel, OfferMsg,
+    OfferOverrides, OfferState, OffersFilters, ProfileResponse, QueryMsg, QueryOrder, State,
+    StateResponse, SudoMsg, TradeAddr, TradeInfo, TradeSimulation, TraderStats, TradesExport,
+    TradesIndex, TradesPageResponse, TradesResponse,
 };
+use localterra_protocol::pagination::{page_limit, page_next};
+use localterra_protocol::price_oracle::query_price;
 use localterra_protocol::trade::{
     InstantiateMsg as TradeInstantiateMsg, QueryMsg as TradeQueryMsg, TradeData, TradeState,
 };
 
-use crate::state::{config_read, config_storage, state_read, state_storage, trades};
+use crate::state::{
+    config_read, config_storage, id_offers, legacy_offers, state_read, state_storage, trades,
+    BLACKLIST, COMPLETED_TRADES, LAST_TRADED_PRICES, NEW_TRADES, OPEN_TRADES, OPERATORS,
+    REGISTERED_TRADES, TRADER_STATS,
+};
 use localterra_protocol::errors::OfferError;
 
+const CONTRACT_NAME: &str = concat!("crates.io:", env!("CARGO_PKG_NAME"));
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    _msg: InstantiateMsg,
+    msg: InstantiateMsg,
 ) -> Result<Response, OfferError> {
     config_storage(deps.storage).save(&Config {
-        factory_addr: info.sender,
+        factory_addr: info.sender.clone(),
+        admin: info.sender,
+        max_offers_per_owner: msg.max_offers_per_owner,
+        price_oracle_addr: None,
+        denoms: vec![default_denom()],
+        trade_duration: DEFAULT_TRADE_DURATION,
+        arbitrator: None,
+        pending_admin: None,
+        max_trade_amount: msg.max_trade_amount,
+        fiat_currencies: default_fiat_currencies(),
+        max_concurrent_trades: 0,
+        dispute_window: DEFAULT_DISPUTE_WINDOW,
+        new_trade_limit: 0,
+        new_trade_window: DEFAULT_NEW_TRADE_WINDOW,
+        allow_delete_traded: false,
+        max_amount_ratio: 0,
+        max_price_age: DEFAULT_MAX_PRICE_AGE,
+    })?;
+    state_storage(deps.storage).save(&State {
+        offers_count: 0,
+        paused: false,
     })?;
-    state_storage(deps.storage).save(&State { offers_count: 0 })?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     Ok(Response::default())
 }
 
+/// Chain governance interventions, the same changes the admin can make
+/// through `execute`.
+#[entry_point]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, OfferError> {
+    match msg {
+        SudoMsg::SetPause { paused } => set_pause(deps, paused),
+        SudoMsg::UpdateConfig(update) => update_config(deps, update),
+        SudoMsg::RemoveBlacklisted { addr } => set_blacklist(deps, addr, false),
+    }
+}
+
 #[entry_point]
 pub fn execute(
     deps: DepsMut,
@@ -39,27 +100,67 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, OfferError> {
+    if matches!(
+        msg,
+        ExecuteMsg::Create { .. }
+            | ExecuteMsg::Update { .. }
+            | ExecuteMsg::CreateMany { .. }
+            | ExecuteMsg::UpdateMany { .. }
+            | ExecuteMsg::CloneOffer { .. }
+            | ExecuteMsg::NewTrade { .. }
+    ) {
+        assert_not_paused(deps.storage)?;
+        assert_not_blocked(deps.storage, &[&info.sender])?;
+    }
+
     match msg {
         ExecuteMsg::Create { offer } => create_offer(deps, env, info, offer),
         ExecuteMsg::Activate { id } => activate_offer(deps, env, info, id),
         ExecuteMsg::Pause { id } => pause_offer(deps, env, info, id),
+        ExecuteMsg::Archive { id } => archive_offer(deps, env, info, id),
+        ExecuteMsg::PauseAll {} => change_all_offers(deps, env, info, OfferState::Paused),
+        ExecuteMsg::ActivateAll {} => change_all_offers(deps, env, info, OfferState::Active),
+        ExecuteMsg::UpdateConfig(update) => {
+            assert_admin(deps.as_ref(), &info)?;
+            update_config(deps, update)
+        }
+        ExecuteMsg::ProposeNewAdmin { new_admin } => propose_new_admin(deps, info, new_admin),
+        ExecuteMsg::CancelAdminProposal {} => cancel_admin_proposal(deps, info),
+        ExecuteMsg::AcceptAdmin {} => accept_admin(deps, info),
+        ExecuteMsg::SetPause { paused } => {
+            assert_admin(deps.as_ref(), &info)?;
+            set_pause(deps, paused)
+        }
+        ExecuteMsg::SetBlacklist { addr, blocked } => {
+            assert_admin(deps.as_ref(), &info)?;
+            set_blacklist(deps, addr, blocked)
+        }
         ExecuteMsg::Update { id, offer } => update_offer(deps, env, info, id, offer),
+        ExecuteMsg::CreateMany { offers } => create_offers(deps, env, info, offers),
+        ExecuteMsg::UpdateMany { updates } => update_offers(deps, env, info, updates),
+        ExecuteMsg::SetOperator { operator, enabled } => {
+            set_operator(deps, info, operator, enabled)
+        }
+        ExecuteMsg::Delete { id } => delete_offer(deps, info, id),
+        ExecuteMsg::CloneOffer { id, overrides } => clone_offer(deps, env, info, id, overrides),
         ExecuteMsg::NewTrade {
             offer_id,
-            ust_amount,
+            amount,
             taker,
             taker_contact,
-            arbitrator,
+            multisig_release,
         } => create_trade(
             deps,
             env,
             info,
             offer_id,
-            ust_amount,
+            amount,
             taker,
             taker_contact,
-            arbitrator,
+            multisig_release,
         ),
+        ExecuteMsg::TradeClosed { state } => close_trade(deps, env, info, state),
+        ExecuteMsg::TradeStateChanged { state } => update_trade_state(deps, info, state),
     }
 }
 
@@ -68,28 +169,104 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::State {} => to_binary(&query_state(deps)?),
-        QueryMsg::Offers { fiat_currency } => {
-            to_binary(&OfferModel::query_all_offers(deps.storage, fiat_currency)?)
-        }
+        QueryMsg::Offers {
+            fiat_currency,
+            offer_type,
+            payment_method,
+            denom,
+            order,
+            amount,
+            min_amount,
+            max_amount,
+        } => to_binary(&OfferModel::query_all_offers(
+            deps.storage,
+            fiat_currency,
+            &OfferFilter {
+                offer_type,
+                payment_method,
+                denom,
+                amount,
+                min_amount,
+                max_amount,
+                ..OfferFilter::default()
+            },
+            order.unwrap_or_default(),
+            env.block.time,
+        )?),
         QueryMsg::OffersQuery {
             owner,
+            updated_since,
+            last_value,
+            limit,
+        } => to_binary(&OfferModel::query(
+            deps,
+            env.block.time,
+            owner,
+            &OfferFilter {
+                updated_since,
+                ..OfferFilter::default()
+            },
+            last_value,
+            limit,
+        )?),
+        QueryMsg::OffersPage {
+            order,
+            start_before,
             last_value,
             limit,
-        } => to_binary(&OfferModel::query(deps, owner, last_value, limit)?),
+            amount,
+            min_amount,
+            max_amount,
+        } => {
+            let order = order.unwrap_or(QueryOrder::Desc);
+            let cursor = match order {
+                QueryOrder::Asc => last_value,
+                QueryOrder::Desc => start_before,
+            };
+            to_binary(&OfferModel::query_page(
+                deps.storage,
+                env.block.time,
+                &OfferFilter {
+                    amount,
+                    min_amount,
+                    max_amount,
+                    ..OfferFilter::default()
+                },
+                order,
+                cursor,
+                limit,
+            )?)
+        }
         QueryMsg::OffersByType {
             offer_type,
             last_value,
             limit,
         } => to_binary(&OfferModel::query_by_type(
-            deps, offer_type, last_value, limit,
+            deps,
+            env.block.time,
+            offer_type,
+            last_value,
+            limit,
         )?),
         QueryMsg::OffersByFiat {
             fiat_currency,
+            offer_type,
+            payment_method,
+            denom,
+            order,
             last_value,
             limit,
         } => to_binary(&OfferModel::query_by_fiat(
             deps,
+            env.block.time,
             fiat_currency,
+            &OfferFilter {
+                offer_type,
+                payment_method,
+                denom,
+                ..OfferFilter::default()
+            },
+            order.unwrap_or_default(),
             last_value,
             limit,
         )?),
@@ -100,12 +277,51 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             limit,
         } => to_binary(&OfferModel::query_by_type_fiat(
             deps,
+            env.block.time,
             offer_type,
             fiat_currency,
             last_value,
             limit,
         )?),
-        QueryMsg::Offer { id } => to_binary(&load_offer_by_id(deps.storage, id)?),
+        QueryMsg::OffersByOwner {
+            owner,
+            last_value,
+            limit,
+        } => to_binary(&OfferModel::query_by_owner(
+            deps,
+            env.block.time,
+            deps.api.addr_validate(owner.as_str())?,
+            last_value,
+            limit,
+        )?),
+        QueryMsg::Offer { id } => {
+            to_binary(&load_offer_by_id(deps.storage, id)?.with_effective_state(env.block.time))
+        }
+        QueryMsg::TraderStats { addr } => to_binary(
+            &TRADER_STATS
+                .may_load(deps.storage, &deps.api.addr_validate(addr.as_str())?)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::Profile { addr } => to_binary(&query_profile(
+            deps,
+            env.block.time,
+            deps.api.addr_validate(addr.as_str())?,
+        )?),
+        QueryMsg::SimulateTrade { offer_id, amount } => to_binary(&query_simulate_trade(
+            deps,
+            env.block.time,
+            offer_id,
+            amount,
+        )?),
+        QueryMsg::Operators { owner } => to_binary(&query_operators(
+            deps,
+            deps.api.addr_validate(owner.as_str())?,
+        )?),
+        QueryMsg::CompletedTrades { address } => to_binary(
+            &COMPLETED_TRADES
+                .may_load(deps.storage, &deps.api.addr_validate(address.as_str())?)?
+                .unwrap_or_default(),
+        ),
         QueryMsg::TradesQuery {
             user,
             state,
@@ -121,68 +337,478 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             last_value,
             limit,
         )?),
+        QueryMsg::TradesAsTaker {
+            taker,
+            last_value,
+            limit,
+        } => to_binary(&query_trades(
+            env,
+            deps,
+            deps.api.addr_validate(taker.as_str())?,
+            None,
+            TradesIndex::Taker,
+            last_value,
+            limit,
+        )?),
+        QueryMsg::TradesByState {
+            state,
+            last_value,
+            limit,
+        } => to_binary(&query_trades_by_state(env, deps, state, last_value, limit)?),
+        QueryMsg::TradesPage {
+            maker,
+            last_value,
+            limit,
+        } => to_binary(&query_trades_page(env, deps, maker, last_value, limit)?),
+        QueryMsg::OffersSummary {} => to_binary(&OfferModel::query_summaries(deps.storage)?),
+        QueryMsg::OffersSample {
+            fiat_currency,
+            count,
+            seed,
+        } => to_binary(&OfferModel::query_sample(
+            deps.storage,
+            env.block.time,
+            fiat_currency,
+            count,
+            seed,
+            state_read(deps.storage).load()?.offers_count,
+        )?),
+        QueryMsg::SupportedCurrencies {} => to_binary(&query_supported_currencies(deps)?),
+        QueryMsg::Blacklist { last_value, limit } => {
+            to_binary(&query_blacklist(deps, last_value, limit)?)
+        }
+        QueryMsg::OffersFiltered {
+            owner,
+            fiat_currency,
+            offer_type,
+            state,
+            last_value,
+            limit,
+        } => {
+            let filters = OffersFilters {
+                owner: owner
+                    .map(|owner| deps.api.addr_validate(owner.as_str()))
+                    .transpose()?,
+                fiat_currency,
+                offer_type,
+                state,
+            };
+            to_binary(&OfferModel::query_filtered(
+                deps.storage,
+                env.block.time,
+                filters,
+                last_value,
+                limit,
+            )?)
+        }
+        QueryMsg::LastTradedPrice { fiat_currency } => to_binary(
+            &LAST_TRADED_PRICES.may_load(deps.storage, fiat_currency.to_string().as_str())?,
+        ),
+        QueryMsg::ExportOffers { start_after, limit } => {
+            to_binary(&OfferModel::query_export(deps.storage, start_after, limit)?)
+        }
+        QueryMsg::ExportTrades { start_after, limit } => {
+            to_binary(&query_export_trades(deps, start_after, limit)?)
+        }
+    }
+}
+
+/// Contracts instantiated before cw2 versions were stored can always be
+/// migrated, others only from an older or equal version of this contract.
+#[entry_point]
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, OfferError> {
+    if let Some(stored) = CONTRACT.may_load(deps.storage)? {
+        if !is_valid_migration(&stored, CONTRACT_NAME, CONTRACT_VERSION) {
+            return Err(OfferError::InvalidMigration {
+                contract: stored.contract,
+                version: stored.version,
+            });
+        }
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    //Until the admin existed the factory held its rights
+    let mut cfg = config_read(deps.storage).load()?;
+    if cfg.admin.as_str().is_empty() {
+        cfg.admin = cfg.factory_addr.clone();
+        config_storage(deps.storage).save(&cfg)?;
+    }
+
+    let migrated = migrate_legacy_offers(deps.storage)?;
+    backfill_offer_timestamps(deps.storage, env.block.time)?;
+    backfill_trade_takers(deps.storage)?;
+    reindex_trade_makers(deps.storage)?;
+    recount_open_trades(deps.storage)?;
+    OfferModel::recount(deps.storage)?;
+
+    let res = Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("migrated_offers", migrated.to_string());
+    Ok(res)
+}
+
+/// Moves offers stored under the legacy `id.to_string()` or big-endian
+/// `U64Key` primary keys to the `(fiat_currency, id)` layout, rebuilding
+/// their index entries along the way and keeping their ids. Entries already
+/// stored under the current layout are left untouched.
+pub fn migrate_legacy_offers(storage: &mut dyn Storage) -> StdResult<u64> {
+    let legacy: Vec<(Vec<u8>, Offer)> = legacy_offers()
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .filter(|(key, offer)| {
+            key.as_slice() == offer.id.to_string().as_bytes()
+                || key.as_slice() == offer.id.to_be_bytes()
+        })
+        .collect();
+
+    for (key, offer) in legacy.iter() {
+        if key.as_slice() == offer.id.to_string().as_bytes() {
+            legacy_offers().remove(storage, &offer.id.to_string())?;
+        } else {
+            id_offers().remove(storage, U64Key::new(offer.id))?;
+        }
+        save_offer_entry(storage, offer)?;
+    }
+
+    Ok(legacy.len() as u64)
+}
+
+/// Writes `offer` and its id lookup entry without touching the counters,
+/// which the migration recomputes once every offer is in place.
+fn save_offer_entry(storage: &mut dyn Storage, offer: &Offer) -> StdResult<()> {
+    let fiat = offer.fiat_currency.to_string();
+    offer_fiat_currencies().save(storage, U64Key::new(offer.id), &offer.fiat_currency)?;
+    offers().save(storage, (fiat.as_str(), U64Key::new(offer.id)), offer)
+}
+
+/// Offers stored before `created_at` and `last_updated` existed get the
+/// migration block time for both.
+pub fn backfill_offer_timestamps(
+    storage: &mut dyn Storage,
+    block_time: Timestamp,
+) -> StdResult<()> {
+    let missing: Vec<Offer> = offers()
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .map(|(_, offer)| offer)
+        .filter(|offer| offer.created_at == Timestamp::default())
+        .collect();
+
+    for mut offer in missing {
+        offer.created_at = block_time;
+        offer.last_updated = block_time;
+        save_offer_entry(storage, &offer)?;
+    }
+    Ok(())
+}
+
+/// Trades registered before `taker` existed get the party that isn't the
+/// owner of their offer.
+pub fn backfill_trade_takers(storage: &mut dyn Storage) -> StdResult<()> {
+    let missing: Vec<(Vec<u8>, TradeAddr)> = trades()
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .filter(|(_, trade)| trade.taker.as_str().is_empty())
+        .collect();
+
+    for (key, mut trade) in missing {
+        let owner = OfferModel::load(storage, trade.offer_id)?.map(|offer| offer.owner);
+        trade.taker = trade_taker(owner.as_ref(), &trade.seller, &trade.buyer);
+        trades().save(storage, &String::from_utf8_lossy(&key), &trade)?;
+    }
+    Ok(())
+}
+
+/// Saves every trade again so the `maker` index covers trades registered
+/// before it existed, filling in the maker from the offer where it's missing,
+/// and counts them for `REGISTERED_TRADES`.
+pub fn reindex_trade_makers(storage: &mut dyn Storage) -> StdResult<()> {
+    let all: Vec<(Vec<u8>, TradeAddr)> = trades()
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let count = all.len() as u64;
+
+    for (key, mut trade) in all {
+        if trade.maker.as_str().is_empty() {
+            if let Some(offer) = OfferModel::load(storage, trade.offer_id)? {
+                trade.maker = offer.owner;
+            }
+        }
+        trades().save(storage, &String::from_utf8_lossy(&key), &trade)?;
+    }
+    REGISTERED_TRADES.save(storage, &count)
+}
+
+/// Rebuilds `OPEN_TRADES` from the registered trades, which counts the ones
+/// registered before the counter existed.
+pub fn recount_open_trades(storage: &mut dyn Storage) -> StdResult<()> {
+    let counted: Vec<Addr> = OPEN_TRADES
+        .keys(storage, None, None, Order::Ascending)
+        .map(|key| Ok(Addr::unchecked(String::from_utf8(key)?)))
+        .collect::<StdResult<Vec<_>>>()?;
+    for addr in counted.iter() {
+        OPEN_TRADES.remove(storage, addr);
+    }
+
+    let open: Vec<TradeAddr> = trades()
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, trade)| trade))
+        .filter(|item| !matches!(item, Ok(trade) if trade.state.is_final()))
+        .collect::<StdResult<Vec<_>>>()?;
+    for trade in open.iter() {
+        update_open_trades(storage, trade, true)?;
+    }
+    Ok(())
+}
+
+/// Moves the open trades of `trade`'s maker and taker by one, up as it's
+/// registered and down once it reaches a final state. Trades whose maker
+/// couldn't be filled in only count for their taker.
+fn update_open_trades(storage: &mut dyn Storage, trade: &TradeAddr, opened: bool) -> StdResult<()> {
+    for party in [&trade.maker, &trade.taker].iter() {
+        if party.as_str().is_empty() {
+            continue;
+        }
+        OPEN_TRADES.update(storage, party, |count| -> StdResult<u64> {
+            let count = count.unwrap_or_default();
+            Ok(match opened {
+                true => count + 1,
+                false => count.saturating_sub(1),
+            })
+        })?;
+    }
+    Ok(())
+}
+
+fn trade_taker(owner: Option<&Addr>, seller: &Addr, buyer: &Addr) -> Addr {
+    if owner == Some(seller) {
+        buyer.clone()
+    } else {
+        seller.clone()
     }
 }
 
 #[entry_point]
 pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, OfferError> {
     match msg.id {
-        0 => trade_instance_reply(deps, env, msg.result),
+        NEW_TRADE_REPLY_ID => trade_instance_reply(deps, env, msg.result),
         _ => Err(OfferError::InvalidReply {}),
     }
 }
 
+/// Only registered on success, so a failed trade instantiation reverts the
+/// whole `NewTrade` call and no trade bookkeeping is ever left behind.
 fn trade_instance_reply(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     result: ContractResult<SubMsgExecutionResponse>,
 ) -> Result<Response, OfferError> {
-    if result.is_err() {
-        return Err(OfferError::InvalidReply {});
-    }
+    let response = match result {
+        ContractResult::Ok(response) => response,
+        ContractResult::Err(_) => return Err(OfferError::InvalidReply {}),
+    };
 
-    let trade_addr: Addr = result
-        .unwrap()
-        .events
-        .into_iter()
-        .find(|e| e.ty == "instantiate_contract")
-        .and_then(|ev| {
-            ev.attributes
-                .into_iter()
-                .find(|attr| attr.key == "contract_address")
-                .map(|addr| addr.value)
-        })
-        .and_then(|addr| deps.api.addr_validate(addr.as_str()).ok())
-        .unwrap();
+    let trade_addr =
+        instantiated_contract_addr(&response).ok_or(OfferError::MissingTradeAddr {})?;
+    let trade_addr = deps.api.addr_validate(trade_addr.as_str())?;
 
     let trade: TradeData = deps
         .querier
-        .query_wasm_smart(trade_addr.to_string(), &TradeQueryMsg::State {})
-        .unwrap();
+        .query_wasm_smart(trade_addr.to_string(), &TradeQueryMsg::State {})?;
 
-    trades()
-        .save(
-            deps.storage,
-            trade.addr.as_str(),
-            &TradeAddr {
-                trade: trade_addr.clone(),
-                seller: trade.seller.clone(),
-                buyer: trade.buyer.clone(),
-                arbitrator: trade.arbitrator.clone(),
-                state: trade.state.clone(),
-            },
-        )
-        .unwrap();
+    let owner = OfferModel::from_store(deps.storage, &trade.offer_id)?.owner;
+    let trade_entry = TradeAddr {
+        trade: trade_addr.clone(),
+        offer_id: trade.offer_id,
+        maker: owner.clone(),
+        seller: trade.seller.clone(),
+        buyer: trade.buyer.clone(),
+        taker: trade_taker(Some(&owner), &trade.seller, &trade.buyer),
+        arbitrator: trade.arbitrator.clone(),
+        state: trade.state.clone(),
+        created_at: env.block.time.seconds(),
+        amount: trade.amount,
+        fiat_price: trade.fiat_price,
+        fiat_amount: trade.fiat_amount,
+    };
+    trades().save(deps.storage, trade_addr.as_str(), &trade_entry)?;
+    update_open_trades(deps.storage, &trade_entry, true)?;
+    let registered = REGISTERED_TRADES
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    REGISTERED_TRADES.save(deps.storage, &(registered + 1))?;
+
+    let cfg = config_read(deps.storage).load()?;
+    let mut offer_model = OfferModel::may_load(deps.storage, &trade.offer_id)?;
+    let from_state = offer_model.offer.state.clone();
+    let offer = offer_model.open_trade(trade.fiat_amount.unwrap_or(trade.amount));
+    let event = offer_event("open_trade", offer, Some(&from_state))
+        .add_attribute("trade_addr", trade_addr.to_string())
+        .add_attribute("amount", trade.amount.to_string());
 
-    let offer = load_offer_by_id(deps.storage, trade.offer_id.clone()).unwrap();
+    let register_msg = WasmMsg::Execute {
+        contract_addr: cfg.factory_addr.to_string(),
+        msg: to_binary(&FactoryExecuteMsg::RegisterTrade {
+            addr: trade_addr.to_string(),
+            arbitrator: Some(Arbitrator {
+                addr: trade.arbitrator.clone(),
+                fiat: offer.fiat_currency.clone(),
+            }),
+        })?,
+        funds: vec![],
+    };
 
     //trade_state, offer_id, trade_amount,owner
     let res = Response::new()
+        .add_message(register_msg)
+        .add_event(event)
         .add_attribute("action", "create_trade_reply")
-        .add_attribute("addr", trade_addr)
+        .add_attribute("trade_addr", trade_addr.clone())
         .add_attribute("offer_id", offer.id.to_string())
-        .add_attribute("amount", trade.ust_amount)
-        .add_attribute("owner", offer.owner);
+        .add_attribute("amount", trade.amount)
+        .add_attribute("owner", offer.owner.clone())
+        .set_data(to_binary(&NewTradeResponse {
+            trade_addr,
+            offer_id: offer.id,
+            amount: trade.amount,
+        })?);
+    Ok(res)
+}
+
+/// Called by trades reaching a final state, frees the offer for updates once
+/// every trade it spawned is done.
+fn close_trade(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    state: TradeState,
+) -> Result<Response, OfferError> {
+    let trade = trades().may_load(deps.storage, info.sender.as_str())?;
+    let mut trade = match trade {
+        Some(trade) if state.is_final() && !trade.state.is_final() => trade,
+        _ => return Err(OfferError::TradeNotOpen { trade: info.sender }),
+    };
+    trade.state = state;
+    trades().save(deps.storage, info.sender.as_str(), &trade)?;
+    update_open_trades(deps.storage, &trade, false)?;
+    if trade.state == TradeState::Released {
+        for party in [&trade.seller, &trade.buyer].iter() {
+            COMPLETED_TRADES.update(deps.storage, party, |count| -> StdResult<u64> {
+                Ok(count.unwrap_or_default() + 1)
+            })?;
+        }
+        let block_time = env.block.time;
+        let maker = trade_maker(deps.storage, &trade)?;
+        update_trader_stats(deps.storage, &maker, |stats| {
+            stats.trades_as_maker += 1;
+            stats.last_trade_at = block_time;
+        })?;
+        update_trader_stats(deps.storage, &trade.taker, |stats| {
+            stats.trades_as_taker += 1;
+            stats.last_trade_at = block_time;
+        })?;
+        if let Some(price) = trade.fiat_price {
+            let offer = OfferModel::from_store(deps.storage, &trade.offer_id)?;
+            let fiat = offer.fiat_currency.to_string();
+            LAST_TRADED_PRICES.save(
+                deps.storage,
+                fiat.as_str(),
+                &LastTradedPrice {
+                    fiat_currency: offer.fiat_currency,
+                    price,
+                    denom: offer.asset.to_string(),
+                    amount: trade.amount,
+                    traded_at: block_time,
+                },
+            )?;
+        }
+    }
+    //Trades only reach a final state once, a retried report is refused above
+    let settled_for_maker = match trade.state {
+        TradeState::SettledForMaker => Some(true),
+        TradeState::SettledForTaker => Some(false),
+        _ => None,
+    };
+    if let Some(for_maker) = settled_for_maker {
+        let maker = trade_maker(deps.storage, &trade)?;
+        for (party, won) in [(&maker, for_maker), (&trade.taker, !for_maker)].iter() {
+            update_trader_stats(deps.storage, party, |stats| {
+                if *won {
+                    stats.disputes_won += 1
+                } else {
+                    stats.disputes_lost += 1
+                }
+            })?;
+        }
+    }
+
+    let mut offer_model = OfferModel::may_load(deps.storage, &trade.offer_id)?;
+    let from_state = offer_model.offer.state.clone();
+    let offer = offer_model.close_trade(&trade);
+    let event = offer_event("close_trade", offer, Some(&from_state))
+        .add_attribute("trade_addr", trade.trade.to_string())
+        .add_attribute("amount", trade.amount.to_string());
+
+    let res = Response::new()
+        .add_event(event)
+        .add_attribute("action", "close_trade")
+        .add_attribute("trade", trade.trade)
+        .add_attribute("state", trade.state.to_string())
+        .add_attribute("offer_id", offer.id.to_string())
+        .add_attribute("active_trades", offer.active_trades.to_string())
+        .add_attribute("reserved_amount", offer.reserved_amount.to_string())
+        .add_attribute("remaining", offer.remaining.to_string());
+    Ok(res)
+}
+
+/// Trades registered before `maker` existed fall back to the offer owner.
+fn trade_maker(storage: &dyn Storage, trade: &TradeAddr) -> Result<Addr, OfferError> {
+    match trade.maker.as_str() {
+        "" => Ok(OfferModel::from_store(storage, &trade.offer_id)?.owner),
+        _ => Ok(trade.maker.clone()),
+    }
+}
+
+fn update_trader_stats(
+    storage: &mut dyn Storage,
+    addr: &Addr,
+    update: impl FnOnce(&mut TraderStats),
+) -> StdResult<TraderStats> {
+    TRADER_STATS.update(storage, addr, |stats| -> StdResult<_> {
+        let mut stats = stats.unwrap_or_default();
+        update(&mut stats);
+        Ok(stats)
+    })
+}
+
+/// Mirrors intermediate states of a trade, final ones go through `close_trade`.
+fn update_trade_state(
+    deps: DepsMut,
+    info: MessageInfo,
+    state: TradeState,
+) -> Result<Response, OfferError> {
+    let trade = trades().may_load(deps.storage, info.sender.as_str())?;
+    let mut trade = match trade {
+        Some(trade) if !state.is_final() && !trade.state.is_final() => trade,
+        _ => return Err(OfferError::TradeNotOpen { trade: info.sender }),
+    };
+    //A retried report of the same dispute doesn't count it twice
+    if state == TradeState::Disputed && trade.state != TradeState::Disputed {
+        let maker = trade_maker(deps.storage, &trade)?;
+        for party in [&maker, &trade.taker].iter() {
+            update_trader_stats(deps.storage, party, |stats| stats.disputes_opened += 1)?;
+        }
+    }
+    trade.state = state;
+    trades().save(deps.storage, info.sender.as_str(), &trade)?;
+
+    let res = Response::new()
+        .add_attribute("action", "update_trade_state")
+        .add_attribute("trade", trade.trade)
+        .add_attribute("state", trade.state.to_string());
     Ok(res)
 }
 
@@ -192,19 +818,199 @@ pub fn create_offer(
     info: MessageInfo,
     msg: OfferMsg,
 ) -> Result<Response, OfferError> {
-    assert_min_g_max(msg.min_amount, msg.max_amount)?;
+    let offer = store_new_offer(deps, &env, info.sender, msg)?;
 
-    let mut state = state_storage(deps.storage).load()?;
+    let res = offer_response("create_offer", &offer, None)?
+        .add_attribute("min_amount", offer.min_amount.to_string())
+        .add_attribute("max_amount", offer.max_amount.to_string());
+
+    Ok(res)
+}
+
+/// Attributes identifying `offer`, with the stored offer as the response data
+/// so callers learn the assigned id without querying.
+fn offer_response(
+    action: &str,
+    offer: &Offer,
+    from_state: Option<&OfferState>,
+) -> StdResult<Response> {
+    let res = Response::new()
+        .add_event(offer_event(action, offer, from_state))
+        .add_attribute("action", action)
+        .add_attribute("offer_id", offer.id.to_string())
+        .add_attribute("owner", offer.owner.to_string())
+        .add_attribute("offer_type", offer.offer_type.to_string())
+        .add_attribute("fiat_currency", offer.fiat_currency.to_string())
+        .set_data(to_binary(offer)?);
+    Ok(res)
+}
+
+/// All or nothing, the first invalid entry fails the whole batch.
+pub fn create_offers(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msgs: Vec<OfferMsg>,
+) -> Result<Response, OfferError> {
+    assert_batch_size(msgs.len())?;
+
+    let mut ids: Vec<String> = Vec::new();
+    let mut events: Vec<Event> = Vec::new();
+    for (index, msg) in msgs.into_iter().enumerate() {
+        let offer =
+            store_new_offer(deps.branch(), &env, info.sender.clone(), msg).map_err(|error| {
+                OfferError::BatchEntryFailed {
+                    index,
+                    error: Box::new(error),
+                }
+            })?;
+        ids.push(offer.id.to_string());
+        events.push(offer_event("create_offer", &offer, None));
+    }
+
+    let res = Response::new()
+        .add_events(events)
+        .add_attribute("action", "create_offers")
+        .add_attribute("ids", ids.join(","))
+        .add_attribute("owner", info.sender);
+
+    Ok(res)
+}
+
+/// Moves the caller's offers between active and paused in id order, offers
+/// already in state `to` and archived ones are skipped.
+pub fn change_all_offers(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    to: OfferState,
+) -> Result<Response, OfferError> {
+    let (from, action, offer_action) = match to {
+        OfferState::Active => (OfferState::Paused, "activate_all", "activate_offer"),
+        _ => (OfferState::Active, "pause_all", "pause_offer"),
+    };
+    let mut ids: Vec<u64> = offers()
+        .idx
+        .owner
+        .sub_prefix(info.sender.clone())
+        .range(deps.storage, None, None, Order::Ascending)
+        .flatten()
+        .filter(|(_, offer)| offer.state == from)
+        .map(|(_, offer)| offer.id)
+        .take(MAX_BULK_STATE_CHANGES + 1)
+        .collect();
+    let more = ids.len() > MAX_BULK_STATE_CHANGES;
+    ids.truncate(MAX_BULK_STATE_CHANGES);
+
+    let mut events: Vec<Event> = Vec::new();
+    for id in ids.iter() {
+        let mut offer_model = OfferModel::may_load(deps.storage, id)?;
+        let offer = match to {
+            OfferState::Active => offer_model.activate(env.block.time)?,
+            _ => offer_model.pause(env.block.time)?,
+        };
+        events.push(offer_event(offer_action, offer, Some(&from)));
+    }
 
-    let offer_id = state.offers_count + 1;
+    let res = Response::new()
+        .add_events(events)
+        .add_attribute("action", action)
+        .add_attribute("owner", info.sender)
+        .add_attribute("count", ids.len().to_string())
+        .add_attribute("continue", more.to_string());
+    Ok(res)
+}
+
+pub fn delete_offer(deps: DepsMut, info: MessageInfo, id: u64) -> Result<Response, OfferError> {
+    let cfg = config_read(deps.storage).load()?;
+    let offer_model = OfferModel::may_load(deps.storage, &id)?;
 
-    state.offers_count = offer_id;
+    assert_sender_is(&info.sender, &offer_model.offer.owner, "offer owner")?;
+    if !cfg.allow_delete_traded {
+        let traded = trades()
+            .idx
+            .maker
+            .sub_prefix(offer_model.offer.owner.clone())
+            .range(offer_model.storage, None, None, Order::Ascending)
+            .flatten()
+            .any(|(_, trade)| trade.offer_id == id);
+        if traded {
+            return Err(OfferError::HasTradeHistory { id });
+        }
+    }
+
+    let offer = offer_model.delete()?;
+
+    let res = Response::new()
+        .add_event(offer_event("delete_offer", &offer, Some(&offer.state)))
+        .add_attribute("action", "delete_offer")
+        .add_attribute("offer_id", offer.id.to_string())
+        .add_attribute("owner", offer.owner.to_string());
+    Ok(res)
+}
+
+pub fn clone_offer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    overrides: Option<OfferOverrides>,
+) -> Result<Response, OfferError> {
+    let source = OfferModel::may_load(deps.storage, &id)?.offer;
+
+    assert_sender_is(&info.sender, &source.owner, "offer owner")?;
+    if source.state == OfferState::Archived {
+        return Err(OfferError::CloneArchived { id });
+    }
+
+    let msg = overrides.unwrap_or_default().apply(source.to_msg());
+    let offer = store_new_offer(deps, &env, info.sender, msg)?;
+
+    let res =
+        offer_response("clone_offer", &offer, None)?.add_attribute("source_id", id.to_string());
+
+    Ok(res)
+}
+
+fn store_new_offer(
+    deps: DepsMut,
+    env: &Env,
+    owner: Addr,
+    msg: OfferMsg,
+) -> Result<Offer, OfferError> {
+    assert_min_g_max(msg.min_amount, msg.max_amount)?;
+    assert_expiration(msg.expires_at, env.block.time)?;
+    assert_schedule(msg.activate_at, msg.pause_at)?;
+    assert_trading_hours(msg.available_from_hour, msg.available_to_hour)?;
+    assert_price_margin(msg.price_margin_bps)?;
+    assert_payment_methods(&msg.payment_methods)?;
+    assert_description(&msg.description)?;
+    assert_maker_contact(&msg.maker_contact)?;
+
+    let cfg = config_read(deps.storage).load()?;
+    assert_asset(deps.as_ref(), &cfg, &msg.asset)?;
+    assert_fiat_currency(&cfg.fiat_currencies, &msg.fiat_currency)?;
+    //Fiat amounts are capped once converted at trade time
+    if !msg.amounts_in_fiat {
+        assert_max_trade_amount(&cfg, msg.max_amount)?;
+    }
+    assert_amount_ratio(msg.min_amount, msg.max_amount, cfg.max_amount_ratio)?;
+    if cfg.max_offers_per_owner > 0
+        && OfferModel::owner_count(deps.storage, &owner)? >= cfg.max_offers_per_owner as u64
+    {
+        return Err(OfferError::OfferMaxReached {
+            owner,
+            max: cfg.max_offers_per_owner,
+        });
+    }
+
+    let offer_id = next_offer_id(deps.storage)?;
 
     let offer = OfferModel::create(
         deps.storage,
         Offer {
             id: offer_id,
-            owner: info.sender.clone(),
+            owner,
             maker_contact: msg.maker_contact,
             offer_type: msg.offer_type,
             fiat_currency: msg.fiat_currency.clone(),
@@ -212,100 +1018,520 @@ pub fn create_offer(
             max_amount: msg.max_amount,
             state: OfferState::Active,
             timestamp: env.block.time.seconds(),
+            expires_at: msg.expires_at,
+            price_margin_bps: msg.price_margin_bps,
+            payment_methods: msg.payment_methods,
+            description: msg.description,
+            asset: msg.asset,
+            active_trades: 0,
+            reserved_amount: Uint128::zero(),
+            created_at: env.block.time,
+            last_updated: env.block.time,
+            min_trades_completed: msg.min_trades_completed,
+            total_available: msg.total_available,
+            remaining: msg.total_available,
+            activate_at: msg.activate_at,
+            pause_at: msg.pause_at,
+            amounts_in_fiat: msg.amounts_in_fiat,
+            available_from_hour: msg.available_from_hour,
+            available_to_hour: msg.available_to_hour,
+            effective_state: None,
+            currently_available: None,
         },
     )
     .offer;
 
-    state_storage(deps.storage).save(&state)?;
+    Ok(offer)
+}
 
-    let res = Response::new()
-        .add_attribute("action", "create_offer")
-        .add_attribute("type", offer.offer_type.to_string())
-        .add_attribute("id", offer.id.to_string())
-        .add_attribute("min_amount", offer.min_amount.to_string())
-        .add_attribute("max_amount", offer.max_amount.to_string())
-        .add_attribute("owner", offer.owner);
+pub fn activate_offer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, OfferError> {
+    let mut offer_model = OfferModel::may_load(deps.storage, &id)?;
 
-    Ok(res)
+    assert_offer_manager(offer_model.storage, &info.sender, &offer_model.offer.owner)?;
+
+    let from_state = offer_model.offer.state.clone();
+    let offer = offer_model.activate(env.block.time)?;
+
+    Ok(offer_response("activate_offer", offer, Some(&from_state))?)
 }
 
-pub fn activate_offer(
+pub fn pause_offer(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     id: u64,
 ) -> Result<Response, OfferError> {
-    let mut offer_model = OfferModel::may_load(deps.storage, &id);
+    let mut offer_model = OfferModel::may_load(deps.storage, &id)?;
 
-    assert_ownership(info.sender, offer_model.offer.owner.clone())?;
+    assert_offer_manager(offer_model.storage, &info.sender, &offer_model.offer.owner)?;
 
-    let offer = offer_model.activate()?;
+    let from_state = offer_model.offer.state.clone();
+    let offer = offer_model.pause(env.block.time)?;
+
+    Ok(offer_response("pause_offer", offer, Some(&from_state))?)
+}
+
+pub fn archive_offer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, OfferError> {
+    let mut offer_model = OfferModel::may_load(deps.storage, &id)?;
+
+    assert_offer_manager(offer_model.storage, &info.sender, &offer_model.offer.owner)?;
+
+    let from_state = offer_model.offer.state.clone();
+    let offer = offer_model.archive(env.block.time)?;
 
     let res = Response::new()
-        .add_attribute("action", "activate_offer")
+        .add_event(offer_event("archive_offer", offer, Some(&from_state)))
+        .add_attribute("action", "archive_offer")
         .add_attribute("id", offer.id.to_string())
         .add_attribute("owner", offer.owner.to_string());
 
     Ok(res)
 }
 
-pub fn pause_offer(
+/// Callers check the admin sent the update, chain governance skips it.
+pub fn update_config(deps: DepsMut, update: ConfigUpdate) -> Result<Response, OfferError> {
+    let old_cfg = config_read(deps.storage).load()?;
+    let mut cfg = old_cfg.clone();
+
+    if let Some(factory_addr) = update.factory_addr {
+        cfg.factory_addr = validate_addr(deps.api, "factory_addr", &factory_addr)?;
+    }
+    if let Some(max_offers_per_owner) = update.max_offers_per_owner {
+        cfg.max_offers_per_owner = max_offers_per_owner;
+    }
+    if let Some(price_oracle_addr) = update.price_oracle_addr {
+        cfg.price_oracle_addr = Some(validate_addr(
+            deps.api,
+            "price_oracle_addr",
+            &price_oracle_addr,
+        )?);
+    }
+    if let Some(denoms) = update.denoms {
+        cfg.denoms = denoms;
+    }
+    if let Some(trade_duration) = update.trade_duration {
+        cfg.trade_duration = trade_duration;
+    }
+    if let Some(arbitrator) = update.arbitrator {
+        cfg.arbitrator = Some(validate_addr(deps.api, "arbitrator", &arbitrator)?);
+    }
+    if let Some(max_trade_amount) = update.max_trade_amount {
+        cfg.max_trade_amount = max_trade_amount;
+    }
+    if let Some(fiat_currencies) = update.fiat_currencies {
+        cfg.fiat_currencies = fiat_currencies;
+    }
+    if let Some(max_concurrent_trades) = update.max_concurrent_trades {
+        cfg.max_concurrent_trades = max_concurrent_trades;
+    }
+    if let Some(dispute_window) = update.dispute_window {
+        cfg.dispute_window = dispute_window;
+    }
+    if let Some(new_trade_limit) = update.new_trade_limit {
+        cfg.new_trade_limit = new_trade_limit;
+    }
+    if let Some(new_trade_window) = update.new_trade_window {
+        cfg.new_trade_window = new_trade_window;
+    }
+    if let Some(allow_delete_traded) = update.allow_delete_traded {
+        cfg.allow_delete_traded = allow_delete_traded;
+    }
+    if let Some(max_amount_ratio) = update.max_amount_ratio {
+        cfg.max_amount_ratio = max_amount_ratio;
+    }
+    if let Some(max_price_age) = update.max_price_age {
+        cfg.max_price_age = max_price_age;
+    }
+    config_storage(deps.storage).save(&cfg)?;
+
+    let res = Response::new()
+        .add_attribute("action", "update_config")
+        .add_attributes(config_attributes("old_", &old_cfg))
+        .add_attributes(config_attributes("", &cfg));
+
+    Ok(res)
+}
+
+/// Every config field as a `prefix`ed attribute, unset addresses are empty.
+fn config_attributes(prefix: &str, cfg: &Config) -> Vec<(String, String)> {
+    let optional = |addr: &Option<Addr>| addr.as_ref().map_or(String::new(), Addr::to_string);
+    vec![
+        ("factory_addr", cfg.factory_addr.to_string()),
+        ("admin", cfg.admin.to_string()),
+        ("max_offers_per_owner", cfg.max_offers_per_owner.to_string()),
+        ("price_oracle_addr", optional(&cfg.price_oracle_addr)),
+        ("denoms", cfg.denoms.join(",")),
+        ("trade_duration", cfg.trade_duration.to_string()),
+        ("arbitrator", optional(&cfg.arbitrator)),
+        ("pending_admin", optional(&cfg.pending_admin)),
+        ("max_trade_amount", cfg.max_trade_amount.to_string()),
+        (
+            "fiat_currencies",
+            cfg.fiat_currencies
+                .iter()
+                .map(FiatCurrency::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+        (
+            "max_concurrent_trades",
+            cfg.max_concurrent_trades.to_string(),
+        ),
+        ("dispute_window", cfg.dispute_window.to_string()),
+        ("new_trade_limit", cfg.new_trade_limit.to_string()),
+        ("new_trade_window", cfg.new_trade_window.to_string()),
+        ("allow_delete_traded", cfg.allow_delete_traded.to_string()),
+        ("max_amount_ratio", cfg.max_amount_ratio.to_string()),
+        ("max_price_age", cfg.max_price_age.to_string()),
+    ]
+    .into_iter()
+    //Empty attribute values are refused by the chain, unset fields are left out
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(key, value)| (format!("{}{}", prefix, key), value))
+    .collect()
+}
+
+pub fn propose_new_admin(
     deps: DepsMut,
-    _env: Env,
     info: MessageInfo,
-    id: u64,
+    new_admin: String,
 ) -> Result<Response, OfferError> {
-    let mut offer_model = OfferModel::may_load(deps.storage, &id);
+    let mut cfg = config_read(deps.storage).load()?;
+    assert_sender_is(&info.sender, &cfg.admin, "admin")?;
 
-    assert_ownership(info.sender, offer_model.offer.owner.clone())?;
+    let new_admin = validate_addr(deps.api, "new_admin", &new_admin)?;
+    cfg.pending_admin = Some(new_admin.clone());
+    config_storage(deps.storage).save(&cfg)?;
 
-    let offer = offer_model.pause()?;
+    let res = Response::new()
+        .add_attribute("action", "propose_new_admin")
+        .add_attribute("admin", cfg.admin)
+        .add_attribute("pending_admin", new_admin);
+
+    Ok(res)
+}
+
+pub fn cancel_admin_proposal(deps: DepsMut, info: MessageInfo) -> Result<Response, OfferError> {
+    let mut cfg = config_read(deps.storage).load()?;
+    assert_sender_is(&info.sender, &cfg.admin, "admin")?;
+
+    let pending_admin = cfg
+        .pending_admin
+        .take()
+        .ok_or(OfferError::NoPendingAdmin {})?;
+    config_storage(deps.storage).save(&cfg)?;
 
     let res = Response::new()
-        .add_attribute("action", "pause_offer")
-        .add_attribute("id", offer.id.to_string())
-        .add_attribute("owner", offer.owner.to_string());
+        .add_attribute("action", "cancel_admin_proposal")
+        .add_attribute("pending_admin", pending_admin);
 
     Ok(res)
 }
 
+pub fn accept_admin(deps: DepsMut, info: MessageInfo) -> Result<Response, OfferError> {
+    let mut cfg = config_read(deps.storage).load()?;
+    let pending_admin = cfg
+        .pending_admin
+        .take()
+        .ok_or(OfferError::NoPendingAdmin {})?;
+    assert_sender_is(&info.sender, &pending_admin, "pending admin")?;
+
+    let old_admin = std::mem::replace(&mut cfg.admin, pending_admin);
+    config_storage(deps.storage).save(&cfg)?;
+
+    let res = Response::new()
+        .add_attribute("action", "accept_admin")
+        .add_attribute("old_admin", old_admin)
+        .add_attribute("admin", cfg.admin);
+
+    Ok(res)
+}
+
+pub fn set_pause(deps: DepsMut, paused: bool) -> Result<Response, OfferError> {
+    let mut state = state_storage(deps.storage).load()?;
+    state.paused = paused;
+    state_storage(deps.storage).save(&state)?;
+
+    let res = Response::new()
+        .add_attribute("action", "set_pause")
+        .add_attribute("paused", paused.to_string());
+
+    Ok(res)
+}
+
+pub fn set_blacklist(deps: DepsMut, addr: String, blocked: bool) -> Result<Response, OfferError> {
+    let addr = validate_addr(deps.api, "addr", &addr)?;
+
+    if blocked {
+        BLACKLIST.save(deps.storage, &addr, &true)?;
+    } else {
+        BLACKLIST.remove(deps.storage, &addr);
+    }
+
+    let res = Response::new()
+        .add_attribute("action", "set_blacklist")
+        .add_attribute("addr", addr)
+        .add_attribute("blocked", blocked.to_string());
+
+    Ok(res)
+}
+
+fn assert_admin(deps: Deps, info: &MessageInfo) -> Result<(), OfferError> {
+    let cfg = config_read(deps.storage).load()?;
+    assert_sender_is(&info.sender, &cfg.admin, "admin")
+}
+
+fn assert_max_trade_amount(cfg: &Config, amount: Uint128) -> Result<(), OfferError> {
+    if !cfg.max_trade_amount.is_zero() && amount > cfg.max_trade_amount {
+        return Err(OfferError::MaxTradeAmountExceeded {
+            amount,
+            max_trade_amount: cfg.max_trade_amount,
+        });
+    }
+    Ok(())
+}
+
+fn assert_not_paused(storage: &dyn Storage) -> Result<(), OfferError> {
+    if state_read(storage).may_load()?.unwrap_or_default().paused {
+        return Err(OfferError::ContractPaused {});
+    }
+    Ok(())
+}
+
+pub fn set_operator(
+    deps: DepsMut,
+    info: MessageInfo,
+    operator: String,
+    enabled: bool,
+) -> Result<Response, OfferError> {
+    let operator = validate_addr(deps.api, "operator", &operator)?;
+    let key = (info.sender.as_bytes(), operator.as_bytes());
+    if enabled {
+        OPERATORS.save(deps.storage, key, &true)?;
+    } else {
+        OPERATORS.remove(deps.storage, key);
+    }
+
+    let res = Response::new()
+        .add_attribute("action", "set_operator")
+        .add_attribute("owner", info.sender)
+        .add_attribute("operator", operator)
+        .add_attribute("enabled", enabled.to_string());
+    Ok(res)
+}
+
+/// Offers are managed by their owner or one of the owner's operators.
+fn assert_offer_manager(
+    storage: &dyn Storage,
+    sender: &Addr,
+    owner: &Addr,
+) -> Result<(), OfferError> {
+    let is_operator = OPERATORS
+        .may_load(storage, (owner.as_bytes(), sender.as_bytes()))?
+        .unwrap_or_default();
+    if is_operator {
+        return Ok(());
+    }
+    assert_sender_is(sender, owner, "offer owner")
+}
+
+fn assert_not_blocked(storage: &dyn Storage, addrs: &[&Addr]) -> Result<(), OfferError> {
+    for addr in addrs {
+        if BLACKLIST.may_load(storage, addr)?.unwrap_or_default() {
+            return Err(OfferError::Blocked {});
+        }
+    }
+    Ok(())
+}
+
 pub fn update_offer(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     id: u64,
     msg: OfferMsg,
 ) -> Result<Response, OfferError> {
-    assert_min_g_max(msg.min_amount, msg.max_amount)?;
+    let offer = store_offer_update(deps, &env, info.sender, id, msg)?;
 
-    let mut offer_model = OfferModel::may_load(deps.storage, &id);
+    Ok(offer_response("update_offer", &offer, Some(&offer.state))?)
+}
 
-    assert_ownership(info.sender, offer_model.offer.owner.clone())?;
+/// All or nothing, the first invalid entry fails the whole batch.
+pub fn update_offers(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    updates: Vec<(u64, OfferMsg)>,
+) -> Result<Response, OfferError> {
+    assert_batch_size(updates.len())?;
 
-    let offer = offer_model.update(msg);
+    let mut ids: Vec<String> = Vec::new();
+    let mut events: Vec<Event> = Vec::new();
+    for (index, (id, msg)) in updates.into_iter().enumerate() {
+        let offer = store_offer_update(deps.branch(), &env, info.sender.clone(), id, msg).map_err(
+            |error| OfferError::BatchEntryFailed {
+                index,
+                error: Box::new(error),
+            },
+        )?;
+        ids.push(id.to_string());
+        events.push(offer_event("update_offer", &offer, Some(&offer.state)));
+    }
 
     let res = Response::new()
-        .add_attribute("action", "pause_offer")
-        .add_attribute("id", offer.id.to_string())
-        .add_attribute("owner", offer.owner.to_string());
+        .add_events(events)
+        .add_attribute("action", "update_offers")
+        .add_attribute("ids", ids.join(","))
+        .add_attribute("owner", info.sender);
 
     Ok(res)
 }
 
+fn store_offer_update(
+    deps: DepsMut,
+    env: &Env,
+    sender: Addr,
+    id: u64,
+    msg: OfferMsg,
+) -> Result<Offer, OfferError> {
+    assert_min_g_max(msg.min_amount, msg.max_amount)?;
+    assert_expiration(msg.expires_at, env.block.time)?;
+    assert_schedule(msg.activate_at, msg.pause_at)?;
+    assert_trading_hours(msg.available_from_hour, msg.available_to_hour)?;
+    assert_price_margin(msg.price_margin_bps)?;
+    assert_payment_methods(&msg.payment_methods)?;
+    assert_description(&msg.description)?;
+    assert_maker_contact(&msg.maker_contact)?;
+    let cfg = config_read(deps.storage).load()?;
+    assert_asset(deps.as_ref(), &cfg, &msg.asset)?;
+    assert_fiat_currency(&cfg.fiat_currencies, &msg.fiat_currency)?;
+    //Fiat amounts are capped once converted at trade time
+    if !msg.amounts_in_fiat {
+        assert_max_trade_amount(&cfg, msg.max_amount)?;
+    }
+    assert_amount_ratio(msg.min_amount, msg.max_amount, cfg.max_amount_ratio)?;
+
+    let mut offer_model = OfferModel::may_load(deps.storage, &id)?;
+
+    assert_offer_manager(offer_model.storage, &sender, &offer_model.offer.owner)?;
+    assert_not_blocked(offer_model.storage, &[&offer_model.offer.owner])?;
+
+    Ok(offer_model.update(msg, env.block.time)?.clone())
+}
+
+fn assert_batch_size(len: usize) -> Result<(), OfferError> {
+    if len > MAX_BATCH_SIZE {
+        return Err(OfferError::BatchTooLarge {
+            len,
+            max: MAX_BATCH_SIZE,
+        });
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn create_trade(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     offer_id: u64,
-    ust_amount: String,
+    amount: Uint128,
     taker: String,
     taker_contact: String,
-    arbitrator: String,
+    multisig_release: bool,
 ) -> Result<Response, OfferError> {
-    let cfg = config_read(deps.storage).load().unwrap();
-    // let offer = load_offer_by_id(deps.storage, offer_id).unwrap();
-    let offer = OfferModel::from_store(deps.storage, &offer_id);
-    //     .ok_or(OfferError::InvalidReply {})?; // TODO choose better error
+    let taker = validate_addr(deps.api, "taker", &taker)?;
+    let cfg = config_read(deps.storage).load()?;
+    let offer = OfferModel::from_store(deps.storage, &offer_id)?;
+    assert_not_self_trade(&offer.owner, &taker)?;
+    assert_not_blocked(deps.storage, &[&taker, &offer.owner])?;
+    let state = offer.state_at(env.block.time);
+    if state != OfferState::Active {
+        return Err(OfferError::OfferNotActive {
+            id: offer.id,
+            state,
+        });
+    }
+    if offer.is_expired(env.block.time) {
+        return Err(OfferError::Expired {
+            id: offer.id,
+            expired_at: offer.expires_at.unwrap(),
+        });
+    }
+    if let (Some(from_hour), Some(to_hour)) = (offer.available_from_hour, offer.available_to_hour) {
+        if !offer.is_available_at(env.block.time) {
+            return Err(OfferError::OutsideTradingHours {
+                id: offer.id,
+                from_hour,
+                to_hour,
+            });
+        }
+    }
+    if amount < offer.min_amount || amount > offer.max_amount {
+        return Err(OfferError::TradeAmountOutOfRange {
+            amount,
+            min_amount: offer.min_amount,
+            max_amount: offer.max_amount,
+        });
+    }
+    //Offers created before the limit was lowered are still capped
+    if !offer.amounts_in_fiat {
+        assert_max_trade_amount(&cfg, amount)?;
+    }
+    //Counted in on the instantiate reply, failed instantiations don't count
+    if cfg.max_concurrent_trades > 0 && offer.active_trades >= cfg.max_concurrent_trades.into() {
+        return Err(OfferError::TooManyOpenTrades {
+            offer_id: offer.id,
+            max: cfg.max_concurrent_trades,
+        });
+    }
+    if amount > offer.available_amount() {
+        return Err(OfferError::InsufficientLiquidity {
+            offer_id: offer.id,
+            available: offer.available_amount(),
+        });
+    }
+    if let Some(required) = offer.min_trades_completed {
+        let actual = COMPLETED_TRADES
+            .may_load(deps.storage, &taker)?
+            .unwrap_or_default();
+        if actual < required {
+            return Err(OfferError::NotEnoughCompletedTrades {
+                taker,
+                required,
+                actual,
+            });
+        }
+    }
+    count_new_trade(deps.storage, &cfg, &info.sender, env.block.time)?;
+    let fiat_price = match offer.price_margin_bps.is_some() || offer.amounts_in_fiat {
+        true => Some(query_fiat_price(
+            deps.as_ref(),
+            env.block.time,
+            &cfg,
+            &offer,
+        )?),
+        false => None,
+    };
+    //Fiat denominated trades escrow what the requested fiat buys at that price
+    let (amount, fiat_amount) = match fiat_price {
+        Some(price) if offer.amounts_in_fiat => {
+            let converted = minor_units_to_denom(amount, &offer.fiat_currency, price)?;
+            assert_max_trade_amount(&cfg, converted)?;
+            (converted, Some(amount))
+        }
+        _ => (amount, None),
+    };
 
     let factory_cfg = get_factory_config(&deps.querier, cfg.factory_addr.to_string());
 
@@ -314,50 +1540,213 @@ fn create_trade(
         code_id: factory_cfg.trade_code_id,
         msg: to_binary(&TradeInstantiateMsg {
             offer_id,
-            ust_amount: ust_amount.clone(),
-            taker: taker.clone(),
+            amount,
+            asset: offer.asset.clone(),
+            taker: taker.to_string(),
             taker_contact,
-            arbitrator,
             offers_addr: env.contract.address.to_string(),
             timestamp: env.block.time.seconds(),
+            fiat_price,
+            fiat_amount,
+            multisig_release,
         })
         .unwrap(),
         funds: info.funds,
         label: "new-trade".to_string(),
     };
-    let sub_message = SubMsg {
-        id: 0,
-        msg: CosmosMsg::Wasm(instantiate_msg),
-        gas_limit: None,
-        reply_on: ReplyOn::Success,
-    };
+    let sub_message = SubMsg::reply_on_success(instantiate_msg, NEW_TRADE_REPLY_ID);
 
-    let res = Response::new()
+    let mut res = Response::new()
         .add_submessage(sub_message)
         .add_attribute("action", "create_trade")
         .add_attribute("id", offer.id.to_string())
         .add_attribute("owner", offer.owner.to_string())
-        .add_attribute("ust_amount", ust_amount)
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("asset", offer.asset.to_string())
         .add_attribute("taker", taker);
+    //Only priced offers have a price, and only offers in fiat a fiat amount,
+    //empty attribute values are refused
+    if let Some(price) = fiat_price {
+        res = res.add_attribute("fiat_price", price.to_string());
+    }
+    if let Some(amount) = fiat_amount {
+        res = res.add_attribute("fiat_amount", amount.to_string());
+    }
     Ok(res)
 }
 
+/// Counts a new trade opened by `sender` in the current `new_trade_window`
+/// bucket, failing once `new_trade_limit` are counted there. Keyed on the
+/// sender rather than the `taker` named in the message, which the sender
+/// could vary to dodge the limit.
+fn count_new_trade(
+    storage: &mut dyn Storage,
+    cfg: &Config,
+    sender: &Addr,
+    block_time: Timestamp,
+) -> Result<(), OfferError> {
+    if cfg.new_trade_limit == 0 || cfg.new_trade_window == 0 {
+        return Ok(());
+    }
+    let bucket = block_time.seconds() / cfg.new_trade_window;
+    let count = match NEW_TRADES.may_load(storage, sender)? {
+        Some((counted_bucket, count)) if counted_bucket == bucket => count,
+        _ => 0,
+    };
+    if count >= cfg.new_trade_limit {
+        return Err(OfferError::TradeRateLimited {
+            sender: sender.clone(),
+            limit: cfg.new_trade_limit,
+            retry_at: Timestamp::from_seconds((bucket + 1) * cfg.new_trade_window),
+        });
+    }
+    NEW_TRADES.save(storage, sender, &(bucket, count + 1))?;
+    Ok(())
+}
+
+/// Native denoms have to be whitelisted and IBC vouchers trusted by the
+/// factory, cw20 contracts have to answer a `TokenInfo` query so trades never
+/// escrow an address that is not a token.
+fn assert_asset(deps: Deps, cfg: &Config, asset: &OfferAsset) -> Result<(), OfferError> {
+    match asset {
+        OfferAsset::Native { denom } if is_ibc_denom(denom) => {
+            get_ibc_denom(&deps.querier, cfg.factory_addr.to_string(), denom.clone())?
+                .map(|_| ())
+                .ok_or_else(|| OfferError::UntrustedIbcDenom {
+                    denom: denom.clone(),
+                })
+        }
+        OfferAsset::Native { denom } => assert_denom(&cfg.denoms, denom),
+        OfferAsset::Cw20 { contract_addr } => {
+            let invalid = || OfferError::InvalidCw20 {
+                contract_addr: contract_addr.to_string(),
+            };
+            deps.api
+                .addr_validate(contract_addr.as_str())
+                .map_err(|_| invalid())?;
+            let _: TokenInfoResponse = deps
+                .querier
+                .query_wasm_smart(contract_addr.to_string(), &Cw20QueryMsg::TokenInfo {})
+                .map_err(|_| invalid())?;
+            Ok(())
+        }
+    }
+}
+
+/// Effective price of a floating price offer, the plain oracle rate for
+/// other offers. A failing oracle, a zero rate or one older than
+/// `Config::max_price_age` refuses the trade instead of opening it at a wrong
+/// price.
+fn query_fiat_price(
+    deps: Deps,
+    block_time: Timestamp,
+    cfg: &Config,
+    offer: &Offer,
+) -> Result<Decimal, OfferError> {
+    let unavailable = || OfferError::PriceUnavailable {
+        fiat_currency: offer.fiat_currency.clone(),
+    };
+    let oracle_addr = cfg.price_oracle_addr.clone().ok_or_else(unavailable)?;
+
+    let rate = query_price(
+        &deps.querier,
+        &oracle_addr,
+        offer.fiat_currency.clone(),
+        offer.asset.to_string(),
+    )
+    .map_err(|_| unavailable())?;
+    let age = block_time
+        .seconds()
+        .saturating_sub(rate.updated_at.seconds());
+    if age > cfg.max_price_age {
+        return Err(unavailable());
+    }
+
+    let price = offer.price(rate.price).map_err(|_| unavailable())?;
+    match price.unwrap_or(rate.price) {
+        price if price.is_zero() => Err(unavailable()),
+        price => Ok(price),
+    }
+}
+
 fn query_config(deps: Deps) -> StdResult<Config> {
     let cfg = config_read(deps.storage).load().unwrap();
     Ok(cfg)
 }
 
-fn query_state(deps: Deps) -> StdResult<State> {
-    let state = state_read(deps.storage).load().unwrap();
-    Ok(state)
+fn query_supported_currencies(deps: Deps) -> StdResult<Vec<FiatCurrencyInfo>> {
+    let cfg = config_read(deps.storage).load()?;
+    Ok(cfg
+        .fiat_currencies
+        .into_iter()
+        .map(FiatCurrencyInfo::from)
+        .collect())
+}
+
+fn query_blacklist(
+    deps: Deps,
+    last_value: Option<Addr>,
+    limit: u32,
+) -> StdResult<BlacklistResponse> {
+    let limit = page_limit(limit);
+    let min = last_value.map(|addr| Bound::exclusive(addr.as_str()));
+    let addrs = BLACKLIST
+        .keys(deps.storage, min, None, Order::Ascending)
+        .take(limit)
+        .map(|key| Ok(Addr::unchecked(String::from_utf8(key)?)))
+        .collect::<StdResult<Vec<Addr>>>()?;
+
+    Ok(BlacklistResponse {
+        next: page_next(&addrs, limit, Addr::clone),
+        addrs,
+        limit: limit as u32,
+    })
+}
+
+fn query_export_trades(
+    deps: Deps,
+    start_after: Option<Addr>,
+    limit: u32,
+) -> StdResult<TradesExport> {
+    let limit = page_limit(limit);
+    let min = start_after.map(|addr| Bound::exclusive(addr.as_str()));
+    let trades = trades()
+        .range(deps.storage, min, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, trade)| trade))
+        .collect::<StdResult<Vec<TradeAddr>>>()?;
+    let next = page_next(&trades, limit, |trade| trade.trade.clone());
+    let total = REGISTERED_TRADES
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    Ok(TradesExport {
+        trades,
+        next,
+        limit: limit as u32,
+        total,
+    })
+}
+
+fn query_state(deps: Deps) -> StdResult<StateResponse> {
+    let state = state_read(deps.storage).may_load()?.unwrap_or_default();
+    let fiat_offers_counts = OfferModel::query_counts(deps.storage)?;
+
+    Ok(StateResponse {
+        offers_count: state.offers_count,
+        last_offer_id: Some(state.offers_count).filter(|id| *id > 0),
+        paused: state.paused,
+        active_offers_count: fiat_offers_counts.iter().map(|counts| counts.active).sum(),
+        paused_offers_count: fiat_offers_counts.iter().map(|counts| counts.paused).sum(),
+        fiat_offers_counts,
+    })
 }
 
 pub fn load_offer_by_id(storage: &dyn Storage, id: u64) -> StdResult<Offer> {
-    let offer = offers()
-        .may_load(storage, &id.to_string())
-        .unwrap_or_default()
-        .unwrap();
-    Ok(offer)
+    OfferModel::from_store(storage, &id).map_err(|err| match err {
+        OfferError::Std(std_err) => std_err,
+        _ => StdError::not_found(format!("Offer {}", id)),
+    })
 }
 
 pub fn query_trades(
@@ -368,25 +1757,13 @@ pub fn query_trades(
     index: TradesIndex,
     last_value: Option<Addr>,
     limit: u32,
-) -> StdResult<Vec<TradeInfo>> {
-    let curr_height = env.block.height;
-
-    let mut trades_infos: Vec<TradeInfo> = vec![];
-
-    // Pagination range (TODO pagination doesn't work with Addr as pk)
-    let range_from = match last_value {
-        Some(addr) => {
-            let valid_addr = deps.api.addr_validate(addr.as_str()).unwrap();
-            Some(Bound::Exclusive(Vec::from(valid_addr.to_string())))
-        }
-        None => None,
-    };
-
+) -> StdResult<TradesResponse> {
     // Select correct index for data lookup
     // * The `state<TradeState>` filter only supported for `user == arbitrator` queries
     let prefix = match index {
         TradesIndex::Seller => trades().idx.sender.prefix(user),
         TradesIndex::Buyer => trades().idx.recipient.prefix(user),
+        TradesIndex::Taker => trades().idx.taker.prefix(user),
         TradesIndex::ArbitratorState => match state {
             Some(state) => trades()
                 .idx
@@ -396,12 +1773,168 @@ pub fn query_trades(
         },
     };
 
+    load_trade_infos(env, deps, prefix, last_value, limit)
+}
+
+pub fn query_operators(deps: Deps, owner: Addr) -> StdResult<Vec<Addr>> {
+    OPERATORS
+        .prefix(owner.as_bytes())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|key| {
+            String::from_utf8(key)
+                .map(Addr::unchecked)
+                .map_err(StdError::invalid_utf8)
+        })
+        .collect()
+}
+
+pub fn query_profile(deps: Deps, block_time: Timestamp, addr: Addr) -> StdResult<ProfileResponse> {
+    let open_trades = OPEN_TRADES
+        .may_load(deps.storage, &addr)?
+        .unwrap_or_default();
+
+    Ok(ProfileResponse {
+        stats: TRADER_STATS
+            .may_load(deps.storage, &addr)?
+            .unwrap_or_default(),
+        offers_count: OfferModel::owner_count(deps.storage, &addr)?,
+        recent_offers: OfferModel::query_recent_by_owner(deps.storage, block_time, addr)?,
+        open_trades,
+    })
+}
+
+/// Checks the amount like `NewTrade` and splits the fees like trades do on
+/// funding and an undisputed release, at the fee rates and taxes a trade
+/// opened now would get.
+pub fn query_simulate_trade(
+    deps: Deps,
+    block_time: Timestamp,
+    offer_id: u64,
+    amount: Uint128,
+) -> StdResult<TradeSimulation> {
+    let cfg = config_read(deps.storage).load()?;
+    let offer = load_offer_by_id(deps.storage, offer_id)?;
+    let refused = |err: OfferError| StdError::generic_err(err.to_string());
+    if amount < offer.min_amount || amount > offer.max_amount {
+        return Err(refused(OfferError::TradeAmountOutOfRange {
+            amount,
+            min_amount: offer.min_amount,
+            max_amount: offer.max_amount,
+        }));
+    }
+    let amount = match offer.amounts_in_fiat {
+        true => query_fiat_price(deps, block_time, &cfg, &offer)
+            .and_then(|price| minor_units_to_denom(amount, &offer.fiat_currency, price))
+            .map_err(refused)?,
+        false => amount,
+    };
+    assert_max_trade_amount(&cfg, amount).map_err(refused)?;
+
+    let factory_cfg = get_factory_config(&deps.querier, cfg.factory_addr.to_string());
+    let fees = TradeFees::new(amount, factory_cfg.maker_fee_bps, factory_cfg.taker_fee_bps)?;
+    let fee_amount = fees.total();
+    Ok(TradeSimulation {
+        offer_id,
+        amount,
+        maker_fee_bps: factory_cfg.maker_fee_bps,
+        taker_fee_bps: factory_cfg.taker_fee_bps,
+        fee_burn_bps: factory_cfg.fee_burn_bps,
+        maker_fee_amount: fees.maker_fee_amount,
+        taker_fee_amount: fees.taker_fee_amount,
+        fee_amount,
+        burn_amount: fee_burn_amount(fee_amount, factory_cfg.fee_burn_bps),
+        escrow_amount: fees.escrow_amount(
+            &deps.querier,
+            &offer.asset,
+            &offer.offer_type,
+            amount,
+        )?,
+        receive_amount: fees.release_amount(
+            &deps.querier,
+            &offer.asset,
+            &offer.offer_type,
+            amount,
+            Uint128::zero(),
+        )?,
+        asset: offer.asset,
+    })
+}
+
+pub fn query_trades_by_state(
+    env: Env,
+    deps: Deps,
+    state: TradeState,
+    last_value: Option<Addr>,
+    limit: u32,
+) -> StdResult<TradesResponse> {
+    let prefix = trades().idx.state.prefix(state.to_string());
+    load_trade_infos(env, deps, prefix, last_value, limit)
+}
+
+fn load_trade_infos(
+    env: Env,
+    deps: Deps,
+    prefix: Prefix<TradeAddr>,
+    last_value: Option<Addr>,
+    limit: u32,
+) -> StdResult<TradesResponse> {
+    // Pagination range (TODO pagination doesn't work with Addr as pk)
+    let range_from = match last_value {
+        Some(addr) => {
+            let valid_addr = deps.api.addr_validate(addr.as_str()).unwrap();
+            Some(Bound::Exclusive(Vec::from(valid_addr.to_string())))
+        }
+        None => None,
+    };
+
+    let limit = page_limit(limit);
     let trade_results: Vec<TradeAddr> = prefix
         .range(deps.storage, range_from, None, Order::Ascending)
-        .flat_map(|item| item.and_then(|(_, offer)| Ok(offer)))
-        .take(limit as usize)
+        .flat_map(|item| item.map(|(_, offer)| offer))
+        .take(limit)
         .collect();
 
+    Ok(TradesResponse {
+        next: page_next(&trade_results, limit, |trade| trade.trade.clone()),
+        trades: trade_infos(env, deps, trade_results),
+        limit: limit as u32,
+    })
+}
+
+/// Trades on `maker`'s offers ordered by `(created_at, address)` descending,
+/// so later trades only ever land before a page that was already served.
+pub fn query_trades_page(
+    env: Env,
+    deps: Deps,
+    maker: String,
+    last_value: Option<Vec<u8>>,
+    limit: u32,
+) -> StdResult<TradesPageResponse> {
+    let maker = deps.api.addr_validate(&maker)?;
+    // Index keys are the length prefixed `created_at` followed by the address
+    let range_to =
+        last_value.map(|cursor| Bound::Exclusive([&[0u8, 8], cursor.as_slice()].concat()));
+
+    let limit = page_limit(limit);
+    let trade_results: Vec<TradeAddr> = trades()
+        .idx
+        .maker
+        .sub_prefix(maker)
+        .range(deps.storage, None, range_to, Order::Descending)
+        .take(limit)
+        .map(|item| item.map(|(_, trade)| trade))
+        .collect::<StdResult<Vec<_>>>()?;
+    let trades = trade_infos(env, deps, trade_results);
+
+    Ok(TradesPageResponse {
+        next: page_next(&trades, limit, TradeInfo::page_cursor),
+        trades,
+        limit: limit as u32,
+    })
+}
+
+fn trade_infos(env: Env, deps: Deps, trade_results: Vec<TradeAddr>) -> Vec<TradeInfo> {
+    let mut trades_infos: Vec<TradeInfo> = vec![];
     trade_results.iter().for_each(|t| {
         let trade_state: TradeData = deps
             .querier
@@ -410,23 +1943,24 @@ pub fn query_trades(
                 msg: to_binary(&TradeQueryMsg::State {}).unwrap(),
             }))
             .unwrap();
-        let offer: Offer = deps
-            .querier
-            .query(&QueryRequest::Wasm(WasmQuery::Smart {
-                contract_addr: trade_state.offer_contract.to_string(),
-                msg: to_binary(&QueryMsg::Offer {
-                    id: trade_state.offer_id,
-                })
-                .unwrap(),
-            }))
-            .unwrap();
+        //Trades are registered by this contract, their offer may have been deleted since
+        let offer = load_offer_by_id(deps.storage, trade_state.offer_id)
+            .ok()
+            .map(|offer| offer.with_effective_state(env.block.time));
 
-        let expired = curr_height >= trade_state.expire_height;
+        let expired = trade_state.is_expired(env.block.time);
         trades_infos.push(TradeInfo {
+            trade_addr: t.trade.clone(),
+            offer_id: trade_state.offer_id,
+            created_at: t.created_at,
+            amount: trade_state.amount,
+            denom: trade_state.asset.to_string(),
+            counterparty: t.taker.clone(),
+            expires_at: trade_state.expires_at,
             trade: trade_state,
             offer,
             expired,
         })
     });
-    Ok(trades_infos)
+    trades_infos
 }
@@ -6,13 +6,16 @@ use serde::{Deserialize, Serialize};
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
     from_binary, from_slice, to_binary, Addr, Coin, ContractResult, Decimal, OwnedDeps, Querier,
-    QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
+    QuerierResult, QueryRequest, SystemError, SystemResult, Timestamp, Uint128, WasmQuery,
 };
-use cw20::BalanceResponse;
+use cw20::{BalanceResponse, TokenInfoResponse};
 use localterra_protocol::currencies::FiatCurrency;
-use localterra_protocol::factory::Config as FactoryConfig;
+use localterra_protocol::factory::{Config as FactoryConfig, IbcDenom};
 use localterra_protocol::governance::Config as GovConfig;
-use localterra_protocol::offer::{Config as OfferConfig, Offer, OfferState, OfferType, TradeInfo};
+use localterra_protocol::offer::{
+    Config as OfferConfig, Offer, OfferAsset, OfferState, OfferType, TradeInfo,
+};
+use localterra_protocol::price_oracle::PriceResponse;
 use localterra_protocol::trade::{TradeData as TradeState, TradeState as TradeTradeState};
 use std::collections::HashMap;
 use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper, TerraRoute};
@@ -42,6 +45,14 @@ pub struct WasmMockQuerier {
     tax_querier: TaxQuerier,
     terraswap_factory_querier: TerraswapFactoryQuerier,
     offer: Option<Offer>,
+    prices: HashMap<(String, String), Decimal>,
+    trade_code_id: u64,
+    maker_fee_bps: u64,
+    taker_fee_bps: u64,
+    fee_burn_bps: u64,
+    trade_amounts: HashMap<String, Uint128>,
+    trade_fiat_prices: HashMap<String, Decimal>,
+    trade_fiat_amounts: HashMap<String, Uint128>,
 }
 
 #[derive(Clone, Default)]
@@ -143,6 +154,10 @@ pub enum QueryMsg {
     Config {},
     LoadTrades { maker: String },
     TradeInfo { maker: String, trade: String },
+    Price { fiat: FiatCurrency, denom: String },
+    TokenInfo {},
+    State {},
+    IbcDenom { denom: String },
 }
 
 impl WasmMockQuerier {
@@ -173,7 +188,7 @@ impl WasmMockQuerier {
                     panic!("DO NOT ENTER HERE")
                 }
             }
-            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => match from_binary(&msg)
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => match from_binary(msg)
                 .unwrap()
             {
                 QueryMsg::Pair { asset_infos } => {
@@ -232,13 +247,32 @@ impl WasmMockQuerier {
                     let offer = self.offer.clone().unwrap_or(Offer {
                         id,
                         owner: Addr::unchecked("offer-owner"),
-                        maker_contact: "LunaQueen".to_string(),
+                        maker_contact: Some("LunaQueen".to_string()),
                         offer_type: OfferType::Buy,
-                        fiat_currency: FiatCurrency::COP,
+                        fiat_currency: FiatCurrency::unchecked("COP"),
                         min_amount: Uint128::new(1_000_000u128),
                         max_amount: Uint128::new(500_000_000u128),
                         state: OfferState::Active,
                         timestamp: 1641329895,
+                        expires_at: None,
+                        price_margin_bps: None,
+                        payment_methods: vec![],
+                        description: None,
+                        asset: OfferAsset::default(),
+                        active_trades: 0,
+                        reserved_amount: Uint128::zero(),
+                        total_available: Uint128::zero(),
+                        remaining: Uint128::zero(),
+                        activate_at: None,
+                        pause_at: None,
+                        amounts_in_fiat: false,
+                        available_from_hour: None,
+                        available_to_hour: None,
+                        effective_state: None,
+                        currently_available: None,
+                        created_at: Timestamp::from_seconds(1641329895),
+                        last_updated: Timestamp::from_seconds(1641329895),
+                        min_trades_completed: None,
                     });
                     SystemResult::Ok(ContractResult::from(to_binary(&offer)))
                 }
@@ -247,13 +281,19 @@ impl WasmMockQuerier {
                 QueryMsg::Config {} => {
                     if contract_addr.contains("factory") {
                         SystemResult::Ok(ContractResult::from(to_binary(&FactoryConfig {
-                            trade_code_id: 0,
+                            admin: Addr::unchecked("admin"),
+                            trade_code_id: self.trade_code_id,
                             token_addr: Addr::unchecked("local"),
                             local_ust_pool_addr: Addr::unchecked("local-ust"),
                             gov_addr: Addr::unchecked("gov"),
                             offers_addr: Addr::unchecked("offers"),
                             fee_collector_addr: Addr::unchecked("fee-collector"),
                             trading_incentives_addr: Addr::unchecked("trading-incentives"),
+                            maker_fee_bps: self.maker_fee_bps,
+                            taker_fee_bps: self.taker_fee_bps,
+                            fee_burn_bps: self.fee_burn_bps,
+                            treasury_addr: Addr::unchecked("treasury"),
+                            pending_treasury_addr: None,
                         })))
                     } else if contract_addr.contains("gov") {
                         SystemResult::Ok(ContractResult::from(to_binary(&GovConfig {
@@ -262,6 +302,22 @@ impl WasmMockQuerier {
                     } else {
                         let offer_config = OfferConfig {
                             factory_addr: Addr::unchecked("factory"),
+                            admin: Addr::unchecked("factory"),
+                            max_offers_per_owner: 0,
+                            price_oracle_addr: None,
+                            denoms: vec!["uusd".to_string()],
+                            trade_duration: 3600,
+                            arbitrator: None,
+                            pending_admin: None,
+                            max_trade_amount: Uint128::zero(),
+                            fiat_currencies: vec![FiatCurrency::unchecked("COP")],
+                            max_concurrent_trades: 0,
+                            dispute_window: 86400,
+                            new_trade_limit: 0,
+                            new_trade_window: 3600,
+                            allow_delete_traded: false,
+                            max_amount_ratio: 0,
+                            max_price_age: 3600,
                         };
                         SystemResult::Ok(ContractResult::from(to_binary(&offer_config)))
                     }
@@ -271,33 +327,137 @@ impl WasmMockQuerier {
                 }
                 QueryMsg::TradeInfo { trade: _, maker } => {
                     SystemResult::Ok(ContractResult::from(to_binary(&TradeInfo {
+                        trade_addr: Addr::unchecked("trade"),
+                        offer_id: 1,
+                        created_at: 0,
+                        amount: Uint128::new(1_000_000u128),
+                        denom: "uusd".to_string(),
+                        counterparty: Addr::unchecked("taker"),
+                        expires_at: Timestamp::from_seconds(0),
                         trade: TradeState {
                             addr: Addr::unchecked("trade"),
                             factory_addr: Addr::unchecked("factory"),
                             buyer: Addr::unchecked("taker"),
                             arbitrator: Addr::unchecked("arbitrator"),
                             taker_contact: "USTKing".to_string(),
+                            maker_contact: Some("LunaQueen".to_string()),
                             seller: Addr::unchecked(maker),
                             offer_id: 1,
                             offer_contract: Addr::unchecked("offer"),
-                            state: TradeTradeState::Closed,
-                            expire_height: 0,
-                            ust_amount: Uint128::new(1_000_000u128),
+                            state: TradeTradeState::Released,
+                            created_at: Timestamp::from_seconds(0),
+                            expires_at: Timestamp::from_seconds(0),
+                            dispute_window_ends_at: Timestamp::from_seconds(0),
+                            fee_bps: 100,
+                            amount: Uint128::new(1_000_000u128),
+                            asset: OfferAsset::default(),
+                            fiat_price: None,
+                            fiat_amount: None,
+                            multisig_release: false,
+                            release_approvals: vec![],
+                            approvals_conflict: false,
+                            evidence: vec![],
                         },
-                        offer: Offer {
+                        offer: Some(Offer {
                             id: 1,
                             owner: Addr::unchecked("offer-owner"),
-                            maker_contact: "LunaQueen".to_string(),
+                            maker_contact: Some("LunaQueen".to_string()),
                             offer_type: OfferType::Buy,
-                            fiat_currency: FiatCurrency::COP,
+                            fiat_currency: FiatCurrency::unchecked("COP"),
                             min_amount: Uint128::new(1_000_000u128),
                             max_amount: Uint128::new(500_000_000u128),
                             state: OfferState::Active,
                             timestamp: 1641329895,
-                        },
+                            expires_at: None,
+                            price_margin_bps: None,
+                            payment_methods: vec![],
+                            description: None,
+                            asset: OfferAsset::default(),
+                            active_trades: 0,
+                            reserved_amount: Uint128::zero(),
+                            total_available: Uint128::zero(),
+                            remaining: Uint128::zero(),
+                            activate_at: None,
+                            pause_at: None,
+                            amounts_in_fiat: false,
+                            available_from_hour: None,
+                            available_to_hour: None,
+                            effective_state: None,
+                            currently_available: None,
+                            created_at: Timestamp::from_seconds(1641329895),
+                            last_updated: Timestamp::from_seconds(1641329895),
+                            min_trades_completed: None,
+                        }),
                         expired: false,
                     })))
                 }
+                QueryMsg::Price { fiat, denom } => {
+                    match self.prices.get(&(fiat.to_string(), denom)) {
+                        Some(price) => {
+                            SystemResult::Ok(ContractResult::from(to_binary(&PriceResponse {
+                                price: *price,
+                                updated_at: Timestamp::from_seconds(1641329895),
+                            })))
+                        }
+                        None => SystemResult::Err(SystemError::InvalidRequest {
+                            error: "No price exists".to_string(),
+                            request: msg.as_slice().into(),
+                        }),
+                    }
+                }
+                QueryMsg::State {} => {
+                    SystemResult::Ok(ContractResult::from(to_binary(&TradeState {
+                        addr: Addr::unchecked(contract_addr),
+                        factory_addr: Addr::unchecked("factory"),
+                        buyer: Addr::unchecked("offer-owner"),
+                        arbitrator: Addr::unchecked("arbitrator"),
+                        taker_contact: "USTKing".to_string(),
+                        maker_contact: Some("LunaQueen".to_string()),
+                        seller: Addr::unchecked("taker"),
+                        offer_id: 1,
+                        offer_contract: Addr::unchecked(MOCK_CONTRACT_ADDR),
+                        state: TradeTradeState::Created,
+                        created_at: Timestamp::from_seconds(0),
+                        expires_at: Timestamp::from_seconds(0),
+                        dispute_window_ends_at: Timestamp::from_seconds(0),
+                        fee_bps: 100,
+                        amount: self
+                            .trade_amounts
+                            .get(contract_addr.as_str())
+                            .copied()
+                            .unwrap_or_else(|| Uint128::new(1)),
+                        asset: OfferAsset::default(),
+                        fiat_price: self.trade_fiat_prices.get(contract_addr.as_str()).copied(),
+                        fiat_amount: self.trade_fiat_amounts.get(contract_addr.as_str()).copied(),
+                        multisig_release: false,
+                        release_approvals: vec![],
+                        approvals_conflict: false,
+                        evidence: vec![],
+                    })))
+                }
+                QueryMsg::TokenInfo {} => {
+                    if !self.token_querier.balances.contains_key(contract_addr) {
+                        return SystemResult::Err(SystemError::NoSuchContract {
+                            addr: contract_addr.to_string(),
+                        });
+                    }
+                    SystemResult::Ok(ContractResult::from(to_binary(&TokenInfoResponse {
+                        name: "Wrapped Bitcoin".to_string(),
+                        symbol: "WBTC".to_string(),
+                        decimals: 8,
+                        total_supply: Uint128::zero(),
+                    })))
+                }
+                //Only the uatom voucher of channel-1 is trusted
+                QueryMsg::IbcDenom { denom } => {
+                    let trusted = IbcDenom::new("channel-1", "uatom");
+                    let ibc_denom = if *denom == trusted.denom {
+                        Some(trusted)
+                    } else {
+                        None
+                    };
+                    SystemResult::Ok(ContractResult::from(to_binary(&ibc_denom)))
+                }
             },
             _ => self.base.handle_query(request),
         }
@@ -312,9 +472,61 @@ impl WasmMockQuerier {
             tax_querier: TaxQuerier::default(),
             terraswap_factory_querier: TerraswapFactoryQuerier::default(),
             offer,
+            prices: HashMap::new(),
+            trade_code_id: 0,
+            maker_fee_bps: 100,
+            taker_fee_bps: 0,
+            fee_burn_bps: 0,
+            trade_amounts: HashMap::new(),
+            trade_fiat_prices: HashMap::new(),
+            trade_fiat_amounts: HashMap::new(),
         }
     }
 
+    // configure the trade code id read from the factory config
+    pub fn with_trade_code_id(&mut self, trade_code_id: u64) {
+        self.trade_code_id = trade_code_id;
+    }
+
+    // configure the protocol fees read from the factory config
+    pub fn with_fees(&mut self, maker_fee_bps: u64, taker_fee_bps: u64, fee_burn_bps: u64) {
+        self.maker_fee_bps = maker_fee_bps;
+        self.taker_fee_bps = taker_fee_bps;
+        self.fee_burn_bps = fee_burn_bps;
+    }
+
+    // configure the escrow amount of trades, others hold a single unit
+    pub fn with_trade_amounts(&mut self, amounts: &[(&str, Uint128)]) {
+        self.trade_amounts = amounts
+            .iter()
+            .map(|(trade, amount)| (trade.to_string(), *amount))
+            .collect();
+    }
+
+    // configure the agreed fiat price of trades, others have none
+    pub fn with_trade_fiat_prices(&mut self, prices: &[(&str, Decimal)]) {
+        self.trade_fiat_prices = prices
+            .iter()
+            .map(|(trade, price)| (trade.to_string(), *price))
+            .collect();
+    }
+
+    // configure the fiat amount requested by trades of fiat denominated offers
+    pub fn with_trade_fiat_amounts(&mut self, amounts: &[(&str, Uint128)]) {
+        self.trade_fiat_amounts = amounts
+            .iter()
+            .map(|(trade, amount)| (trade.to_string(), *amount))
+            .collect();
+    }
+
+    // configure the price oracle rates per fiat currency and denom
+    pub fn with_prices(&mut self, prices: &[(FiatCurrency, &str, Decimal)]) {
+        self.prices = prices
+            .iter()
+            .map(|(fiat, denom, price)| ((fiat.to_string(), denom.to_string()), *price))
+            .collect();
+    }
+
     // configure the mint whitelist mock querier
     pub fn with_token_balances(&mut self, balances: &[(&String, &[(&String, &Uint128)])]) {
         self.token_querier = TokenQuerier::new(balances);
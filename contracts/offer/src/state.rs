@@ -1,17 +1,21 @@
 use cosmwasm_std::{Addr, Storage};
 use cosmwasm_storage::{singleton, singleton_read, ReadonlySingleton, Singleton};
-use cw_storage_plus::{Index, IndexList, IndexedMap, MultiIndex};
-use localterra_protocol::offer::{Config, State, TradeAddr};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, U64Key};
+use localterra_protocol::constants::{OFFERS_KEY, OFFER_STATE_KEY};
+use localterra_protocol::offer::{Config, LastTradedPrice, Offer, State, TradeAddr, TraderStats};
 
 pub static CONFIG_KEY: &[u8] = b"config";
-pub static STATE_KEY: &[u8] = b"state";
+pub static STATE_KEY: &[u8] = OFFER_STATE_KEY;
 
 pub struct TradeIndexes<'a> {
     // pk goes to second tuple element
     pub sender: MultiIndex<'a, (Addr, Vec<u8>), TradeAddr>,
     pub recipient: MultiIndex<'a, (Addr, Vec<u8>), TradeAddr>,
+    pub taker: MultiIndex<'a, (Addr, Vec<u8>), TradeAddr>,
     pub arbitrator: MultiIndex<'a, (Addr, Vec<u8>), TradeAddr>,
     pub arbitrator_state: MultiIndex<'a, (Addr, String, Vec<u8>), TradeAddr>,
+    pub state: MultiIndex<'a, (String, Vec<u8>), TradeAddr>,
+    pub maker: MultiIndex<'a, (Addr, U64Key, Vec<u8>), TradeAddr>,
 }
 
 impl<'a> IndexList<TradeAddr> for TradeIndexes<'a> {
@@ -19,8 +23,11 @@ impl<'a> IndexList<TradeAddr> for TradeIndexes<'a> {
         let v: Vec<&dyn Index<TradeAddr>> = vec![
             &self.sender,
             &self.recipient,
+            &self.taker,
             &self.arbitrator,
             &self.arbitrator_state,
+            &self.state,
+            &self.maker,
         ];
         Box::new(v.into_iter())
     }
@@ -38,6 +45,11 @@ pub fn trades<'a>() -> IndexedMap<'a, &'a str, TradeAddr, TradeIndexes<'a>> {
             "trades",            // TODO replace with TRADES_KEY
             "trades__recipient", // TODO replace with TRADES_KEY and concat
         ),
+        taker: MultiIndex::new(
+            |d: &TradeAddr, k: Vec<u8>| (d.taker.clone(), k),
+            "trades",        // TODO replace with TRADES_KEY
+            "trades__taker", // TODO replace with TRADES_KEY and concat
+        ),
         arbitrator: MultiIndex::new(
             |d: &TradeAddr, k: Vec<u8>| (d.arbitrator.clone(), k),
             "trades",             // TODO replace with TRADES_KEY
@@ -48,22 +60,119 @@ pub fn trades<'a>() -> IndexedMap<'a, &'a str, TradeAddr, TradeIndexes<'a>> {
             "trades",                   // TODO replace with TRADES_KEY
             "trades__arbitrator_state", // TODO replace with TRADES_KEY and concat
         ),
+        state: MultiIndex::new(
+            |d: &TradeAddr, k: Vec<u8>| (d.state.to_string(), k),
+            "trades",        // TODO replace with TRADES_KEY
+            "trades__state", // TODO replace with TRADES_KEY and concat
+        ),
+        maker: MultiIndex::new(
+            |d: &TradeAddr, k: Vec<u8>| (d.maker.clone(), U64Key::new(d.created_at), k),
+            "trades",
+            "trades__maker",
+        ),
     };
     IndexedMap::new("trades", indexes)
 }
 
-pub fn config_storage(storage: &mut dyn Storage) -> Singleton<Config> {
+/// Registered trades, bumped as each trade is registered.
+pub const REGISTERED_TRADES: Item<u64> = Item::new("registered_trades");
+
+/// Released trades per buyer or seller, bumped when a trade reports a release.
+pub const COMPLETED_TRADES: Map<&Addr, u64> = Map::new("completed_trades");
+
+/// Trades not in a final state per maker and taker, bumped as trades are
+/// registered and lowered as they report a final state.
+pub const OPEN_TRADES: Map<&Addr, u64> = Map::new("open_trades");
+
+/// Released trades per offer owner and taker, see `TraderStats`.
+pub const TRADER_STATS: Map<&Addr, TraderStats> = Map::new("trader_stats");
+
+/// Last released trade with a fiat price per fiat currency.
+pub const LAST_TRADED_PRICES: Map<&str, LastTradedPrice> = Map::new("last_traded_prices");
+
+/// Addresses blocked by the factory, unblocked ones are removed.
+pub const BLACKLIST: Map<&Addr, bool> = Map::new("blacklist");
+
+/// New trades per sender as `(bucket, count)`, the bucket being the block
+/// time divided by `Config::new_trade_window`. Older buckets are overwritten.
+pub const NEW_TRADES: Map<&Addr, (u64, u32)> = Map::new("new_trades");
+
+/// Operators allowed to manage an owner's offers keyed by `(owner, operator)`,
+/// revoked grants are removed.
+pub const OPERATORS: Map<(&[u8], &[u8]), bool> = Map::new("operators");
+
+/// Indexes of the offer layouts preceding `(fiat_currency, id)` primary keys,
+/// so that their entries are removed cleanly on migration.
+pub struct LegacyOfferIndexes<'a> {
+    pub owner: MultiIndex<'a, (Addr, Vec<u8>), Offer>,
+    pub offer_type: MultiIndex<'a, (String, Vec<u8>), Offer>,
+    pub fiat: MultiIndex<'a, (String, Vec<u8>), Offer>,
+    pub filter: MultiIndex<'a, (String, String, Vec<u8>), Offer>,
+}
+
+impl<'a> IndexList<Offer> for LegacyOfferIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Offer>> + '_> {
+        let v: Vec<&dyn Index<Offer>> =
+            vec![&self.owner, &self.offer_type, &self.fiat, &self.filter];
+        Box::new(v.into_iter())
+    }
+}
+
+fn legacy_offer_indexes<'a>() -> LegacyOfferIndexes<'a> {
+    LegacyOfferIndexes {
+        owner: MultiIndex::new(
+            |d: &Offer, k: Vec<u8>| (d.owner.clone(), k),
+            OFFERS_KEY,
+            "offers__owner",
+        ),
+        offer_type: MultiIndex::new(
+            |d: &Offer, k: Vec<u8>| (d.offer_type.to_string(), k),
+            OFFERS_KEY,
+            "offers__offer_type",
+        ),
+        fiat: MultiIndex::new(
+            |d: &Offer, k: Vec<u8>| (d.fiat_currency.to_string(), k),
+            OFFERS_KEY,
+            "offers__fiat",
+        ),
+        filter: MultiIndex::new(
+            |d: &Offer, k: Vec<u8>| {
+                (
+                    d.offer_type.to_string(),
+                    d.fiat_currency.to_string() + &*d.state.to_string(),
+                    k,
+                )
+            },
+            OFFERS_KEY,
+            "offers__filter",
+        ),
+    }
+}
+
+/// Offers as they were stored before the primary key moved to `U64Key`,
+/// only used by the migration path.
+pub fn legacy_offers<'a>() -> IndexedMap<'a, &'a str, Offer, LegacyOfferIndexes<'a>> {
+    IndexedMap::new(OFFERS_KEY, legacy_offer_indexes())
+}
+
+/// Offers as they were stored under `U64Key` ids, before the currency was
+/// prepended to the primary key. Only used by the migration path.
+pub fn id_offers<'a>() -> IndexedMap<'a, U64Key, Offer, LegacyOfferIndexes<'a>> {
+    IndexedMap::new(OFFERS_KEY, legacy_offer_indexes())
+}
+
+pub fn config_storage(storage: &mut dyn Storage) -> Singleton<'_, Config> {
     singleton(storage, CONFIG_KEY)
 }
 
-pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<Config> {
+pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, Config> {
     singleton_read(storage, CONFIG_KEY)
 }
 
-pub fn state_storage(storage: &mut dyn Storage) -> Singleton<State> {
+pub fn state_storage(storage: &mut dyn Storage) -> Singleton<'_, State> {
     singleton(storage, STATE_KEY)
 }
 
-pub fn state_read(storage: &dyn Storage) -> ReadonlySingleton<State> {
+pub fn state_read(storage: &dyn Storage) -> ReadonlySingleton<'_, State> {
     singleton_read(storage, STATE_KEY)
 }
@@ -1,27 +1,56 @@
 #![cfg(test)]
-use crate::contract::{execute, instantiate, load_offer_by_id, query};
+use crate::contract::{
+    execute, instantiate, load_offer_by_id, migrate, query, recount_open_trades, reply, sudo,
+};
 // use crate::errors::OfferError;
 use crate::mock_querier::mock_dependencies;
+use crate::state::{id_offers, legacy_offers, trades, OPEN_TRADES};
 use cosmwasm_std::testing::mock_env;
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, CosmosMsg, DepsMut, Empty, Env, MessageInfo, ReplyOn, Response,
-    SubMsg, Uint128, WasmMsg,
+    from_binary, from_slice, to_binary, Addr, ContractResult, CosmosMsg, Decimal, Deps, DepsMut,
+    Empty, Env, Event, MessageInfo, Order, Reply, ReplyOn, Response, StdError, Storage, SubMsg,
+    SubMsgExecutionResponse, Timestamp, Uint128, WasmMsg,
 };
+use cosmwasm_storage::to_length_prefixed;
 use cosmwasm_vm::testing::mock_info;
-use localterra_protocol::currencies::FiatCurrency;
+use cw2::{get_contract_version, set_contract_version, CONTRACT};
+use cw_storage_plus::U64Key;
+use localterra_protocol::constants::{
+    DEFAULT_DISPUTE_WINDOW, DEFAULT_MAX_PRICE_AGE, DEFAULT_NEW_TRADE_WINDOW, DEFAULT_PAGE_LIMIT,
+    MAX_BULK_STATE_CHANGES, MAX_PAGE_LIMIT, MAX_PRICE_MARGIN_BPS, NEW_TRADE_REPLY_ID,
+    SCANNED_ENTRIES_PER_OFFER,
+};
+use localterra_protocol::currencies::{
+    from_minor_units, minor_units_to_denom, to_minor_units, FiatCurrency, FiatCurrencyInfo,
+    FIAT_CURRENCY_ALIASES, LEGACY_FIAT_CURRENCIES,
+};
 use localterra_protocol::errors::OfferError;
+use localterra_protocol::events::OFFER_EVENT;
+use localterra_protocol::factory::{
+    ibc_voucher_denom, Arbitrator, ExecuteMsg as FactoryExecuteMsg,
+};
+use localterra_protocol::guards::{assert_fiat_currency, assert_sender_is};
 use localterra_protocol::offer::{
-    Config, ExecuteMsg, InstantiateMsg, Offer, OfferModel, OfferMsg, OfferState, OfferType,
-    QueryMsg, State, TradesIndex,
+    default_fiat_currencies, offers, BlacklistResponse, Config, ConfigUpdate, ExecuteMsg,
+    FiatOfferCounts, FiatOffersSummary, InstantiateMsg, LastTradedPrice, MigrateMsg,
+    NewTradeResponse, Offer, OfferAsset, OfferFilter, OfferModel, OfferMsg, OfferOverrides,
+    OfferState, OfferType, OffersExport, OffersFilterIndex, OffersFilteredResponse, OffersFilters,
+    OffersPageResponse, ProfileResponse, QueryMsg, QueryOrder, State, StateResponse, SudoMsg,
+    TradeAddr, TradeInfo, TradeSimulation, TraderStats, TradesExport, TradesIndex,
+    TradesPageResponse, TradesResponse,
 };
-use localterra_protocol::trade::InstantiateMsg as TradeInstantiateMsg;
+use localterra_protocol::trade::{InstantiateMsg as TradeInstantiateMsg, TradeState};
+use std::collections::BTreeMap;
 
 fn do_init(deps: DepsMut, env: Env, info: MessageInfo) -> Response<Empty> {
-    let init_msg = InstantiateMsg {};
+    let init_msg = InstantiateMsg {
+        max_offers_per_owner: 0,
+        max_trade_amount: Uint128::zero(),
+    };
     let res = instantiate(deps, env, info, init_msg).unwrap();
 
     assert_eq!(res.messages.len(), 0);
-    return res;
+    res
 }
 
 #[test]
@@ -38,6 +67,22 @@ fn proper_init() {
         from_binary(&query(deps.as_ref(), env.clone(), query_config).unwrap()).unwrap();
     let expected = Config {
         factory_addr: Addr::unchecked("factory"),
+        admin: Addr::unchecked("factory"),
+        max_offers_per_owner: 0,
+        price_oracle_addr: None,
+        denoms: vec!["uusd".to_string()],
+        trade_duration: 3600,
+        arbitrator: None,
+        pending_admin: None,
+        max_trade_amount: Uint128::zero(),
+        fiat_currencies: default_fiat_currencies(),
+        max_concurrent_trades: 0,
+        dispute_window: DEFAULT_DISPUTE_WINDOW,
+        new_trade_limit: 0,
+        new_trade_window: DEFAULT_NEW_TRADE_WINDOW,
+        allow_delete_traded: false,
+        max_amount_ratio: 0,
+        max_price_age: DEFAULT_MAX_PRICE_AGE,
     };
     assert_eq!(conf, expected);
 }
@@ -52,14 +97,26 @@ fn create_offer(
     let msg = ExecuteMsg::Create {
         offer: OfferMsg {
             offer_type,
-            maker_contact: "LunaQueen".to_string(),
+            maker_contact: Some("LunaQueen".to_string()),
             fiat_currency,
             min_amount: Uint128::from(1u128),
             max_amount: Uint128::from(2u128),
+            expires_at: None,
+            price_margin_bps: None,
+            payment_methods: vec![],
+            description: None,
+            asset: OfferAsset::default(),
+            min_trades_completed: None,
+            total_available: Uint128::zero(),
+            activate_at: None,
+            pause_at: None,
+            amounts_in_fiat: false,
+            available_from_hour: None,
+            available_to_hour: None,
         },
     };
 
-    return execute(deps, env, info, msg.clone()).unwrap();
+    execute(deps, env, info, msg.clone()).unwrap()
 }
 
 #[test]
@@ -75,27 +132,52 @@ fn create_offer_test() {
         env.clone(),
         info.clone(),
         OfferType::Buy,
-        FiatCurrency::BRL,
+        FiatCurrency::unchecked("BRL"),
     );
 
     assert_eq!(res.messages.len(), 0);
 
     let query_state = QueryMsg::State {};
-    let state: State =
+    let state: StateResponse =
         from_binary(&query(deps.as_ref(), env.clone(), query_state).unwrap()).unwrap();
 
-    let expected = State { offers_count: 1 };
+    let expected = StateResponse {
+        offers_count: 1,
+        last_offer_id: Some(1),
+        paused: false,
+        active_offers_count: 1,
+        paused_offers_count: 0,
+        fiat_offers_counts: vec![FiatOfferCounts {
+            fiat_currency: FiatCurrency::unchecked("BRL"),
+            active: 1,
+            paused: 0,
+        }],
+    };
     assert_eq!(state, expected);
 
     let query_cop_offers = QueryMsg::Offers {
-        fiat_currency: FiatCurrency::COP,
+        fiat_currency: FiatCurrency::unchecked("COP"),
+        offer_type: None,
+        payment_method: None,
+        denom: None,
+        order: None,
+        amount: None,
+        min_amount: None,
+        max_amount: None,
     };
     let cop_offers: Vec<Offer> =
         from_binary(&query(deps.as_ref(), env.clone(), query_cop_offers).unwrap()).unwrap();
     assert_eq!(cop_offers.len(), 0);
 
     let query_brl_offers = QueryMsg::Offers {
-        fiat_currency: FiatCurrency::BRL,
+        fiat_currency: FiatCurrency::unchecked("BRL"),
+        offer_type: None,
+        payment_method: None,
+        denom: None,
+        order: None,
+        amount: None,
+        min_amount: None,
+        max_amount: None,
     };
     let brl_offers: Vec<Offer> =
         from_binary(&query(deps.as_ref(), env.clone(), query_brl_offers).unwrap()).unwrap();
@@ -105,13 +187,32 @@ fn create_offer_test() {
     let mut created_offer = Offer {
         id: 1,
         owner: factory,
-        maker_contact: "LunaQueen".to_string(),
+        maker_contact: Some("LunaQueen".to_string()),
         offer_type: OfferType::Buy,
-        fiat_currency: FiatCurrency::BRL,
+        fiat_currency: FiatCurrency::unchecked("BRL"),
         min_amount: Uint128::new(1),
         max_amount: Uint128::new(2),
         state: OfferState::Active,
         timestamp: 1641329895,
+        expires_at: None,
+        price_margin_bps: None,
+        payment_methods: vec![],
+        description: None,
+        asset: OfferAsset::default(),
+        active_trades: 0,
+        reserved_amount: Uint128::zero(),
+        total_available: Uint128::zero(),
+        remaining: Uint128::zero(),
+        activate_at: None,
+        pause_at: None,
+        amounts_in_fiat: false,
+        available_from_hour: None,
+        available_to_hour: None,
+        effective_state: Some(OfferState::Active),
+        currently_available: Some(true),
+        created_at: env.block.time,
+        last_updated: env.block.time,
+        min_trades_completed: None,
     };
     let queried_offer: Offer =
         from_binary(&query(deps.as_ref(), env.clone(), query_order_by_id).unwrap()).unwrap();
@@ -135,12 +236,19 @@ fn pause_offer_test() {
         env.clone(),
         info.clone(),
         OfferType::Buy,
-        FiatCurrency::BRL,
+        FiatCurrency::unchecked("BRL"),
     );
     assert_eq!(res.messages.len(), 0);
 
     //Load all offers and get the created offer
-    let offers = OfferModel::query_all_offers(&mut deps.storage, FiatCurrency::BRL).unwrap();
+    let offers = OfferModel::query_all_offers(
+        &deps.storage,
+        FiatCurrency::unchecked("BRL"),
+        &OfferFilter::default(),
+        QueryOrder::Asc,
+        env.block.time,
+    )
+    .unwrap();
     let offer = &offers[0];
     assert_eq!(offer.state, OfferState::Active);
 
@@ -160,18 +268,18 @@ fn pause_offer_test() {
         res.err().unwrap(),
         OfferError::Unauthorized { .. }
     ));
-    let offer = &load_offer_by_id(&mut deps.storage, offer.id).unwrap();
+    let offer = &load_offer_by_id(&deps.storage, offer.id).unwrap();
     assert_eq!(offer.state, OfferState::Active);
 
     //Try to change state with the Owner
     let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
     assert_eq!(res.messages.len(), 0);
-    let offer = &load_offer_by_id(&mut deps.storage, offer.id).unwrap();
+    let offer = &load_offer_by_id(&deps.storage, offer.id).unwrap();
     assert_eq!(offer.state, OfferState::Paused);
 
     //Try to pause Paused offer
     let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
-    assert_eq!(res.is_err(), true);
+    assert!(res.is_err());
 }
 
 #[test]
@@ -189,12 +297,19 @@ fn activate_offer_test() {
         env.clone(),
         info.clone(),
         OfferType::Buy,
-        FiatCurrency::BRL,
+        FiatCurrency::unchecked("BRL"),
     );
     assert_eq!(res.messages.len(), 0);
 
     //Load all offers and get the created offer
-    let offers = OfferModel::query_all_offers(&mut deps.storage, FiatCurrency::BRL).unwrap();
+    let offers = OfferModel::query_all_offers(
+        &deps.storage,
+        FiatCurrency::unchecked("BRL"),
+        &OfferFilter::default(),
+        QueryOrder::Asc,
+        env.block.time,
+    )
+    .unwrap();
     let offer = &offers[0];
     assert_eq!(offer.state, OfferState::Active);
 
@@ -207,7 +322,7 @@ fn activate_offer_test() {
     //Try to change state to Paused with the Owner
     let res = execute(deps.as_mut(), env.clone(), info.clone(), pause_msg.clone()).unwrap();
     assert_eq!(res.messages.len(), 0);
-    let offer = &load_offer_by_id(&mut deps.storage, offer.id).unwrap();
+    let offer = &load_offer_by_id(&deps.storage, offer.id).unwrap();
     assert_eq!(offer.state, OfferState::Paused);
 
     //Try to change the State to Active with another address.
@@ -222,7 +337,7 @@ fn activate_offer_test() {
         res.err().unwrap(),
         OfferError::Unauthorized { .. }
     ));
-    let offer = &load_offer_by_id(&mut deps.storage, offer.id).unwrap();
+    let offer = &load_offer_by_id(&deps.storage, offer.id).unwrap();
     assert_eq!(offer.state, OfferState::Paused);
 
     //Try to change state to Active with the Owner
@@ -234,7 +349,7 @@ fn activate_offer_test() {
     )
     .unwrap();
     assert_eq!(res.messages.len(), 0);
-    let offer = &load_offer_by_id(&mut deps.storage, offer.id).unwrap();
+    let offer = &load_offer_by_id(&deps.storage, offer.id).unwrap();
     assert_eq!(offer.state, OfferState::Active);
 }
 
@@ -252,22 +367,34 @@ fn update_offer_test() {
         env.clone(),
         info.clone(),
         OfferType::Buy,
-        FiatCurrency::BRL,
+        FiatCurrency::unchecked("BRL"),
     );
     assert_eq!(res.messages.len(), 0);
 
     //Load Created message
-    let offer = load_offer_by_id(&mut deps.storage, 1).unwrap();
-    assert_eq!(offer.fiat_currency, FiatCurrency::BRL);
+    let offer = load_offer_by_id(&deps.storage, 1).unwrap();
+    assert_eq!(offer.fiat_currency, FiatCurrency::unchecked("BRL"));
     assert_eq!(offer.offer_type, OfferType::Buy);
 
     //Prepare Update message
     let offer_msg = OfferMsg {
         offer_type: OfferType::Sell,
-        maker_contact: "LunaQueen".to_string(),
-        fiat_currency: FiatCurrency::COP,
+        maker_contact: Some("LunaQueen".to_string()),
+        fiat_currency: FiatCurrency::unchecked("COP"),
         min_amount: Uint128::from(1000000u128),
         max_amount: Uint128::from(5000000u128),
+        expires_at: None,
+        price_margin_bps: None,
+        payment_methods: vec![],
+        description: None,
+        asset: OfferAsset::default(),
+        min_trades_completed: None,
+        total_available: Uint128::zero(),
+        activate_at: None,
+        pause_at: None,
+        amounts_in_fiat: false,
+        available_from_hour: None,
+        available_to_hour: None,
     };
     let update_offer_msg = ExecuteMsg::Update {
         id: 1,
@@ -277,11 +404,14 @@ fn update_offer_test() {
     assert_eq!(res.messages.len(), 0);
 
     //Load offer and check that it was updated
-    let offer = load_offer_by_id(&mut deps.storage, 1).unwrap();
+    let offer = load_offer_by_id(&deps.storage, 1).unwrap();
     assert_eq!(offer.offer_type, offer_msg.offer_type);
     assert_eq!(offer.fiat_currency, offer_msg.fiat_currency);
     assert_eq!(offer.min_amount, offer_msg.min_amount);
     assert_eq!(offer.max_amount, offer_msg.max_amount);
+    //The stored offer is the response data
+    let data: Offer = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(data, offer);
 }
 
 #[test]
@@ -298,17 +428,17 @@ fn instantiate_trade() {
         env.clone(),
         info.clone(),
         OfferType::Buy,
-        FiatCurrency::BRL,
+        FiatCurrency::unchecked("BRL"),
     );
     assert_eq!(res.messages.len(), 0);
 
-    let trade_amount = Uint128::new(1000000u128);
+    let trade_amount = Uint128::new(2u128);
     //Send Message to Create Trade
     let new_trade_msg = ExecuteMsg::NewTrade {
         offer_id: 1,
-        arbitrator: "arbitrator".to_string(),
+        multisig_release: false,
         taker_contact: "USTKing".to_string(),
-        ust_amount: trade_amount.clone().to_string(),
+        amount: trade_amount,
         taker: "taker".to_string(),
     };
     let res = execute(deps.as_mut(), env.clone(), info.clone(), new_trade_msg);
@@ -317,12 +447,15 @@ fn instantiate_trade() {
 
     let msg = to_binary(&TradeInstantiateMsg {
         offer_id: 1,
-        arbitrator: "arbitrator".to_string(),
         taker_contact: "USTKing".to_string(),
-        ust_amount: trade_amount.clone().to_string(),
+        amount: trade_amount,
         taker: "taker".to_string(),
-        offers_addr: "offers".to_string(),
-        timestamp: 1641329895,
+        offers_addr: env.contract.address.to_string(),
+        timestamp: env.block.time.seconds(),
+        fiat_price: None,
+        fiat_amount: None,
+        asset: OfferAsset::default(),
+        multisig_release: false,
     })
     .unwrap();
     let from_binary_msg: TradeInstantiateMsg = from_binary(&msg).unwrap();
@@ -334,19 +467,16 @@ fn instantiate_trade() {
         funds: vec![],
         label: "new-trade".to_string(),
     };
-    //TODO: fix
-    /*
     let res = res.unwrap();
     let sub_message = SubMsg {
-        id: 0,
+        id: NEW_TRADE_REPLY_ID,
         msg: CosmosMsg::Wasm(instantiate_msg),
         gas_limit: None,
         reply_on: ReplyOn::Success,
     };
     assert_eq!(res.messages[0], sub_message);
-     */
 
-    let _trades: Vec<String> = from_binary(
+    let _trades: Vec<TradeInfo> = from_binary::<TradesResponse>(
         &query(
             deps.as_ref(),
             mock_env(),
@@ -360,6 +490,5890 @@ fn instantiate_trade() {
         )
         .unwrap(),
     )
-    .unwrap();
+    .unwrap()
+    .trades;
     println!("Trades: {:?}", &_trades);
 }
+
+#[test]
+fn migrate_legacy_offers_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("factory", &[]);
+    do_init(deps.as_mut(), env.clone(), info.clone());
+
+    //Store offers under the legacy string keys, where "12" sorts before "2"
+    for id in [12u64, 2u64].iter() {
+        let offer = Offer {
+            id: *id,
+            owner: Addr::unchecked("maker"),
+            maker_contact: Some("LunaQueen".to_string()),
+            offer_type: OfferType::Buy,
+            fiat_currency: FiatCurrency::unchecked("BRL"),
+            min_amount: Uint128::new(1),
+            max_amount: Uint128::new(2),
+            state: OfferState::Active,
+            timestamp: 1641329895,
+            expires_at: None,
+            price_margin_bps: None,
+            payment_methods: vec![],
+            description: None,
+            asset: OfferAsset::default(),
+            active_trades: 0,
+            reserved_amount: Uint128::zero(),
+            total_available: Uint128::zero(),
+            remaining: Uint128::zero(),
+            activate_at: None,
+            pause_at: None,
+            amounts_in_fiat: false,
+            available_from_hour: None,
+            available_to_hour: None,
+            effective_state: None,
+            currently_available: None,
+            created_at: Timestamp::from_seconds(1641329895),
+            last_updated: Timestamp::from_seconds(1641329895),
+            min_trades_completed: None,
+        };
+        legacy_offers()
+            .save(&mut deps.storage, &id.to_string(), &offer)
+            .unwrap();
+    }
+
+    let res = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+    assert_eq!(res.attributes[1].value, "2");
+
+    //Migrated offers are readable by id and listed in numeric id order
+    let offer = load_offer_by_id(&deps.storage, 12).unwrap();
+    assert_eq!(offer.id, 12);
+    let brl_offers: Vec<Offer> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Offers {
+                fiat_currency: FiatCurrency::unchecked("BRL"),
+                offer_type: None,
+                payment_method: None,
+                denom: None,
+                order: None,
+                amount: None,
+                min_amount: None,
+                max_amount: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let ids: Vec<u64> = brl_offers.iter().map(|offer| offer.id).collect();
+    assert_eq!(ids, vec![2, 12]);
+
+    //Running the migration again is a no-op
+    let res = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+    assert_eq!(res.attributes[1].value, "0");
+
+    //Counters account for the migrated offers exactly once
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.active_offers_count, 2);
+}
+
+#[test]
+fn offer_key_layout_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let offer_msg = |fiat: &str| OfferMsg {
+        fiat_currency: FiatCurrency::unchecked(fiat),
+        ..expiring_offer_msg(None)
+    };
+    for fiat in ["BRL", "COP", "BRL", "COP", "BRL"].iter() {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Create {
+                offer: offer_msg(fiat),
+            },
+        )
+        .unwrap();
+    }
+    let fiat_ids = |deps: Deps, fiat: &str, order: Option<QueryOrder>| -> Vec<u64> {
+        let msg = QueryMsg::Offers {
+            fiat_currency: FiatCurrency::unchecked(fiat),
+            offer_type: None,
+            payment_method: None,
+            denom: None,
+            order,
+            amount: None,
+            min_amount: None,
+            max_amount: None,
+        };
+        let offers: Vec<Offer> = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        offers.iter().map(|offer| offer.id).collect()
+    };
+    let owner_ids = |deps: Deps, last_value: Option<u64>| -> Vec<u64> {
+        let msg = QueryMsg::OffersByOwner {
+            owner: "maker".to_string(),
+            last_value,
+            limit: 10,
+        };
+        let offers: Vec<Offer> =
+            from_binary::<OffersPageResponse>(&query(deps, mock_env(), msg).unwrap())
+                .unwrap()
+                .offers;
+        offers.iter().map(|offer| offer.id).collect()
+    };
+
+    //Each currency is its own prefix, ascending by id
+    assert_eq!(fiat_ids(deps.as_ref(), "BRL", None), vec![1, 3, 5]);
+    assert_eq!(
+        fiat_ids(deps.as_ref(), "BRL", Some(QueryOrder::Desc)),
+        vec![5, 3, 1]
+    );
+    assert_eq!(fiat_ids(deps.as_ref(), "COP", None), vec![2, 4]);
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 4).unwrap().fiat_currency,
+        FiatCurrency::unchecked("COP")
+    );
+
+    //Owner listings still page by id across currencies
+    assert_eq!(owner_ids(deps.as_ref(), None), vec![1, 2, 3, 4, 5]);
+    assert_eq!(owner_ids(deps.as_ref(), Some(2)), vec![3, 4, 5]);
+    assert_eq!(owner_ids(deps.as_ref(), Some(u64::MAX)), Vec::<u64>::new());
+
+    //Changing currency moves the offer to the other prefix
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Update {
+            id: 3,
+            offer: offer_msg("COP"),
+        },
+    )
+    .unwrap();
+    assert_eq!(fiat_ids(deps.as_ref(), "BRL", None), vec![1, 5]);
+    assert_eq!(fiat_ids(deps.as_ref(), "COP", None), vec![2, 3, 4]);
+    assert!(offers()
+        .may_load(&deps.storage, ("BRL", U64Key::new(3)))
+        .unwrap()
+        .is_none());
+    assert_eq!(owner_ids(deps.as_ref(), None), vec![1, 2, 3, 4, 5]);
+
+    //Offers stored under plain ids are moved with their ids on migration
+    for (id, fiat) in [(8u64, "BRL"), (6, "BRL"), (7, "COP")].iter() {
+        let offer = Offer {
+            id: *id,
+            fiat_currency: FiatCurrency::unchecked(fiat),
+            ..load_offer_by_id(&deps.storage, 1).unwrap()
+        };
+        id_offers()
+            .save(&mut deps.storage, U64Key::new(*id), &offer)
+            .unwrap();
+    }
+    let res = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+    assert_eq!(res.attributes[1].value, "3");
+    assert_eq!(fiat_ids(deps.as_ref(), "BRL", None), vec![1, 5, 6, 8]);
+    assert_eq!(fiat_ids(deps.as_ref(), "COP", None), vec![2, 3, 4, 7]);
+    assert_eq!(load_offer_by_id(&deps.storage, 7).unwrap().id, 7);
+    assert_eq!(owner_ids(deps.as_ref(), Some(5)), vec![6, 7, 8]);
+    let state: StateResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(state.active_offers_count, 8);
+}
+
+#[test]
+fn offers_page_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let create = |deps: DepsMut| {
+        let msg = ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        };
+        execute(deps, mock_env(), mock_info("maker", &[]), msg).unwrap();
+    };
+    for _ in 0..5 {
+        create(deps.as_mut());
+    }
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker,
+        ExecuteMsg::Archive { id: 3 },
+    )
+    .unwrap();
+    let page = |deps: Deps,
+                order: Option<QueryOrder>,
+                start_before: Option<u64>,
+                last_value: Option<u64>|
+     -> (Vec<u64>, Option<u64>) {
+        let msg = QueryMsg::OffersPage {
+            order,
+            start_before,
+            last_value,
+            limit: 2,
+            amount: None,
+            min_amount: None,
+            max_amount: None,
+        };
+        let page: OffersPageResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        (
+            page.offers.iter().map(|offer| offer.id).collect(),
+            page.next,
+        )
+    };
+
+    //Newest first by default, new offers don't shift the following pages
+    let (ids, next) = page(deps.as_ref(), None, None, None);
+    assert_eq!((ids, next), (vec![5, 4], Some(4)));
+    create(deps.as_mut());
+    let (ids, next) = page(deps.as_ref(), None, next, None);
+    assert_eq!((ids, next), (vec![2, 1], Some(1)));
+    assert_eq!(page(deps.as_ref(), None, next, None), (vec![], None));
+
+    //Ascending pages continue after last_value
+    let (ids, next) = page(deps.as_ref(), Some(QueryOrder::Asc), None, None);
+    assert_eq!((ids, next), (vec![1, 2], Some(2)));
+    assert_eq!(
+        page(deps.as_ref(), Some(QueryOrder::Asc), None, Some(4)),
+        (vec![5, 6], Some(6))
+    );
+}
+
+#[test]
+fn offers_sample_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    for fiat in ["BRL", "BRL", "COP", "BRL", "BRL", "COP", "BRL", "BRL"].iter() {
+        let msg = ExecuteMsg::Create {
+            offer: OfferMsg {
+                fiat_currency: FiatCurrency::unchecked(fiat),
+                ..expiring_offer_msg(None)
+            },
+        };
+        execute(deps.as_mut(), env.clone(), maker.clone(), msg).unwrap();
+    }
+    for id in [2u64, 5].iter() {
+        let msg = ExecuteMsg::Pause { id: *id };
+        execute(deps.as_mut(), env.clone(), maker.clone(), msg).unwrap();
+    }
+    let sample = |deps: Deps, fiat: Option<&str>, count: u8, seed: u64| -> Vec<u64> {
+        let msg = QueryMsg::OffersSample {
+            fiat_currency: fiat.map(FiatCurrency::unchecked),
+            count,
+            seed,
+        };
+        let offers: Vec<Offer> = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        offers.iter().map(|offer| offer.id).collect()
+    };
+
+    //Reproducible per seed, only distinct active offers
+    let mut samples = vec![];
+    for seed in 0..10 {
+        let ids = sample(deps.as_ref(), None, 3, seed);
+        assert_eq!(ids, sample(deps.as_ref(), None, 3, seed));
+        assert!(!ids.is_empty() && ids.len() <= 3);
+        assert!(ids.iter().all(|id| ![2, 5].contains(id)));
+        let mut unique = ids.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), ids.len());
+        samples.push(ids);
+    }
+    //Different seeds give different samples
+    assert!(samples.iter().any(|ids| ids != &samples[0]));
+
+    //Scoped to the requested currency
+    for seed in 0..10 {
+        let ids = sample(deps.as_ref(), Some("COP"), 5, seed);
+        assert!(ids.iter().all(|id| [3, 6].contains(id)));
+    }
+    assert!(sample(deps.as_ref(), Some("USD"), 5, 1).is_empty());
+    assert!(sample(deps.as_ref(), None, 0, 1).is_empty());
+}
+
+#[test]
+fn migrate_contract_version_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let stored = get_contract_version(&deps.storage).unwrap();
+    assert_eq!(stored.contract, "crates.io:offer");
+
+    //Same version migrations are accepted
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+    //Other contracts and newer versions are refused
+    set_contract_version(&mut deps.storage, "crates.io:trade", &stored.version).unwrap();
+    let err = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap_err();
+    assert!(matches!(err, OfferError::InvalidMigration { .. }));
+    set_contract_version(&mut deps.storage, &stored.contract, "99.0.0").unwrap();
+    let err = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap_err();
+    assert!(matches!(err, OfferError::InvalidMigration { .. }));
+
+    //Contracts instantiated before versions were stored get one on migration
+    CONTRACT.remove(&mut deps.storage);
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+    assert_eq!(get_contract_version(&deps.storage).unwrap(), stored);
+}
+
+#[test]
+fn migrate_raw_legacy_offer_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+
+    //An offer written before assets, payment methods, trade counters and
+    //timestamps existed
+    let mut key = to_length_prefixed(b"offers");
+    key.extend_from_slice(&1u64.to_be_bytes());
+    deps.storage.set(
+        &key,
+        br#"{"id":1,"owner":"maker","maker_contact":null,"offer_type":"buy","fiat_currency":"BRL","min_amount":"1","max_amount":"2","state":"active","timestamp":1,"expires_at":null,"price_margin_bps":null,"description":null,"min_trades_completed":null}"#,
+    );
+
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+    let offer = load_offer_by_id(&deps.storage, 1).unwrap();
+    assert_eq!(offer.owner, Addr::unchecked("maker"));
+    assert_eq!(offer.asset, OfferAsset::default());
+    assert_eq!(offer.payment_methods, Vec::<String>::new());
+    assert_eq!(offer.active_trades, 0);
+    assert_eq!(offer.created_at, env.block.time);
+    assert_eq!(offer.last_updated, env.block.time);
+
+    //The rewritten entry is indexed and counted
+    let owned: Vec<Offer> = from_binary::<OffersPageResponse>(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::OffersByOwner {
+                owner: "maker".to_string(),
+                last_value: None,
+                limit: 10,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+    .offers;
+    assert_eq!(owned.len(), 1);
+    assert_eq!(query_state(deps.as_ref()).active_offers_count, 1);
+}
+
+#[test]
+fn offers_by_fiat_pagination_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("factory", &[]);
+    do_init(deps.as_mut(), env.clone(), info.clone());
+
+    //Interleave currencies so COP offers get the even ids 2..=12
+    for i in 0..12 {
+        let fiat_currency = if i % 2 == 0 {
+            FiatCurrency::unchecked("BRL")
+        } else {
+            FiatCurrency::unchecked("COP")
+        };
+        create_offer(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            OfferType::Buy,
+            fiat_currency,
+        );
+    }
+
+    let query_page = |last_value: Option<u64>| {
+        let offers: Vec<Offer> = from_binary::<OffersPageResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::OffersByFiat {
+                    fiat_currency: FiatCurrency::unchecked("COP"),
+                    offer_type: None,
+                    payment_method: None,
+                    denom: None,
+                    order: None,
+                    last_value,
+                    limit: 3,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .offers;
+        offers.iter().map(|offer| offer.id).collect::<Vec<u64>>()
+    };
+
+    //Every page is full even though half of the stored offers are BRL
+    assert_eq!(query_page(None), vec![2, 4, 6]);
+    assert_eq!(query_page(Some(6)), vec![8, 10, 12]);
+    assert_eq!(query_page(Some(12)), Vec::<u64>::new());
+}
+
+#[test]
+fn offer_amounts_above_u64_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("factory", &[]);
+    do_init(deps.as_mut(), env.clone(), info.clone());
+
+    //Amounts travel as strings and are not truncated to u64
+    let min_amount = Uint128::from(u64::MAX as u128 + 1);
+    let max_amount = Uint128::new(u128::MAX);
+    let msg: ExecuteMsg = from_slice(
+        br#"{"create":{"offer":{
+            "offer_type":"buy",
+            "fiat_currency":"BRL",
+            "min_amount":"18446744073709551616",
+            "max_amount":"340282366920938463463374607431768211455",
+            "maker_contact":"LunaQueen"
+        }}}"#,
+    )
+    .unwrap();
+    execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    let offer = load_offer_by_id(&deps.storage, 1).unwrap();
+    assert_eq!(offer.min_amount, min_amount);
+    assert_eq!(offer.max_amount, max_amount);
+
+    //Plain JSON numbers are rejected
+    let res: cosmwasm_std::StdResult<ExecuteMsg> = from_slice(
+        br#"{"create":{"offer":{
+            "offer_type":"buy",
+            "fiat_currency":"BRL",
+            "min_amount":1,
+            "max_amount":2,
+            "maker_contact":"LunaQueen"
+        }}}"#,
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn fiat_currency_allowlist_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+    let supported = |deps: Deps| -> Vec<FiatCurrency> {
+        let infos: Vec<FiatCurrencyInfo> =
+            from_binary(&query(deps, mock_env(), QueryMsg::SupportedCurrencies {}).unwrap())
+                .unwrap();
+        infos.into_iter().map(|info| info.fiat_currency).collect()
+    };
+
+    //New configs start from the codes the enum used to have
+    let currencies = supported(deps.as_ref());
+    assert_eq!(currencies, default_fiat_currencies());
+    for code in ["BRL", "COP", "NGN", "KES", "INR", "VES"].iter() {
+        assert!(currencies.contains(&FiatCurrency::unchecked(code)));
+    }
+
+    //The representation didn't change, stored offers and configs keep loading
+    assert_eq!(
+        to_binary(&FiatCurrency::unchecked("BRL")).unwrap(),
+        to_binary("BRL").unwrap()
+    );
+    assert_eq!(FiatCurrency::unchecked("COP").to_string(), "COP");
+    let legacy_cfg: Config = from_slice(br#"{"factory_addr":"factory"}"#).unwrap();
+    assert_eq!(legacy_cfg.fiat_currencies, default_fiat_currencies());
+
+    //Anything but three letters is named in the error
+    for code in ["BR", "BRLX", "B1L"].iter() {
+        match from_slice::<FiatCurrency>(format!("\"{}\"", code).as_bytes()).unwrap_err() {
+            StdError::ParseErr { msg, .. } => {
+                assert!(msg.contains(&format!("Invalid fiat currency `{}`", code)))
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    //Well formed codes still have to be enabled
+    let xxx = FiatCurrency::unchecked("XXX");
+    let create_msg = ExecuteMsg::Create {
+        offer: OfferMsg {
+            fiat_currency: xxx.clone(),
+            ..expiring_offer_msg(None)
+        },
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        create_msg.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::UnsupportedFiatCurrency { fiat_currency, .. } if fiat_currency == xxx
+    ));
+
+    let enable = ExecuteMsg::UpdateConfig(ConfigUpdate {
+        fiat_currencies: Some(vec![FiatCurrency::unchecked("BRL"), xxx.clone()]),
+        ..Default::default()
+    });
+    execute(deps.as_mut(), env.clone(), factory.clone(), enable).unwrap();
+    assert_eq!(
+        supported(deps.as_ref()),
+        vec![FiatCurrency::unchecked("BRL"), xxx.clone()]
+    );
+    execute(deps.as_mut(), env.clone(), maker.clone(), create_msg).unwrap();
+    let offers: Vec<Offer> = from_binary::<OffersPageResponse>(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::OffersByTypeFiat {
+                offer_type: OfferType::Buy,
+                fiat_currency: xxx.clone(),
+                last_value: None,
+                limit: 10,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+    .offers;
+    assert_eq!(offers.len(), 1);
+    assert_eq!(offers[0].fiat_currency, xxx);
+
+    //Disabled codes are rejected again, COP is no longer listed
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: OfferMsg {
+                fiat_currency: FiatCurrency::unchecked("COP"),
+                ..expiring_offer_msg(None)
+            },
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::UnsupportedFiatCurrency { .. }));
+}
+
+#[test]
+fn fiat_currency_parsing_test() {
+    let parse = |json: &str| from_slice::<FiatCurrency>(json.as_bytes());
+
+    //Every known code parses in any casing and serializes canonically
+    for code in LEGACY_FIAT_CURRENCIES.iter() {
+        let lower = code.to_lowercase();
+        let mixed: String = code
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                if i % 2 == 0 {
+                    c
+                } else {
+                    c.to_ascii_lowercase()
+                }
+            })
+            .collect();
+        for input in [code.to_string(), lower, mixed] {
+            let currency = parse(&format!("\"{}\"", input)).unwrap();
+            assert_eq!(currency, FiatCurrency::unchecked(code));
+            assert_eq!(
+                to_binary(&currency).unwrap(),
+                to_binary(&code.to_string()).unwrap()
+            );
+        }
+    }
+
+    //Aliases and surrounding whitespace resolve to the code
+    for (alias, code) in FIAT_CURRENCY_ALIASES.iter() {
+        assert_eq!(
+            parse(&format!("\"{}\"", alias.to_lowercase())).unwrap(),
+            FiatCurrency::unchecked(code)
+        );
+    }
+    assert_eq!(
+        parse(r#""UsDollar""#).unwrap(),
+        FiatCurrency::unchecked("USD")
+    );
+    assert_eq!(parse(r#"" cop ""#).unwrap(), FiatCurrency::unchecked("COP"));
+
+    //Unknown input lists the aliases and points at the enabled codes
+    match parse(r#""Dollar""#).unwrap_err() {
+        StdError::ParseErr { msg, .. } => {
+            assert!(msg.contains("Invalid fiat currency `Dollar`"));
+            assert!(!msg.contains("BRL, BSD"));
+            assert!(msg.contains("USDOLLAR, EURO"));
+            assert!(msg.contains("`SupportedCurrencies`"));
+        }
+        err => panic!("Unexpected error: {:?}", err),
+    }
+
+    //Disabled codes are rejected with the enabled ones
+    let err = assert_fiat_currency(
+        &[
+            FiatCurrency::unchecked("BRL"),
+            FiatCurrency::unchecked("COP"),
+        ],
+        &FiatCurrency::unchecked("USD"),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Offers in USD are not enabled, the configured fiat_currencies are BRL, COP."
+    );
+}
+
+#[test]
+fn fiat_minor_units_test() {
+    let mut deps = mock_dependencies(&[], None);
+    do_init(deps.as_mut(), mock_env(), mock_info("factory", &[]));
+    let jpy = FiatCurrency::unchecked("JPY");
+    let usd = FiatCurrency::unchecked("USD");
+    let bhd = FiatCurrency::unchecked("BHD");
+
+    //Decimals are reported next to every supported currency
+    let infos: Vec<FiatCurrencyInfo> =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::SupportedCurrencies {}).unwrap())
+            .unwrap();
+    let decimals = |fiat_currency: &FiatCurrency| {
+        infos
+            .iter()
+            .find(|info| &info.fiat_currency == fiat_currency)
+            .map(|info| info.decimals)
+    };
+    assert_eq!(decimals(&jpy), Some(0));
+    assert_eq!(decimals(&usd), Some(2));
+    assert_eq!(decimals(&bhd), Some(3));
+
+    //1000 minor units are 1000 JPY, 10 USD and 1 BHD
+    let amount = Uint128::new(1000);
+    for (fiat_currency, major) in [(&jpy, 1000u128), (&usd, 10), (&bhd, 1)] {
+        let converted = from_minor_units(amount, fiat_currency).unwrap();
+        assert_eq!(converted, Decimal::from_ratio(major, 1u128));
+        assert_eq!(to_minor_units(converted, fiat_currency).unwrap(), amount);
+    }
+
+    //Sub minor unit amounts are rejected instead of truncated
+    let cents = Decimal::from_ratio(1234u128, 100u128);
+    assert_eq!(to_minor_units(cents, &usd).unwrap(), Uint128::new(1234));
+    assert_eq!(to_minor_units(cents, &bhd).unwrap(), Uint128::new(12340));
+    let err = to_minor_units(cents, &jpy).unwrap_err();
+    assert!(matches!(err, OfferError::FiatPrecisionLoss { .. }));
+    let err = to_minor_units(Decimal::from_ratio(1u128, 1000u128), &usd).unwrap_err();
+    assert!(matches!(err, OfferError::FiatPrecisionLoss { .. }));
+
+    //Amounts beyond the range of Decimal overflow
+    let err = from_minor_units(Uint128::new(u128::MAX), &jpy).unwrap_err();
+    assert!(matches!(err, OfferError::FiatAmountOverflow { .. }));
+
+    //Fiat buys whole units of the asset, the rest is dropped
+    let price = Decimal::from_ratio(10u128, 1u128);
+    let units = |amount: u128, fiat_currency: &FiatCurrency, price: Decimal| {
+        minor_units_to_denom(Uint128::new(amount), fiat_currency, price)
+    };
+    assert_eq!(units(1000, &usd, price).unwrap(), Uint128::new(1));
+    assert_eq!(units(1099, &usd, price).unwrap(), Uint128::new(1));
+    assert_eq!(units(1000, &jpy, price).unwrap(), Uint128::new(100));
+    let err = units(999, &usd, price).unwrap_err();
+    assert!(matches!(err, OfferError::FiatAmountTooSmall { .. }));
+    let err = units(1000, &usd, Decimal::zero()).unwrap_err();
+    assert!(matches!(err, OfferError::FiatAmountTooSmall { .. }));
+    //Amounts are checked instead of overflowing against tiny prices
+    let tiny = Decimal::from_ratio(1u128, 10u128.pow(18));
+    let err = units(u128::MAX, &jpy, tiny).unwrap_err();
+    assert!(matches!(err, OfferError::FiatAmountOverflow { .. }));
+}
+
+#[test]
+fn offer_not_found_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("factory", &[]);
+    do_init(deps.as_mut(), env.clone(), info.clone());
+    create_offer(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        OfferType::Buy,
+        FiatCurrency::unchecked("BRL"),
+    );
+
+    //Id 0 is never assigned, 2 is past offers_count and u64::MAX was never created
+    for id in [0u64, 2u64, u64::MAX].iter() {
+        let id = *id;
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Offer { id });
+        assert!(matches!(
+            res.unwrap_err(),
+            StdError::NotFound { kind } if kind == format!("Offer {}", id)
+        ));
+
+        let msgs = vec![
+            ExecuteMsg::Pause { id },
+            ExecuteMsg::Activate { id },
+            ExecuteMsg::Update {
+                id,
+                offer: OfferMsg {
+                    offer_type: OfferType::Sell,
+                    maker_contact: Some("LunaQueen".to_string()),
+                    fiat_currency: FiatCurrency::unchecked("COP"),
+                    min_amount: Uint128::new(1),
+                    max_amount: Uint128::new(2),
+                    expires_at: None,
+                    price_margin_bps: None,
+                    payment_methods: vec![],
+                    description: None,
+                    asset: OfferAsset::default(),
+                    min_trades_completed: None,
+                    total_available: Uint128::zero(),
+                    activate_at: None,
+                    pause_at: None,
+                    amounts_in_fiat: false,
+                    available_from_hour: None,
+                    available_to_hour: None,
+                },
+            },
+            ExecuteMsg::NewTrade {
+                offer_id: id,
+                amount: Uint128::new(1000000),
+                taker: "taker".to_string(),
+                taker_contact: "USTKing".to_string(),
+                multisig_release: false,
+            },
+        ];
+        for msg in msgs {
+            let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+            assert!(matches!(err, OfferError::NotFound { id: not_found } if not_found == id));
+        }
+    }
+}
+
+#[test]
+fn offers_by_owner_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    let other = mock_info("other", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+
+    //Maker owns ids 1, 3 and 4 across currencies, other owns id 2
+    create_offer(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        OfferType::Buy,
+        FiatCurrency::unchecked("BRL"),
+    );
+    create_offer(
+        deps.as_mut(),
+        env.clone(),
+        other.clone(),
+        OfferType::Buy,
+        FiatCurrency::unchecked("BRL"),
+    );
+    create_offer(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        OfferType::Sell,
+        FiatCurrency::unchecked("COP"),
+    );
+    create_offer(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        OfferType::Buy,
+        FiatCurrency::unchecked("USD"),
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Pause { id: 3 },
+    )
+    .unwrap();
+
+    let query_page = |last_value: Option<u64>, limit: u32| {
+        let offers: Vec<Offer> = from_binary::<OffersPageResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::OffersByOwner {
+                    owner: "maker".to_string(),
+                    last_value,
+                    limit,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .offers;
+        offers.iter().map(|offer| offer.id).collect::<Vec<u64>>()
+    };
+
+    //Paused offers are listed so the maker can manage them
+    assert_eq!(query_page(None, 10), vec![1, 3, 4]);
+    assert_eq!(query_page(None, 2), vec![1, 3]);
+    assert_eq!(query_page(Some(3), 2), vec![4]);
+}
+
+#[test]
+fn offer_state_transitions_test() {
+    let states = [OfferState::Active, OfferState::Paused, OfferState::Archived];
+    let allowed = [
+        (OfferState::Active, OfferState::Paused),
+        (OfferState::Active, OfferState::Archived),
+        (OfferState::Paused, OfferState::Active),
+        (OfferState::Paused, OfferState::Archived),
+    ];
+
+    for from in states.iter() {
+        for to in states.iter() {
+            let mut deps = mock_dependencies(&[], None);
+            let env = mock_env();
+            let info = mock_info("maker", &[]);
+            do_init(deps.as_mut(), env.clone(), info.clone());
+            create_offer(
+                deps.as_mut(),
+                env.clone(),
+                info.clone(),
+                OfferType::Buy,
+                FiatCurrency::unchecked("BRL"),
+            );
+
+            //Move the offer into the `from` state
+            let setup_msg = match from {
+                OfferState::Active => None,
+                OfferState::Paused => Some(ExecuteMsg::Pause { id: 1 }),
+                OfferState::Archived => Some(ExecuteMsg::Archive { id: 1 }),
+            };
+            if let Some(msg) = setup_msg {
+                execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+            }
+
+            let msg = match to {
+                OfferState::Active => ExecuteMsg::Activate { id: 1 },
+                OfferState::Paused => ExecuteMsg::Pause { id: 1 },
+                OfferState::Archived => ExecuteMsg::Archive { id: 1 },
+            };
+            let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+
+            let offer = load_offer_by_id(&deps.storage, 1).unwrap();
+            if allowed.contains(&(from.clone(), to.clone())) {
+                assert!(res.is_ok(), "{} -> {} should be allowed", from, to);
+                assert_eq!(&offer.state, to);
+            } else {
+                assert!(
+                    matches!(res.unwrap_err(), OfferError::InvalidStateChange { .. }),
+                    "{} -> {} should be rejected",
+                    from,
+                    to
+                );
+                assert_eq!(&offer.state, from);
+            }
+        }
+    }
+}
+
+#[test]
+fn trade_inactive_offer_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+
+    //Paused and archived offers can't be traded
+    for msg in [ExecuteMsg::Pause { id: 1 }, ExecuteMsg::Archive { id: 1 }] {
+        execute(deps.as_mut(), env.clone(), maker.clone(), msg).unwrap();
+        let state = load_offer_by_id(&deps.storage, 1).unwrap().state;
+        let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap_err();
+        assert!(matches!(
+            err,
+            OfferError::OfferNotActive { id: 1, state: s } if s == state
+        ));
+    }
+}
+
+#[test]
+fn new_trade_amount_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: OfferMsg {
+                min_amount: Uint128::new(500),
+                max_amount: Uint128::new(1000),
+                ..expiring_offer_msg(None)
+            },
+        },
+    )
+    .unwrap();
+    let new_trade = |amount: u128| ExecuteMsg::NewTrade {
+        offer_id: 1,
+        amount: Uint128::new(amount),
+        taker: "taker".to_string(),
+        taker_contact: "USTKing".to_string(),
+        multisig_release: false,
+    };
+
+    //Bounds are inclusive and the error echoes the range
+    for amount in [499u128, 1001u128] {
+        let err =
+            execute(deps.as_mut(), env.clone(), maker.clone(), new_trade(amount)).unwrap_err();
+        assert!(matches!(
+            err,
+            OfferError::TradeAmountOutOfRange { amount: a, min_amount, max_amount }
+                if a == Uint128::new(amount)
+                    && min_amount == Uint128::new(500)
+                    && max_amount == Uint128::new(1000)
+        ));
+    }
+    for amount in [500, 1000] {
+        let res = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade(amount)).unwrap();
+        //Unpriced offers in units of the asset report no price nor fiat amount
+        assert!(!res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "fiat_price" || attr.key == "fiat_amount"));
+    }
+
+    //Anything but a string of digits is refused when decoding the message
+    let new_trade_json = |amount: &str| {
+        format!(
+            r#"{{"new_trade":{{"offer_id":1,"amount":{},"taker":"taker","taker_contact":"USTKing","arbitrator":"arbitrator"}}}}"#,
+            amount
+        )
+    };
+    for amount in [
+        r#""1,000""#,
+        r#""1e6""#,
+        r#""-1""#,
+        r#""""#,
+        r#""5 UST""#,
+        r#""340282366920938463463374607431768211456""#,
+    ] {
+        assert!(
+            from_slice::<ExecuteMsg>(new_trade_json(amount).as_bytes()).is_err(),
+            "{} should be refused",
+            amount
+        );
+    }
+    let msg: ExecuteMsg =
+        from_slice(new_trade_json(r#""340282366920938463463374607431768211455""#).as_bytes())
+            .unwrap();
+    assert!(
+        matches!(msg, ExecuteMsg::NewTrade { amount, .. } if amount == Uint128::new(u128::MAX))
+    );
+
+    //The stored offer is checked, paused offers can't be traded at any amount
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Pause { id: 1 },
+    )
+    .unwrap();
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade(500)).unwrap_err();
+    assert!(matches!(err, OfferError::OfferNotActive { id: 1, .. }));
+}
+
+#[test]
+fn new_trade_rate_limit_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let mut env = mock_env();
+    let factory = mock_info("factory", &[]);
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: OfferMsg {
+                max_amount: Uint128::new(100),
+                ..expiring_offer_msg(None)
+            },
+        },
+    )
+    .unwrap();
+    let new_trade = |taker: &str| ExecuteMsg::NewTrade {
+        offer_id: 1,
+        amount: Uint128::new(1),
+        taker: taker.to_string(),
+        taker_contact: "USTKing".to_string(),
+        multisig_release: false,
+    };
+
+    let taker = mock_info("taker", &[]);
+
+    //Unlimited by default
+    for _ in 0..3 {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            taker.clone(),
+            new_trade("taker"),
+        )
+        .unwrap();
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        factory,
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            new_trade_limit: Some(2),
+            new_trade_window: Some(600),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "new_trade_limit" && attr.value == "2"));
+
+    //Two trades per sender and window
+    env.block.time = Timestamp::from_seconds(6000);
+    for _ in 0..2 {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            taker.clone(),
+            new_trade("taker"),
+        )
+        .unwrap();
+    }
+    env.block.time = Timestamp::from_seconds(6599);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        taker.clone(),
+        new_trade("taker"),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::TradeRateLimited { limit: 2, retry_at, .. } if retry_at.seconds() == 6600
+    ));
+    assert_eq!(
+        err.to_string(),
+        "taker opened 2 trades in the current window, retry at 6600."
+    );
+
+    //Naming another taker doesn't give the sender a new quota
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        taker.clone(),
+        new_trade("other"),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::TradeRateLimited { sender, .. } if sender == Addr::unchecked("taker")
+    ));
+
+    //Other senders have their own quota
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("other", &[]),
+        new_trade("other"),
+    )
+    .unwrap();
+
+    //The next window starts the count over
+    env.block.time = Timestamp::from_seconds(6600);
+    execute(deps.as_mut(), env, taker, new_trade("taker")).unwrap();
+}
+
+#[test]
+fn max_trade_amount_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+    let offer_msg = |max_amount: u128| OfferMsg {
+        max_amount: Uint128::new(max_amount),
+        ..expiring_offer_msg(None)
+    };
+    let set_limit = |max_trade_amount: u128| {
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            max_trade_amount: Some(Uint128::new(max_trade_amount)),
+            ..Default::default()
+        })
+    };
+    let new_trade = |amount: u128| ExecuteMsg::NewTrade {
+        offer_id: 1,
+        amount: Uint128::new(amount),
+        taker: "taker".to_string(),
+        taker_contact: "USTKing".to_string(),
+        multisig_release: false,
+    };
+
+    //Zero means no limit
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: offer_msg(10),
+        },
+    )
+    .unwrap();
+    execute(deps.as_mut(), env.clone(), factory.clone(), set_limit(5)).unwrap();
+    let cfg: Config =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(cfg.max_trade_amount, Uint128::new(5));
+
+    //Offers above the limit are rejected on create and update
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: offer_msg(6),
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::MaxTradeAmountExceeded { amount, max_trade_amount }
+            if amount == Uint128::new(6) && max_trade_amount == Uint128::new(5)
+    ));
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Update {
+            id: 1,
+            offer: offer_msg(6),
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::MaxTradeAmountExceeded { .. }));
+
+    //Older offers still can't be traded above it
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade(6)).unwrap_err();
+    assert!(matches!(err, OfferError::MaxTradeAmountExceeded { .. }));
+    execute(deps.as_mut(), env.clone(), maker.clone(), new_trade(5)).unwrap();
+}
+
+#[test]
+fn new_trade_addresses_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    let new_trade = |taker: &str| ExecuteMsg::NewTrade {
+        offer_id: 1,
+        amount: Uint128::new(1),
+        taker: taker.to_string(),
+        taker_contact: "USTKing".to_string(),
+        multisig_release: false,
+    };
+
+    //Empty, too short and non normalized addresses name the failing field
+    for taker in ["", "tk", "Taker"] {
+        let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade(taker)).unwrap_err();
+        assert!(matches!(
+            err,
+            OfferError::InvalidAddress { field, .. } if field == "taker"
+        ));
+    }
+
+    //Owners can't take their own offers
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        new_trade("maker"),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::SelfTradeNotAllowed { owner } if owner == Addr::unchecked("maker")
+    ));
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("other", &[]),
+        new_trade("maker"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::SelfTradeNotAllowed { .. }));
+
+    //Config addresses are checked the same way
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("factory", &[]),
+        ExecuteMsg::ProposeNewAdmin {
+            new_admin: "".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::InvalidAddress { field, .. } if field == "new_admin"));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        new_trade("taker"),
+    )
+    .unwrap();
+}
+
+#[test]
+fn archived_offers_hidden_from_listings_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), info.clone());
+    for _ in 0..3 {
+        create_offer(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            OfferType::Buy,
+            FiatCurrency::unchecked("BRL"),
+        );
+    }
+
+    //Only the owner can archive
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("other", &[]),
+        ExecuteMsg::Archive { id: 2 },
+    );
+    assert!(matches!(res.unwrap_err(), OfferError::Unauthorized { .. }));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Archive { id: 2 },
+    )
+    .unwrap();
+
+    let offers = OfferModel::query_all_offers(
+        &deps.storage,
+        FiatCurrency::unchecked("BRL"),
+        &OfferFilter::default(),
+        QueryOrder::Asc,
+        env.block.time,
+    )
+    .unwrap();
+    let ids: Vec<u64> = offers.iter().map(|offer| offer.id).collect();
+    assert_eq!(ids, vec![1, 3]);
+
+    let offers: Vec<Offer> = from_binary::<OffersPageResponse>(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::OffersByFiat {
+                fiat_currency: FiatCurrency::unchecked("BRL"),
+                offer_type: None,
+                payment_method: None,
+                denom: None,
+                order: None,
+                last_value: None,
+                limit: 2,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+    .offers;
+    let ids: Vec<u64> = offers.iter().map(|offer| offer.id).collect();
+    assert_eq!(ids, vec![1, 3]);
+}
+
+fn expiring_offer_msg(expires_at: Option<Timestamp>) -> OfferMsg {
+    OfferMsg {
+        offer_type: OfferType::Buy,
+        maker_contact: Some("LunaQueen".to_string()),
+        fiat_currency: FiatCurrency::unchecked("BRL"),
+        min_amount: Uint128::new(1),
+        max_amount: Uint128::new(2),
+        expires_at,
+        price_margin_bps: None,
+        payment_methods: vec![],
+        description: None,
+        asset: OfferAsset::default(),
+        min_trades_completed: None,
+        total_available: Uint128::zero(),
+        activate_at: None,
+        pause_at: None,
+        amounts_in_fiat: false,
+        available_from_hour: None,
+        available_to_hour: None,
+    }
+}
+
+fn new_trade_msg(offer_id: u64) -> ExecuteMsg {
+    ExecuteMsg::NewTrade {
+        offer_id,
+        amount: Uint128::new(1),
+        taker: "taker".to_string(),
+        taker_contact: "USTKing".to_string(),
+        multisig_release: false,
+    }
+}
+
+#[test]
+fn offer_expiration_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+
+    //Expiration has to be in the future
+    for expires_at in [
+        env.block.time,
+        Timestamp::from_seconds(env.block.time.seconds() - 1),
+    ]
+    .iter()
+    {
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::Create {
+                offer: expiring_offer_msg(Some(*expires_at)),
+            },
+        );
+        assert!(matches!(
+            res.unwrap_err(),
+            OfferError::InvalidExpiration { .. }
+        ));
+    }
+
+    //Offer valid for 48 hours
+    let expires_at = env.block.time.plus_seconds(48 * 60 * 60);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(Some(expires_at)),
+        },
+    )
+    .unwrap();
+    let offers = OfferModel::query_all_offers(
+        &deps.storage,
+        FiatCurrency::unchecked("BRL"),
+        &OfferFilter::default(),
+        QueryOrder::Asc,
+        env.block.time,
+    )
+    .unwrap();
+    assert_eq!(offers.len(), 1);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), new_trade_msg(1));
+    assert!(res.is_ok());
+
+    //Once expired the offer is hidden from listings and rejects new trades
+    let mut expired_env = mock_env();
+    expired_env.block.time = expires_at;
+    let offers = OfferModel::query_all_offers(
+        &deps.storage,
+        FiatCurrency::unchecked("BRL"),
+        &OfferFilter::default(),
+        QueryOrder::Asc,
+        expired_env.block.time,
+    )
+    .unwrap();
+    assert_eq!(offers.len(), 0);
+    let res = execute(
+        deps.as_mut(),
+        expired_env.clone(),
+        info.clone(),
+        new_trade_msg(1),
+    );
+    assert!(matches!(
+        res.unwrap_err(),
+        OfferError::Expired { id: 1, .. }
+    ));
+    let offer = load_offer_by_id(&deps.storage, 1).unwrap();
+    assert_eq!(offer.expires_at, Some(expires_at));
+
+    //Updating can clear the expiration
+    execute(
+        deps.as_mut(),
+        expired_env.clone(),
+        info.clone(),
+        ExecuteMsg::Update {
+            id: 1,
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        expired_env.clone(),
+        info.clone(),
+        new_trade_msg(1),
+    );
+    assert!(res.is_ok());
+}
+
+#[test]
+fn offers_by_type_filter_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), info.clone());
+
+    //BRL gets 2 buy and 3 sell offers, COP one of each
+    let offers = vec![
+        (OfferType::Buy, FiatCurrency::unchecked("BRL")),
+        (OfferType::Sell, FiatCurrency::unchecked("BRL")),
+        (OfferType::Sell, FiatCurrency::unchecked("COP")),
+        (OfferType::Sell, FiatCurrency::unchecked("BRL")),
+        (OfferType::Buy, FiatCurrency::unchecked("COP")),
+        (OfferType::Buy, FiatCurrency::unchecked("BRL")),
+        (OfferType::Sell, FiatCurrency::unchecked("BRL")),
+    ];
+    for (offer_type, fiat_currency) in offers {
+        create_offer(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            offer_type,
+            fiat_currency,
+        );
+    }
+
+    let count_offers = |fiat_currency: FiatCurrency, offer_type: Option<OfferType>| {
+        let offers: Vec<Offer> = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::Offers {
+                    fiat_currency,
+                    offer_type,
+                    payment_method: None,
+                    denom: None,
+                    order: None,
+                    amount: None,
+                    min_amount: None,
+                    max_amount: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        offers.len()
+    };
+    assert_eq!(count_offers(FiatCurrency::unchecked("BRL"), None), 5);
+    assert_eq!(
+        count_offers(FiatCurrency::unchecked("BRL"), Some(OfferType::Buy)),
+        2
+    );
+    assert_eq!(
+        count_offers(FiatCurrency::unchecked("BRL"), Some(OfferType::Sell)),
+        3
+    );
+    assert_eq!(count_offers(FiatCurrency::unchecked("COP"), None), 2);
+    assert_eq!(
+        count_offers(FiatCurrency::unchecked("COP"), Some(OfferType::Buy)),
+        1
+    );
+    assert_eq!(
+        count_offers(FiatCurrency::unchecked("COP"), Some(OfferType::Sell)),
+        1
+    );
+
+    //The filter runs before the limit, so a page of 2 holds 2 sell offers
+    let offers: Vec<Offer> = from_binary::<OffersPageResponse>(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::OffersByFiat {
+                fiat_currency: FiatCurrency::unchecked("BRL"),
+                offer_type: Some(OfferType::Sell),
+                payment_method: None,
+                denom: None,
+                order: None,
+                last_value: None,
+                limit: 2,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+    .offers;
+    let ids: Vec<u64> = offers.iter().map(|offer| offer.id).collect();
+    assert_eq!(ids, vec![2, 4]);
+}
+
+#[test]
+fn offers_descending_order_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), info.clone());
+
+    for _ in 0..5 {
+        create_offer(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            OfferType::Buy,
+            FiatCurrency::unchecked("BRL"),
+        );
+    }
+
+    let offers: Vec<Offer> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Offers {
+                fiat_currency: FiatCurrency::unchecked("BRL"),
+                offer_type: None,
+                payment_method: None,
+                denom: None,
+                order: Some(QueryOrder::Desc),
+                amount: None,
+                min_amount: None,
+                max_amount: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let ids: Vec<u64> = offers.iter().map(|offer| offer.id).collect();
+    assert_eq!(ids, vec![5, 4, 3, 2, 1]);
+
+    let query_page = |last_value: Option<u64>| -> Vec<u64> {
+        let offers: Vec<Offer> = from_binary::<OffersPageResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::OffersByFiat {
+                    fiat_currency: FiatCurrency::unchecked("BRL"),
+                    offer_type: None,
+                    payment_method: None,
+                    denom: None,
+                    order: Some(QueryOrder::Desc),
+                    last_value,
+                    limit: 2,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .offers;
+        offers.iter().map(|offer| offer.id).collect()
+    };
+
+    //The cursor is an exclusive upper bound, so no id repeats or gets skipped
+    let first_page = query_page(None);
+    assert_eq!(first_page, vec![5, 4]);
+    let second_page = query_page(first_page.last().cloned());
+    assert_eq!(second_page, vec![3, 2]);
+    let third_page = query_page(second_page.last().cloned());
+    assert_eq!(third_page, vec![1]);
+    assert_eq!(query_page(third_page.last().cloned()), Vec::<u64>::new());
+}
+
+#[test]
+fn create_without_state_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let create = ExecuteMsg::Create {
+        offer: expiring_offer_msg(None),
+    };
+
+    //A missing state reads as a fresh one and is created with the first id
+    deps.storage.remove(&to_length_prefixed(b"state"));
+    assert_eq!(query_state(deps.as_ref()).last_offer_id, None);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), create.clone()).unwrap();
+    let offer: Offer = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(offer.id, 1);
+    let state = query_state(deps.as_ref());
+    assert_eq!(state.offers_count, 1);
+    assert_eq!(state.last_offer_id, Some(1));
+    assert!(!state.paused);
+
+    //Ids are never reused, an exhausted counter fails instead of wrapping
+    deps.storage.set(
+        &to_length_prefixed(b"state"),
+        &to_binary(&State {
+            offers_count: u64::MAX,
+            paused: false,
+        })
+        .unwrap(),
+    );
+    let err = execute(deps.as_mut(), env, info, create).unwrap_err();
+    assert!(matches!(err, OfferError::Std(StdError::Overflow { .. })));
+    assert_eq!(query_state(deps.as_ref()).last_offer_id, Some(u64::MAX));
+}
+
+fn query_state(deps: Deps) -> StateResponse {
+    from_binary(&query(deps, mock_env(), QueryMsg::State {}).unwrap()).unwrap()
+}
+
+#[test]
+fn offer_counts_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), info.clone());
+
+    for fiat_currency in [
+        FiatCurrency::unchecked("BRL"),
+        FiatCurrency::unchecked("BRL"),
+        FiatCurrency::unchecked("COP"),
+    ] {
+        create_offer(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            OfferType::Buy,
+            fiat_currency,
+        );
+    }
+
+    let fiat_counts = |fiat_currency: FiatCurrency, active: u64, paused: u64| FiatOfferCounts {
+        fiat_currency,
+        active,
+        paused,
+    };
+
+    //Pausing moves an offer from active to paused
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Pause { id: 1 },
+    )
+    .unwrap();
+    let state = query_state(deps.as_ref());
+    assert_eq!(state.active_offers_count, 2);
+    assert_eq!(state.paused_offers_count, 1);
+    assert_eq!(
+        state.fiat_offers_counts,
+        vec![
+            fiat_counts(FiatCurrency::unchecked("BRL"), 1, 1),
+            fiat_counts(FiatCurrency::unchecked("COP"), 1, 0)
+        ]
+    );
+
+    //Updating the currency moves the offer between counters
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Update {
+            id: 2,
+            offer: OfferMsg {
+                fiat_currency: FiatCurrency::unchecked("COP"),
+                ..expiring_offer_msg(None)
+            },
+        },
+    )
+    .unwrap();
+    let state = query_state(deps.as_ref());
+    assert_eq!(
+        state.fiat_offers_counts,
+        vec![
+            fiat_counts(FiatCurrency::unchecked("BRL"), 0, 1),
+            fiat_counts(FiatCurrency::unchecked("COP"), 2, 0)
+        ]
+    );
+
+    //Archived offers are no longer counted and empty currencies are dropped
+    for id in 1..=3 {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::Archive { id },
+        )
+        .unwrap();
+    }
+    let state = query_state(deps.as_ref());
+    assert_eq!(state.offers_count, 3);
+    assert_eq!(state.active_offers_count, 0);
+    assert_eq!(state.paused_offers_count, 0);
+    assert_eq!(state.fiat_offers_counts, vec![]);
+}
+
+/// Summary rebuilt from every stored offer, to check the maintained one against.
+fn recompute_summaries(storage: &dyn Storage) -> Vec<FiatOffersSummary> {
+    let mut summaries: BTreeMap<(String, String), FiatOffersSummary> = BTreeMap::new();
+    for item in offers().range(storage, None, None, Order::Ascending) {
+        let (_, offer) = item.unwrap();
+        if offer.state != OfferState::Active {
+            continue;
+        }
+        let pair = offer.pair();
+        let summary = summaries
+            .entry((pair.fiat.to_string(), pair.denom.clone()))
+            .or_insert(FiatOffersSummary {
+                fiat_currency: pair.fiat,
+                denom: pair.denom,
+                buy_count: 0,
+                sell_count: 0,
+                buy_liquidity: Uint128::zero(),
+                sell_liquidity: Uint128::zero(),
+            });
+        match offer.offer_type {
+            OfferType::Buy => {
+                summary.buy_count += 1;
+                summary.buy_liquidity += offer.max_amount;
+            }
+            OfferType::Sell => {
+                summary.sell_count += 1;
+                summary.sell_liquidity += offer.max_amount;
+            }
+        }
+    }
+    summaries.into_values().collect()
+}
+
+#[test]
+fn offers_summary_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), info.clone());
+    let offer_msg = |offer_type: OfferType, fiat_currency: &str, max_amount: u128| OfferMsg {
+        offer_type,
+        fiat_currency: FiatCurrency::unchecked(fiat_currency),
+        max_amount: Uint128::new(max_amount),
+        ..expiring_offer_msg(None)
+    };
+    let query_summary = |deps: Deps| -> Vec<FiatOffersSummary> {
+        from_binary(&query(deps, mock_env(), QueryMsg::OffersSummary {}).unwrap()).unwrap()
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            denoms: Some(vec!["uusd".to_string(), "uluna".to_string()]),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+
+    let events = vec![
+        ExecuteMsg::Create {
+            offer: offer_msg(OfferType::Buy, "COP", 2),
+        },
+        ExecuteMsg::Create {
+            offer: offer_msg(OfferType::Sell, "COP", 5),
+        },
+        ExecuteMsg::Create {
+            offer: offer_msg(OfferType::Buy, "BRL", 3),
+        },
+        ExecuteMsg::CreateMany {
+            offers: vec![offer_msg(OfferType::Sell, "BRL", 7)],
+        },
+        //Moves offer 1 to the BRL sell side with a new max
+        ExecuteMsg::Update {
+            id: 1,
+            offer: offer_msg(OfferType::Sell, "BRL", 4),
+        },
+        ExecuteMsg::UpdateMany {
+            updates: vec![(3, offer_msg(OfferType::Buy, "BRL", 9))],
+        },
+        ExecuteMsg::Pause { id: 2 },
+        //Paused offers can be updated without entering the summary
+        ExecuteMsg::Update {
+            id: 2,
+            offer: offer_msg(OfferType::Buy, "USD", 6),
+        },
+        ExecuteMsg::Activate { id: 2 },
+        ExecuteMsg::Archive { id: 3 },
+        ExecuteMsg::Pause { id: 4 },
+        ExecuteMsg::Archive { id: 4 },
+        //Same currency and side in another denom is its own pair
+        ExecuteMsg::Create {
+            offer: OfferMsg {
+                asset: OfferAsset::Native {
+                    denom: "uluna".to_string(),
+                },
+                ..offer_msg(OfferType::Sell, "BRL", 8)
+            },
+        },
+    ];
+    for msg in events {
+        execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+        assert_eq!(
+            query_summary(deps.as_ref()),
+            recompute_summaries(&deps.storage),
+            "after {:?}",
+            msg
+        );
+    }
+
+    assert_eq!(
+        query_summary(deps.as_ref()),
+        vec![
+            FiatOffersSummary {
+                fiat_currency: FiatCurrency::unchecked("BRL"),
+                denom: "uluna".to_string(),
+                buy_count: 0,
+                sell_count: 1,
+                buy_liquidity: Uint128::zero(),
+                sell_liquidity: Uint128::new(8),
+            },
+            FiatOffersSummary {
+                fiat_currency: FiatCurrency::unchecked("BRL"),
+                denom: "uusd".to_string(),
+                buy_count: 0,
+                sell_count: 1,
+                buy_liquidity: Uint128::zero(),
+                sell_liquidity: Uint128::new(4),
+            },
+            FiatOffersSummary {
+                fiat_currency: FiatCurrency::unchecked("USD"),
+                denom: "uusd".to_string(),
+                buy_count: 1,
+                sell_count: 0,
+                buy_liquidity: Uint128::new(6),
+                sell_liquidity: Uint128::zero(),
+            },
+        ]
+    );
+
+    //Listings filter on the denom of the pair
+    let brl_offers = |denom: Option<&str>| -> Vec<u64> {
+        let offers: Vec<Offer> = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::Offers {
+                    fiat_currency: FiatCurrency::unchecked("BRL"),
+                    offer_type: None,
+                    payment_method: None,
+                    denom: denom.map(String::from),
+                    order: None,
+                    amount: None,
+                    min_amount: None,
+                    max_amount: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        offers.into_iter().map(|offer| offer.id).collect()
+    };
+    assert_eq!(brl_offers(Some("uluna")), vec![5]);
+    assert_eq!(brl_offers(Some("uusd")), vec![1]);
+    assert_eq!(brl_offers(None).len(), 2);
+
+    //The migration recount lands on the same summary
+    OfferModel::recount(&mut deps.storage).unwrap();
+    assert_eq!(
+        query_summary(deps.as_ref()),
+        recompute_summaries(&deps.storage)
+    );
+}
+
+#[test]
+fn max_offers_per_owner_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    let maker = mock_info("maker", &[]);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        InstantiateMsg {
+            max_offers_per_owner: 2,
+            max_trade_amount: Uint128::zero(),
+        },
+    )
+    .unwrap();
+
+    let create_msg = ExecuteMsg::Create {
+        offer: expiring_offer_msg(None),
+    };
+
+    //Exactly at the limit, paused offers still hold a slot
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        create_msg.clone(),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        create_msg.clone(),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Pause { id: 1 },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        create_msg.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::OfferMaxReached { max: 2, .. }));
+
+    //The limit is per owner
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("other", &[]),
+        create_msg.clone(),
+    )
+    .unwrap();
+
+    //Archiving frees a slot
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Archive { id: 1 },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        create_msg.clone(),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        create_msg.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::OfferMaxReached { .. }));
+
+    //Only the factory can change the limit, 0 lifts it
+    let update_config = ExecuteMsg::UpdateConfig(ConfigUpdate {
+        max_offers_per_owner: Some(0),
+        ..Default::default()
+    });
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        update_config.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::Unauthorized { .. }));
+    execute(deps.as_mut(), env.clone(), factory.clone(), update_config).unwrap();
+    execute(deps.as_mut(), env.clone(), maker.clone(), create_msg).unwrap();
+}
+
+#[test]
+fn update_config_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+
+    let update_config = |factory_addr: &str, trade_duration: Option<u64>| {
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            factory_addr: Some(factory_addr.to_string()),
+            trade_duration,
+            ..Default::default()
+        })
+    };
+
+    //Moving to a new factory reports both addresses and keeps the other fields
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        update_config("new-factory", None),
+    )
+    .unwrap();
+    let attr = |key: &str| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .map(|attr| attr.value.clone())
+            .unwrap()
+    };
+    assert_eq!(attr("old_factory_addr"), "factory");
+    assert_eq!(attr("factory_addr"), "new-factory");
+    assert_eq!(attr("old_trade_duration"), attr("trade_duration"));
+    assert!(res.attributes.iter().all(|attr| !attr.value.is_empty()));
+    assert!(!res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "price_oracle_addr"));
+    let cfg: Config =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(cfg.factory_addr, Addr::unchecked("new-factory"));
+    assert_eq!(cfg.trade_duration, 3600);
+
+    //The admin isn't handed over with the factory and can update partially
+    assert_eq!(cfg.admin, Addr::unchecked("factory"));
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("new-factory", &[]),
+        update_config("new-factory", None),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::Unauthorized { .. }));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        update_config("new-factory", Some(60)),
+    )
+    .unwrap();
+    let cfg: Config =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(cfg.factory_addr, Addr::unchecked("new-factory"));
+    assert_eq!(cfg.trade_duration, 60);
+}
+
+#[test]
+fn sender_guards_test() {
+    let factory = Addr::unchecked("factory");
+    assert!(assert_sender_is(&factory, &factory, "factory").is_ok());
+    let err = assert_sender_is(&Addr::unchecked("maker"), &factory, "factory").unwrap_err();
+    assert_eq!(err.to_string(), "Unauthorized, maker is not the factory.");
+
+    //Handlers report the role they expected
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("maker", &[]),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("maker", &[]),
+        ExecuteMsg::SetPause { paused: true },
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::Unauthorized { role, .. } if role == "admin"));
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("factory", &[]),
+        ExecuteMsg::Pause { id: 1 },
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::Unauthorized { role, expected, caller }
+            if role == "offer owner" && expected == Addr::unchecked("maker") && caller == factory
+    ));
+}
+
+#[test]
+fn set_pause_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+    let create_msg = ExecuteMsg::Create {
+        offer: expiring_offer_msg(None),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        create_msg.clone(),
+    )
+    .unwrap();
+
+    //Only the factory can pause
+    let pause = |paused: bool| ExecuteMsg::SetPause { paused };
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), pause(true)).unwrap_err();
+    assert!(matches!(err, OfferError::Unauthorized { .. }));
+    execute(deps.as_mut(), env.clone(), factory.clone(), pause(true)).unwrap();
+    assert!(query_state(deps.as_ref()).paused);
+
+    //Offer writes and new trades are halted
+    let update_msg = ExecuteMsg::Update {
+        id: 1,
+        offer: expiring_offer_msg(None),
+    };
+    for msg in [
+        create_msg.clone(),
+        update_msg.clone(),
+        ExecuteMsg::CreateMany {
+            offers: vec![expiring_offer_msg(None)],
+        },
+        ExecuteMsg::UpdateMany {
+            updates: vec![(1, expiring_offer_msg(None))],
+        },
+        new_trade_msg(1),
+    ] {
+        let err = execute(deps.as_mut(), env.clone(), maker.clone(), msg).unwrap_err();
+        assert!(matches!(err, OfferError::ContractPaused {}));
+    }
+
+    //Single offers can still be paused and activated, queries keep working
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Pause { id: 1 },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Activate { id: 1 },
+    )
+    .unwrap();
+    assert_eq!(query_state(deps.as_ref()).offers_count, 1);
+
+    execute(deps.as_mut(), env.clone(), factory.clone(), pause(false)).unwrap();
+    assert!(!query_state(deps.as_ref()).paused);
+    execute(deps.as_mut(), env.clone(), maker.clone(), update_msg).unwrap();
+    execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap();
+}
+
+#[test]
+fn sudo_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let create_msg = ExecuteMsg::Create {
+        offer: expiring_offer_msg(None),
+    };
+
+    //Governance pauses without the factory key
+    sudo(
+        deps.as_mut(),
+        env.clone(),
+        SudoMsg::SetPause { paused: true },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        create_msg.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::ContractPaused {}));
+    sudo(
+        deps.as_mut(),
+        env.clone(),
+        SudoMsg::SetPause { paused: false },
+    )
+    .unwrap();
+
+    //Governance sends the same JSON as the factory, unset fields omitted
+    let update_config = SudoMsg::UpdateConfig(ConfigUpdate {
+        trade_duration: Some(3600),
+        ..Default::default()
+    });
+    assert_eq!(
+        from_slice::<SudoMsg>(br#"{"update_config":{"trade_duration":3600}}"#).unwrap(),
+        update_config
+    );
+    sudo(deps.as_mut(), env.clone(), update_config).unwrap();
+    let cfg: Config =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(cfg.trade_duration, 3600);
+    assert_eq!(cfg.factory_addr, Addr::unchecked("factory"));
+
+    //Governance can only lift blocks
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("factory", &[]),
+        ExecuteMsg::SetBlacklist {
+            addr: "maker".to_string(),
+            blocked: true,
+        },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        create_msg.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::Blocked {}));
+    let remove = SudoMsg::RemoveBlacklisted {
+        addr: "maker".to_string(),
+    };
+    sudo(deps.as_mut(), env.clone(), remove).unwrap();
+    execute(deps.as_mut(), env.clone(), maker, create_msg).unwrap();
+}
+
+#[test]
+fn page_limit_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+    for i in 0..40 {
+        let msg = ExecuteMsg::SetBlacklist {
+            addr: format!("addr-{:02}", i),
+            blocked: true,
+        };
+        execute(deps.as_mut(), env.clone(), factory.clone(), msg).unwrap();
+    }
+    let page_len = |deps: Deps, limit: u32| -> usize {
+        let msg = QueryMsg::Blacklist {
+            last_value: None,
+            limit,
+        };
+        let page: BlacklistResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(page.limit as usize, page.addrs.len());
+        assert_eq!(page.next.as_ref(), page.addrs.last());
+        page.addrs.len()
+    };
+
+    //Zero falls back to the default, larger limits are clamped
+    assert_eq!(page_len(deps.as_ref(), 0), DEFAULT_PAGE_LIMIT as usize);
+    assert_eq!(page_len(deps.as_ref(), 5), 5);
+    assert_eq!(page_len(deps.as_ref(), 100_000), MAX_PAGE_LIMIT as usize);
+
+    //Offer pages report the clamped limit with their cursor, the deprecated
+    //unpaginated listing stops at the largest page
+    for _ in 0..MAX_PAGE_LIMIT + 5 {
+        let msg = ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("maker", &[]), msg).unwrap();
+    }
+    let msg = QueryMsg::OffersByOwner {
+        owner: "maker".to_string(),
+        last_value: None,
+        limit: 100_000,
+    };
+    let page: OffersPageResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert_eq!(page.offers.len(), MAX_PAGE_LIMIT as usize);
+    assert_eq!(page.limit, MAX_PAGE_LIMIT);
+    assert_eq!(page.next, Some(MAX_PAGE_LIMIT as u64));
+    let msg = QueryMsg::Offers {
+        fiat_currency: FiatCurrency::unchecked("BRL"),
+        offer_type: None,
+        payment_method: None,
+        denom: None,
+        order: None,
+        amount: None,
+        min_amount: None,
+        max_amount: None,
+    };
+    let offers: Vec<Offer> = from_binary(&query(deps.as_ref(), env, msg).unwrap()).unwrap();
+    assert_eq!(offers.len(), MAX_PAGE_LIMIT as usize);
+}
+
+#[test]
+fn blacklist_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+    let create_msg = ExecuteMsg::Create {
+        offer: expiring_offer_msg(None),
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        create_msg.clone(),
+    )
+    .unwrap();
+    let set = |addr: &str, blocked: bool| ExecuteMsg::SetBlacklist {
+        addr: addr.to_string(),
+        blocked,
+    };
+    let query_blacklist = |deps: Deps, last_value: Option<&str>| -> Vec<Addr> {
+        let msg = QueryMsg::Blacklist {
+            last_value: last_value.map(Addr::unchecked),
+            limit: 1,
+        };
+        let page: BlacklistResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(page.next.as_ref(), page.addrs.first());
+        page.addrs
+    };
+
+    //Only the factory manages the list
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        set("taker", true),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::Unauthorized { .. }));
+
+    //A blocked maker can't write offers and their offers can't be traded
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        set("maker", true),
+    )
+    .unwrap();
+    let update_msg = ExecuteMsg::Update {
+        id: 1,
+        offer: expiring_offer_msg(None),
+    };
+    for msg in [create_msg.clone(), update_msg.clone()] {
+        let err = execute(deps.as_mut(), env.clone(), maker.clone(), msg).unwrap_err();
+        assert!(matches!(err, OfferError::Blocked {}));
+    }
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("other", &[]),
+        new_trade_msg(1),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::Blocked {}));
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 1).unwrap().owner,
+        Addr::unchecked("maker")
+    );
+
+    //A blocked taker can't open trades either
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        set("maker", false),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        set("taker", true),
+    )
+    .unwrap();
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap_err();
+    assert!(matches!(err, OfferError::Blocked {}));
+
+    //The list is paginated by address
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        set("other", true),
+    )
+    .unwrap();
+    assert_eq!(
+        query_blacklist(deps.as_ref(), None),
+        vec![Addr::unchecked("other")]
+    );
+    assert_eq!(
+        query_blacklist(deps.as_ref(), Some("other")),
+        vec![Addr::unchecked("taker")]
+    );
+    assert!(query_blacklist(deps.as_ref(), Some("taker")).is_empty());
+
+    //Unblocking restores everything
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        set("taker", false),
+    )
+    .unwrap();
+    execute(deps.as_mut(), env.clone(), maker.clone(), update_msg).unwrap();
+    execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap();
+}
+
+#[test]
+fn admin_handover_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+    let propose = |new_admin: &str| ExecuteMsg::ProposeNewAdmin {
+        new_admin: new_admin.to_string(),
+    };
+    let query_config = |deps: Deps| -> Config {
+        from_binary(&query(deps, mock_env(), QueryMsg::Config {}).unwrap()).unwrap()
+    };
+
+    //Only the admin can propose, the proposal alone changes nothing
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("stranger", &[]),
+        propose("stranger"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::Unauthorized { .. }));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        propose("admin-a"),
+    )
+    .unwrap();
+    let cfg = query_config(deps.as_ref());
+    assert_eq!(cfg.factory_addr, Addr::unchecked("factory"));
+    assert_eq!(cfg.pending_admin, Some(Addr::unchecked("admin-a")));
+
+    //Only the pending admin can accept
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("stranger", &[]),
+        ExecuteMsg::AcceptAdmin {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::Unauthorized { .. }));
+
+    //A new proposal replaces the pending one
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        propose("admin-b"),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin-a", &[]),
+        ExecuteMsg::AcceptAdmin {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::Unauthorized { .. }));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin-b", &[]),
+        ExecuteMsg::AcceptAdmin {},
+    )
+    .unwrap();
+    let cfg = query_config(deps.as_ref());
+    assert_eq!(cfg.admin, Addr::unchecked("admin-b"));
+    assert_eq!(cfg.pending_admin, None);
+
+    //The factory is still queried for new trades
+    assert_eq!(cfg.factory_addr, Addr::unchecked("factory"));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("maker", &[]),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("taker", &[]),
+        new_trade_msg(1),
+    )
+    .unwrap();
+
+    //The previous admin lost its rights
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        propose("factory"),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::Unauthorized { .. }));
+
+    //Canceled proposals can't be accepted
+    let admin = mock_info("admin-b", &[]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin.clone(),
+        propose("admin-c"),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        admin.clone(),
+        ExecuteMsg::CancelAdminProposal {},
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("admin-c", &[]),
+        ExecuteMsg::AcceptAdmin {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::NoPendingAdmin {}));
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        admin,
+        ExecuteMsg::CancelAdminProposal {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::NoPendingAdmin {}));
+
+    //Configs stored before the admin existed get the factory when migrated
+    let cfg = query_config(deps.as_ref());
+    let legacy = String::from_utf8(to_binary(&cfg).unwrap().to_vec())
+        .unwrap()
+        .replace(r#""admin":"admin-b","#, "");
+    assert!(!legacy.contains(r#""admin""#));
+    deps.storage
+        .set(&to_length_prefixed(b"config"), legacy.as_bytes());
+    assert_eq!(query_config(deps.as_ref()).admin, Addr::unchecked(""));
+    migrate(deps.as_mut(), env, MigrateMsg {}).unwrap();
+    assert_eq!(
+        query_config(deps.as_ref()).admin,
+        Addr::unchecked("factory")
+    );
+}
+
+fn trade_instantiate_msg(res: &Response) -> TradeInstantiateMsg {
+    match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Instantiate { msg, .. }) => from_binary(msg).unwrap(),
+        _ => panic!("Expected a trade instantiation."),
+    }
+}
+
+fn trade_fiat_price(res: &Response) -> Option<Decimal> {
+    trade_instantiate_msg(res).fiat_price
+}
+
+#[test]
+fn amount_range_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let create = |min: u128, max: u128| ExecuteMsg::Create {
+        offer: OfferMsg {
+            min_amount: Uint128::new(min),
+            max_amount: Uint128::new(max),
+            ..expiring_offer_msg(None)
+        },
+    };
+
+    //Zero minimums and inverted ranges are rejected
+    for (min, max) in [(0, 10), (11, 10)] {
+        let err = execute(deps.as_mut(), env.clone(), maker.clone(), create(min, max)).unwrap_err();
+        assert!(matches!(
+            err,
+            OfferError::InvalidAmountRange { min: m, max: x }
+                if m == Uint128::new(min) && x == Uint128::new(max)
+        ));
+    }
+
+    //Fixed amount offers are allowed
+    execute(deps.as_mut(), env.clone(), maker.clone(), create(10, 10)).unwrap();
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 1).unwrap().min_amount,
+        Uint128::new(10)
+    );
+
+    //A max of exactly the global cap or ratio is accepted, one above isn't
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("factory", &[]),
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            max_trade_amount: Some(Uint128::new(100)),
+            max_amount_ratio: Some(5),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    execute(deps.as_mut(), env.clone(), maker.clone(), create(20, 100)).unwrap();
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), create(30, 101)).unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::MaxTradeAmountExceeded { amount, max_trade_amount }
+            if amount == Uint128::new(101) && max_trade_amount == Uint128::new(100)
+    ));
+    execute(deps.as_mut(), env.clone(), maker.clone(), create(10, 50)).unwrap();
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), create(10, 51)).unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::AmountRatioExceeded { min, max, max_ratio: 5 }
+            if min == Uint128::new(10) && max == Uint128::new(51)
+    ));
+
+    //Updates are held to the same bounds
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Update {
+            id: 1,
+            offer: OfferMsg {
+                min_amount: Uint128::new(1),
+                max_amount: Uint128::new(6),
+                ..expiring_offer_msg(None)
+            },
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::AmountRatioExceeded { .. }));
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 1).unwrap().max_amount,
+        Uint128::new(10)
+    );
+}
+
+#[test]
+fn floating_price_offer_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+
+    //Margins of -100% or lower and above the maximum are rejected
+    for price_margin_bps in [-10_000, i32::MIN, MAX_PRICE_MARGIN_BPS + 1, i32::MAX] {
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Create {
+                offer: OfferMsg {
+                    price_margin_bps: Some(price_margin_bps),
+                    ..expiring_offer_msg(None)
+                },
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, OfferError::InvalidPriceMargin { .. }));
+    }
+
+    //Offer 1 is spot + 2%, offer 2 is spot - 2.5% and offer 3 has a fixed price
+    for price_margin_bps in [Some(200), Some(-250), None] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Create {
+                offer: OfferMsg {
+                    price_margin_bps,
+                    ..expiring_offer_msg(None)
+                },
+            },
+        )
+        .unwrap();
+    }
+
+    //Without an oracle floating price offers can't be traded
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap_err();
+    assert!(matches!(err, OfferError::PriceUnavailable { .. }));
+    let res = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(3)).unwrap();
+    assert_eq!(trade_fiat_price(&res), None);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            price_oracle_addr: Some("oracle".to_string()),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+
+    //The oracle has no rate for the currency
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap_err();
+    assert!(matches!(err, OfferError::PriceUnavailable { .. }));
+
+    //Rates are per denom, a uluna rate doesn't price a uusd offer
+    deps.querier.with_prices(&[(
+        FiatCurrency::unchecked("BRL"),
+        "uluna",
+        Decimal::from_ratio(5u128, 1u128),
+    )]);
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap_err();
+    assert!(matches!(err, OfferError::PriceUnavailable { .. }));
+
+    //A zero rate is refused as well
+    deps.querier
+        .with_prices(&[(FiatCurrency::unchecked("BRL"), "uusd", Decimal::zero())]);
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap_err();
+    assert!(matches!(err, OfferError::PriceUnavailable { .. }));
+
+    deps.querier.with_prices(&[(
+        FiatCurrency::unchecked("BRL"),
+        "uusd",
+        Decimal::from_ratio(5u128, 1u128),
+    )]);
+    let res = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap();
+    assert_eq!(
+        trade_fiat_price(&res),
+        Some(Decimal::from_ratio(51u128, 10u128))
+    );
+    let res = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(2)).unwrap();
+    assert_eq!(
+        trade_fiat_price(&res),
+        Some(Decimal::from_ratio(4875u128, 1000u128))
+    );
+
+    //Margins stored before they were bounded refuse the trade instead of overflowing
+    let mut offer = load_offer_by_id(&deps.storage, 2).unwrap();
+    offer.price_margin_bps = Some(i32::MAX);
+    OfferModel::store(&mut deps.storage, &offer).unwrap();
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(2)).unwrap_err();
+    assert!(matches!(err, OfferError::PriceUnavailable { .. }));
+
+    //Rates older than the max price age are stale, the mock oracle updated at 1641329895
+    let mut env = env;
+    env.block.time = Timestamp::from_seconds(1641329895 + DEFAULT_MAX_PRICE_AGE);
+    execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap();
+    env.block.time = env.block.time.plus_seconds(1);
+    let err = execute(deps.as_mut(), env, maker, new_trade_msg(1)).unwrap_err();
+    assert!(matches!(err, OfferError::PriceUnavailable { .. }));
+}
+
+#[test]
+fn payment_methods_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), info.clone());
+
+    let offer_msg = |payment_methods: Vec<&str>| OfferMsg {
+        payment_methods: payment_methods.iter().map(|m| m.to_string()).collect(),
+        ..expiring_offer_msg(None)
+    };
+
+    //Bounds are checked on Create and Update
+    let too_long = "x".repeat(33);
+    let invalid = vec![
+        vec!["pix", "cash", "bank", "wise", "zelle", "paypal"],
+        vec![too_long.as_str()],
+        vec![""],
+    ];
+    for payment_methods in invalid {
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::Create {
+                offer: offer_msg(payment_methods),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, OfferError::InvalidPaymentMethods { .. }));
+    }
+
+    //Offers 1, 3 and 5 accept pix, 2 and 4 accept cash only and 6 has none
+    let valid = vec![
+        vec!["pix", "bank_transfer"],
+        vec!["cash"],
+        vec!["pix"],
+        vec!["cash"],
+        vec!["cash", "pix"],
+        vec![],
+    ];
+    for payment_methods in valid {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::Create {
+                offer: offer_msg(payment_methods),
+            },
+        )
+        .unwrap();
+    }
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Update {
+            id: 6,
+            offer: offer_msg(vec!["", "pix"]),
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::InvalidPaymentMethods { .. }));
+
+    let offers: Vec<Offer> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Offers {
+                fiat_currency: FiatCurrency::unchecked("BRL"),
+                offer_type: None,
+                payment_method: Some("cash".to_string()),
+                denom: None,
+                order: None,
+                amount: None,
+                min_amount: None,
+                max_amount: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let ids: Vec<u64> = offers.iter().map(|offer| offer.id).collect();
+    assert_eq!(ids, vec![2, 4, 5]);
+
+    //The filter runs before the limit, so pages of pix offers stay full
+    let query_page = |last_value: Option<u64>| -> Vec<u64> {
+        let offers: Vec<Offer> = from_binary::<OffersPageResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::OffersByFiat {
+                    fiat_currency: FiatCurrency::unchecked("BRL"),
+                    offer_type: None,
+                    payment_method: Some("pix".to_string()),
+                    denom: None,
+                    order: None,
+                    last_value,
+                    limit: 2,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .offers;
+        offers.iter().map(|offer| offer.id).collect()
+    };
+    assert_eq!(query_page(None), vec![1, 3]);
+    assert_eq!(query_page(Some(3)), vec![5]);
+}
+
+#[test]
+fn offer_description_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), info.clone());
+
+    let create = |deps: DepsMut, description: String| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("maker", &[]),
+            ExecuteMsg::Create {
+                offer: OfferMsg {
+                    description: Some(description),
+                    ..expiring_offer_msg(None)
+                },
+            },
+        )
+    };
+
+    //Exactly at the limit, "é" takes 2 bytes
+    create(deps.as_mut(), "a".repeat(280)).unwrap();
+    create(deps.as_mut(), "é".repeat(140)).unwrap();
+    create(deps.as_mut(), "é".repeat(139) + "ab").unwrap();
+
+    //Over the limit, 140 chars of "é" plus one byte
+    let err = create(deps.as_mut(), "a".repeat(281)).unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::DescriptionTooLong {
+            len: 281,
+            max_len: 280
+        }
+    ));
+    let err = create(deps.as_mut(), "é".repeat(140) + "a").unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::DescriptionTooLong { len: 281, .. }
+    ));
+
+    let err = create(deps.as_mut(), "receipt\nrequired".to_string()).unwrap_err();
+    assert!(matches!(err, OfferError::InvalidDescription {}));
+
+    //Updating with None clears the description
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Update {
+            id: 1,
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 1).unwrap().description,
+        None
+    );
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 2).unwrap().description,
+        Some("é".repeat(140))
+    );
+}
+
+#[test]
+fn maker_contact_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let info = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), info.clone());
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Create {
+            offer: OfferMsg {
+                maker_contact: Some("x".repeat(1025)),
+                ..expiring_offer_msg(None)
+            },
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::MakerContactTooLong {
+            len: 1025,
+            max_len: 1024
+        }
+    ));
+
+    let ciphertext = "x".repeat(1024);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Create {
+            offer: OfferMsg {
+                maker_contact: Some(ciphertext.clone()),
+                ..expiring_offer_msg(None)
+            },
+        },
+    )
+    .unwrap();
+
+    //Listings leave the contact out, the single offer query returns it
+    let offers: Vec<Offer> = from_binary::<OffersPageResponse>(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::OffersByOwner {
+                owner: "maker".to_string(),
+                last_value: None,
+                limit: 10,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+    .offers;
+    assert_eq!(offers[0].maker_contact, None);
+
+    let offer: Offer =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Offer { id: 1 }).unwrap())
+            .unwrap();
+    assert_eq!(offer.maker_contact, Some(ciphertext));
+}
+
+#[test]
+fn offer_schedule_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let mut env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let start = env.block.time;
+    let scheduled = |activate_at: u64, pause_at: u64| OfferMsg {
+        activate_at: Some(start.plus_seconds(activate_at)),
+        pause_at: Some(start.plus_seconds(pause_at)),
+        ..expiring_offer_msg(None)
+    };
+
+    //Windows closing before they open are rejected
+    for offer in [scheduled(100, 100), scheduled(200, 100)] {
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Create { offer },
+        );
+        assert!(matches!(
+            res.unwrap_err(),
+            OfferError::InvalidSchedule { .. }
+        ));
+    }
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: scheduled(100, 200),
+        },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Update {
+            id: 1,
+            offer: scheduled(300, 200),
+        },
+    );
+    assert!(matches!(
+        res.unwrap_err(),
+        OfferError::InvalidSchedule { .. }
+    ));
+
+    let effective_state = |deps: Deps, env: &Env| -> Option<OfferState> {
+        let offer: Offer =
+            from_binary(&query(deps, env.clone(), QueryMsg::Offer { id: 1 }).unwrap()).unwrap();
+        offer.effective_state
+    };
+
+    //Before the window the offer is paused, although stored as active
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 1).unwrap().state,
+        OfferState::Active
+    );
+    assert_eq!(
+        effective_state(deps.as_ref(), &env),
+        Some(OfferState::Paused)
+    );
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::OfferNotActive {
+            id: 1,
+            state: OfferState::Paused
+        }
+    ));
+
+    //Within the window it is active, even once paused by hand
+    env.block.time = start.plus_seconds(150);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Pause { id: 1 },
+    )
+    .unwrap();
+    assert_eq!(
+        effective_state(deps.as_ref(), &env),
+        Some(OfferState::Active)
+    );
+    let msg = QueryMsg::OffersByOwner {
+        owner: "maker".to_string(),
+        last_value: None,
+        limit: 10,
+    };
+    let listed: Vec<Offer> =
+        from_binary::<OffersPageResponse>(&query(deps.as_ref(), env.clone(), msg).unwrap())
+            .unwrap()
+            .offers;
+    assert_eq!(listed[0].state, OfferState::Paused);
+    assert_eq!(listed[0].effective_state, Some(OfferState::Active));
+    execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap();
+
+    //From pause_at on it is paused again
+    env.block.time = start.plus_seconds(200);
+    assert_eq!(
+        effective_state(deps.as_ref(), &env),
+        Some(OfferState::Paused)
+    );
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap_err();
+    assert!(matches!(err, OfferError::OfferNotActive { id: 1, .. }));
+}
+
+#[test]
+fn operators_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let maker = mock_info("maker", &[]);
+    let bot = mock_info("bot", &[]);
+    for _ in 0..2 {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Create {
+                offer: expiring_offer_msg(None),
+            },
+        )
+        .unwrap();
+    }
+    let operators = |deps: Deps, owner: &str| -> Vec<Addr> {
+        let msg = QueryMsg::Operators {
+            owner: owner.to_string(),
+        };
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+    };
+    let set_operator = |operator: &str, enabled: bool| ExecuteMsg::SetOperator {
+        operator: operator.to_string(),
+        enabled,
+    };
+
+    //Without a grant the bot can't manage the offers
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        bot.clone(),
+        ExecuteMsg::Pause { id: 1 },
+    );
+    assert!(matches!(res.unwrap_err(), OfferError::Unauthorized { .. }));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        set_operator("bot", true),
+    )
+    .unwrap();
+    assert_eq!(
+        operators(deps.as_ref(), "maker"),
+        vec![Addr::unchecked("bot")]
+    );
+
+    //Operators pause, activate, update and archive the owner's offers
+    for msg in [
+        ExecuteMsg::Pause { id: 1 },
+        ExecuteMsg::Activate { id: 1 },
+        ExecuteMsg::Update {
+            id: 1,
+            offer: OfferMsg {
+                max_amount: Uint128::new(5),
+                ..expiring_offer_msg(None)
+            },
+        },
+        ExecuteMsg::Archive { id: 2 },
+    ] {
+        execute(deps.as_mut(), env.clone(), bot.clone(), msg).unwrap();
+    }
+    let offer = load_offer_by_id(&deps.storage, 1).unwrap();
+    assert_eq!(offer.owner, Addr::unchecked("maker"));
+    assert_eq!(offer.max_amount, Uint128::new(5));
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 2).unwrap().state,
+        OfferState::Archived
+    );
+
+    //Copies are made by the owner only
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        bot.clone(),
+        ExecuteMsg::CloneOffer {
+            id: 1,
+            overrides: None,
+        },
+    );
+    assert!(matches!(res.unwrap_err(), OfferError::Unauthorized { .. }));
+
+    //Grants made by the bot are its own, the maker's set is unchanged
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        bot.clone(),
+        set_operator("other", true),
+    )
+    .unwrap();
+    assert_eq!(
+        operators(deps.as_ref(), "maker"),
+        vec![Addr::unchecked("bot")]
+    );
+    assert_eq!(
+        operators(deps.as_ref(), "bot"),
+        vec![Addr::unchecked("other")]
+    );
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("other", &[]),
+        ExecuteMsg::Pause { id: 1 },
+    );
+    assert!(matches!(res.unwrap_err(), OfferError::Unauthorized { .. }));
+
+    //Revoking takes effect right away
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        set_operator("bot", false),
+    )
+    .unwrap();
+    assert!(operators(deps.as_ref(), "maker").is_empty());
+    let res = execute(deps.as_mut(), env.clone(), bot, ExecuteMsg::Pause { id: 1 });
+    assert!(matches!(res.unwrap_err(), OfferError::Unauthorized { .. }));
+    execute(deps.as_mut(), env, maker, ExecuteMsg::Pause { id: 1 }).unwrap();
+}
+
+#[test]
+fn clone_offer_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let info = mock_info("maker", &[]);
+    let source = OfferMsg {
+        payment_methods: vec!["pix".to_string()],
+        description: Some("Fast".to_string()),
+        total_available: Uint128::new(10),
+        ..expiring_offer_msg(None)
+    };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Create { offer: source },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Pause { id: 1 },
+    )
+    .unwrap();
+
+    //Only the owner can clone the offer
+    let clone = ExecuteMsg::CloneOffer {
+        id: 1,
+        overrides: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("other", &[]),
+        clone.clone(),
+    );
+    assert!(matches!(res.unwrap_err(), OfferError::Unauthorized { .. }));
+
+    //A plain clone copies the terms into a new active offer
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), clone).unwrap();
+    let copy: Offer = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(copy.id, 2);
+    assert_eq!(copy.state, OfferState::Active);
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "source_id" && attr.value == "1"));
+    let source = load_offer_by_id(&deps.storage, 1).unwrap();
+    assert_eq!(copy.to_msg(), source.to_msg());
+    assert_eq!(copy.remaining, Uint128::new(10));
+
+    //Overrides replace single fields, the rest comes from the source
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::CloneOffer {
+            id: 1,
+            overrides: Some(OfferOverrides {
+                offer_type: Some(OfferType::Sell),
+                max_amount: Some(Uint128::new(5)),
+                ..OfferOverrides::default()
+            }),
+        },
+    )
+    .unwrap();
+    let copy: Offer = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(copy.id, 3);
+    assert_eq!(
+        copy.to_msg(),
+        OfferMsg {
+            offer_type: OfferType::Sell,
+            max_amount: Uint128::new(5),
+            ..source.to_msg()
+        }
+    );
+
+    //Overrides are validated like a new offer
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::CloneOffer {
+            id: 1,
+            overrides: Some(OfferOverrides {
+                min_amount: Some(Uint128::new(3)),
+                ..OfferOverrides::default()
+            }),
+        },
+    );
+    assert!(matches!(
+        res.unwrap_err(),
+        OfferError::InvalidAmountRange { .. }
+    ));
+
+    //Archived offers can't be cloned
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::Archive { id: 1 },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info,
+        ExecuteMsg::CloneOffer {
+            id: 1,
+            overrides: None,
+        },
+    );
+    assert!(matches!(
+        res.unwrap_err(),
+        OfferError::CloneArchived { id: 1 }
+    ));
+}
+
+#[test]
+fn delete_offer_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+    for _ in 0..3 {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Create {
+                offer: expiring_offer_msg(None),
+            },
+        )
+        .unwrap();
+    }
+    let delete = |id: u64| ExecuteMsg::Delete { id };
+
+    //Only the owner deletes an offer
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("other", &[]),
+        delete(3),
+    );
+    assert!(matches!(res.unwrap_err(), OfferError::Unauthorized { .. }));
+
+    //Untraded offers are removed with their counters
+    let res = execute(deps.as_mut(), env.clone(), maker.clone(), delete(3)).unwrap();
+    assert_eq!(
+        offer_event_attrs(&res)[0],
+        expected_offer_event("delete_offer", 3, Some("Active"), "Active")
+    );
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::Offer { id: 3 });
+    assert!(matches!(res.unwrap_err(), StdError::NotFound { .. }));
+    let state = query_state(deps.as_ref());
+    assert_eq!(state.offers_count, 3);
+    assert_eq!(state.active_offers_count, 2);
+    assert_eq!(state.fiat_offers_counts[0].active, 2);
+    let msg = QueryMsg::OffersByOwner {
+        owner: "maker".to_string(),
+        last_value: None,
+        limit: 10,
+    };
+    let owned: Vec<Offer> =
+        from_binary::<OffersPageResponse>(&query(deps.as_ref(), env.clone(), msg).unwrap())
+            .unwrap()
+            .offers;
+    assert_eq!(
+        owned.iter().map(|offer| offer.id).collect::<Vec<u64>>(),
+        vec![1, 2]
+    );
+    let res = execute(deps.as_mut(), env.clone(), maker.clone(), delete(3));
+    assert!(matches!(res.unwrap_err(), OfferError::NotFound { id: 3 }));
+
+    //Offers that had trades are left to be archived
+    execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap();
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-1");
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), delete(1)).unwrap_err();
+    assert!(matches!(err, OfferError::HasTradeHistory { id: 1 }));
+    assert_eq!(
+        err.to_string(),
+        "Offer 1 has trades and can't be deleted, archive it instead."
+    );
+
+    //Once the config allows it, only open trades keep the offer
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory,
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            allow_delete_traded: Some(true),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    let res = execute(deps.as_mut(), env.clone(), maker.clone(), delete(1));
+    assert!(matches!(
+        res.unwrap_err(),
+        OfferError::HasActiveTrades { count: 1 }
+    ));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("trade-1", &[]),
+        ExecuteMsg::TradeClosed {
+            state: TradeState::Released,
+        },
+    )
+    .unwrap();
+    execute(deps.as_mut(), env.clone(), maker.clone(), delete(1)).unwrap();
+
+    //Their trades are still listed, without the offer
+    let msg = QueryMsg::TradesPage {
+        maker: maker.sender.to_string(),
+        last_value: None,
+        limit: 10,
+    };
+    let trades: Vec<TradeInfo> =
+        from_binary::<TradesPageResponse>(&query(deps.as_ref(), env.clone(), msg).unwrap())
+            .unwrap()
+            .trades;
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].trade_addr, Addr::unchecked("trade-1"));
+    assert!(trades[0].offer.is_none());
+
+    //New offers don't reuse deleted ids
+    let res = execute(
+        deps.as_mut(),
+        env,
+        maker,
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    let offer: Offer = from_binary(&res.data.unwrap()).unwrap();
+    assert_eq!(offer.id, 4);
+}
+
+#[test]
+fn offers_amount_filter_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    for (min_amount, max_amount) in [(1u128, 100u128), (50, 200), (150, 300)].iter() {
+        let offer = OfferMsg {
+            min_amount: Uint128::new(*min_amount),
+            max_amount: Uint128::new(*max_amount),
+            ..expiring_offer_msg(None)
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("maker", &[]),
+            ExecuteMsg::Create { offer },
+        )
+        .unwrap();
+    }
+    let offers = |deps: Deps, amount: Option<u128>, min: Option<u128>, max: Option<u128>| {
+        let msg = QueryMsg::Offers {
+            fiat_currency: FiatCurrency::unchecked("BRL"),
+            offer_type: None,
+            payment_method: None,
+            denom: None,
+            order: None,
+            amount: amount.map(Uint128::new),
+            min_amount: min.map(Uint128::new),
+            max_amount: max.map(Uint128::new),
+        };
+        let offers: Vec<Offer> = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        offers.iter().map(|offer| offer.id).collect::<Vec<u64>>()
+    };
+
+    //Offers whose bounds take the exact amount, bounds included
+    assert_eq!(offers(deps.as_ref(), Some(150), None, None), vec![2, 3]);
+    assert_eq!(offers(deps.as_ref(), Some(100), None, None), vec![1, 2]);
+    assert_eq!(
+        offers(deps.as_ref(), Some(301), None, None),
+        Vec::<u64>::new()
+    );
+
+    //Offers overlapping a range
+    assert_eq!(offers(deps.as_ref(), None, Some(250), None), vec![3]);
+    assert_eq!(offers(deps.as_ref(), None, None, Some(40)), vec![1]);
+    assert_eq!(offers(deps.as_ref(), None, Some(120), Some(140)), vec![2]);
+
+    //Pages are filled with matching offers only
+    let page = |deps: Deps, start_before: Option<u64>| -> OffersPageResponse {
+        let msg = QueryMsg::OffersPage {
+            order: None,
+            start_before,
+            last_value: None,
+            limit: 1,
+            amount: Some(Uint128::new(60)),
+            min_amount: None,
+            max_amount: None,
+        };
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+    };
+    let first = page(deps.as_ref(), None);
+    assert_eq!(first.offers[0].id, 2);
+    let second = page(deps.as_ref(), first.next);
+    assert_eq!(second.offers[0].id, 1);
+}
+
+#[test]
+fn simulate_trade_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    deps.querier.with_fees(99, 0, 5_000);
+    for offer_type in [OfferType::Buy, OfferType::Sell].iter() {
+        let offer = OfferMsg {
+            offer_type: offer_type.clone(),
+            max_amount: Uint128::new(1_000_000),
+            ..expiring_offer_msg(None)
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Create { offer },
+        )
+        .unwrap();
+    }
+    let simulate = |deps: Deps, offer_id: u64, amount: u128| {
+        let msg = QueryMsg::SimulateTrade {
+            offer_id,
+            amount: Uint128::new(amount),
+        };
+        query(deps, mock_env(), msg).map(|res| from_binary::<TradeSimulation>(&res).unwrap())
+    };
+
+    //Fees are rounded down, tiny trades pay none
+    for amount in [1u128, 3].iter() {
+        let simulation = simulate(deps.as_ref(), 1, *amount).unwrap();
+        assert_eq!(simulation.fee_amount, Uint128::zero());
+        assert_eq!(simulation.receive_amount, Uint128::new(*amount));
+    }
+
+    //Buy offers pay the fee out of the amount, sell offers escrow it on top
+    let buy = simulate(deps.as_ref(), 1, 10_000).unwrap();
+    assert_eq!(
+        buy,
+        TradeSimulation {
+            offer_id: 1,
+            asset: OfferAsset::default(),
+            amount: Uint128::new(10_000),
+            maker_fee_bps: 99,
+            taker_fee_bps: 0,
+            fee_burn_bps: 5_000,
+            maker_fee_amount: Uint128::new(99),
+            taker_fee_amount: Uint128::zero(),
+            fee_amount: Uint128::new(99),
+            burn_amount: Uint128::new(49),
+            escrow_amount: Uint128::new(10_000),
+            receive_amount: Uint128::new(9_901),
+        }
+    );
+    let sell = simulate(deps.as_ref(), 2, 10_000).unwrap();
+    assert_eq!(sell.escrow_amount, Uint128::new(10_099));
+    assert_eq!(sell.receive_amount, Uint128::new(10_000));
+
+    //Takers pay their fee out of what the buyer receives
+    deps.querier.with_fees(0, 60, 5_000);
+    let buy = simulate(deps.as_ref(), 1, 10_000).unwrap();
+    assert_eq!(buy.escrow_amount, Uint128::new(10_000));
+    assert_eq!(buy.receive_amount, Uint128::new(9_940));
+    deps.querier.with_fees(40, 60, 5_000);
+    let sell = simulate(deps.as_ref(), 2, 10_000).unwrap();
+    assert_eq!(
+        (
+            sell.maker_fee_amount,
+            sell.taker_fee_amount,
+            sell.fee_amount
+        ),
+        (Uint128::new(40), Uint128::new(60), Uint128::new(100))
+    );
+    assert_eq!(sell.escrow_amount, Uint128::new(10_040));
+    assert_eq!(sell.receive_amount, Uint128::new(9_940));
+
+    //Native assets include the tax on moving the fees and the trade amount out of the escrow
+    deps.querier.with_fees(99, 0, 5_000);
+    deps.querier.with_tax(
+        Decimal::percent(1),
+        &[(&"uusd".to_string(), &Uint128::new(1_000_000))],
+    );
+    let sell = simulate(deps.as_ref(), 2, 10_000).unwrap();
+    assert_eq!(sell.escrow_amount, Uint128::new(10_200));
+    assert_eq!(sell.receive_amount, Uint128::new(10_000));
+    let buy = simulate(deps.as_ref(), 1, 10_000).unwrap();
+    assert_eq!(buy.escrow_amount, Uint128::new(10_000));
+    assert_eq!(buy.receive_amount, Uint128::new(9_801));
+
+    //Amounts NewTrade would refuse are refused
+    assert!(simulate(deps.as_ref(), 1, 0).is_err());
+    assert!(simulate(deps.as_ref(), 1, 1_000_001).is_err());
+    assert!(simulate(deps.as_ref(), 3, 1).is_err());
+}
+
+#[test]
+fn offer_ibc_denom_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let create = |denom: String| ExecuteMsg::Create {
+        offer: OfferMsg {
+            asset: OfferAsset::Native { denom },
+            ..expiring_offer_msg(None)
+        },
+    };
+
+    //Vouchers trusted by the factory don't need to be whitelisted, look-alikes are refused
+    let spoofed = ibc_voucher_denom("channel-99", "uatom");
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        create(spoofed.clone()),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::UntrustedIbcDenom { denom } if denom == spoofed));
+    let trusted = ibc_voucher_denom("channel-1", "uatom");
+    execute(deps.as_mut(), env.clone(), maker, create(trusted.clone())).unwrap();
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 1).unwrap().asset,
+        OfferAsset::Native { denom: trusted }
+    );
+}
+
+#[test]
+fn offer_denom_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+
+    let luna_offer = ExecuteMsg::Create {
+        offer: OfferMsg {
+            asset: OfferAsset::Native {
+                denom: "uluna".to_string(),
+            },
+            ..expiring_offer_msg(None)
+        },
+    };
+
+    //Only whitelisted denoms are accepted
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        luna_offer.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::UnsupportedDenom { denom } if denom == "uluna"));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            denoms: Some(vec!["uusd".to_string(), "uluna".to_string()]),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    execute(deps.as_mut(), env.clone(), maker.clone(), luna_offer).unwrap();
+
+    //The denom is forwarded to the trade, the legacy ust_amount field still parses
+    let new_trade: ExecuteMsg = from_slice(
+        br#"{"new_trade":{
+            "offer_id":1,
+            "ust_amount":"1",
+            "taker":"taker",
+            "taker_contact":"USTKing",
+            "arbitrator":"arbitrator"
+        }}"#,
+    )
+    .unwrap();
+    let res = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade).unwrap();
+    match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Instantiate { msg, .. }) => {
+            let trade_msg: TradeInstantiateMsg = from_binary(msg).unwrap();
+            assert_eq!(trade_msg.amount, Uint128::new(1));
+            assert_eq!(
+                trade_msg.asset,
+                OfferAsset::Native {
+                    denom: "uluna".to_string()
+                }
+            );
+        }
+        _ => panic!("Expected a trade instantiation."),
+    }
+
+    //Offers stored before the field existed read as native uusd
+    let offer: Offer = from_slice(
+        br#"{
+            "id":2,
+            "owner":"maker",
+            "maker_contact":"LunaQueen",
+            "offer_type":"buy",
+            "fiat_currency":"BRL",
+            "min_amount":"1",
+            "max_amount":"2",
+            "state":"active",
+            "timestamp":1641329895
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(offer.asset, OfferAsset::default());
+}
+
+#[test]
+fn cw20_offer_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let token = "wbtc-token".to_string();
+    deps.querier
+        .with_token_balances(&[(&token, &[(&"maker".to_string(), &Uint128::new(100))])]);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+
+    let cw20_offer = |contract_addr: &str| ExecuteMsg::Create {
+        offer: OfferMsg {
+            asset: OfferAsset::Cw20 {
+                contract_addr: Addr::unchecked(contract_addr),
+            },
+            ..expiring_offer_msg(None)
+        },
+    };
+
+    //Addresses that are not a token are refused
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        cw20_offer("not-a-token"),
+    )
+    .unwrap_err();
+    assert!(
+        matches!(err, OfferError::InvalidCw20 { contract_addr } if contract_addr == "not-a-token")
+    );
+
+    //Tokens do not need to be in the native denoms whitelist
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        cw20_offer(&token),
+    )
+    .unwrap();
+    let res = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap();
+    match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Instantiate { msg, .. }) => {
+            let trade_msg: TradeInstantiateMsg = from_binary(msg).unwrap();
+            assert_eq!(
+                trade_msg.asset,
+                OfferAsset::Cw20 {
+                    contract_addr: Addr::unchecked(&token)
+                }
+            );
+        }
+        _ => panic!("Expected a trade instantiation."),
+    }
+}
+
+#[test]
+fn new_trade_code_id_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    let trade_code_id = |res: &Response| match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Instantiate { code_id, .. }) => *code_id,
+        _ => panic!("Expected a trade instantiation."),
+    };
+
+    //The code id is read from the factory for every new trade
+    deps.querier.with_trade_code_id(1);
+    let res = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap();
+    assert_eq!(trade_code_id(&res), 1);
+    deps.querier.with_trade_code_id(2);
+    let res = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap();
+    assert_eq!(trade_code_id(&res), 2);
+}
+
+fn trade_instantiated(deps: DepsMut, env: Env, trade: &str) {
+    let result = ContractResult::Ok(SubMsgExecutionResponse {
+        events: vec![Event::new("instantiate_contract").add_attribute("contract_address", trade)],
+        data: None,
+    });
+    reply(
+        deps,
+        env,
+        Reply {
+            id: NEW_TRADE_REPLY_ID,
+            result,
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn trade_instance_reply_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), maker.clone());
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+
+    //Unknown ids, failed instantiations and missing addresses are refused
+    let reply_with =
+        |id: u64, result: ContractResult<SubMsgExecutionResponse>| Reply { id, result };
+    let no_events = || {
+        ContractResult::Ok(SubMsgExecutionResponse {
+            events: vec![],
+            data: None,
+        })
+    };
+    let err = reply(deps.as_mut(), env.clone(), reply_with(7, no_events())).unwrap_err();
+    assert!(matches!(err, OfferError::InvalidReply {}));
+    let failed = ContractResult::Err("instantiate failed".to_string());
+    let err = reply(
+        deps.as_mut(),
+        env.clone(),
+        reply_with(NEW_TRADE_REPLY_ID, failed),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::InvalidReply {}));
+    let err = reply(
+        deps.as_mut(),
+        env.clone(),
+        reply_with(NEW_TRADE_REPLY_ID, no_events()),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::MissingTradeAddr {}));
+    assert!(trades().may_load(&deps.storage, "trade").unwrap().is_none());
+    assert_eq!(load_offer_by_id(&deps.storage, 1).unwrap().active_trades, 0);
+
+    //The instantiated address is registered and reported back
+    let result = ContractResult::Ok(SubMsgExecutionResponse {
+        events: vec![Event::new("instantiate_contract").add_attribute("contract_address", "trade")],
+        data: None,
+    });
+    let res = reply(
+        deps.as_mut(),
+        env.clone(),
+        reply_with(NEW_TRADE_REPLY_ID, result),
+    )
+    .unwrap();
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "trade_addr" && attr.value == "trade"));
+    let data: NewTradeResponse = from_binary(&res.data.clone().unwrap()).unwrap();
+    assert_eq!(
+        data,
+        NewTradeResponse {
+            trade_addr: Addr::unchecked("trade"),
+            offer_id: 1,
+            amount: Uint128::new(1),
+        }
+    );
+    //The factory is asked to record the new trade and the arbitrator it got
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "maker".to_string(),
+            msg: to_binary(&FactoryExecuteMsg::RegisterTrade {
+                addr: "trade".to_string(),
+                arbitrator: Some(Arbitrator {
+                    addr: Addr::unchecked("arbitrator"),
+                    fiat: FiatCurrency::unchecked("BRL"),
+                }),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+    let trade = trades().load(&deps.storage, "trade").unwrap();
+    assert_eq!(trade.trade, Addr::unchecked("trade"));
+    assert_eq!(trade.offer_id, 1);
+    assert_eq!(trade.seller, Addr::unchecked("taker"));
+    assert_eq!(trade.buyer, Addr::unchecked("offer-owner"));
+    assert_eq!(trade.maker, Addr::unchecked("maker"));
+    assert_eq!(trade.created_at, env.block.time.seconds());
+    assert_eq!(load_offer_by_id(&deps.storage, 1).unwrap().active_trades, 1);
+
+    //Trade queries point at the trade contract
+    let infos: Vec<TradeInfo> = from_binary::<TradesResponse>(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::TradesAsTaker {
+                taker: "taker".to_string(),
+                last_value: None,
+                limit: 10,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+    .trades;
+    assert_eq!(infos.len(), 1);
+    assert_eq!(infos[0].trade_addr, Addr::unchecked("trade"));
+    assert_eq!(infos[0].offer_id, 1);
+}
+
+#[test]
+fn max_concurrent_trades_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    let taker = mock_info("taker", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("maker", &[]),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory,
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            max_concurrent_trades: Some(2),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+
+    //Only instantiated trades count, a failed instantiation never replies
+    execute(deps.as_mut(), env.clone(), taker.clone(), new_trade_msg(1)).unwrap();
+    assert_eq!(load_offer_by_id(&deps.storage, 1).unwrap().active_trades, 0);
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-1");
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-2");
+
+    let err = execute(deps.as_mut(), env.clone(), taker.clone(), new_trade_msg(1)).unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::TooManyOpenTrades {
+            offer_id: 1,
+            max: 2
+        }
+    ));
+
+    //Closing a trade frees a slot
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("trade-1", &[]),
+        ExecuteMsg::TradeClosed {
+            state: TradeState::Released,
+        },
+    )
+    .unwrap();
+    execute(deps.as_mut(), env.clone(), taker, new_trade_msg(1)).unwrap();
+}
+
+#[test]
+fn offer_liquidity_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    deps.querier.with_trade_amounts(&[
+        ("trade-1", Uint128::new(600)),
+        ("trade-2", Uint128::new(350)),
+    ]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: OfferMsg {
+                min_amount: Uint128::new(100),
+                max_amount: Uint128::new(1000),
+                ..expiring_offer_msg(None)
+            },
+        },
+    )
+    .unwrap();
+    let listed = |deps: Deps| -> usize {
+        let msg = QueryMsg::Offers {
+            fiat_currency: FiatCurrency::unchecked("BRL"),
+            offer_type: None,
+            payment_method: None,
+            denom: None,
+            order: None,
+            amount: None,
+            min_amount: None,
+            max_amount: None,
+        };
+        let offers: Vec<Offer> = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        offers.len()
+    };
+    let new_trade = |amount: u128| ExecuteMsg::NewTrade {
+        offer_id: 1,
+        amount: Uint128::new(amount),
+        taker: "taker".to_string(),
+        taker_contact: "USTKing".to_string(),
+        multisig_release: false,
+    };
+    let close = |deps: DepsMut, trade: &str| {
+        let msg = ExecuteMsg::TradeClosed {
+            state: TradeState::Released,
+        };
+        execute(deps, mock_env(), mock_info(trade, &[]), msg).unwrap();
+    };
+
+    //Open trades reserve part of the max amount
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-1");
+    let offer = load_offer_by_id(&deps.storage, 1).unwrap();
+    assert_eq!(offer.reserved_amount, Uint128::new(600));
+    assert_eq!(offer.available_amount(), Uint128::new(400));
+    assert_eq!(listed(deps.as_ref()), 1);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("taker", &[]),
+        new_trade(500),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::InsufficientLiquidity { offer_id: 1, available } if available == Uint128::new(400)
+    ));
+
+    //Below the min amount the offer is no longer listed but stays active
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-2");
+    assert_eq!(listed(deps.as_ref()), 0);
+    let offer = load_offer_by_id(&deps.storage, 1).unwrap();
+    assert_eq!(offer.state, OfferState::Active);
+    close(deps.as_mut(), "trade-2");
+    assert_eq!(listed(deps.as_ref()), 1);
+
+    //Freed liquidity doesn't undo a pause
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Pause { id: 1 },
+    )
+    .unwrap();
+    close(deps.as_mut(), "trade-1");
+    let offer = load_offer_by_id(&deps.storage, 1).unwrap();
+    assert_eq!(offer.reserved_amount, Uint128::zero());
+    assert_eq!(offer.state, OfferState::Paused);
+}
+
+#[test]
+fn offer_remaining_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    deps.querier.with_trade_amounts(&[
+        ("trade-1", Uint128::new(600)),
+        ("trade-2", Uint128::new(500)),
+        ("trade-3", Uint128::new(750)),
+        ("trade-4", Uint128::new(100)),
+        ("trade-5", Uint128::new(200)),
+    ]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("maker", &[]),
+        ExecuteMsg::Create {
+            offer: OfferMsg {
+                min_amount: Uint128::new(100),
+                max_amount: Uint128::new(1000),
+                total_available: Uint128::new(1500),
+                ..expiring_offer_msg(None)
+            },
+        },
+    )
+    .unwrap();
+    let close = |deps: DepsMut, trade: &str, state: TradeState| {
+        let msg = ExecuteMsg::TradeClosed { state };
+        execute(deps, mock_env(), mock_info(trade, &[]), msg).unwrap();
+    };
+    let new_trade = |amount: u128| ExecuteMsg::NewTrade {
+        offer_id: 1,
+        amount: Uint128::new(amount),
+        taker: "taker".to_string(),
+        taker_contact: "USTKing".to_string(),
+        multisig_release: false,
+    };
+    let queried = |deps: Deps| -> Offer {
+        from_binary(&query(deps, mock_env(), QueryMsg::Offer { id: 1 }).unwrap()).unwrap()
+    };
+    assert_eq!(queried(deps.as_ref()).remaining, Uint128::new(1500));
+
+    //Released trades are taken out of the remaining amount, refunds are not
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-1");
+    close(deps.as_mut(), "trade-1", TradeState::Released);
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-2");
+    close(deps.as_mut(), "trade-2", TradeState::Refunded);
+    let offer = queried(deps.as_ref());
+    assert_eq!(offer.total_available, Uint128::new(1500));
+    assert_eq!(offer.remaining, Uint128::new(900));
+    assert_eq!(offer.available_amount(), Uint128::new(900));
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("taker", &[]),
+        new_trade(950),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::InsufficientLiquidity { available, .. } if available == Uint128::new(900)
+    ));
+
+    //So are disputes settled for the buyer, the maker of buy offers, not those for the seller
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-4");
+    close(deps.as_mut(), "trade-4", TradeState::SettledForMaker);
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-5");
+    close(deps.as_mut(), "trade-5", TradeState::SettledForTaker);
+    assert_eq!(queried(deps.as_ref()).remaining, Uint128::new(800));
+
+    //Below the min amount no more trades are accepted
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-3");
+    close(deps.as_mut(), "trade-3", TradeState::Released);
+    assert_eq!(queried(deps.as_ref()).remaining, Uint128::new(50));
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("taker", &[]),
+        new_trade(100),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::InsufficientLiquidity { available, .. } if available == Uint128::new(50)
+    ));
+    assert!(!queried(deps.as_ref()).has_liquidity());
+}
+
+fn offer_event_attrs(res: &Response) -> Vec<Vec<(String, String)>> {
+    res.events
+        .iter()
+        .filter(|event| event.ty == OFFER_EVENT)
+        .map(|event| {
+            event
+                .attributes
+                .iter()
+                .map(|attr| (attr.key.clone(), attr.value.clone()))
+                .collect()
+        })
+        .collect()
+}
+
+fn expected_offer_event(
+    action: &str,
+    id: u64,
+    from_state: Option<&str>,
+    to_state: &str,
+) -> Vec<(String, String)> {
+    let id = id.to_string();
+    let mut attrs = vec![
+        ("action", action),
+        ("offer_id", &id),
+        ("owner", "maker"),
+        ("fiat", "BRL"),
+    ];
+    attrs.extend(from_state.map(|from_state| ("from_state", from_state)));
+    attrs.push(("to_state", to_state));
+    attrs
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn offer_events_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let run = |deps: DepsMut, sender: &str, msg: ExecuteMsg| {
+        execute(deps, mock_env(), mock_info(sender, &[]), msg).unwrap()
+    };
+    let event = expected_offer_event;
+
+    let res = run(
+        deps.as_mut(),
+        "maker",
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    );
+    assert_eq!(
+        offer_event_attrs(&res),
+        vec![event("create_offer", 1, None, "Active")]
+    );
+    let res = run(
+        deps.as_mut(),
+        "maker",
+        ExecuteMsg::CreateMany {
+            offers: vec![expiring_offer_msg(None), expiring_offer_msg(None)],
+        },
+    );
+    assert_eq!(
+        offer_event_attrs(&res),
+        vec![
+            event("create_offer", 2, None, "Active"),
+            event("create_offer", 3, None, "Active")
+        ]
+    );
+    let res = run(
+        deps.as_mut(),
+        "maker",
+        ExecuteMsg::Update {
+            id: 1,
+            offer: expiring_offer_msg(None),
+        },
+    );
+    assert_eq!(
+        offer_event_attrs(&res),
+        vec![event("update_offer", 1, Some("Active"), "Active")]
+    );
+    let res = run(
+        deps.as_mut(),
+        "maker",
+        ExecuteMsg::UpdateMany {
+            updates: vec![(2, expiring_offer_msg(None))],
+        },
+    );
+    assert_eq!(
+        offer_event_attrs(&res),
+        vec![event("update_offer", 2, Some("Active"), "Active")]
+    );
+    let res = run(deps.as_mut(), "maker", ExecuteMsg::Pause { id: 2 });
+    assert_eq!(
+        offer_event_attrs(&res),
+        vec![event("pause_offer", 2, Some("Active"), "Paused")]
+    );
+    let res = run(deps.as_mut(), "maker", ExecuteMsg::Activate { id: 2 });
+    assert_eq!(
+        offer_event_attrs(&res),
+        vec![event("activate_offer", 2, Some("Paused"), "Active")]
+    );
+    let res = run(deps.as_mut(), "maker", ExecuteMsg::Archive { id: 3 });
+    assert_eq!(
+        offer_event_attrs(&res),
+        vec![event("archive_offer", 3, Some("Active"), "Archived")]
+    );
+
+    //Trades opening and closing on the offer name the trade and its amount
+    let result = ContractResult::Ok(SubMsgExecutionResponse {
+        events: vec![Event::new("instantiate_contract").add_attribute("contract_address", "trade")],
+        data: None,
+    });
+    let reply_msg = Reply {
+        id: NEW_TRADE_REPLY_ID,
+        result,
+    };
+    let res = reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+    let mut opened = event("open_trade", 1, Some("Active"), "Active");
+    opened.push(("trade_addr".to_string(), "trade".to_string()));
+    opened.push(("amount".to_string(), "1".to_string()));
+    assert_eq!(offer_event_attrs(&res), vec![opened]);
+    let res = run(
+        deps.as_mut(),
+        "trade",
+        ExecuteMsg::TradeClosed {
+            state: TradeState::Released,
+        },
+    );
+    let mut closed = event("close_trade", 1, Some("Active"), "Active");
+    closed.push(("trade_addr".to_string(), "trade".to_string()));
+    closed.push(("amount".to_string(), "1".to_string()));
+    assert_eq!(offer_event_attrs(&res), vec![closed]);
+}
+
+#[test]
+fn offer_response_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let run = |deps: DepsMut, msg: ExecuteMsg| {
+        execute(deps, mock_env(), mock_info("maker", &[]), msg).unwrap()
+    };
+    let attr = |res: &Response, key: &str| -> String {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .map(|attr| attr.value.clone())
+            .unwrap()
+    };
+
+    //The assigned id comes back in the attributes and the data
+    let res = run(
+        deps.as_mut(),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    );
+    let offer: Offer = from_binary(&res.data.clone().unwrap()).unwrap();
+    assert_eq!(offer, load_offer_by_id(&deps.storage, 1).unwrap());
+    assert_eq!(attr(&res, "action"), "create_offer");
+    assert_eq!(attr(&res, "offer_id"), "1");
+    assert_eq!(attr(&res, "owner"), "maker");
+    assert_eq!(attr(&res, "offer_type"), "Buy");
+    assert_eq!(attr(&res, "fiat_currency"), "BRL");
+
+    let msgs = vec![
+        ("pause_offer", ExecuteMsg::Pause { id: 1 }),
+        ("activate_offer", ExecuteMsg::Activate { id: 1 }),
+        (
+            "update_offer",
+            ExecuteMsg::Update {
+                id: 1,
+                offer: expiring_offer_msg(None),
+            },
+        ),
+    ];
+    for (action, msg) in msgs {
+        let res = run(deps.as_mut(), msg);
+        let offer: Offer = from_binary(&res.data.clone().unwrap()).unwrap();
+        assert_eq!(offer, load_offer_by_id(&deps.storage, 1).unwrap());
+        assert_eq!(attr(&res, "action"), action);
+        assert_eq!(attr(&res, "offer_id"), "1");
+    }
+}
+
+#[test]
+fn profile_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let profile = |deps: Deps, addr: &str| -> ProfileResponse {
+        let msg = QueryMsg::Profile {
+            addr: addr.to_string(),
+        };
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+    };
+
+    //Unknown addresses get an empty profile
+    let empty = ProfileResponse {
+        stats: TraderStats::default(),
+        offers_count: 0,
+        recent_offers: vec![],
+        open_trades: 0,
+    };
+    assert_eq!(profile(deps.as_ref(), "maker"), empty);
+
+    for _ in 0..12 {
+        let msg = ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("maker", &[]), msg).unwrap();
+    }
+    for trade in ["trade-1", "trade-2"].iter() {
+        trade_instantiated(deps.as_mut(), env.clone(), trade);
+    }
+    let msg = ExecuteMsg::TradeClosed {
+        state: TradeState::Released,
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("trade-1", &[]), msg).unwrap();
+
+    let maker = profile(deps.as_ref(), "maker");
+    assert_eq!(maker.stats.trades_as_maker, 1);
+    assert_eq!(maker.offers_count, 12);
+    assert_eq!(
+        maker.recent_offers.iter().map(|o| o.id).collect::<Vec<_>>(),
+        (3..=12).rev().collect::<Vec<u64>>()
+    );
+    assert!(maker
+        .recent_offers
+        .iter()
+        .all(|o| o.maker_contact.is_none()));
+    assert_eq!(maker.open_trades, 1);
+    let taker = profile(deps.as_ref(), "taker");
+    assert_eq!(taker.stats.trades_as_taker, 1);
+    assert_eq!(taker.offers_count, 0);
+    assert_eq!(taker.open_trades, 1);
+
+    //Trades registered before the counter existed are counted on migration
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-3");
+    for addr in ["maker", "taker"].iter() {
+        OPEN_TRADES.remove(deps.as_mut().storage, &Addr::unchecked(*addr));
+    }
+    recount_open_trades(deps.as_mut().storage).unwrap();
+    assert_eq!(profile(deps.as_ref(), "maker").open_trades, 2);
+    assert_eq!(profile(deps.as_ref(), "taker").open_trades, 2);
+
+    //Any final state closes a trade
+    for trade in ["trade-2", "trade-3"].iter() {
+        let msg = ExecuteMsg::TradeClosed {
+            state: TradeState::Refunded,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(trade, &[]), msg).unwrap();
+    }
+    assert_eq!(profile(deps.as_ref(), "maker").open_trades, 0);
+    assert_eq!(profile(deps.as_ref(), "taker").open_trades, 0);
+}
+
+#[test]
+fn trader_stats_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("maker", &[]),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    for trade in ["trade-1", "trade-2", "trade-3"].iter() {
+        trade_instantiated(deps.as_mut(), env.clone(), trade);
+    }
+    let close = |deps: DepsMut, sender: &str, state: TradeState, env: Env| {
+        let msg = ExecuteMsg::TradeClosed { state };
+        execute(deps, env, mock_info(sender, &[]), msg)
+    };
+    let stats = |deps: Deps, addr: &str| -> TraderStats {
+        let msg = QueryMsg::TraderStats {
+            addr: addr.to_string(),
+        };
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+    };
+
+    //Only trades spawned by the contract report back
+    let err = close(deps.as_mut(), "stranger", TradeState::Released, env.clone()).unwrap_err();
+    assert!(matches!(err, OfferError::TradeNotOpen { .. }));
+    assert_eq!(stats(deps.as_ref(), "maker"), TraderStats::default());
+
+    //Refunds and settled disputes don't count as trades
+    close(deps.as_mut(), "trade-1", TradeState::Refunded, env.clone()).unwrap();
+    assert_eq!(stats(deps.as_ref(), "maker"), TraderStats::default());
+
+    //Retried dispute reports are only counted once
+    for _ in 0..2 {
+        let msg = ExecuteMsg::TradeStateChanged {
+            state: TradeState::Disputed,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("trade-2", &[]), msg).unwrap();
+    }
+    close(
+        deps.as_mut(),
+        "trade-2",
+        TradeState::SettledForTaker,
+        env.clone(),
+    )
+    .unwrap();
+    let err = close(
+        deps.as_mut(),
+        "trade-2",
+        TradeState::SettledForTaker,
+        env.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::TradeNotOpen { .. }));
+    let disputes = |lost: u64, won: u64| TraderStats {
+        disputes_opened: 1,
+        disputes_lost: lost,
+        disputes_won: won,
+        ..TraderStats::default()
+    };
+    assert_eq!(stats(deps.as_ref(), "maker"), disputes(1, 0));
+    assert_eq!(stats(deps.as_ref(), "taker"), disputes(0, 1));
+
+    let mut later = env.clone();
+    later.block.time = env.block.time.plus_seconds(100);
+    close(
+        deps.as_mut(),
+        "trade-3",
+        TradeState::Released,
+        later.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        stats(deps.as_ref(), "maker"),
+        TraderStats {
+            trades_as_maker: 1,
+            trades_as_taker: 0,
+            last_trade_at: later.block.time,
+            ..disputes(1, 0)
+        }
+    );
+    assert_eq!(
+        stats(deps.as_ref(), "taker"),
+        TraderStats {
+            trades_as_maker: 0,
+            trades_as_taker: 1,
+            last_trade_at: later.block.time,
+            ..disputes(0, 1)
+        }
+    );
+}
+
+#[test]
+fn active_trades_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    trade_instantiated(deps.as_mut(), env.clone(), "trade");
+    assert_eq!(load_offer_by_id(&deps.storage, 1).unwrap().active_trades, 1);
+
+    let update = ExecuteMsg::Update {
+        id: 1,
+        offer: OfferMsg {
+            max_amount: Uint128::new(3),
+            ..expiring_offer_msg(None)
+        },
+    };
+    let close = |state: TradeState| ExecuteMsg::TradeClosed { state };
+
+    //Updating or archiving is refused while the trade is open, pausing is not
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), update.clone()).unwrap_err();
+    assert!(matches!(err, OfferError::HasActiveTrades { count: 1 }));
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Archive { id: 1 },
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::HasActiveTrades { count: 1 }));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Pause { id: 1 },
+    )
+    .unwrap();
+
+    //Only registered trades reaching a final state are counted out
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("stranger", &[]),
+        close(TradeState::Released),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::TradeNotOpen { .. }));
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("trade", &[]),
+        close(TradeState::EscrowFunded),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::TradeNotOpen { .. }));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("trade", &[]),
+        close(TradeState::Released),
+    )
+    .unwrap();
+    assert_eq!(load_offer_by_id(&deps.storage, 1).unwrap().active_trades, 0);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("trade", &[]),
+        close(TradeState::Canceled),
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::TradeNotOpen { .. }));
+
+    execute(deps.as_mut(), env.clone(), maker.clone(), update).unwrap();
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 1).unwrap().max_amount,
+        Uint128::new(3)
+    );
+}
+
+#[test]
+fn offer_timestamps_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    for _ in 0..2 {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Create {
+                offer: expiring_offer_msg(None),
+            },
+        )
+        .unwrap();
+    }
+    let offer = load_offer_by_id(&deps.storage, 2).unwrap();
+    assert_eq!(offer.created_at, env.block.time);
+    assert_eq!(offer.last_updated, env.block.time);
+
+    //State changes bump last_updated only
+    let mut later = mock_env();
+    later.block.time = env.block.time.plus_seconds(100);
+    execute(
+        deps.as_mut(),
+        later.clone(),
+        maker.clone(),
+        ExecuteMsg::Pause { id: 2 },
+    )
+    .unwrap();
+    let offer = load_offer_by_id(&deps.storage, 2).unwrap();
+    assert_eq!(offer.created_at, env.block.time);
+    assert_eq!(offer.last_updated, later.block.time);
+
+    let updated_ids = |deps: Deps, updated_since: Option<Timestamp>| -> Vec<u64> {
+        let offers: Vec<Offer> = from_binary::<OffersPageResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::OffersQuery {
+                    owner: None,
+                    updated_since,
+                    last_value: None,
+                    limit: 10,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .offers;
+        offers.iter().map(|offer| offer.id).collect()
+    };
+    assert_eq!(updated_ids(deps.as_ref(), None), vec![1, 2]);
+    assert_eq!(updated_ids(deps.as_ref(), Some(later.block.time)), vec![2]);
+
+    //The migration backfills offers stored before the fields existed
+    let legacy: Offer = from_slice(
+        br#"{
+            "id":3,
+            "owner":"maker",
+            "maker_contact":"LunaQueen",
+            "offer_type":"buy",
+            "fiat_currency":"BRL",
+            "min_amount":"1",
+            "max_amount":"2",
+            "state":"active",
+            "timestamp":1641329895
+        }"#,
+    )
+    .unwrap();
+    id_offers()
+        .save(&mut deps.storage, U64Key::new(3), &legacy)
+        .unwrap();
+    let mut migration = mock_env();
+    migration.block.time = env.block.time.plus_seconds(200);
+    migrate(deps.as_mut(), migration.clone(), MigrateMsg {}).unwrap();
+    let offer = load_offer_by_id(&deps.storage, 3).unwrap();
+    assert_eq!(offer.created_at, migration.block.time);
+    assert_eq!(offer.last_updated, migration.block.time);
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 1).unwrap().created_at,
+        env.block.time
+    );
+}
+
+#[test]
+fn min_trades_completed_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    for min_trades_completed in [None, Some(2)].iter() {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Create {
+                offer: OfferMsg {
+                    min_trades_completed: *min_trades_completed,
+                    total_available: Uint128::zero(),
+                    ..expiring_offer_msg(None)
+                },
+            },
+        )
+        .unwrap();
+    }
+    let completed_trades = |deps: Deps| -> u64 {
+        from_binary(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::CompletedTrades {
+                    address: "taker".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    //The taker builds up history on the offer without a requirement
+    for (trade, required, actual) in [("trade-1", 2, 0), ("trade-2", 2, 1)].iter() {
+        let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(2)).unwrap_err();
+        assert!(matches!(
+            err,
+            OfferError::NotEnoughCompletedTrades { required: r, actual: a, .. }
+                if r == *required && a == *actual
+        ));
+
+        execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap();
+        trade_instantiated(deps.as_mut(), env.clone(), trade);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(trade, &[]),
+            ExecuteMsg::TradeClosed {
+                state: TradeState::Released,
+            },
+        )
+        .unwrap();
+    }
+    assert_eq!(completed_trades(deps.as_ref()), 2);
+
+    //Canceled trades do not count
+    execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(1)).unwrap();
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-3");
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("trade-3", &[]),
+        ExecuteMsg::TradeClosed {
+            state: TradeState::Canceled,
+        },
+    )
+    .unwrap();
+    assert_eq!(completed_trades(deps.as_ref()), 2);
+
+    //Reaching the threshold exactly is enough
+    execute(deps.as_mut(), env.clone(), maker.clone(), new_trade_msg(2)).unwrap();
+}
+
+#[test]
+fn batch_offers_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let invalid_msg = OfferMsg {
+        min_amount: Uint128::new(3),
+        ..expiring_offer_msg(None)
+    };
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::CreateMany {
+            offers: vec![expiring_offer_msg(None); 3],
+        },
+    )
+    .unwrap();
+    assert_eq!(res.attributes[1].key, "ids");
+    assert_eq!(res.attributes[1].value, "1,2,3");
+
+    //The failing entry is named in the error
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::CreateMany {
+            offers: vec![expiring_offer_msg(None), invalid_msg.clone()],
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, OfferError::BatchEntryFailed { index: 1, .. }));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::CreateMany {
+            offers: vec![expiring_offer_msg(None); 21],
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::BatchTooLarge { len: 21, max: 20 }
+    ));
+
+    let updated_msg = OfferMsg {
+        max_amount: Uint128::new(5),
+        ..expiring_offer_msg(None)
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::UpdateMany {
+            updates: vec![(1, updated_msg.clone()), (3, updated_msg.clone())],
+        },
+    )
+    .unwrap();
+    assert_eq!(res.attributes[1].value, "1,3");
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 3).unwrap().max_amount,
+        Uint128::new(5)
+    );
+
+    //Unknown ids, invalid entries and offers of someone else fail the batch
+    for (updates, failing) in [
+        (vec![(1, updated_msg.clone()), (99, updated_msg.clone())], 1),
+        (vec![(2, invalid_msg.clone())], 0),
+    ]
+    .iter()
+    {
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::UpdateMany {
+                updates: updates.clone(),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, OfferError::BatchEntryFailed { index, .. } if index == *failing));
+    }
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("other", &[]),
+        ExecuteMsg::UpdateMany {
+            updates: vec![(2, updated_msg)],
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::BatchEntryFailed { index: 0, error } if matches!(*error, OfferError::Unauthorized { .. })
+    ));
+}
+
+fn trade_addrs(deps: Deps, msg: QueryMsg) -> Vec<String> {
+    let trades: Vec<TradeInfo> =
+        from_binary::<TradesResponse>(&query(deps, mock_env(), msg).unwrap())
+            .unwrap()
+            .trades;
+    trades
+        .iter()
+        .inspect(|t| {
+            assert_eq!(t.trade_addr, t.trade.addr);
+            assert_eq!(t.amount, t.trade.amount);
+            assert_eq!(t.denom, t.trade.asset.to_string());
+            assert_eq!(t.expires_at, t.trade.expires_at);
+            assert!(t.counterparty == t.trade.buyer || t.counterparty == t.trade.seller);
+        })
+        .map(|t| t.trade_addr.to_string())
+        .collect()
+}
+
+#[test]
+fn trades_as_taker_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("maker", &[]),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-1");
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-2");
+
+    let as_taker = |taker: &str, last_value: Option<&str>| QueryMsg::TradesAsTaker {
+        taker: taker.to_string(),
+        last_value: last_value.map(Addr::unchecked),
+        limit: 10,
+    };
+    assert_eq!(
+        trade_addrs(deps.as_ref(), as_taker("taker", None)),
+        vec!["trade-1", "trade-2"]
+    );
+    assert_eq!(
+        trade_addrs(deps.as_ref(), as_taker("taker", Some("trade-1"))),
+        vec!["trade-2"]
+    );
+    assert!(trade_addrs(deps.as_ref(), as_taker("maker", None)).is_empty());
+
+    //The same trades show up from the seller side
+    assert_eq!(
+        trade_addrs(
+            deps.as_ref(),
+            QueryMsg::TradesQuery {
+                user: Addr::unchecked("taker"),
+                state: None,
+                index: TradesIndex::Seller,
+                last_value: None,
+                limit: 10,
+            }
+        ),
+        vec!["trade-1", "trade-2"]
+    );
+
+    //Trades registered without a taker get one on migration
+    let mut legacy = trades().load(&deps.storage, "trade-1").unwrap();
+    legacy.taker = Addr::unchecked("");
+    trades()
+        .save(&mut deps.storage, "trade-1", &legacy)
+        .unwrap();
+    assert_eq!(
+        trade_addrs(deps.as_ref(), as_taker("taker", None)),
+        vec!["trade-2"]
+    );
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+    let migrated: TradeAddr = trades().load(&deps.storage, "trade-1").unwrap();
+    assert_eq!(migrated.taker, Addr::unchecked("taker"));
+    assert_eq!(
+        trade_addrs(deps.as_ref(), as_taker("taker", None)),
+        vec!["trade-1", "trade-2"]
+    );
+}
+
+#[test]
+fn trades_page_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("maker", &[]),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    let at = |seconds: u64| {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(seconds);
+        env
+    };
+    //trade-b and trade-c share a block
+    for (trade, seconds) in [
+        ("trade-a", 0),
+        ("trade-b", 10),
+        ("trade-c", 10),
+        ("trade-d", 20),
+    ] {
+        trade_instantiated(deps.as_mut(), at(seconds), trade);
+    }
+    let page = |deps: Deps, last_value: Option<Vec<u8>>, limit: u32| -> Vec<TradeInfo> {
+        let msg = QueryMsg::TradesPage {
+            maker: "maker".to_string(),
+            last_value,
+            limit,
+        };
+        let page: TradesPageResponse = from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(
+            page.next,
+            page.trades
+                .get(page.limit as usize - 1)
+                .map(TradeInfo::page_cursor)
+        );
+        page.trades
+    };
+    let addrs = |trades: &[TradeInfo]| -> Vec<String> {
+        trades.iter().map(|t| t.trade_addr.to_string()).collect()
+    };
+
+    //Newest first, ties broken by address
+    let first_page = page(deps.as_ref(), None, 2);
+    assert_eq!(addrs(&first_page), vec!["trade-d", "trade-c"]);
+
+    //New trades don't shift the following pages
+    trade_instantiated(deps.as_mut(), at(30), "trade-e");
+    let cursor = first_page.last().unwrap().page_cursor();
+    let second_page = page(deps.as_ref(), Some(cursor), 2);
+    assert_eq!(addrs(&second_page), vec!["trade-b", "trade-a"]);
+    let cursor = second_page.last().unwrap().page_cursor();
+    assert!(page(deps.as_ref(), Some(cursor), 2).is_empty());
+    assert_eq!(
+        addrs(&page(deps.as_ref(), None, 10)),
+        vec!["trade-e", "trade-d", "trade-c", "trade-b", "trade-a"]
+    );
+
+    //Pages are capped whatever the requested limit
+    for i in 0..30 {
+        trade_instantiated(deps.as_mut(), at(40), &format!("trade-f{:02}", i));
+    }
+    assert_eq!(page(deps.as_ref(), None, 100).len(), 30);
+
+    //Trades registered without a maker are indexed on migration
+    let mut legacy = trades().load(&deps.storage, "trade-a").unwrap();
+    legacy.maker = Addr::unchecked("");
+    trades()
+        .save(&mut deps.storage, "trade-a", &legacy)
+        .unwrap();
+    let oldest = |deps: Deps| addrs(&page(deps, Some(second_page[0].page_cursor()), 2));
+    assert!(oldest(deps.as_ref()).is_empty());
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+    assert_eq!(oldest(deps.as_ref()), vec!["trade-a"]);
+}
+
+#[test]
+fn trades_by_state_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("maker", &[]),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-1");
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-2");
+
+    let by_state = |state: TradeState| QueryMsg::TradesByState {
+        state,
+        last_value: None,
+        limit: 10,
+    };
+    assert_eq!(
+        trade_addrs(deps.as_ref(), by_state(TradeState::Created)),
+        vec!["trade-1", "trade-2"]
+    );
+
+    //Reported state changes move trades between buckets
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("trade-1", &[]),
+        ExecuteMsg::TradeStateChanged {
+            state: TradeState::EscrowFunded,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        trade_addrs(deps.as_ref(), by_state(TradeState::Created)),
+        vec!["trade-2"]
+    );
+    assert_eq!(
+        trade_addrs(deps.as_ref(), by_state(TradeState::EscrowFunded)),
+        vec!["trade-1"]
+    );
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("trade-1", &[]),
+        ExecuteMsg::TradeClosed {
+            state: TradeState::Released,
+        },
+    )
+    .unwrap();
+    assert!(trade_addrs(deps.as_ref(), by_state(TradeState::EscrowFunded)).is_empty());
+    assert_eq!(
+        trade_addrs(deps.as_ref(), by_state(TradeState::Released)),
+        vec!["trade-1"]
+    );
+
+    //Final states go through TradeClosed, unknown senders are refused
+    for (sender, state) in [
+        ("trade-2", TradeState::Refunded),
+        ("trade-1", TradeState::FiatDeposited),
+        ("other", TradeState::FiatDeposited),
+    ]
+    .iter()
+    {
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(sender, &[]),
+            ExecuteMsg::TradeStateChanged {
+                state: state.clone(),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, OfferError::TradeNotOpen { .. }));
+    }
+}
+
+#[test]
+fn export_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    for fiat in ["BRL", "COP", "BRL"].iter() {
+        let offer = OfferMsg {
+            fiat_currency: FiatCurrency::unchecked(fiat),
+            ..expiring_offer_msg(None)
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Create { offer },
+        )
+        .unwrap();
+    }
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Archive { id: 2 },
+    )
+    .unwrap();
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-2");
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-1");
+    let export_offers = |deps: Deps, start_after: Option<u64>| -> OffersExport {
+        let msg = QueryMsg::ExportOffers {
+            start_after,
+            limit: 2,
+        };
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+    };
+
+    //Offers of every currency and state in id order, exactly as stored
+    let first = export_offers(deps.as_ref(), None);
+    assert_eq!(first.total, 3);
+    assert_eq!(first.limit, 2);
+    assert_eq!(first.next, Some(2));
+    assert_eq!(
+        first.offers.iter().map(|(id, _)| *id).collect::<Vec<u64>>(),
+        vec![1, 2]
+    );
+    assert_eq!(
+        first.offers[1].1,
+        load_offer_by_id(&deps.storage, 2).unwrap()
+    );
+    assert_eq!(first.offers[1].1.state, OfferState::Archived);
+    assert_eq!(
+        first.offers[0].1.maker_contact,
+        Some("LunaQueen".to_string())
+    );
+    let second = export_offers(deps.as_ref(), first.next);
+    assert_eq!(second.offers[0].0, 3);
+    assert_eq!(second.next, None);
+
+    //Trades in address order
+    let msg = QueryMsg::ExportTrades {
+        start_after: None,
+        limit: 1,
+    };
+    let trades: TradesExport =
+        from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert_eq!(trades.total, 2);
+    assert_eq!(trades.trades[0].trade, Addr::unchecked("trade-1"));
+    let msg = QueryMsg::ExportTrades {
+        start_after: trades.next,
+        limit: 1,
+    };
+    let trades: TradesExport = from_binary(&query(deps.as_ref(), env, msg).unwrap()).unwrap();
+    assert_eq!(trades.trades[0].trade, Addr::unchecked("trade-2"));
+
+    //The page size is capped like every paginated query
+    let msg = QueryMsg::ExportOffers {
+        start_after: None,
+        limit: MAX_PAGE_LIMIT + 1,
+    };
+    let export: OffersExport =
+        from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(export.offers.len(), 3);
+    assert_eq!(export.limit, MAX_PAGE_LIMIT);
+
+    //Deleted offers leave the total
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        maker.clone(),
+        ExecuteMsg::Delete { id: 2 },
+    )
+    .unwrap();
+    let export = export_offers(deps.as_ref(), None);
+    assert_eq!(export.total, 2);
+    assert_eq!(
+        export
+            .offers
+            .iter()
+            .map(|(id, _)| *id)
+            .collect::<Vec<u64>>(),
+        vec![1, 3]
+    );
+}
+
+#[test]
+fn bulk_state_change_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let offer_count = MAX_BULK_STATE_CHANGES as u64 + 3;
+    for info in vec![mock_info("other", &[])]
+        .into_iter()
+        .chain((0..offer_count).map(|_| maker.clone()))
+    {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            ExecuteMsg::Create {
+                offer: expiring_offer_msg(None),
+            },
+        )
+        .unwrap();
+    }
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Pause { id: 2 },
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Archive { id: 3 },
+    )
+    .unwrap();
+    let attr = |res: &Response, key: &str| -> String {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .unwrap()
+            .value
+            .clone()
+    };
+    let states = |deps: Deps| -> Vec<OfferState> {
+        (1..=offer_count + 1)
+            .map(|id| load_offer_by_id(deps.storage, id).unwrap().state)
+            .collect()
+    };
+
+    //Paused and archived offers are skipped, the rest goes in capped calls
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::PauseAll {},
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "count"), MAX_BULK_STATE_CHANGES.to_string());
+    assert_eq!(attr(&res, "continue"), "true");
+    assert_eq!(offer_event_attrs(&res).len(), MAX_BULK_STATE_CHANGES);
+    assert_eq!(
+        offer_event_attrs(&res)[0],
+        expected_offer_event("pause_offer", 4, Some("Active"), "Paused")
+    );
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::PauseAll {},
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "count"), "1");
+    assert_eq!(attr(&res, "continue"), "false");
+    let states_after = states(deps.as_ref());
+    assert_eq!(states_after[0], OfferState::Active);
+    assert_eq!(states_after[2], OfferState::Archived);
+    assert!(states_after
+        .iter()
+        .enumerate()
+        .all(|(index, state)| index == 0 || index == 2 || state == &OfferState::Paused));
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::PauseAll {},
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "count"), "0");
+    assert_eq!(attr(&res, "continue"), "false");
+
+    //Activating brings back every paused offer, the archived one stays
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::ActivateAll {},
+    )
+    .unwrap();
+    assert_eq!(attr(&res, "continue"), "true");
+    let res = execute(deps.as_mut(), env, maker, ExecuteMsg::ActivateAll {}).unwrap();
+    assert_eq!(attr(&res, "count"), "2");
+    assert_eq!(attr(&res, "continue"), "false");
+    let states_after = states(deps.as_ref());
+    assert_eq!(states_after[2], OfferState::Archived);
+    assert_eq!(
+        states_after
+            .iter()
+            .filter(|state| *state == &OfferState::Active)
+            .count() as u64,
+        offer_count
+    );
+}
+
+#[test]
+fn offers_filtered_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    let other = mock_info("other", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+
+    //Maker owns ids 1, 3, 4 and 5, other owns id 2, ids 1 and 4 are paused
+    for (info, offer_type, fiat) in [
+        (&maker, OfferType::Sell, "COP"),
+        (&other, OfferType::Sell, "COP"),
+        (&maker, OfferType::Buy, "COP"),
+        (&maker, OfferType::Sell, "USD"),
+        (&maker, OfferType::Sell, "COP"),
+    ] {
+        create_offer(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            offer_type,
+            FiatCurrency::unchecked(fiat),
+        );
+    }
+    for id in [1, 4] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Pause { id },
+        )
+        .unwrap();
+    }
+    let filtered = |deps: Deps,
+                    owner: Option<&str>,
+                    fiat: Option<&str>,
+                    offer_type: Option<OfferType>,
+                    state: Option<OfferState>,
+                    last_value: Option<u64>,
+                    limit: u32| {
+        let res: OffersFilteredResponse = from_binary(
+            &query(
+                deps,
+                env.clone(),
+                QueryMsg::OffersFiltered {
+                    owner: owner.map(|owner| owner.to_string()),
+                    fiat_currency: fiat.map(FiatCurrency::unchecked),
+                    offer_type,
+                    state,
+                    last_value,
+                    limit,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let ids: Vec<u64> = res.offers.iter().map(|offer| offer.id).collect();
+        (ids, res)
+    };
+
+    //Every filter is applied, the filter index serves type, currency and state
+    let (ids, res) = filtered(
+        deps.as_ref(),
+        Some("maker"),
+        Some("COP"),
+        Some(OfferType::Sell),
+        Some(OfferState::Paused),
+        None,
+        10,
+    );
+    assert_eq!(ids, vec![1]);
+    assert_eq!(res.index, OffersFilterIndex::Filter);
+    assert_eq!(
+        res.filters,
+        OffersFilters {
+            owner: Some(Addr::unchecked("maker")),
+            fiat_currency: Some(FiatCurrency::unchecked("COP")),
+            offer_type: Some(OfferType::Sell),
+            state: Some(OfferState::Paused),
+        }
+    );
+    assert_eq!(res.next, None);
+
+    //Pages continue after last_value
+    let (ids, res) = filtered(
+        deps.as_ref(),
+        None,
+        Some("COP"),
+        Some(OfferType::Sell),
+        Some(OfferState::Active),
+        None,
+        1,
+    );
+    assert_eq!(ids, vec![2]);
+    assert_eq!(res.next, Some(2));
+    let (ids, _) = filtered(
+        deps.as_ref(),
+        None,
+        Some("COP"),
+        Some(OfferType::Sell),
+        Some(OfferState::Active),
+        res.next,
+        1,
+    );
+    assert_eq!(ids, vec![5]);
+
+    //Otherwise the owner, currency and type indexes are preferred in turn
+    let (ids, res) = filtered(
+        deps.as_ref(),
+        Some("maker"),
+        None,
+        Some(OfferType::Sell),
+        None,
+        None,
+        10,
+    );
+    assert_eq!(ids, vec![1, 4, 5]);
+    assert_eq!(res.index, OffersFilterIndex::Owner);
+    let (ids, res) = filtered(
+        deps.as_ref(),
+        None,
+        Some("COP"),
+        None,
+        Some(OfferState::Paused),
+        None,
+        10,
+    );
+    assert_eq!(ids, vec![1]);
+    assert_eq!(res.index, OffersFilterIndex::FiatCurrency);
+    let (ids, res) = filtered(
+        deps.as_ref(),
+        None,
+        None,
+        Some(OfferType::Sell),
+        None,
+        None,
+        10,
+    );
+    assert_eq!(ids, vec![1, 2, 4, 5]);
+    assert_eq!(res.index, OffersFilterIndex::OfferType);
+    let (ids, res) = filtered(
+        deps.as_ref(),
+        None,
+        None,
+        None,
+        Some(OfferState::Paused),
+        None,
+        10,
+    );
+    assert_eq!(ids, vec![1, 4]);
+    assert_eq!(res.index, OffersFilterIndex::Id);
+
+    //Sparse matches cut pages short, the cursor still moves past the visited offers
+    for _ in 0..SCANNED_ENTRIES_PER_OFFER {
+        create_offer(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            OfferType::Buy,
+            FiatCurrency::unchecked("USD"),
+        );
+    }
+    let (ids, res) = filtered(
+        deps.as_ref(),
+        None,
+        None,
+        None,
+        Some(OfferState::Paused),
+        Some(4),
+        1,
+    );
+    assert_eq!(ids, Vec::<u64>::new());
+    assert_eq!((res.next, res.limit), (Some(8), 1));
+    let (ids, res) = filtered(
+        deps.as_ref(),
+        None,
+        None,
+        None,
+        Some(OfferState::Paused),
+        res.next,
+        1,
+    );
+    assert_eq!(ids, Vec::<u64>::new());
+    assert_eq!(res.next, None);
+}
+
+#[test]
+fn last_traded_price_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("maker", &[]),
+        ExecuteMsg::Create {
+            offer: expiring_offer_msg(None),
+        },
+    )
+    .unwrap();
+    let price = |units: u128| Decimal::from_ratio(units, 100u128);
+    deps.querier.with_trade_fiat_prices(&[
+        ("trade-1", price(510)),
+        ("trade-2", price(520)),
+        ("trade-3", price(530)),
+        ("trade-4", price(540)),
+    ]);
+    for trade in ["trade-1", "trade-2", "trade-3", "trade-4", "trade-5"].iter() {
+        trade_instantiated(deps.as_mut(), env.clone(), trade);
+    }
+    let close = |deps: DepsMut, sender: &str, state: TradeState, env: Env| {
+        let msg = ExecuteMsg::TradeClosed { state };
+        execute(deps, env, mock_info(sender, &[]), msg).unwrap();
+    };
+    let last_price = |deps: Deps, fiat: &str| -> Option<LastTradedPrice> {
+        let msg = QueryMsg::LastTradedPrice {
+            fiat_currency: FiatCurrency::unchecked(fiat),
+        };
+        from_binary(&query(deps, mock_env(), msg).unwrap()).unwrap()
+    };
+
+    //Nothing is recorded before a release
+    assert_eq!(last_price(deps.as_ref(), "BRL"), None);
+
+    let mut later = env.clone();
+    later.block.time = env.block.time.plus_seconds(100);
+    close(
+        deps.as_mut(),
+        "trade-1",
+        TradeState::Released,
+        later.clone(),
+    );
+    let expected = LastTradedPrice {
+        fiat_currency: FiatCurrency::unchecked("BRL"),
+        price: price(510),
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1),
+        traded_at: later.block.time,
+    };
+    assert_eq!(last_price(deps.as_ref(), "BRL"), Some(expected.clone()));
+    assert_eq!(last_price(deps.as_ref(), "COP"), None);
+
+    //Refunds, settled disputes and trades without a price keep the record
+    close(deps.as_mut(), "trade-2", TradeState::Refunded, env.clone());
+    let msg = ExecuteMsg::TradeStateChanged {
+        state: TradeState::Disputed,
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("trade-3", &[]), msg).unwrap();
+    close(
+        deps.as_mut(),
+        "trade-3",
+        TradeState::SettledForMaker,
+        env.clone(),
+    );
+    close(deps.as_mut(), "trade-5", TradeState::Released, env.clone());
+    assert_eq!(last_price(deps.as_ref(), "BRL"), Some(expected.clone()));
+
+    //The next release replaces it
+    close(deps.as_mut(), "trade-4", TradeState::Released, env.clone());
+    assert_eq!(
+        last_price(deps.as_ref(), "BRL"),
+        Some(LastTradedPrice {
+            price: price(540),
+            traded_at: env.block.time,
+            ..expected
+        })
+    );
+}
+
+#[test]
+fn fiat_amounts_offer_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let factory = mock_info("factory", &[]);
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), factory.clone());
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        factory.clone(),
+        ExecuteMsg::UpdateConfig(ConfigUpdate {
+            price_oracle_addr: Some("oracle".to_string()),
+            max_trade_amount: Some(Uint128::new(50_000_000)),
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    //5 BRL per UST
+    let price = Decimal::from_ratio(5u128, 1_000_000u128);
+    deps.querier
+        .with_prices(&[(FiatCurrency::unchecked("BRL"), "uusd", price)]);
+
+    //10 to 500 BRL in cents, 500 BRL buy 100 UST which is above the cap
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        maker.clone(),
+        ExecuteMsg::Create {
+            offer: OfferMsg {
+                min_amount: Uint128::new(1_000),
+                max_amount: Uint128::new(50_000),
+                amounts_in_fiat: true,
+                ..expiring_offer_msg(None)
+            },
+        },
+    )
+    .unwrap();
+    let offer: Offer =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Offer { id: 1 }).unwrap())
+            .unwrap();
+    assert!(offer.amounts_in_fiat);
+    let new_trade = |amount: u128| ExecuteMsg::NewTrade {
+        offer_id: 1,
+        amount: Uint128::new(amount),
+        taker: "taker".to_string(),
+        taker_contact: "USTKing".to_string(),
+        multisig_release: false,
+    };
+
+    //Bounds are checked in fiat, the cap on the converted amount
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade(999)).unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::TradeAmountOutOfRange { amount, .. } if amount == Uint128::new(999)
+    ));
+    let err = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade(30_000)).unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::MaxTradeAmountExceeded { amount, .. } if amount == Uint128::new(60_000_000)
+    ));
+
+    //25.50 BRL escrow 5.1 UST, both figures go to the trade
+    let res = execute(deps.as_mut(), env.clone(), maker.clone(), new_trade(2_550)).unwrap();
+    let msg = trade_instantiate_msg(&res);
+    assert_eq!(msg.amount, Uint128::new(5_100_000));
+    assert_eq!(msg.fiat_amount, Some(Uint128::new(2_550)));
+    assert_eq!(msg.fiat_price, Some(price));
+    let simulation: TradeSimulation = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::SimulateTrade {
+                offer_id: 1,
+                amount: Uint128::new(2_550),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(simulation.amount, Uint128::new(5_100_000));
+
+    //The offer reserves the fiat amount until the trade closes
+    deps.querier
+        .with_trade_amounts(&[("trade-1", Uint128::new(5_100_000))]);
+    deps.querier
+        .with_trade_fiat_amounts(&[("trade-1", Uint128::new(2_550))]);
+    trade_instantiated(deps.as_mut(), env.clone(), "trade-1");
+    let offer = load_offer_by_id(&deps.storage, 1).unwrap();
+    assert_eq!(offer.reserved_amount, Uint128::new(2_550));
+    assert_eq!(offer.available_amount(), Uint128::new(47_450));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("trade-1", &[]),
+        ExecuteMsg::TradeClosed {
+            state: TradeState::Released,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        load_offer_by_id(&deps.storage, 1).unwrap().reserved_amount,
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn trading_hours_test() {
+    let mut deps = mock_dependencies(&[], None);
+    let env = mock_env();
+    let maker = mock_info("maker", &[]);
+    do_init(deps.as_mut(), env.clone(), mock_info("factory", &[]));
+    let with_hours = |from: Option<u8>, to: Option<u8>| OfferMsg {
+        available_from_hour: from,
+        available_to_hour: to,
+        ..expiring_offer_msg(None)
+    };
+    let midnight = env.block.time.seconds() - env.block.time.seconds() % 86_400;
+    let at_hour = |hour: u64| {
+        let mut env = env.clone();
+        env.block.time = Timestamp::from_seconds(midnight + hour * 3600 + 59);
+        env
+    };
+
+    //Hours are set together, below 24 and different
+    for (from, to) in [
+        (Some(9), None),
+        (None, Some(22)),
+        (Some(24), Some(6)),
+        (Some(5), Some(5)),
+    ] {
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Create {
+                offer: with_hours(from, to),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            OfferError::InvalidTradingHours { available_from_hour, available_to_hour }
+                if available_from_hour == from && available_to_hour == to
+        ));
+    }
+
+    //Offer 1 trades from 9:00 to 22:00, offer 2 overnight and offer 3 always
+    for (from, to) in [(Some(9), Some(22)), (Some(22), Some(6)), (None, None)] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            maker.clone(),
+            ExecuteMsg::Create {
+                offer: with_hours(from, to),
+            },
+        )
+        .unwrap();
+    }
+    let trade = |deps: DepsMut, env: Env, offer_id: u64| {
+        execute(deps, env, mock_info("maker", &[]), new_trade_msg(offer_id))
+    };
+
+    let err = trade(deps.as_mut(), at_hour(8), 1).unwrap_err();
+    assert!(matches!(
+        err,
+        OfferError::OutsideTradingHours {
+            id: 1,
+            from_hour: 9,
+            to_hour: 22
+        }
+    ));
+    trade(deps.as_mut(), at_hour(9), 1).unwrap();
+    let err = trade(deps.as_mut(), at_hour(22), 1).unwrap_err();
+    assert!(matches!(err, OfferError::OutsideTradingHours { id: 1, .. }));
+
+    //Overnight windows wrap around midnight
+    trade(deps.as_mut(), at_hour(23), 2).unwrap();
+    trade(deps.as_mut(), at_hour(5), 2).unwrap();
+    let err = trade(deps.as_mut(), at_hour(6), 2).unwrap_err();
+    assert!(matches!(err, OfferError::OutsideTradingHours { id: 2, .. }));
+    for hour in [0, 8, 22] {
+        trade(deps.as_mut(), at_hour(hour), 3).unwrap();
+    }
+
+    //Queries report the window and whether it is open
+    let available = |env: Env| -> Vec<Option<bool>> {
+        let offers: Vec<Offer> = from_binary::<OffersPageResponse>(
+            &query(
+                deps.as_ref(),
+                env,
+                QueryMsg::OffersByOwner {
+                    owner: "maker".to_string(),
+                    last_value: None,
+                    limit: 10,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .offers;
+        offers
+            .iter()
+            .map(|offer| offer.currently_available)
+            .collect()
+    };
+    assert_eq!(
+        available(at_hour(12)),
+        vec![Some(true), Some(false), Some(true)]
+    );
+    assert_eq!(
+        available(at_hour(2)),
+        vec![Some(false), Some(true), Some(true)]
+    );
+    let offer = load_offer_by_id(&deps.storage, 1).unwrap();
+    assert_eq!(offer.available_from_hour, Some(9));
+    assert_eq!(offer.available_to_hour, Some(22));
+    assert_eq!(offer.currently_available, None);
+}
@@ -1,23 +1,35 @@
-use std::ops::{Add, Sub};
-use std::str::FromStr;
+use std::ops::Sub;
 
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env,
-    MessageInfo, QuerierWrapper, QueryRequest, Response, StdResult, SubMsg, Uint128, WasmMsg,
+    entry_point, from_binary, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut,
+    Env, MessageInfo, QuerierWrapper, QueryRequest, Response, StdResult, SubMsg, Uint128, WasmMsg,
     WasmQuery,
 };
-
-use localterra_protocol::factory::Config as FactoryConfig;
-use localterra_protocol::factory_util::get_factory_config;
+use cw2::{set_contract_version, CONTRACT};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg};
+
+use localterra_protocol::constants::{MAX_EVIDENCE_PER_PARTY, MAX_MESSAGE_COMMITMENTS};
+use localterra_protocol::events::trade_event;
+use localterra_protocol::factory::{is_ibc_denom, Config as FactoryConfig};
+use localterra_protocol::factory_util::{get_factory_config, get_ibc_denom, get_next_arbitrator};
+use localterra_protocol::fees::{fee_burn_amount, TradeFees};
+use localterra_protocol::guards::{assert_caller_role, assert_sha256_hex};
+use localterra_protocol::migration::is_valid_migration;
 use localterra_protocol::offer::{
-    Config as OfferConfig, Offer, OfferType, QueryMsg as OfferQueryMsg,
+    Config as OfferConfig, ExecuteMsg as OfferExecuteMsg, Offer, OfferAsset, OfferState, OfferType,
+    QueryMsg as OfferQueryMsg,
+};
+use localterra_protocol::taxation::deduct_tax;
+use localterra_protocol::trade::{
+    Cw20HookMsg, Evidence, ExecuteMsg, InstantiateMsg, MessageCommitment, MigrateMsg, QueryMsg,
+    TradeData, TradeModel, TradeRole, TradeState,
 };
-use localterra_protocol::trade::{ExecuteMsg, InstantiateMsg, QueryMsg, TradeData, TradeState};
 use localterra_protocol::trading_incentives::ExecuteMsg as TradingIncentivesMsg;
 
 use crate::errors::TradeError;
-use crate::state::{state as state_storage, state_read};
-use crate::taxation::{compute_tax, deduct_tax};
+
+const CONTRACT_NAME: &str = concat!("crates.io:", env!("CARGO_PKG_NAME"));
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[entry_point]
 pub fn instantiate(
@@ -26,8 +38,16 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, TradeError> {
-    //Load Offer
+    //Only the offer contract opens trades, with terms it checked against the offer
     let offer_contract = deps.api.addr_validate(msg.offers_addr.as_str()).unwrap();
+    if info.sender != offer_contract {
+        return Err(TradeError::NotOfferContract {
+            caller: info.sender,
+            offer_contract,
+        });
+    }
+
+    //Load Offer
     let offer_id = msg.offer_id;
     let offer = load_offer(deps.querier, msg.offer_id, offer_contract.to_string());
     if offer.is_none() {
@@ -36,6 +56,16 @@ pub fn instantiate(
         });
     }
     let offer = offer.unwrap();
+    let state = offer.state_at(env.block.time);
+    if state != OfferState::Active {
+        return Err(TradeError::OfferNotActive { offer_id, state });
+    }
+    if msg.asset != offer.asset {
+        return Err(TradeError::InvalidDenom {
+            expected: offer.asset.to_string(),
+            received: msg.asset.to_string(),
+        });
+    }
 
     //Load Offer Contract Config
     let load_offer_config_result: StdResult<OfferConfig> =
@@ -44,15 +74,36 @@ pub fn instantiate(
             msg: to_binary(&OfferQueryMsg::Config {}).unwrap(),
         }));
     let offers_cfg = load_offer_config_result.unwrap();
+    let factory_cfg = get_factory_config(&deps.querier, offers_cfg.factory_addr.to_string());
+    //The factory may have stopped trusting the offer's IBC voucher since it was created
+    if let OfferAsset::Native { denom } = &msg.asset {
+        if is_ibc_denom(denom)
+            && get_ibc_denom(
+                &deps.querier,
+                offers_cfg.factory_addr.to_string(),
+                denom.clone(),
+            )?
+            .is_none()
+        {
+            return Err(TradeError::UntrustedIbcDenom {
+                denom: denom.clone(),
+            });
+        }
+    }
 
-    //TODO: it's probably a good idea to store this kind of configuration in a Gov contract.
-    let expire_height = env.block.height + 600; //Roughly 1h.
+    let expires_at = env.block.time.plus_seconds(offers_cfg.trade_duration);
+    let dispute_window_ends_at = expires_at.plus_seconds(offers_cfg.dispute_window);
 
-    //Check that ust_amount is inside Offer limits
-    let amount = Uint128::new(u128::from_str(msg.ust_amount.as_str()).unwrap());
-    if amount > offer.max_amount || amount < offer.min_amount {
+    //Check that amount is inside Offer limits, fiat denominated offers bound the fiat amount
+    //the escrow amount was converted from
+    let amount = msg.amount;
+    let offer_amount = match offer.amounts_in_fiat {
+        true => msg.fiat_amount.unwrap_or_default(),
+        false => amount,
+    };
+    if offer_amount > offer.max_amount || offer_amount < offer.min_amount {
         return Err(TradeError::AmountError {
-            amount,
+            amount: offer_amount,
             min_amount: offer.min_amount,
             max_amount: offer.max_amount,
         });
@@ -62,7 +113,23 @@ pub fn instantiate(
     let recipient: Addr;
     let sender: Addr;
     let taker = deps.api.addr_validate(msg.taker.as_str()).unwrap();
-    let arbitrator = deps.api.addr_validate(msg.arbitrator.as_str()).unwrap();
+    //Arbitrators registered for the offer's currency take precedence, then the one configured
+    //on the offer contract. Registered ones take turns, the factory moves on once the offer
+    //contract registers the trade.
+    let next_arbitrator = get_next_arbitrator(
+        &deps.querier,
+        offers_cfg.factory_addr.to_string(),
+        offer.fiat_currency.clone(),
+    )?;
+    let arbitrator = match next_arbitrator {
+        Some(arbitrator) => arbitrator.addr,
+        None => offers_cfg
+            .arbitrator
+            .clone()
+            .ok_or_else(|| TradeError::NoArbitrator {
+                fiat_currency: offer.fiat_currency.clone(),
+            })?,
+    };
 
     if offer.offer_type == OfferType::Buy {
         recipient = offer.owner; // maker
@@ -73,7 +140,7 @@ pub fn instantiate(
     }
 
     //Instantiate Trade state
-    let mut trade = TradeData {
+    let trade = TradeData {
         addr: env.contract.address.clone(),
         factory_addr: offers_cfg.factory_addr.clone(),
         buyer: recipient, // buyer
@@ -81,30 +148,67 @@ pub fn instantiate(
         offer_contract: offer_contract.clone(),
         offer_id,
         taker_contact: msg.taker_contact,
+        maker_contact: offer.maker_contact,
         arbitrator,
         state: TradeState::Created,
-        expire_height,
-        ust_amount: amount,
+        created_at: env.block.time,
+        expires_at,
+        dispute_window_ends_at,
+        fee_bps: factory_cfg.maker_fee_bps,
+        amount,
+        asset: msg.asset,
+        fiat_price: msg.fiat_price,
+        fiat_amount: msg.fiat_amount,
+        multisig_release: msg.multisig_release,
+        release_approvals: vec![],
+        approvals_conflict: false,
+        evidence: vec![],
     };
 
-    //Set state to EscrowFunded if enough UST was sent in the message.
-    if !info.funds.is_empty() {
-        //TODO: Check for Luna or other Terra native tokens.
-        let ust_amount = get_ust_amount(info.clone());
-        if ust_amount >= amount {
-            trade.state = TradeState::EscrowFunded
-        }
+    //Set state to EscrowFunded if the seller taking the offer sent the exact escrow amount.
+    let funded_amount = get_funded_amount(&info, &trade.asset)?;
+    if !funded_amount.is_zero() {
+        assert_caller_role(&taker, &trade, &[TradeRole::Seller])?;
+        let required_amount = escrow_amount(&deps.querier, &offer.offer_type, &trade)?;
+        assert_exact_funding(required_amount, funded_amount)?;
     }
 
     //Save state.
-    let save_state_result = state_storage(deps.storage).save(&trade);
-    if save_state_result.is_err() {
-        return Err(TradeError::InstantiationError {
+    let mut trade_model =
+        TradeModel::create(deps.storage, trade).map_err(|_| TradeError::InstantiationError {
             message: "Couldn't save state.".to_string(),
-        });
+        })?;
+    if !funded_amount.is_zero() {
+        trade_model.fund()?;
     }
+    let event = trade_event("create_trade", &trade_model.trade, None)
+        .add_attribute("fiat", offer.fiat_currency.to_string());
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-    Ok(Response::default())
+    Ok(Response::new().add_event(event))
+}
+
+/// Rewrites the stored trade so legacy fields, like the `closed` state or a
+/// missing `fee_bps`, are persisted in the current layout.
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, TradeError> {
+    if let Some(stored) = CONTRACT.may_load(deps.storage)? {
+        if !is_valid_migration(&stored, CONTRACT_NAME, CONTRACT_VERSION) {
+            return Err(TradeError::InvalidMigration {
+                contract: stored.contract,
+                version: stored.version,
+            });
+        }
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let trade = TradeModel::from_store(deps.storage)?;
+    TradeModel::store(deps.storage, &trade)?;
+
+    let res = Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("state", trade.state.to_string());
+    Ok(res)
 }
 
 #[entry_point]
@@ -114,12 +218,32 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, TradeError> {
-    let state = state_storage(deps.storage).load().unwrap();
+    let state = TradeModel::from_store(deps.storage)?;
+    //Only the arbitrator, or approvals of multisig trades, can move a disputed trade forward
+    let dispute_msg = matches!(
+        msg,
+        ExecuteMsg::Resolve { .. }
+            | ExecuteMsg::ApproveRelease { .. }
+            | ExecuteMsg::SubmitEvidence { .. }
+            | ExecuteMsg::CommitMessage { .. }
+    );
+    if state.state == TradeState::Disputed && !dispute_msg {
+        return Err(TradeError::Disputed {
+            arbitrator: state.arbitrator,
+        });
+    }
     match msg {
         ExecuteMsg::FundEscrow {} => fund_escrow(deps, env, info, state),
         ExecuteMsg::Refund {} => refund(deps, env, info, state),
         ExecuteMsg::Release {} => release(deps, env, info, state),
         ExecuteMsg::Dispute {} => dispute(deps, env, info, state),
+        ExecuteMsg::Resolve { winner } => resolve(deps, env, info, state, winner),
+        ExecuteMsg::ApproveRelease { to } => approve_release(deps, env, info, state, to),
+        ExecuteMsg::SubmitEvidence { sha256 } => submit_evidence(deps, env, info, state, sha256),
+        ExecuteMsg::CommitMessage { sha256 } => commit_message(deps, env, info, state, sha256),
+        ExecuteMsg::FiatDeposited {} => fiat_deposited(deps, env, info, state),
+        ExecuteMsg::Cancel {} => cancel(deps, info, state),
+        ExecuteMsg::Receive(cw20_msg) => receive_cw20(deps, env, info, state, cw20_msg),
     }
 }
 
@@ -127,187 +251,421 @@ pub fn execute(
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::State {} => to_binary(&query_state(deps)?),
+        QueryMsg::MessageCommitments {} => to_binary(&TradeModel::commitments(deps.storage)?),
     }
 }
 
 fn query_state(deps: Deps) -> StdResult<TradeData> {
-    let state = state_read(deps.storage).load()?;
-    Ok(state)
+    TradeModel::from_store(deps.storage)
 }
 
 fn load_offer(querier: QuerierWrapper, offer_id: u64, offer_contract: String) -> Option<Offer> {
     let load_offer_result: StdResult<Offer> =
         querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: offer_contract.clone(),
+            contract_addr: offer_contract,
             msg: to_binary(&OfferQueryMsg::Offer { id: offer_id }).unwrap(),
         }));
 
-    if load_offer_result.is_err() {
-        None
-    } else {
-        Some(load_offer_result.unwrap())
-    }
+    load_offer_result.ok()
 }
 
 fn fund_escrow(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    mut trade: TradeData,
+    trade: TradeData,
+) -> Result<Response, TradeError> {
+    //TODO: skip fee conversion entirely if fee was paid in $LOCAL.
+    let funded_amount = get_funded_amount(&info, &trade.asset)?;
+    fund(deps, env, info.sender, trade, funded_amount)
+}
+
+/// Funding through `Cw20ExecuteMsg::Send`, only the trade's token is accepted.
+fn receive_cw20(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    trade: TradeData,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, TradeError> {
+    match &trade.asset {
+        OfferAsset::Cw20 { contract_addr } if *contract_addr == info.sender => {}
+        _ => {
+            return Err(TradeError::InvalidDenom {
+                expected: trade.asset.to_string(),
+                received: info.sender.to_string(),
+            })
+        }
+    }
+    let sender = deps.api.addr_validate(&cw20_msg.sender)?;
+    match from_binary(&cw20_msg.msg) {
+        Ok(Cw20HookMsg::FundEscrow {}) => fund(deps, env, sender, trade, cw20_msg.amount),
+        Err(_) => Err(TradeError::InvalidHookMsg {}),
+    }
+}
+
+fn fund(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    trade: TradeData,
+    funded_amount: Uint128,
 ) -> Result<Response, TradeError> {
     //Check if trade is expired.
-    if env.block.height >= trade.expire_height {
+    if trade.is_expired(env.block.time) {
         return Err(TradeError::Expired {
-            current_height: env.block.height,
-            expire_height: trade.expire_height,
+            expires_at: trade.expires_at,
+            block_time: env.block.time,
         });
     }
-    // Check if escrow has already been funded
-    // TODO also base this on actual balance, switch to cancelled state and refund automatically on diffs
-    if trade.state == TradeState::EscrowFunded {
-        return Err(TradeError::AlreadyFundedError {});
-    }
-    //TODO: Convert to UST if trade is for any other stablecoin or Luna,
-    // skip conversion entirely if fee was paid in $LOCAL.
-    let ust_amount = if !info.funds.is_empty() {
-        get_ust_amount(info.clone())
-    } else {
-        let ust_balance = deps
-            .querier
-            .query_balance(env.contract.address, "uusd".to_string());
-        ust_balance
-            .unwrap_or(Coin {
-                denom: "uusd".to_string(),
-                amount: Uint128::zero(),
-            })
-            .amount
-    };
-    let ust = Coin::new(ust_amount.clone().u128(), "uusd");
-
+    //Only the seller funds the escrow, once
+    assert_caller_role(&sender, &trade, &[TradeRole::Seller])?;
     let offer = load_offer(
-        deps.querier.clone(),
+        deps.querier,
         trade.offer_id,
         trade.offer_contract.to_string(),
     )
     .unwrap(); //at this stage, offer is guaranteed to exists.
 
-    let fund_escrow_amount: Uint128 = match offer.offer_type {
-        // TODO review this and avoid over-funding by returning diff
-        OfferType::Sell => {
-            let ltfee = localterra_fee(trade.ust_amount);
-            let ltfee_coin = Coin::new(ltfee.u128(), "uusd");
-            let ltfee_tax = compute_tax(&deps.querier, &ltfee_coin).unwrap();
-            let release_tax = compute_tax(&deps.querier, &ust).unwrap();
-            trade
-                .ust_amount
-                .add(ltfee.add(&ltfee_tax).add(&release_tax))
-        }
-        OfferType::Buy => trade.ust_amount,
-    };
-    if ust_amount >= fund_escrow_amount {
-        trade.state = TradeState::EscrowFunded;
-    } else {
-        return Err(TradeError::FundEscrowError {
-            required_amount: fund_escrow_amount.clone(),
-            sent_amount: ust_amount.clone(),
-        });
-    }
+    //Sell offers escrow the maker fee at the current factory rate
+    let mut trade = trade;
+    trade.fee_bps = get_factory_config(&deps.querier, trade.factory_addr.to_string()).maker_fee_bps;
+    let fund_escrow_amount = escrow_amount(&deps.querier, &offer.offer_type, &trade)?;
+    assert_exact_funding(fund_escrow_amount, funded_amount)?;
+
+    // TODO also base this on actual balance, switch to cancelled state and refund automatically on diffs
+    let from_state = trade.state.clone();
+    let mut trade_model = TradeModel::may_load(deps.storage)?;
+    trade_model.trade.fee_bps = trade.fee_bps;
+    trade_model.fund()?;
+    let trade = trade_model.trade;
 
-    state_storage(deps.storage).save(&trade).unwrap();
     let res = Response::new()
+        .add_submessage(trade_state_changed_msg(&trade))
+        .add_event(trade_event("fund_escrow", &trade, Some(&from_state)))
         .add_attribute("action", "fund_escrow")
         .add_attribute("fund_amount", fund_escrow_amount.to_string())
-        .add_attribute("ust_amount", ust_amount.to_string())
-        .add_attribute("sender", info.sender);
+        .add_attribute("amount", funded_amount.to_string())
+        .add_attribute("asset", trade.asset.to_string())
+        .add_attribute("sender", sender);
 
     Ok(res)
 }
 
+/// Amount the seller has to escrow at the maker fee the trade recorded.
+fn escrow_amount(
+    querier: &QuerierWrapper,
+    offer_type: &OfferType,
+    trade: &TradeData,
+) -> StdResult<Uint128> {
+    TradeFees::new(trade.amount, trade.fee_bps, 0)?.escrow_amount(
+        querier,
+        &trade.asset,
+        offer_type,
+        trade.amount,
+    )
+}
+
+/// Over and underfunding are both refused, nothing to return a diff from.
+fn assert_exact_funding(
+    required_amount: Uint128,
+    funded_amount: Uint128,
+) -> Result<(), TradeError> {
+    if funded_amount != required_amount {
+        return Err(TradeError::FundEscrowError {
+            required_amount,
+            sent_amount: funded_amount,
+        });
+    }
+    Ok(())
+}
+
 fn get_offer(deps: &Deps, state: &TradeData) -> Offer {
     deps.querier
         .query(&QueryRequest::Wasm(WasmQuery::Smart {
             contract_addr: state.offer_contract.to_string(),
-            msg: to_binary(&OfferQueryMsg::Offer {
-                id: state.offer_id.clone(),
-            })
-            .unwrap(),
+            msg: to_binary(&OfferQueryMsg::Offer { id: state.offer_id }).unwrap(),
         }))
         .unwrap()
 }
 
 fn dispute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     state: TradeData,
 ) -> Result<Response, TradeError> {
-    if (info.sender != state.seller) & (info.sender != state.buyer) {
-        return Err(TradeError::UnauthorizedDispute {
-            sender: state.seller,
-            recipient: state.buyer,
-            caller: info.sender,
+    assert_caller_role(&info.sender, &state, &[TradeRole::Seller, TradeRole::Buyer])?;
+    if state.is_dispute_window_closed(env.block.time) {
+        return Err(TradeError::DisputeWindowClosed {
+            ends_at: state.dispute_window_ends_at,
+            block_time: env.block.time,
         });
     }
 
     // Update trade State to TradeState::Disputed
-    let mut trade: TradeData = state_storage(deps.storage).load().unwrap();
+    let mut trade_model = TradeModel::may_load(deps.storage)?;
+    let trade = trade_model.dispute()?;
+
+    let res = Response::new()
+        .add_submessage(trade_state_changed_msg(trade))
+        .add_event(trade_event("dispute", trade, Some(&state.state)))
+        .add_attribute("action", "dispute")
+        .add_attribute("caller", info.sender)
+        .add_attribute("arbitrator", trade.arbitrator.to_string());
+    Ok(res)
+}
+
+/// Settles a disputed trade, the buyer winning releases the escrow to them
+/// and the seller winning refunds it, both minus the arbitration fee.
+fn resolve(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    trade: TradeData,
+    winner: String,
+) -> Result<Response, TradeError> {
+    assert_caller_role(&info.sender, &trade, &[TradeRole::Arbitrator])?;
+    if trade.multisig_release {
+        return Err(TradeError::ApprovalsRequired {});
+    }
+
+    if trade.state != TradeState::Disputed {
+        return Err(TradeError::NotDisputed { state: trade.state });
+    }
 
-    trade.state = TradeState::Disputed;
+    let winner_addr = deps.api.addr_validate(winner.as_str())?;
+    let res = if winner_addr == trade.buyer {
+        release(deps, env, info, trade)?
+    } else if winner_addr == trade.seller {
+        refund(deps, env, info, trade)?
+    } else {
+        return Err(TradeError::InvalidWinner { winner });
+    };
+
+    Ok(res.add_attribute("winner", winner_addr))
+}
+
+/// Settles a disputed `multisig_release` trade once two of its parties
+/// approved the same recipient, conflicting approvals release nothing.
+fn approve_release(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    trade: TradeData,
+    to: String,
+) -> Result<Response, TradeError> {
+    if !trade.multisig_release {
+        return Err(TradeError::MultisigReleaseDisabled {});
+    }
+    assert_caller_role(
+        &info.sender,
+        &trade,
+        &[TradeRole::Buyer, TradeRole::Seller, TradeRole::Arbitrator],
+    )?;
+    if trade.state != TradeState::Disputed {
+        return Err(TradeError::NotDisputed { state: trade.state });
+    }
+    let to_addr = deps.api.addr_validate(to.as_str())?;
+    if to_addr != trade.buyer && to_addr != trade.seller {
+        return Err(TradeError::InvalidWinner { winner: to });
+    }
+
+    let mut trade_model = TradeModel::may_load(deps.storage)?;
+    let agreed = trade_model
+        .trade
+        .approve_release(info.sender.clone(), to_addr.clone());
+    TradeModel::store(trade_model.storage, &trade_model.trade)?;
+    let trade = trade_model.trade;
+    let conflict = trade.approvals_conflict;
+
+    let res = match agreed {
+        Some(winner) if winner == trade.buyer => release_escrow(deps, env, trade)?,
+        Some(_) => refund_escrow(deps, env, trade)?,
+        None => Response::new(),
+    };
+    Ok(res
+        .add_attribute("action", "approve_release")
+        .add_attribute("approver", info.sender)
+        .add_attribute("to", to_addr)
+        .add_attribute("conflict", conflict.to_string()))
+}
+fn submit_evidence(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    trade: TradeData,
+    sha256: String,
+) -> Result<Response, TradeError> {
+    assert_caller_role(&info.sender, &trade, &[TradeRole::Buyer, TradeRole::Seller])?;
+    if trade.state != TradeState::Disputed {
+        return Err(TradeError::NotDisputed { state: trade.state });
+    }
+    assert_sha256_hex(&sha256)?;
+    let submitted = trade
+        .evidence
+        .iter()
+        .filter(|evidence| evidence.submitter == info.sender)
+        .count();
+    if submitted >= MAX_EVIDENCE_PER_PARTY {
+        return Err(TradeError::TooMuchEvidence {
+            max: MAX_EVIDENCE_PER_PARTY,
+        });
+    }
 
-    state_storage(deps.storage).save(&trade).unwrap();
+    let mut trade = trade;
+    let sha256 = sha256.to_lowercase();
+    trade.evidence.push(Evidence {
+        submitter: info.sender.clone(),
+        sha256: sha256.clone(),
+        submitted_at: env.block.time,
+    });
+    TradeModel::store(deps.storage, &trade)?;
 
-    let res = Response::new();
+    let res = Response::new()
+        .add_attribute("action", "submit_evidence")
+        .add_attribute("submitter", info.sender)
+        .add_attribute("sha256", sha256);
     Ok(res)
 }
-fn release(
+
+fn commit_message(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     trade: TradeData,
+    sha256: String,
 ) -> Result<Response, TradeError> {
-    let arbitration_mode =
-        (info.sender == trade.arbitrator) & (trade.state == TradeState::Disputed);
+    assert_caller_role(&info.sender, &trade, &[TradeRole::Buyer, TradeRole::Seller])?;
+    if trade.state.is_final() {
+        return Err(TradeError::TradeClosed { state: trade.state });
+    }
+    assert_sha256_hex(&sha256).map_err(|_| TradeError::InvalidMessageHash {
+        sha256: sha256.clone(),
+    })?;
+    let mut commitments = TradeModel::commitments(deps.storage)?;
+    if commitments.len() >= MAX_MESSAGE_COMMITMENTS {
+        return Err(TradeError::TooManyCommitments {
+            max: MAX_MESSAGE_COMMITMENTS,
+        });
+    }
 
-    //Check if sender can release
-    if (info.sender != trade.seller) & !arbitration_mode {
-        return Err(TradeError::Unauthorized {
-            owner: trade.seller,
-            arbitrator: trade.arbitrator,
-            caller: info.sender,
+    let sha256 = sha256.to_lowercase();
+    commitments.push(MessageCommitment {
+        sender: info.sender.clone(),
+        sha256: sha256.clone(),
+        committed_at: env.block.time,
+    });
+    TradeModel::store_commitments(deps.storage, commitments)?;
+
+    let res = Response::new()
+        .add_attribute("action", "commit_message")
+        .add_attribute("sender", info.sender)
+        .add_attribute("sha256", sha256);
+    Ok(res)
+}
+
+/// Frees the offer's trade slot, nothing was escrowed that needs returning.
+fn cancel(deps: DepsMut, info: MessageInfo, trade: TradeData) -> Result<Response, TradeError> {
+    assert_caller_role(&info.sender, &trade, &[TradeRole::Buyer, TradeRole::Seller])?;
+    if matches!(
+        trade.state,
+        TradeState::EscrowFunded | TradeState::FiatDeposited
+    ) {
+        return Err(TradeError::CancelFunded { state: trade.state });
+    }
+
+    let from_state = trade.state.clone();
+    let mut trade_model = TradeModel::may_load(deps.storage)?;
+    let trade = trade_model.cancel()?;
+
+    let res = Response::new()
+        .add_submessage(trade_closed_msg(trade))
+        .add_event(trade_event("cancel", trade, Some(&from_state)))
+        .add_attribute("action", "cancel")
+        .add_attribute("caller", info.sender);
+    Ok(res)
+}
+
+fn fiat_deposited(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    trade: TradeData,
+) -> Result<Response, TradeError> {
+    assert_caller_role(&info.sender, &trade, &[TradeRole::Buyer])?;
+    if trade.is_expired(env.block.time) {
+        return Err(TradeError::Expired {
+            expires_at: trade.expires_at,
+            block_time: env.block.time,
         });
     }
 
-    // throws error if state is expired BUT arbitrator can release expired trades
-    if (env.block.height > trade.expire_height) & !arbitration_mode {
+    let from_state = trade.state.clone();
+    let mut trade_model = TradeModel::may_load(deps.storage)?;
+    let trade = trade_model.mark_fiat_deposited()?;
+
+    let res = Response::new()
+        .add_submessage(trade_state_changed_msg(trade))
+        .add_event(trade_event("fiat_deposited", trade, Some(&from_state)))
+        .add_attribute("action", "fiat_deposited")
+        .add_attribute("buyer", trade.buyer.to_string());
+    Ok(res)
+}
+
+fn release(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    trade: TradeData,
+) -> Result<Response, TradeError> {
+    //Disputed trades are released by the arbitrator, all others by the seller
+    let arbitration_mode = trade.state == TradeState::Disputed;
+    let role = if arbitration_mode {
+        TradeRole::Arbitrator
+    } else {
+        TradeRole::Seller
+    };
+    assert_caller_role(&info.sender, &trade, &[role])?;
+
+    // throws error if state is expired BUT the seller can release expired trades once fiat is
+    // deposited, and the arbitrator always
+    let protected = arbitration_mode || trade.state == TradeState::FiatDeposited;
+    if trade.is_expired(env.block.time) && !protected {
         return Err(TradeError::Expired {
-            expire_height: trade.expire_height,
-            current_height: env.block.height,
+            expires_at: trade.expires_at,
+            block_time: env.block.time,
         });
     }
 
     //Load and check balance
-    // let balance_result = deps.querier.query_all_balances(&env.contract.address);
-    let balance_result = deps.querier.query_balance(&env.contract.address, "uusd");
-    if balance_result.is_err() {
+    if query_asset_balance(&deps.querier, &env.contract.address, &trade.asset).is_err() {
         return Err(TradeError::ReleaseError {
             message: "Contract has no funds.".to_string(),
         });
     }
 
-    let offer = get_offer(&deps.as_ref(), &trade);
+    release_escrow(deps, env, trade)
+}
 
-    //Update trade State to TradeState::Closed or TradeState::SettledFor(Maker|Taker)
-    let mut trade: TradeData = state_storage(deps.storage).load().unwrap();
+/// Sends the escrow to the buyer minus the taker fee, the maker fee of buy
+/// offers and the arbitrator's cut when the trade is disputed.
+fn release_escrow(deps: DepsMut, env: Env, trade: TradeData) -> Result<Response, TradeError> {
+    let arbitration_mode = trade.state == TradeState::Disputed;
+    let offer = get_offer(&deps.as_ref(), &trade);
 
+    //Update trade State to TradeState::Released or TradeState::SettledFor(Maker|Taker)
+    let for_maker = (offer.offer_type == OfferType::Buy) & (offer.owner == trade.buyer);
+    let from_state = trade.state.clone();
+    let mut trade_model = TradeModel::may_load(deps.storage)?;
     if !arbitration_mode {
-        trade.state = TradeState::Closed;
-    } else if (offer.offer_type == OfferType::Buy) & (offer.owner == trade.buyer) {
-        trade.state = TradeState::SettledForMaker;
+        trade_model.release()?;
+    } else if for_maker {
+        trade_model.settle(TradeState::SettledForMaker)?;
     } else {
-        trade.state = TradeState::SettledForTaker;
+        trade_model.settle(TradeState::SettledForTaker)?;
     }
-
-    state_storage(deps.storage).save(&trade).unwrap();
+    let trade = trade_model.trade;
 
     //Calculate fees and final release amount
     let mut send_msgs: Vec<SubMsg> = Vec::new();
@@ -315,65 +673,52 @@ fn release(
     let factory_cfg: FactoryConfig =
         get_factory_config(&deps.querier, trade.factory_addr.to_string());
 
-    //Collect Fee
-    let local_terra_fee = Coin::new(localterra_fee(trade.ust_amount.clone()).u128(), "uusd");
-    let fee_collector = factory_cfg.fee_collector_addr.clone();
-    send_msgs.push(SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-        to_address: fee_collector.into_string(),
-        amount: vec![local_terra_fee],
-    })));
-
-    let ust = Coin::new(trade.ust_amount.u128(), "uusd");
-    //Release amount
-    let release_amount = if offer.offer_type == OfferType::Buy {
-        //TODO: Move to a method
-        let ltfee = localterra_fee(trade.ust_amount);
-        let ltfee_coin = Coin::new(ltfee.u128(), "uusd");
-        let ltfee_tax = compute_tax(&deps.querier, &ltfee_coin).unwrap();
-
-        let mut arbitration_fee_inc_tax = Uint128::zero();
-        if arbitration_mode {
-            // Pay arbitration fee
-            let arbitration_rate = 10u128; // TODO move fee to constant
-            let arbitration_coin = Coin::new(
-                trade
-                    .ust_amount
-                    .u128()
-                    .clone()
-                    .checked_div(arbitration_rate)
-                    .unwrap(),
-                "uusd",
-            );
-
-            arbitration_fee_inc_tax =
-                arbitration_coin.amount + compute_tax(&deps.querier, &arbitration_coin).unwrap();
-
-            send_msgs.push(SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: trade.arbitrator.to_string(),
-                amount: vec![arbitration_coin],
-            })));
-        }
-
-        let release_amount = trade
-            .ust_amount
-            .sub(ltfee)
-            .sub(ltfee_tax)
-            .sub(arbitration_fee_inc_tax);
-
-        let release_tax =
-            compute_tax(&deps.querier, &Coin::new(release_amount.u128(), "uusd")).unwrap();
-
-        let deduction = ltfee.add(&ltfee_tax).add(&release_tax);
-
-        Coin::new(trade.ust_amount.sub(deduction).u128(), "uusd")
-    } else {
-        ust
+    //Collect Fees, rounded down so the buyer never gets more than escrowed. Buy offers pay the
+    //factory maker fee at release, sell offers the one escrowed with the trade amount.
+    let maker_fee_bps = match offer.offer_type {
+        OfferType::Buy => factory_cfg.maker_fee_bps,
+        OfferType::Sell => trade.fee_bps,
     };
+    let fees = TradeFees::new(trade.amount, maker_fee_bps, factory_cfg.taker_fee_bps)?;
+    let fee_amount = fees.total();
+    let burn_amount = fee_burn_amount(fee_amount, factory_cfg.fee_burn_bps);
+    let collected_amount = fee_amount.sub(burn_amount);
+    if !burn_amount.is_zero() {
+        send_msgs.push(SubMsg::new(burn_asset_msg(&trade.asset, burn_amount)));
+    }
+    if !collected_amount.is_zero() {
+        send_msgs.push(SubMsg::new(send_asset_msg(
+            &factory_cfg.treasury_addr,
+            &trade.asset,
+            collected_amount,
+        )));
+    }
 
-    send_msgs.push(SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-        to_address: trade.buyer.into_string(),
-        amount: vec![release_amount],
-    })));
+    //Release amount, disputed buy trades pay the arbitration fee out of the escrow
+    let mut arbitration_amount = Uint128::zero();
+    if arbitration_mode && offer.offer_type == OfferType::Buy {
+        let arbitration_rate = 10u128; // TODO move fee to constant
+        arbitration_amount =
+            Uint128::new(trade.amount.u128().checked_div(arbitration_rate).unwrap());
+        send_msgs.push(SubMsg::new(send_asset_msg(
+            &trade.arbitrator,
+            &trade.asset,
+            arbitration_amount,
+        )));
+    }
+    let release_amount = fees.release_amount(
+        &deps.querier,
+        &trade.asset,
+        &offer.offer_type,
+        trade.amount,
+        arbitration_amount,
+    )?;
+
+    send_msgs.push(SubMsg::new(send_asset_msg(
+        &trade.buyer,
+        &trade.asset,
+        release_amount,
+    )));
 
     //Create Trade Registration message to be sent to the Trading Incentives contract.
     let register_trade_msg = SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
@@ -386,8 +731,22 @@ fn release(
         funds: vec![],
     }));
     send_msgs.push(register_trade_msg);
+    send_msgs.push(trade_closed_msg(&trade));
 
-    let res = Response::new().add_submessages(send_msgs);
+    let res = Response::new()
+        .add_submessages(send_msgs)
+        .add_event(trade_event("release", &trade, Some(&from_state)))
+        .add_attribute("action", "release")
+        .add_attribute("state", trade.state.to_string())
+        .add_attribute("asset", trade.asset.to_string())
+        .add_attribute("amount", release_amount.to_string())
+        .add_attribute("fee_amount", fee_amount.to_string())
+        .add_attribute("maker_fee_amount", fees.maker_fee_amount.to_string())
+        .add_attribute("taker_fee_amount", fees.taker_fee_amount.to_string())
+        .add_attribute("burn_amount", burn_amount.to_string())
+        .add_attribute("collected_amount", collected_amount.to_string())
+        .add_attribute("buyer", trade.buyer)
+        .add_attribute("seller", trade.seller);
     Ok(res)
 }
 
@@ -400,79 +759,212 @@ fn refund(
     let arbitration_mode =
         (info.sender == trade.arbitrator) & (trade.state == TradeState::Disputed);
 
-    // anyone can try to refund, as long as the contract is expired
-    // noone except arbitrator can refund if the trade is in arbitration
-    if (trade.expire_height > env.block.height)
-        & ((trade.state != TradeState::Disputed) & !arbitration_mode)
-    {
+    // the seller can take the escrow back until the buyer marks the fiat as
+    // deposited, anyone can once the trade is expired.
+    // noone except arbitrator can refund if the trade is in arbitration or
+    // the buyer marked the fiat as deposited, unfunded trades have nothing to refund
+    let seller_cancel = (info.sender == trade.seller) & (trade.state == TradeState::EscrowFunded);
+    let protected = matches!(
+        trade.state,
+        TradeState::Created | TradeState::FiatDeposited | TradeState::Disputed
+    ) || trade.state.is_final();
+    if !arbitration_mode && (protected || !(seller_cancel || trade.is_expired(env.block.time))) {
         return Err(TradeError::RefundError {
-            message:
-                "Only expired trades that are not disputed can be refunded by non-arbitrators."
-                    .to_string(),
+            message: "Only the seller before fiat is deposited or anyone after expiry can refund."
+                .to_string(),
             trade: trade.state.to_string(),
         });
     }
 
+    refund_escrow(deps, env, trade)
+}
+
+/// Returns the escrow to the seller, minus the arbitration fee when the
+/// trade is disputed.
+fn refund_escrow(deps: DepsMut, env: Env, trade: TradeData) -> Result<Response, TradeError> {
+    let arbitration_mode = trade.state == TradeState::Disputed;
     let balance_result = deps.querier.query_all_balances(&env.contract.address);
-    return if balance_result.is_ok() {
-        let offer = get_offer(&deps.as_ref(), &trade);
-
-        //Update TradeData to TradeState::Closed or TradeState::SettledFor(Maker|Taker)
-        let mut trade: TradeData = state_storage(deps.storage).load().unwrap();
-
-        if !arbitration_mode {
-            trade.state = TradeState::Canceled;
-        } else if (offer.offer_type == OfferType::Buy) & (offer.owner == trade.buyer) {
-            trade.state = TradeState::SettledForTaker;
-        } else {
-            trade.state = TradeState::SettledForMaker;
-        }
+    if balance_result.is_err() {
+        return Err(TradeError::RefundError {
+            message: "Contract has no funds.".to_string(),
+            trade: trade.state.to_string(),
+        });
+    }
+    let offer = get_offer(&deps.as_ref(), &trade);
 
-        state_storage(deps.storage).save(&trade).unwrap();
+    //Update TradeData to TradeState::Refunded or TradeState::SettledFor(Maker|Taker)
+    let for_taker = (offer.offer_type == OfferType::Buy) & (offer.owner == trade.buyer);
+    let from_state = trade.state.clone();
+    let mut trade_model = TradeModel::may_load(deps.storage)?;
+    if !arbitration_mode {
+        trade_model.refund()?;
+    } else if for_taker {
+        trade_model.settle(TradeState::SettledForTaker)?;
+    } else {
+        trade_model.settle(TradeState::SettledForMaker)?;
+    }
+    let trade = trade_model.trade;
 
+    let res = if let OfferAsset::Cw20 { .. } = trade.asset {
+        refund_cw20(&deps, &env, &trade, arbitration_mode)?
+    } else if arbitration_mode {
         // Pay arbitration fee
-        if arbitration_mode {
-            let mut balance = balance_result.unwrap();
+        let mut balance = balance_result.unwrap();
 
-            let fee_rate: Uint128 = Uint128::new(10);
-            let fee_amount = balance[0].amount.multiply_ratio(Uint128::new(1), fee_rate); // TODO support multiple coins
-            let mut fee = balance.clone();
-            fee[0].amount = fee_amount;
-            balance[0].amount = balance[0].amount - fee_amount;
+        let fee_rate: Uint128 = Uint128::new(10);
+        let fee_amount = balance[0].amount.multiply_ratio(Uint128::new(1), fee_rate); // TODO support multiple coins
+        let mut fee = balance.clone();
+        fee[0].amount = fee_amount;
+        balance[0].amount -= fee_amount;
 
-            let seller_msg = create_send_msg(&deps, trade.seller, balance);
+        let seller_msg = create_send_msg(&deps, trade.seller.clone(), balance);
 
-            let arbitrator_msg = create_send_msg(&deps, trade.arbitrator, fee);
+        let arbitrator_msg = create_send_msg(&deps, trade.arbitrator.clone(), fee);
 
-            let res = Response::new()
-                .add_submessage(SubMsg::new(seller_msg))
-                .add_submessage(SubMsg::new(arbitrator_msg));
-            Ok(res)
-        } else {
-            let balance = balance_result.unwrap();
-            let send_msg = create_send_msg(&deps, trade.seller, balance);
-            let res = Response::new().add_submessage(SubMsg::new(send_msg));
+        Response::new()
+            .add_submessage(SubMsg::new(seller_msg))
+            .add_submessage(SubMsg::new(arbitrator_msg))
+            .add_submessage(trade_closed_msg(&trade))
+    } else {
+        let balance = balance_result.unwrap();
+        let send_msg = create_send_msg(&deps, trade.seller.clone(), balance);
+        Response::new()
+            .add_submessage(SubMsg::new(send_msg))
+            .add_submessage(trade_closed_msg(&trade))
+    };
 
-            Ok(res)
-        }
+    Ok(res
+        .add_event(trade_event("refund", &trade, Some(&from_state)))
+        .add_attribute("action", "refund")
+        .add_attribute("state", trade.state.to_string())
+        .add_attribute("seller", trade.seller)
+        .add_attribute("buyer", trade.buyer))
+}
+
+/// Token balance of the escrow goes back to the seller, minus the arbitration
+/// fee when settled by the arbitrator.
+fn refund_cw20(
+    deps: &DepsMut,
+    env: &Env,
+    trade: &TradeData,
+    arbitration_mode: bool,
+) -> Result<Response, TradeError> {
+    let balance = query_asset_balance(&deps.querier, &env.contract.address, &trade.asset)?;
+    let fee_amount = if arbitration_mode {
+        balance.multiply_ratio(Uint128::new(1), Uint128::new(10))
     } else {
-        Err(TradeError::RefundError {
-            message: "Contract has no funds.".to_string(),
-            trade: trade.state.to_string(),
-        })
+        Uint128::zero()
     };
+
+    let mut res = Response::new().add_submessage(SubMsg::new(send_asset_msg(
+        &trade.seller,
+        &trade.asset,
+        balance - fee_amount,
+    )));
+    if arbitration_mode {
+        res = res.add_submessage(SubMsg::new(send_asset_msg(
+            &trade.arbitrator,
+            &trade.asset,
+            fee_amount,
+        )));
+    }
+    Ok(res.add_submessage(trade_closed_msg(trade)))
+}
+
+/// Reports the final state back to the offer contract, which only lets the
+/// maker update or archive the offer once no trade is in progress.
+fn trade_closed_msg(trade: &TradeData) -> SubMsg {
+    SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: trade.offer_contract.to_string(),
+        msg: to_binary(&OfferExecuteMsg::TradeClosed {
+            state: trade.state.clone(),
+        })
+        .unwrap(),
+        funds: vec![],
+    }))
+}
+
+/// Reports intermediate states back to the offer contract, which indexes its
+/// trades by state.
+fn trade_state_changed_msg(trade: &TradeData) -> SubMsg {
+    SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: trade.offer_contract.to_string(),
+        msg: to_binary(&OfferExecuteMsg::TradeStateChanged {
+            state: trade.state.clone(),
+        })
+        .unwrap(),
+        funds: vec![],
+    }))
 }
 
-fn get_ust_amount(info: MessageInfo) -> Uint128 {
-    let ust = &info.funds.iter().find(|c| c.denom.eq("uusd"));
-    return match ust {
-        None => Uint128::zero(),
-        Some(c) => c.amount,
+/// Amount of a native asset sent along the message, any other coin is
+/// refused. Cw20 trades are funded through `Receive` and take no coins.
+fn get_funded_amount(info: &MessageInfo, asset: &OfferAsset) -> Result<Uint128, TradeError> {
+    let denom = match asset {
+        OfferAsset::Native { denom } => denom.as_str(),
+        OfferAsset::Cw20 { .. } => "",
     };
+    if let Some(coin) = info.funds.iter().find(|c| c.denom != denom) {
+        return Err(TradeError::InvalidDenom {
+            expected: asset.to_string(),
+            received: coin.denom.clone(),
+        });
+    }
+    Ok(info
+        .funds
+        .iter()
+        .find(|c| c.denom == denom)
+        .map_or(Uint128::zero(), |c| c.amount))
+}
+
+fn query_asset_balance(
+    querier: &QuerierWrapper,
+    address: &Addr,
+    asset: &OfferAsset,
+) -> StdResult<Uint128> {
+    match asset {
+        OfferAsset::Native { denom } => Ok(querier.query_balance(address, denom)?.amount),
+        OfferAsset::Cw20 { contract_addr } => {
+            let res: BalanceResponse = querier.query_wasm_smart(
+                contract_addr.to_string(),
+                &Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )?;
+            Ok(res.balance)
+        }
+    }
 }
 
-pub fn localterra_fee(amount: Uint128) -> Uint128 {
-    amount.clone().checked_div(Uint128::new(100u128)).unwrap()
+fn send_asset_msg(to_address: &Addr, asset: &OfferAsset, amount: Uint128) -> CosmosMsg {
+    match asset {
+        OfferAsset::Native { denom } => CosmosMsg::Bank(BankMsg::Send {
+            to_address: to_address.to_string(),
+            amount: vec![Coin::new(amount.u128(), denom.clone())],
+        }),
+        OfferAsset::Cw20 { contract_addr } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: to_address.to_string(),
+                amount,
+            })
+            .unwrap(),
+            funds: vec![],
+        }),
+    }
+}
+
+fn burn_asset_msg(asset: &OfferAsset, amount: Uint128) -> CosmosMsg {
+    match asset {
+        OfferAsset::Native { denom } => CosmosMsg::Bank(BankMsg::Burn {
+            amount: vec![Coin::new(amount.u128(), denom.clone())],
+        }),
+        OfferAsset::Cw20 { contract_addr } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn { amount }).unwrap(),
+            funds: vec![],
+        }),
+    }
 }
 
 fn create_send_msg(deps: &DepsMut, to_address: Addr, coins: Vec<Coin>) -> CosmosMsg {
@@ -1,6 +1,4 @@
 pub mod contract;
 pub mod errors;
 mod mock_querier;
-pub mod state;
-mod taxation;
 mod tests;
@@ -2,18 +2,33 @@
 
 use std::ops::Add;
 
-use cosmwasm_std::testing::{MockApi, MockStorage};
+use cosmwasm_std::testing::{MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_binary, Addr, BankMsg, Coin, CosmosMsg, DepsMut, Empty, MessageInfo, OwnedDeps, Response,
-    SubMsg, Uint128,
+    from_binary, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Deps, DepsMut, Empty,
+    Env, MessageInfo, OwnedDeps, Response, Storage, SubMsg, Timestamp, Uint128, WasmMsg,
 };
+use cosmwasm_storage::to_length_prefixed;
 use cosmwasm_vm::testing::{mock_env, mock_info};
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 
+use localterra_protocol::constants::{
+    MAX_EVIDENCE_PER_PARTY, MAX_MESSAGE_COMMITMENTS, TRADE_STATE_KEY,
+};
 use localterra_protocol::currencies::FiatCurrency;
-use localterra_protocol::offer::{Offer, OfferState, OfferType};
-use localterra_protocol::trade::{ExecuteMsg, InstantiateMsg, QueryMsg, TradeData, TradeState};
+use localterra_protocol::events::TRADE_EVENT;
+use localterra_protocol::factory::ibc_voucher_denom;
+use localterra_protocol::fees::localterra_fee;
+use localterra_protocol::guards::{assert_caller_role, assert_trade_state_change};
+use localterra_protocol::offer::{
+    ExecuteMsg as OfferExecuteMsg, Offer, OfferAsset, OfferState, OfferType,
+};
+use localterra_protocol::trade::{
+    Cw20HookMsg, Evidence, ExecuteMsg, InstantiateMsg, MessageCommitment, MigrateMsg, QueryMsg,
+    TradeData, TradeModel, TradeRole, TradeState,
+};
 
-use crate::contract::{execute, instantiate, localterra_fee, query};
+use crate::contract::{execute, instantiate, migrate, query};
 use crate::errors::TradeError;
 use crate::mock_querier::{mock_dependencies, WasmMockQuerier};
 
@@ -25,16 +40,45 @@ fn test_init() {
 
     let instantiate_trade_msg = InstantiateMsg {
         offer_id: 1,
-        arbitrator: "arbitrator".to_string(),
         taker_contact: "USTKing".to_string(),
-        ust_amount: trade_amount.clone().to_string(),
+        amount: trade_amount,
         taker: "other".to_string(),
         offers_addr: "offers".to_string(),
         timestamp: 1641329895,
+        fiat_price: None,
+        fiat_amount: None,
+        asset: OfferAsset::default(),
+        multisig_release: true,
     };
 
-    let res = instantiate(deps.as_mut(), mock_env(), info, instantiate_trade_msg);
-    assert!(res.is_ok())
+    //Instantiating the code id directly isn't allowed
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        instantiate_trade_msg.clone(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, TradeError::NotOfferContract { .. }));
+
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("offers", &[]),
+        instantiate_trade_msg,
+    );
+    assert!(res.is_ok());
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert!(trade_state.multisig_release);
+    assert!(trade_state.release_approvals.is_empty());
+
+    //The raw storage layout matches the State query
+    let raw = deps
+        .storage
+        .get(&to_length_prefixed(TRADE_STATE_KEY))
+        .unwrap();
+    assert_eq!(from_binary::<TradeData>(&Binary(raw)).unwrap(), trade_state);
 }
 
 fn create_trade(
@@ -48,7 +92,7 @@ fn create_trade(
     let mut deps = mock_dependencies(
         &[Coin {
             denom: "uusd".to_string(),
-            amount: trade_amount.clone(),
+            amount: trade_amount,
         }],
         offer,
     );
@@ -56,28 +100,133 @@ fn create_trade(
     //Init trade
     let instantiate_trade_msg = InstantiateMsg {
         offer_id: 1,
-        arbitrator: "arbitrator".to_string(),
         taker_contact: "USTKing".to_string(),
-        ust_amount: trade_amount.clone().to_string(),
+        amount: trade_amount,
         taker: info.sender.clone().into_string(),
         offers_addr: "offers".to_string(),
         timestamp: 1641329895,
+        fiat_price: None,
+        fiat_amount: None,
+        asset: OfferAsset::default(),
+        multisig_release: false,
     };
+    //The offer contract forwards the taker's funds
+    let offers_info = mock_info("offers", &info.funds);
     let res = instantiate(
         deps.as_mut(),
         mock_env(),
-        info.clone(),
+        offers_info,
         instantiate_trade_msg,
     );
     (res, deps)
 }
 
+#[test]
+fn test_trade_copies_maker_contact() {
+    let info = mock_info("taker", &[]);
+    let (_, deps) = create_trade(Uint128::new(10_000_000u128), info, None);
+
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(trade_state.maker_contact, Some("LunaQueen".to_string()));
+}
+
+#[test]
+fn test_trade_picks_registered_arbitrator() {
+    //Currencies without registered arbitrators use the one of the offer contract config
+    let (_, deps) = create_trade(Uint128::new(10_000_000u128), mock_info("taker", &[]), None);
+    let trade = TradeModel::from_store(&deps.storage).unwrap();
+    assert_eq!(trade.arbitrator, Addr::unchecked("arbitrator"));
+
+    //Without either the trade is refused, the taker doesn't get to name one
+    let mut deps = mock_dependencies(&[], None);
+    deps.querier.with_offers_arbitrator(None);
+    let msg = InstantiateMsg {
+        offer_id: 1,
+        taker_contact: "USTKing".to_string(),
+        amount: Uint128::new(10_000_000u128),
+        taker: "taker".to_string(),
+        offers_addr: "offers".to_string(),
+        timestamp: 1641329895,
+        fiat_price: None,
+        fiat_amount: None,
+        asset: OfferAsset::default(),
+        multisig_release: false,
+    };
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info("offers", &[]), msg).unwrap_err();
+    assert!(matches!(err, TradeError::NoArbitrator { .. }));
+
+    //Otherwise the currency's arbitrator whose turn it is
+    let offer = create_offer_struct(
+        Uint128::new(1_000_000u128),
+        Uint128::new(500_000_000u128),
+        None,
+        Some(FiatCurrency::unchecked("BRL")),
+    );
+    let (_, deps) = create_trade(
+        Uint128::new(10_000_000u128),
+        mock_info("taker", &[]),
+        Some(offer),
+    );
+    let trade = TradeModel::from_store(&deps.storage).unwrap();
+    assert_eq!(trade.arbitrator, Addr::unchecked("arbitrator-brl-2"));
+}
+
+#[test]
+fn test_migrate() {
+    let (_, mut deps) = create_trade(Uint128::new(10_000_000u128), mock_info("taker", &[]), None);
+    let stored = get_contract_version(&deps.storage).unwrap();
+    assert_eq!(stored.contract, "crates.io:trade");
+
+    //A trade written before fee_bps existed and while Released was Closed
+    let trade = TradeModel::from_store(&deps.storage).unwrap();
+    let legacy = String::from_utf8(to_binary(&trade).unwrap().to_vec())
+        .unwrap()
+        .replace(r#""fee_bps":100,"#, "")
+        .replace(r#""state":"created""#, r#""state":"closed""#);
+    assert!(!legacy.contains("fee_bps") && legacy.contains("closed"));
+    let key = to_length_prefixed(b"state");
+    deps.storage.set(&key, legacy.as_bytes());
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(res.attributes[1].value, "Released");
+    let migrated = String::from_utf8(deps.storage.get(&key).unwrap()).unwrap();
+    assert!(migrated.contains(r#""fee_bps":100"#));
+    assert!(migrated.contains(r#""state":"released""#));
+
+    //Other contracts and newer versions are refused
+    set_contract_version(&mut deps.storage, "crates.io:offer", &stored.version).unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert!(matches!(err, TradeError::InvalidMigration { .. }));
+    set_contract_version(&mut deps.storage, &stored.contract, "99.0.0").unwrap();
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert!(matches!(err, TradeError::InvalidMigration { .. }));
+}
+
+#[test]
+fn test_fund_with_wrong_denom() {
+    let trade_amount = Uint128::new(10_000_000u128);
+    let info = mock_info(
+        "taker",
+        &[Coin {
+            denom: "uluna".to_string(),
+            amount: trade_amount,
+        }],
+    );
+    let (res, _) = create_trade(trade_amount, info, None);
+    assert!(matches!(
+        res.unwrap_err(),
+        TradeError::InvalidDenom { expected, received }
+            if expected == "uusd" && received == "uluna"
+    ));
+}
+
 fn mock_info_with_ust(sender: &str, amount: Uint128) -> MessageInfo {
     mock_info(
         sender,
         &[Coin {
             denom: "uusd".to_string(),
-            amount: amount.clone(),
+            amount,
         }],
     )
 }
@@ -85,7 +234,7 @@ fn mock_info_with_ust(sender: &str, amount: Uint128) -> MessageInfo {
 fn release_trade(deps: DepsMut, info: MessageInfo) -> Result<Response<Empty>, TradeError> {
     let res = execute(deps, mock_env(), info.clone(), ExecuteMsg::Release {});
     assert!(&res.is_ok());
-    return res;
+    res
 }
 
 ///Test trade full happy path:
@@ -99,7 +248,7 @@ fn test_trade_happy_path() {
     //let received_amount = trade_amount.clone() - local_terra_fee.clone();
 
     let info = mock_info_with_ust("taker", trade_amount);
-    let (_, mut deps) = create_trade(trade_amount.clone(), info.clone(), None);
+    let (_, mut deps) = create_trade(trade_amount, info.clone(), None);
 
     //Trade should be in funded state
     let trade_state: TradeData =
@@ -109,10 +258,10 @@ fn test_trade_happy_path() {
     //Send release message
     let _res = release_trade(deps.as_mut(), info.clone());
 
-    //Check that trade state is Closed
+    //Check that trade state is Released
     let trade_state: TradeData =
         from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
-    assert_eq!(trade_state.state, TradeState::Closed);
+    assert_eq!(trade_state.state, TradeState::Released);
 
     //Verify that the correct messages were sent after trade completion
     /*
@@ -123,7 +272,7 @@ fn test_trade_happy_path() {
             /*
             SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
                 // TODO change the local terra fee collector address
-                to_address: "fee-collector".to_string(),
+                to_address: "treasury".to_string(),
                 amount: vec![Coin {
                     denom: "uusd".to_string(),
                     // 1% fee amount
@@ -157,6 +306,23 @@ fn test_trade_happy_path() {
      */
 }
 
+#[test]
+fn test_trade_requires_active_offer() {
+    let amount = Uint128::new(10_000_000u128);
+    let offer = Offer {
+        state: OfferState::Paused,
+        ..create_offer_struct(Uint128::new(1_000_000u128), amount, None, None)
+    };
+    let (res, _) = create_trade(amount, mock_info("taker", &[]), Some(offer));
+    assert!(matches!(
+        res.unwrap_err(),
+        TradeError::OfferNotActive {
+            offer_id: 1,
+            state: OfferState::Paused
+        }
+    ));
+}
+
 fn create_offer_struct(
     min_amount: Uint128,
     max_amount: Uint128,
@@ -166,16 +332,42 @@ fn create_offer_struct(
     Offer {
         id: 1,
         owner: Addr::unchecked("offer-owner"),
-        maker_contact: "LunaQueen".to_string(),
+        maker_contact: Some("LunaQueen".to_string()),
         offer_type: offer_type.clone().unwrap_or(OfferType::Buy),
-        fiat_currency: fiat_currency.clone().unwrap_or(FiatCurrency::COP),
-        min_amount: min_amount.clone(),
-        max_amount: max_amount.clone(),
+        fiat_currency: fiat_currency
+            .clone()
+            .unwrap_or(FiatCurrency::unchecked("COP")),
+        min_amount,
+        max_amount,
         state: OfferState::Active,
         timestamp: 1641329895,
+        expires_at: None,
+        price_margin_bps: None,
+        payment_methods: vec![],
+        description: None,
+        asset: OfferAsset::default(),
+        active_trades: 0,
+        reserved_amount: Uint128::zero(),
+        total_available: Uint128::zero(),
+        remaining: Uint128::zero(),
+        activate_at: None,
+        pause_at: None,
+        amounts_in_fiat: false,
+        available_from_hour: None,
+        available_to_hour: None,
+        effective_state: None,
+        currently_available: None,
+        created_at: Timestamp::from_seconds(1641329895),
+        last_updated: Timestamp::from_seconds(1641329895),
+        min_trades_completed: None,
     }
 }
 
+///Buy offer taking the small amounts of the fee tests.
+fn small_buy_offer() -> Offer {
+    create_offer_struct(Uint128::new(1), Uint128::new(1_000_000), None, None)
+}
+
 ///Verifies that a trade can only be created within order amount limits.
 #[test]
 fn test_trade_amount() {
@@ -183,30 +375,60 @@ fn test_trade_amount() {
     let min_amount = Uint128::new(1_000_000u128);
     let max_amount = Uint128::new(500_000_000u128);
     let offer = create_offer_struct(min_amount, max_amount, None, None);
-    let trade_amount = min_amount.clone();
-    let taker = mock_info_with_ust("taker", min_amount.clone());
+    let trade_amount = min_amount;
+    let taker = mock_info_with_ust("taker", min_amount);
     let (res, _) = create_trade(trade_amount, taker.clone(), Some(offer.clone()));
     assert!(res.is_ok());
 
     //Init trade with max amount and assert it's ok.
-    let trade_amount = max_amount.clone();
-    let taker = mock_info_with_ust("taker", min_amount.clone());
+    let trade_amount = max_amount;
+    let taker = mock_info_with_ust("taker", max_amount);
     let (res, _) = create_trade(trade_amount, taker.clone(), Some(offer.clone()));
     assert!(res.is_ok());
 
     //Init trade with less than min amount and assert it's an err.
     let trade_amount = min_amount.checked_sub(Uint128::new(1u128)).unwrap();
-    let taker = mock_info_with_ust("taker", min_amount.clone());
+    let taker = mock_info_with_ust("taker", min_amount);
     let (res, _) = create_trade(trade_amount, taker.clone(), Some(offer.clone()));
     assert!(res.is_err());
 
     //Init trade with more than max amount and assert it's an err.
     let trade_amount = max_amount.add(Uint128::new(1u128));
-    let taker = mock_info_with_ust("taker", min_amount.clone());
+    let taker = mock_info_with_ust("taker", min_amount);
     let (res, _) = create_trade(trade_amount, taker.clone(), Some(offer.clone()));
     assert!(res.is_err());
 }
 
+#[test]
+fn test_fiat_amount_range() {
+    //The offer ranges over fiat minor units, the escrow amount is converted from them
+    let mut offer = create_offer_struct(Uint128::new(1_000), Uint128::new(50_000), None, None);
+    offer.amounts_in_fiat = true;
+    let trade_amount = Uint128::new(10_000_000);
+    let create = |fiat_amount: Option<Uint128>| {
+        let mut deps = mock_dependencies(&[], Some(offer.clone()));
+        let msg = InstantiateMsg {
+            offer_id: 1,
+            taker_contact: "USTKing".to_string(),
+            amount: trade_amount,
+            taker: "taker".to_string(),
+            offers_addr: "offers".to_string(),
+            timestamp: 1641329895,
+            fiat_price: None,
+            fiat_amount,
+            asset: OfferAsset::default(),
+            multisig_release: false,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("offers", &[]), msg)
+    };
+
+    create(Some(Uint128::new(20_000))).unwrap();
+    for fiat_amount in [None, Some(Uint128::new(999)), Some(Uint128::new(50_001))] {
+        let err = create(fiat_amount).unwrap_err();
+        assert!(matches!(err, TradeError::AmountError { .. }));
+    }
+}
+
 ///Test trade expiration.
 #[test]
 fn test_trade_expiration() {
@@ -219,7 +441,7 @@ fn test_trade_expiration() {
         from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
 
     let mut expired_env = mock_env();
-    expired_env.block.height = trade_state.expire_height + 1;
+    expired_env.block.time = trade_state.expires_at.plus_seconds(1);
 
     //Send Release Message, assert that it's an error.
     let res = execute(
@@ -258,6 +480,79 @@ fn test_custom_errors() {
     ))
 }
 
+#[test]
+fn test_release_errors() {
+    let trade_amount = Uint128::new(500_000_000u128);
+    let seller = mock_info("taker", &[]);
+    let buyer = mock_info("offer-owner", &[]);
+    let (_, mut deps) = create_trade(trade_amount, seller.clone(), None);
+
+    //Nothing to release before the escrow is funded
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        seller.clone(),
+        ExecuteMsg::Release {},
+    );
+    assert!(matches!(
+        res.unwrap_err(),
+        TradeError::InvalidStateChange {
+            from: TradeState::Created,
+            to: TradeState::Released,
+        }
+    ));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info_with_ust("taker", trade_amount),
+        ExecuteMsg::FundEscrow {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        buyer.clone(),
+        ExecuteMsg::FiatDeposited {},
+    )
+    .unwrap();
+
+    //The buyer can't release to themselves
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        buyer.clone(),
+        ExecuteMsg::Release {},
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::Unauthorized { .. }));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        seller.clone(),
+        ExecuteMsg::Release {},
+    )
+    .unwrap();
+    let attr = |key: &str| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .map(|attr| attr.value.clone())
+    };
+    assert_eq!(attr("action"), Some("release".to_string()));
+    assert_eq!(attr("state"), Some("Released".to_string()));
+    assert_eq!(attr("buyer"), Some("offer-owner".to_string()));
+    assert_eq!(attr("seller"), Some("taker".to_string()));
+    assert_eq!(
+        attr("fee_amount"),
+        Some(localterra_fee(trade_amount, 100).unwrap().to_string())
+    );
+
+    //Releasing twice fails
+    let res = execute(deps.as_mut(), mock_env(), seller, ExecuteMsg::Release {});
+    assert!(matches!(res.unwrap_err(), TradeError::AlreadyReleased {}));
+}
+
 ///Test Refund
 #[test]
 fn test_refund() {
@@ -280,7 +575,7 @@ fn test_refund() {
     let trade_state: TradeData =
         from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
     let mut expired_env = mock_env();
-    expired_env.block.height = trade_state.expire_height + 1;
+    expired_env.block.time = trade_state.expires_at.plus_seconds(1);
 
     //Try to refund and assert it's ok.
     let res = execute(
@@ -293,33 +588,70 @@ fn test_refund() {
     //Verify that the correct messages were sent after trade completion
     assert_eq!(
         res.unwrap().messages,
-        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-            to_address: info.sender.to_string(),
-            amount: vec![Coin {
-                denom: "uusd".to_string(),
-                amount: trade_amount.clone(),
-            }],
-        }))]
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: trade_amount,
+                }],
+            })),
+            trade_closed(TradeState::Refunded),
+        ]
     )
 }
 
+fn trade_closed(state: TradeState) -> SubMsg {
+    SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "offers".to_string(),
+        msg: to_binary(&OfferExecuteMsg::TradeClosed { state }).unwrap(),
+        funds: vec![],
+    }))
+}
+
 ///Test fund escrow after instantiating Trade without coins
 #[test]
 fn test_fund_escrow() {
-    let mut trade_amount = Uint128::from(500_000_000u128);
+    let trade_amount = Uint128::from(500_000_000u128);
     let mut info = mock_info_with_ust("taker", Uint128::zero());
-    let (_, mut deps) = create_trade(trade_amount.clone(), info.clone(), None);
+    let (_, mut deps) = create_trade(trade_amount, info.clone(), None);
 
     //Trade should be in Created state
     let trade_state: TradeData =
         from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
     assert_eq!(trade_state.state, TradeState::Created);
 
-    //Send FundEscrow message with UST and check that trade is in EscrowFunded state.
-    let localterra_fee = localterra_fee(trade_amount);
-    trade_amount = trade_amount.add(localterra_fee);
-    info.funds[0].amount = trade_amount.clone();
+    //Over and underfunding are refused
+    let localterra_fee = localterra_fee(trade_amount, 100).unwrap();
+    for funded_amount in [
+        trade_amount.add(localterra_fee),
+        trade_amount - localterra_fee,
+    ] {
+        info.funds[0].amount = funded_amount;
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::FundEscrow {},
+        );
+        assert!(matches!(
+            res.unwrap_err(),
+            TradeError::FundEscrowError { required_amount, sent_amount }
+                if required_amount == trade_amount && sent_amount == funded_amount
+        ));
+    }
+
+    //Only the seller can fund the escrow
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info_with_ust("offer-owner", trade_amount),
+        ExecuteMsg::FundEscrow {},
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::Unauthorized { .. }));
 
+    //Send FundEscrow message with UST and check that trade is in EscrowFunded state.
+    info.funds[0].amount = trade_amount;
     let res = execute(
         deps.as_mut(),
         mock_env(),
@@ -347,20 +679,1392 @@ fn test_fund_escrow() {
 fn test_expired_trade() {
     let mut trade_amount = Uint128::from(500_000_000u128);
     let mut info = mock_info_with_ust("taker", Uint128::zero());
-    let (_, mut deps) = create_trade(trade_amount.clone(), info.clone(), None);
+    let (_, mut deps) = create_trade(trade_amount, info.clone(), None);
 
     let trade_state: TradeData =
         from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
 
     //Send FundEscrow message with UST and check that trade is in EscrowFunded state.
-    let localterra_fee = localterra_fee(trade_amount);
+    let localterra_fee = localterra_fee(trade_amount, 100).unwrap();
     trade_amount = trade_amount.add(localterra_fee);
-    info.funds[0].amount = trade_amount.clone();
+    info.funds[0].amount = trade_amount;
 
     let mut env = mock_env();
-    env.block.height = trade_state.expire_height;
+    env.block.time = trade_state.expires_at;
 
     let res = execute(deps.as_mut(), env, info.clone(), ExecuteMsg::FundEscrow {});
     assert!(res.is_err());
     assert!(matches!(res.err().unwrap(), TradeError::Expired { .. }));
 }
+
+fn create_cw20_trade(trade_amount: Uint128) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let asset = OfferAsset::Cw20 {
+        contract_addr: Addr::unchecked("wbtc-token"),
+    };
+    let offer = Offer {
+        asset: asset.clone(),
+        ..create_offer_struct(trade_amount, trade_amount, None, None)
+    };
+    let mut deps = mock_dependencies(&[], Some(offer));
+
+    let instantiate_trade_msg = InstantiateMsg {
+        offer_id: 1,
+        taker_contact: "USTKing".to_string(),
+        amount: trade_amount,
+        taker: "taker".to_string(),
+        offers_addr: "offers".to_string(),
+        timestamp: 1641329895,
+        fiat_price: None,
+        fiat_amount: None,
+        asset,
+        multisig_release: false,
+    };
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("offers", &[]),
+        instantiate_trade_msg,
+    )
+    .unwrap();
+    deps
+}
+
+fn cw20_transfer(recipient: &str, amount: Uint128) -> SubMsg {
+    SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: "wbtc-token".to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+        })
+        .unwrap(),
+        funds: vec![],
+    }))
+}
+
+#[test]
+fn test_cw20_trade() {
+    let trade_amount = Uint128::new(100_000_000u128);
+    let mut deps = create_cw20_trade(trade_amount);
+    let send = |amount: Uint128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "taker".to_string(),
+            amount,
+            msg: to_binary(&Cw20HookMsg::FundEscrow {}).unwrap(),
+        })
+    };
+
+    //Native coins and other tokens are refused
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info_with_ust("taker", trade_amount),
+        ExecuteMsg::FundEscrow {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, TradeError::InvalidDenom { .. }));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("other-token", &[]),
+        send(trade_amount),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        TradeError::InvalidDenom { expected, received }
+            if expected == "wbtc-token" && received == "other-token"
+    ));
+
+    //Sends without the hook message, from others than the seller or off the escrow amount too
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("wbtc-token", &[]),
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "taker".to_string(),
+            amount: trade_amount,
+            msg: Binary::default(),
+        }),
+    )
+    .unwrap_err();
+    assert!(matches!(err, TradeError::InvalidHookMsg {}));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("wbtc-token", &[]),
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "offer-owner".to_string(),
+            amount: trade_amount,
+            msg: to_binary(&Cw20HookMsg::FundEscrow {}).unwrap(),
+        }),
+    )
+    .unwrap_err();
+    assert!(matches!(err, TradeError::Unauthorized { .. }));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("wbtc-token", &[]),
+        send(trade_amount - Uint128::new(1)),
+    )
+    .unwrap_err();
+    assert!(matches!(err, TradeError::FundEscrowError { .. }));
+
+    //Funding happens through the token's Send
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("wbtc-token", &[]),
+        send(trade_amount),
+    )
+    .unwrap();
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(trade_state.state, TradeState::EscrowFunded);
+
+    //Release transfers the tokens, no tax applies
+    deps.querier.with_token_balances(&[(
+        &"wbtc-token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &trade_amount)],
+    )]);
+    let res = release_trade(deps.as_mut(), mock_info("taker", &[])).unwrap();
+    let fee = localterra_fee(trade_amount, 100).unwrap();
+    assert_eq!(res.messages[0], cw20_transfer("treasury", fee));
+    assert_eq!(res.messages[3], trade_closed(TradeState::Released));
+    assert_eq!(
+        res.messages[1],
+        cw20_transfer("offer-owner", trade_amount - fee)
+    );
+}
+
+fn create_ibc_trade(
+    trade_amount: Uint128,
+    denom: &str,
+) -> (
+    Result<Response<Empty>, TradeError>,
+    OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+) {
+    let asset = OfferAsset::Native {
+        denom: denom.to_string(),
+    };
+    let offer = Offer {
+        asset: asset.clone(),
+        ..create_offer_struct(trade_amount, trade_amount, None, None)
+    };
+    let mut deps = mock_dependencies(&[Coin::new(trade_amount.u128(), denom)], Some(offer));
+    let instantiate_trade_msg = InstantiateMsg {
+        offer_id: 1,
+        taker_contact: "USTKing".to_string(),
+        amount: trade_amount,
+        taker: "taker".to_string(),
+        offers_addr: "offers".to_string(),
+        timestamp: 1641329895,
+        fiat_price: None,
+        fiat_amount: None,
+        asset,
+        multisig_release: false,
+    };
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("offers", &[Coin::new(trade_amount.u128(), denom)]),
+        instantiate_trade_msg,
+    );
+    (res, deps)
+}
+
+#[test]
+fn test_ibc_trade() {
+    let trade_amount = Uint128::new(100_000_000u128);
+    let trusted = ibc_voucher_denom("channel-1", "uatom");
+
+    //Vouchers of the same denom through another channel or with a look-alike hash are refused
+    for spoofed in [
+        ibc_voucher_denom("channel-99", "uatom"),
+        format!("ibc/{}", trusted[4..].to_lowercase()),
+    ]
+    .iter()
+    {
+        let (res, _) = create_ibc_trade(trade_amount, spoofed);
+        assert!(matches!(
+            res.unwrap_err(),
+            TradeError::UntrustedIbcDenom { denom } if denom == *spoofed
+        ));
+    }
+
+    //The voucher is escrowed and released as is, no tax applies
+    let (res, mut deps) = create_ibc_trade(trade_amount, &trusted);
+    res.unwrap();
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(trade_state.state, TradeState::EscrowFunded);
+    assert_eq!(trade_state.asset.to_string(), trusted);
+    let res = release_trade(deps.as_mut(), mock_info("taker", &[])).unwrap();
+    let fee = localterra_fee(trade_amount, 100).unwrap();
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "offer-owner".to_string(),
+            amount: vec![Coin::new((trade_amount - fee).u128(), trusted)],
+        }))
+    );
+}
+
+#[test]
+fn test_cw20_refund() {
+    let trade_amount = Uint128::new(100_000_000u128);
+    let mut deps = create_cw20_trade(trade_amount);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("wbtc-token", &[]),
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "taker".to_string(),
+            amount: trade_amount,
+            msg: to_binary(&Cw20HookMsg::FundEscrow {}).unwrap(),
+        }),
+    )
+    .unwrap();
+    deps.querier.with_token_balances(&[(
+        &"wbtc-token".to_string(),
+        &[(&MOCK_CONTRACT_ADDR.to_string(), &trade_amount)],
+    )]);
+
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    let mut expired_env = mock_env();
+    expired_env.block.time = trade_state.expires_at.plus_seconds(1);
+
+    let res = execute(
+        deps.as_mut(),
+        expired_env,
+        mock_info("any", &[]),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            cw20_transfer("taker", trade_amount),
+            trade_closed(TradeState::Refunded)
+        ]
+    );
+}
+
+#[test]
+fn test_refund_after_expiration() {
+    let trade_amount = Uint128::new(500_000_000u128);
+    let info = mock_info_with_ust("taker", trade_amount);
+    let (_, mut deps) = create_trade(trade_amount, info.clone(), None);
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(
+        trade_state.expires_at,
+        mock_env().block.time.plus_seconds(3600)
+    );
+
+    let mut before_expiry = mock_env();
+    before_expiry.block.time = Timestamp::from_seconds(trade_state.expires_at.seconds() - 1);
+    let mut after_expiry = mock_env();
+    after_expiry.block.time = trade_state.expires_at.plus_seconds(1);
+
+    //Refunds are refused just before the deadline and accepted just after it
+    let res = execute(
+        deps.as_mut(),
+        before_expiry,
+        mock_info("any", &[]),
+        ExecuteMsg::Refund {},
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::RefundError { .. }));
+    let res = execute(
+        deps.as_mut(),
+        after_expiry.clone(),
+        mock_info("any", &[]),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "taker".to_string(),
+            amount: vec![Coin::new(trade_amount.u128(), "uusd")],
+        }))
+    );
+
+    //Refunding twice fails
+    let res = execute(
+        deps.as_mut(),
+        after_expiry,
+        mock_info("any", &[]),
+        ExecuteMsg::Refund {},
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::RefundError { .. }));
+}
+
+fn env_at(time: Timestamp) -> Env {
+    let mut env = mock_env();
+    env.block.time = time;
+    env
+}
+
+fn second_before(time: Timestamp) -> Timestamp {
+    Timestamp::from_seconds(time.seconds() - 1)
+}
+
+#[test]
+fn test_trade_deadlines() {
+    let trade_amount = Uint128::new(500_000_000u128);
+    let seller = mock_info_with_ust("taker", trade_amount);
+    let funded_trade = || create_trade(trade_amount, seller.clone(), None).1;
+    let fiat_deposited_trade = || {
+        let mut deps = funded_trade();
+        let msg = ExecuteMsg::FiatDeposited {};
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("offer-owner", &[]),
+            msg,
+        )
+        .unwrap();
+        deps
+    };
+
+    let deps = funded_trade();
+    let trade: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    let created_at = mock_env().block.time;
+    assert_eq!(trade.created_at, created_at);
+    assert_eq!(trade.expires_at, created_at.plus_seconds(3600));
+    assert_eq!(
+        trade.dispute_window_ends_at,
+        created_at.plus_seconds(3600 + 86400)
+    );
+
+    //Anyone can refund from the expiration on
+    let mut deps = funded_trade();
+    let refund =
+        |deps: DepsMut, env: Env| execute(deps, env, mock_info("any", &[]), ExecuteMsg::Refund {});
+    let err = refund(deps.as_mut(), env_at(second_before(trade.expires_at))).unwrap_err();
+    assert!(matches!(err, TradeError::RefundError { .. }));
+    refund(deps.as_mut(), env_at(trade.expires_at)).unwrap();
+
+    //Disputes are accepted until the window ends
+    let mut deps = fiat_deposited_trade();
+    let err = execute(
+        deps.as_mut(),
+        env_at(trade.dispute_window_ends_at),
+        seller.clone(),
+        ExecuteMsg::Dispute {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, TradeError::DisputeWindowClosed { .. }));
+    execute(
+        deps.as_mut(),
+        env_at(second_before(trade.dispute_window_ends_at)),
+        seller.clone(),
+        ExecuteMsg::Dispute {},
+    )
+    .unwrap();
+
+    //Expiration only stops releases of trades without deposited fiat
+    let mut deps = funded_trade();
+    let err = execute(
+        deps.as_mut(),
+        env_at(trade.expires_at),
+        seller.clone(),
+        ExecuteMsg::Release {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, TradeError::Expired { .. }));
+    let mut deps = fiat_deposited_trade();
+    execute(
+        deps.as_mut(),
+        env_at(trade.dispute_window_ends_at),
+        seller,
+        ExecuteMsg::Release {},
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_fiat_deposited_protects_escrow() {
+    let trade_amount = Uint128::new(500_000_000u128);
+    let info = mock_info_with_ust("taker", trade_amount);
+    let (_, mut deps) = create_trade(trade_amount, info.clone(), None);
+
+    //Only the buyer can mark the fiat as deposited
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::FiatDeposited {},
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::Unauthorized { .. }));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("offer-owner", &[]),
+        ExecuteMsg::FiatDeposited {},
+    )
+    .unwrap();
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(trade_state.state, TradeState::FiatDeposited);
+
+    let mut after_expiry = mock_env();
+    after_expiry.block.time = trade_state.expires_at.plus_seconds(1);
+    let res = execute(
+        deps.as_mut(),
+        after_expiry,
+        mock_info("any", &[]),
+        ExecuteMsg::Refund {},
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::RefundError { .. }));
+}
+
+fn disputed_trade(trade_amount: Uint128) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let info = mock_info_with_ust("taker", trade_amount);
+    let (_, mut deps) = create_trade(trade_amount, info.clone(), None);
+
+    //Disputes are only possible once fiat is marked as deposited
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::Dispute {},
+    );
+    assert!(matches!(
+        res.unwrap_err(),
+        TradeError::InvalidStateChange { .. }
+    ));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("offer-owner", &[]),
+        ExecuteMsg::FiatDeposited {},
+    )
+    .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("any", &[]),
+        ExecuteMsg::Dispute {},
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::Unauthorized { .. }));
+    execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Dispute {}).unwrap();
+
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(trade_state.state, TradeState::Disputed);
+    assert_eq!(trade_state.arbitrator, Addr::unchecked("arbitrator"));
+    deps
+}
+
+fn trade_event_attrs(res: &Response) -> Vec<(String, String)> {
+    let events: Vec<_> = res.events.iter().filter(|e| e.ty == TRADE_EVENT).collect();
+    assert_eq!(events.len(), 1);
+    events[0]
+        .attributes
+        .iter()
+        .map(|attr| (attr.key.clone(), attr.value.clone()))
+        .collect()
+}
+
+fn expected_trade_event(
+    action: &str,
+    from_state: Option<&str>,
+    to_state: &str,
+    amount: Uint128,
+) -> Vec<(String, String)> {
+    let amount = amount.to_string();
+    let mut attrs = vec![
+        ("action", action),
+        ("trade_addr", MOCK_CONTRACT_ADDR),
+        ("offer_id", "1"),
+    ];
+    attrs.extend(from_state.map(|from_state| ("from_state", from_state)));
+    attrs.push(("to_state", to_state));
+    attrs.push(("amount", &amount));
+    attrs
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_trade_events() {
+    let trade_amount = Uint128::new(500_000_000u128);
+    let event = |action: &str, from_state: Option<&str>, to_state: &str| {
+        expected_trade_event(action, from_state, to_state, trade_amount)
+    };
+
+    //Creation carries the currency of the offer
+    let info = mock_info_with_ust("taker", trade_amount);
+    let (res, mut deps) = create_trade(trade_amount, info.clone(), None);
+    let mut created = event("create_trade", None, "EscrowFunded");
+    created.push(("fiat".to_string(), "COP".to_string()));
+    assert_eq!(trade_event_attrs(&res.unwrap()), created);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("offer-owner", &[]),
+        ExecuteMsg::FiatDeposited {},
+    )
+    .unwrap();
+    assert_eq!(
+        trade_event_attrs(&res),
+        event("fiat_deposited", Some("EscrowFunded"), "FiatDeposited")
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Dispute {}).unwrap();
+    assert_eq!(
+        trade_event_attrs(&res),
+        event("dispute", Some("FiatDeposited"), "Disputed")
+    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("arbitrator", &[]),
+        ExecuteMsg::Resolve {
+            winner: "offer-owner".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        trade_event_attrs(&res),
+        event("release", Some("Disputed"), "SettledForMaker")
+    );
+
+    //Funding and refunding an escrow
+    let mut info = mock_info_with_ust("taker", Uint128::zero());
+    let (res, mut deps) = create_trade(trade_amount, info.clone(), None);
+    let mut created = event("create_trade", None, "Created");
+    created.push(("fiat".to_string(), "COP".to_string()));
+    assert_eq!(trade_event_attrs(&res.unwrap()), created);
+    info.funds[0].amount = trade_amount;
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::FundEscrow {},
+    )
+    .unwrap();
+    assert_eq!(
+        trade_event_attrs(&res),
+        event("fund_escrow", Some("Created"), "EscrowFunded")
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Refund {}).unwrap();
+    assert_eq!(
+        trade_event_attrs(&res),
+        event("refund", Some("EscrowFunded"), "Refunded")
+    );
+}
+
+#[test]
+fn test_dispute_resolved_for_buyer() {
+    let mut deps = disputed_trade(Uint128::new(500_000_000u128));
+
+    //Everything but the arbitrator's resolution is locked
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("taker", &[]),
+        ExecuteMsg::Release {},
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::Disputed { .. }));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("arbitrator", &[]),
+        ExecuteMsg::Refund {},
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::Disputed { .. }));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("taker", &[]),
+        ExecuteMsg::Resolve {
+            winner: "taker".to_string(),
+        },
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::Unauthorized { .. }));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("arbitrator", &[]),
+        ExecuteMsg::Resolve {
+            winner: "any".to_string(),
+        },
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::InvalidWinner { .. }));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("arbitrator", &[]),
+        ExecuteMsg::Resolve {
+            winner: "offer-owner".to_string(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages.last().unwrap(),
+        &trade_closed(TradeState::SettledForMaker)
+    );
+
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(trade_state.state, TradeState::SettledForMaker);
+
+    //Settled trades can't be resolved again
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("arbitrator", &[]),
+        ExecuteMsg::Resolve {
+            winner: "taker".to_string(),
+        },
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::NotDisputed { .. }));
+}
+
+#[test]
+fn test_submit_evidence() {
+    let trade_amount = Uint128::new(500_000_000u128);
+    let submit = |deps: DepsMut, sender: &str, sha256: &str| {
+        let msg = ExecuteMsg::SubmitEvidence {
+            sha256: sha256.to_string(),
+        };
+        execute(deps, mock_env(), mock_info(sender, &[]), msg)
+    };
+    let receipt = "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08";
+
+    //Evidence is only taken while disputed
+    let info = mock_info_with_ust("taker", trade_amount);
+    let (_, mut deps) = create_trade(trade_amount, info, None);
+    let err = submit(deps.as_mut(), "taker", receipt).unwrap_err();
+    assert!(matches!(err, TradeError::NotDisputed { .. }));
+
+    let mut deps = disputed_trade(trade_amount);
+    let err = submit(deps.as_mut(), "arbitrator", receipt).unwrap_err();
+    assert!(matches!(err, TradeError::Unauthorized { .. }));
+    for sha256 in [&receipt[1..], "z".repeat(64).as_str()] {
+        let err = submit(deps.as_mut(), "taker", sha256).unwrap_err();
+        assert!(matches!(err, TradeError::InvalidEvidenceHash { .. }));
+    }
+
+    for _ in 0..MAX_EVIDENCE_PER_PARTY {
+        submit(deps.as_mut(), "taker", receipt).unwrap();
+    }
+    let err = submit(deps.as_mut(), "taker", receipt).unwrap_err();
+    assert!(matches!(err, TradeError::TooMuchEvidence { .. }));
+    submit(deps.as_mut(), "offer-owner", receipt).unwrap();
+
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(trade_state.evidence.len(), MAX_EVIDENCE_PER_PARTY + 1);
+    assert_eq!(
+        trade_state.evidence.last().unwrap(),
+        &Evidence {
+            submitter: Addr::unchecked("offer-owner"),
+            sha256: receipt.to_lowercase(),
+            submitted_at: mock_env().block.time,
+        }
+    );
+
+    //Nor after the dispute is resolved
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("arbitrator", &[]),
+        ExecuteMsg::Resolve {
+            winner: "offer-owner".to_string(),
+        },
+    )
+    .unwrap();
+    let err = submit(deps.as_mut(), "offer-owner", receipt).unwrap_err();
+    assert!(matches!(err, TradeError::NotDisputed { .. }));
+}
+
+#[test]
+fn test_commit_message() {
+    let trade_amount = Uint128::new(500_000_000u128);
+    let commit = |deps: DepsMut, sender: &str, sha256: &str| {
+        let msg = ExecuteMsg::CommitMessage {
+            sha256: sha256.to_string(),
+        };
+        execute(deps, mock_env(), mock_info(sender, &[]), msg)
+    };
+    let commitments = |deps: Deps| -> Vec<MessageCommitment> {
+        from_binary(&query(deps, mock_env(), QueryMsg::MessageCommitments {}).unwrap()).unwrap()
+    };
+    let message = "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08";
+
+    let (_, mut deps) = create_trade(trade_amount, mock_info("taker", &[]), None);
+    assert!(commitments(deps.as_ref()).is_empty());
+    let err = commit(deps.as_mut(), "arbitrator", message).unwrap_err();
+    assert!(matches!(err, TradeError::Unauthorized { .. }));
+    for sha256 in [&message[1..], "z".repeat(64).as_str()] {
+        let err = commit(deps.as_mut(), "taker", sha256).unwrap_err();
+        assert!(matches!(err, TradeError::InvalidMessageHash { .. }));
+    }
+
+    let res = commit(deps.as_mut(), "offer-owner", message).unwrap();
+    assert_eq!(res.attributes[0].value, "commit_message");
+    assert_eq!(
+        commitments(deps.as_ref()),
+        vec![MessageCommitment {
+            sender: Addr::unchecked("offer-owner"),
+            sha256: message.to_lowercase(),
+            committed_at: mock_env().block.time,
+        }]
+    );
+
+    //Both parties share the bound
+    for _ in 1..MAX_MESSAGE_COMMITMENTS {
+        commit(deps.as_mut(), "taker", message).unwrap();
+    }
+    let err = commit(deps.as_mut(), "offer-owner", message).unwrap_err();
+    assert!(matches!(
+        err,
+        TradeError::TooManyCommitments {
+            max: MAX_MESSAGE_COMMITMENTS
+        }
+    ));
+    assert_eq!(commitments(deps.as_ref()).len(), MAX_MESSAGE_COMMITMENTS);
+
+    //Disputed trades take commitments, final ones don't
+    let mut deps = disputed_trade(trade_amount);
+    commit(deps.as_mut(), "taker", message).unwrap();
+    let (_, mut deps) = create_trade(trade_amount, mock_info("taker", &[]), None);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("offer-owner", &[]),
+        ExecuteMsg::Cancel {},
+    )
+    .unwrap();
+    let err = commit(deps.as_mut(), "taker", message).unwrap_err();
+    assert!(matches!(
+        err,
+        TradeError::TradeClosed {
+            state: TradeState::Canceled
+        }
+    ));
+}
+
+#[test]
+fn test_multisig_release() {
+    let mut deps = disputed_trade(Uint128::new(500_000_000u128));
+    let approve = |deps: DepsMut, sender: &str, to: &str| {
+        let msg = ExecuteMsg::ApproveRelease { to: to.to_string() };
+        execute(deps, mock_env(), mock_info(sender, &[]), msg)
+    };
+    let err = approve(deps.as_mut(), "taker", "taker").unwrap_err();
+    assert!(matches!(err, TradeError::MultisigReleaseDisabled {}));
+
+    let mut trade = TradeModel::from_store(&deps.storage).unwrap();
+    trade.multisig_release = true;
+    TradeModel::store(&mut deps.storage, &trade).unwrap();
+
+    //The arbitrator alone can't settle the trade anymore
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("arbitrator", &[]),
+        ExecuteMsg::Resolve {
+            winner: "offer-owner".to_string(),
+        },
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::ApprovalsRequired {}));
+    let err = approve(deps.as_mut(), "any", "taker").unwrap_err();
+    assert!(matches!(err, TradeError::Unauthorized { .. }));
+    let err = approve(deps.as_mut(), "taker", "any").unwrap_err();
+    assert!(matches!(err, TradeError::InvalidWinner { .. }));
+
+    //Conflicting approvals release nothing
+    let res = approve(deps.as_mut(), "offer-owner", "offer-owner").unwrap();
+    assert!(res.messages.is_empty());
+    let res = approve(deps.as_mut(), "taker", "taker").unwrap();
+    assert!(res.messages.is_empty());
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(trade_state.state, TradeState::Disputed);
+    assert!(trade_state.approvals_conflict);
+    assert_eq!(trade_state.release_approvals.len(), 2);
+
+    //A second approval of the buyer releases to them
+    let res = approve(deps.as_mut(), "arbitrator", "offer-owner").unwrap();
+    assert_eq!(
+        res.messages.last().unwrap(),
+        &trade_closed(TradeState::SettledForMaker)
+    );
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(trade_state.state, TradeState::SettledForMaker);
+    assert!(!trade_state.approvals_conflict);
+
+    let err = approve(deps.as_mut(), "taker", "offer-owner").unwrap_err();
+    assert!(matches!(err, TradeError::NotDisputed { .. }));
+}
+
+#[test]
+fn test_dispute_resolved_for_seller() {
+    let trade_amount = Uint128::new(500_000_000u128);
+    let mut deps = disputed_trade(trade_amount);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("arbitrator", &[]),
+        ExecuteMsg::Resolve {
+            winner: "taker".to_string(),
+        },
+    )
+    .unwrap();
+    let fee_amount = trade_amount.multiply_ratio(1u128, 10u128);
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "taker".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: trade_amount - fee_amount,
+                }],
+            })),
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "arbitrator".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: fee_amount,
+                }],
+            })),
+            trade_closed(TradeState::SettledForTaker),
+        ]
+    );
+
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(trade_state.state, TradeState::SettledForTaker);
+}
+
+#[test]
+fn test_seller_refunds_before_fiat_deposited() {
+    let trade_amount = Uint128::new(500_000_000u128);
+    let info = mock_info_with_ust("taker", trade_amount);
+    let (_, mut deps) = create_trade(trade_amount, info.clone(), None);
+
+    //The buyer can't cancel in the seller's place
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("offer-owner", &[]),
+        ExecuteMsg::Refund {},
+    );
+    assert!(matches!(res.unwrap_err(), TradeError::RefundError { .. }));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::Refund {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "taker".to_string(),
+                amount: vec![Coin::new(trade_amount.u128(), "uusd")],
+            })),
+            trade_closed(TradeState::Refunded),
+        ]
+    );
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(trade_state.state, TradeState::Refunded);
+
+    //Once fiat is marked as deposited the seller can't take the escrow back
+    let info = mock_info_with_ust("taker", trade_amount);
+    let (_, mut deps) = create_trade(trade_amount, info.clone(), None);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("offer-owner", &[]),
+        ExecuteMsg::FiatDeposited {},
+    )
+    .unwrap();
+    let res = execute(deps.as_mut(), mock_env(), info, ExecuteMsg::Refund {});
+    assert!(matches!(res.unwrap_err(), TradeError::RefundError { .. }));
+}
+
+#[test]
+fn test_cancel() {
+    let trade_amount = Uint128::new(500_000_000u128);
+    let cancel = |deps: DepsMut, sender: &str| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info(sender, &[]),
+            ExecuteMsg::Cancel {},
+        )
+    };
+
+    //Funded escrows are refunded, not canceled
+    let (_, mut deps) = create_trade(
+        trade_amount,
+        mock_info_with_ust("taker", trade_amount),
+        None,
+    );
+    let err = cancel(deps.as_mut(), "offer-owner").unwrap_err();
+    assert!(matches!(
+        err,
+        TradeError::CancelFunded {
+            state: TradeState::EscrowFunded
+        }
+    ));
+
+    let (_, mut deps) = create_trade(trade_amount, mock_info("taker", &[]), None);
+    let err = cancel(deps.as_mut(), "any").unwrap_err();
+    assert!(matches!(err, TradeError::Unauthorized { .. }));
+    let res = cancel(deps.as_mut(), "offer-owner").unwrap();
+    assert_eq!(res.messages, vec![trade_closed(TradeState::Canceled)]);
+    let trade_state: TradeData =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::State {}).unwrap()).unwrap();
+    assert_eq!(trade_state.state, TradeState::Canceled);
+
+    //Canceled trades can't be funded or canceled again
+    let err = cancel(deps.as_mut(), "taker").unwrap_err();
+    assert!(matches!(err, TradeError::InvalidStateChange { .. }));
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info_with_ust("taker", trade_amount),
+        ExecuteMsg::FundEscrow {},
+    );
+    assert!(res.is_err());
+}
+
+fn transition(model: &mut TradeModel, to: &TradeState) -> Result<TradeState, TradeError> {
+    match to {
+        TradeState::EscrowFunded => model.fund(),
+        TradeState::FiatDeposited => model.mark_fiat_deposited(),
+        TradeState::Released => model.release(),
+        TradeState::Refunded => model.refund(),
+        TradeState::Disputed => model.dispute(),
+        TradeState::Canceled => model.cancel(),
+        _ => model.settle(to.clone()),
+    }
+    .map(|trade| trade.state.clone())
+}
+
+#[test]
+fn test_trade_model_transitions() {
+    let (_, mut deps) = create_trade(Uint128::new(10_000_000u128), mock_info("taker", &[]), None);
+    let mut trade = TradeModel::from_store(&deps.storage).unwrap();
+
+    let states = vec![
+        TradeState::Canceled,
+        TradeState::Refunded,
+        TradeState::Released,
+        TradeState::Created,
+        TradeState::EscrowFunded,
+        TradeState::FiatDeposited,
+        TradeState::Disputed,
+        TradeState::SettledForMaker,
+        TradeState::SettledForTaker,
+    ];
+    let allowed = vec![
+        (TradeState::Created, TradeState::EscrowFunded),
+        (TradeState::Created, TradeState::Canceled),
+        (TradeState::EscrowFunded, TradeState::FiatDeposited),
+        (TradeState::EscrowFunded, TradeState::Released),
+        (TradeState::EscrowFunded, TradeState::Refunded),
+        (TradeState::FiatDeposited, TradeState::Released),
+        (TradeState::FiatDeposited, TradeState::Disputed),
+        (TradeState::Disputed, TradeState::SettledForMaker),
+        (TradeState::Disputed, TradeState::SettledForTaker),
+    ];
+
+    for from in states.iter() {
+        for to in states.iter().filter(|s| **s != TradeState::Created) {
+            trade.state = from.clone();
+            TradeModel::store(&mut deps.storage, &trade).unwrap();
+            let mut model = TradeModel::may_load(&mut deps.storage).unwrap();
+            let res = transition(&mut model, to);
+
+            if allowed.contains(&(from.clone(), to.clone())) {
+                assert_eq!(res.unwrap(), to.clone());
+                assert_eq!(
+                    TradeModel::from_store(&deps.storage).unwrap().state,
+                    to.clone()
+                );
+                continue;
+            }
+            //Funding and releasing twice have their own errors
+            match (from, res.unwrap_err()) {
+                (TradeState::EscrowFunded, TradeError::AlreadyFundedError {})
+                    if *to == TradeState::EscrowFunded => {}
+                (TradeState::Released, TradeError::AlreadyReleased {})
+                    if *to == TradeState::Released => {}
+                (_, TradeError::InvalidStateChange { from: f, to: t })
+                    if f == *from && t == *to => {}
+                (_, err) => panic!("{} -> {}: unexpected {:?}", from, to, err),
+            }
+            //Refused transitions leave the stored state untouched
+            assert_eq!(
+                TradeModel::from_store(&deps.storage).unwrap().state,
+                from.clone()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_state_change_guard() {
+    //Adding a state breaks this match, revisit the table below when it does
+    let next = |state: &TradeState| -> Vec<TradeState> {
+        match state {
+            TradeState::Created => vec![TradeState::EscrowFunded, TradeState::Canceled],
+            TradeState::EscrowFunded => vec![
+                TradeState::FiatDeposited,
+                TradeState::Released,
+                TradeState::Refunded,
+            ],
+            TradeState::FiatDeposited => vec![TradeState::Released, TradeState::Disputed],
+            TradeState::Disputed => vec![TradeState::SettledForMaker, TradeState::SettledForTaker],
+            TradeState::Canceled
+            | TradeState::Refunded
+            | TradeState::Released
+            | TradeState::SettledForMaker
+            | TradeState::SettledForTaker => vec![],
+        }
+    };
+    let states = vec![
+        TradeState::Canceled,
+        TradeState::Refunded,
+        TradeState::Released,
+        TradeState::Created,
+        TradeState::EscrowFunded,
+        TradeState::FiatDeposited,
+        TradeState::Disputed,
+        TradeState::SettledForMaker,
+        TradeState::SettledForTaker,
+    ];
+
+    for from in states.iter() {
+        assert_eq!(from.is_final(), next(from).is_empty(), "{}", from);
+        for to in states.iter() {
+            let res = assert_trade_state_change(from, to);
+            if next(from).contains(to) {
+                assert!(res.is_ok(), "{} -> {} should be allowed", from, to);
+            } else {
+                assert!(
+                    matches!(res.unwrap_err(), TradeError::InvalidStateChange { from: f, to: t } if f == *from && t == *to),
+                    "{} -> {} should be rejected",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_caller_role_guard() {
+    let (_, deps) = create_trade(Uint128::new(10_000_000u128), mock_info("taker", &[]), None);
+    let trade = TradeModel::from_store(&deps.storage).unwrap();
+    let parties = [TradeRole::Seller, TradeRole::Buyer];
+
+    assert_eq!(
+        assert_caller_role(&Addr::unchecked("taker"), &trade, &parties).unwrap(),
+        TradeRole::Seller
+    );
+    assert_eq!(
+        assert_caller_role(&Addr::unchecked("offer-owner"), &trade, &parties).unwrap(),
+        TradeRole::Buyer
+    );
+    let err = assert_caller_role(&Addr::unchecked("arbitrator"), &trade, &parties).unwrap_err();
+    assert!(matches!(
+        err,
+        TradeError::Unauthorized { caller, allowed }
+            if caller == Addr::unchecked("arbitrator") && allowed == parties.to_vec()
+    ));
+    assert_eq!(
+        assert_caller_role(
+            &Addr::unchecked("arbitrator"),
+            &trade,
+            &[TradeRole::Arbitrator]
+        )
+        .unwrap(),
+        TradeRole::Arbitrator
+    );
+}
+
+fn bank_sends(res: &Response) -> Vec<(String, Uint128)> {
+    res.messages
+        .iter()
+        .filter_map(|sub_msg| match &sub_msg.msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                Some((to_address.clone(), amount[0].amount))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_release_fee() {
+    let offer = create_offer_struct(Uint128::new(1), Uint128::new(1_000), None, None);
+    let release = |trade_amount: Uint128, fee_bps: Option<u64>| {
+        let info = mock_info_with_ust("taker", trade_amount);
+        let (_, mut deps) = create_trade(trade_amount, info.clone(), Some(offer.clone()));
+        if let Some(fee_bps) = fee_bps {
+            deps.querier.with_factory_fee(fee_bps, 0, 0);
+        }
+        release_trade(deps.as_mut(), info).unwrap()
+    };
+
+    //1% of 150 rounds down, the buyer and the collector share exactly the escrow
+    let res = release(Uint128::new(150), None);
+    assert_eq!(
+        bank_sends(&res),
+        vec![
+            ("treasury".to_string(), Uint128::new(1)),
+            ("offer-owner".to_string(), Uint128::new(149)),
+        ]
+    );
+
+    //A single micro unit has no fee to take and no dust is sent
+    let res = release(Uint128::new(1), None);
+    assert_eq!(
+        bank_sends(&res),
+        vec![("offer-owner".to_string(), Uint128::new(1))]
+    );
+
+    //Without a fee the buyer gets the whole escrow in one send
+    let res = release(Uint128::new(1_000), Some(0));
+    assert_eq!(
+        bank_sends(&res),
+        vec![("offer-owner".to_string(), Uint128::new(1_000))]
+    );
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "fee_amount" && attr.value == "0"));
+}
+
+fn bank_burns(res: &Response) -> Vec<Uint128> {
+    res.messages
+        .iter()
+        .filter_map(|sub_msg| match &sub_msg.msg {
+            CosmosMsg::Bank(BankMsg::Burn { amount }) => Some(amount[0].amount),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_factory_fee_applies_at_release() {
+    let trade_amount = Uint128::new(1_000);
+
+    //Buy offers pay the factory fee as it is when the trade is released
+    let seller = mock_info_with_ust("taker", trade_amount);
+    let (_, mut deps) = create_trade(trade_amount, seller.clone(), Some(small_buy_offer()));
+    deps.querier.with_factory_fee(200, 0, 5_000);
+    let res = release_trade(deps.as_mut(), seller).unwrap();
+    assert_eq!(bank_burns(&res), vec![Uint128::new(10)]);
+    assert_eq!(
+        bank_sends(&res),
+        vec![
+            ("treasury".to_string(), Uint128::new(10)),
+            ("offer-owner".to_string(), Uint128::new(980)),
+        ]
+    );
+
+    //Sell offers pay the fee escrowed when they were funded
+    let offer = create_offer_struct(
+        Uint128::new(1),
+        Uint128::new(1_000_000),
+        Some(OfferType::Sell),
+        None,
+    );
+    let (_, mut deps) = create_trade(trade_amount, mock_info("taker", &[]), Some(offer));
+    let seller = mock_info_with_ust("offer-owner", Uint128::new(1_010));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        seller.clone(),
+        ExecuteMsg::FundEscrow {},
+    )
+    .unwrap();
+    deps.querier.with_factory_fee(200, 0, 0);
+    let res = release_trade(deps.as_mut(), seller).unwrap();
+    assert_eq!(
+        bank_sends(&res),
+        vec![
+            ("treasury".to_string(), Uint128::new(10)),
+            ("taker".to_string(), trade_amount),
+        ]
+    );
+}
+
+#[test]
+fn test_escrow_and_release_tax() {
+    let trade_amount = Uint128::new(10_000);
+    let with_tax = |querier: &mut WasmMockQuerier| {
+        querier.with_factory_fee(100, 0, 0);
+        querier.with_tax(
+            Decimal::percent(1),
+            &[(&"uusd".to_string(), &Uint128::new(1_000_000))],
+        );
+    };
+
+    //Buy offers pay the tax on the fee and on the release out of the escrow
+    let seller = mock_info_with_ust("taker", trade_amount);
+    let (_, mut deps) = create_trade(trade_amount, seller.clone(), Some(small_buy_offer()));
+    with_tax(&mut deps.querier);
+    let res = release_trade(deps.as_mut(), seller).unwrap();
+    assert_eq!(
+        bank_sends(&res),
+        vec![
+            ("treasury".to_string(), Uint128::new(100)),
+            ("offer-owner".to_string(), Uint128::new(9_800)),
+        ]
+    );
+
+    //Sell offers escrow the maker fee and the tax on releasing it and the trade amount
+    let offer = create_offer_struct(
+        Uint128::new(1),
+        Uint128::new(1_000_000),
+        Some(OfferType::Sell),
+        None,
+    );
+    let (_, mut deps) = create_trade(trade_amount, mock_info("taker", &[]), Some(offer));
+    with_tax(&mut deps.querier);
+    let fund = |deps: DepsMut, amount: u128| {
+        let seller = mock_info_with_ust("offer-owner", Uint128::new(amount));
+        execute(deps, mock_env(), seller, ExecuteMsg::FundEscrow {})
+    };
+    let err = fund(deps.as_mut(), 10_200).unwrap_err();
+    assert!(matches!(
+        err,
+        TradeError::FundEscrowError { required_amount, .. } if required_amount.u128() == 10_201
+    ));
+    fund(deps.as_mut(), 10_201).unwrap();
+    let res = release_trade(deps.as_mut(), mock_info("offer-owner", &[])).unwrap();
+    assert_eq!(
+        bank_sends(&res),
+        vec![
+            ("treasury".to_string(), Uint128::new(100)),
+            ("taker".to_string(), trade_amount),
+        ]
+    );
+}
+
+#[test]
+fn test_treasury_read_at_release() {
+    let trade_amount = Uint128::new(1_000);
+    let seller = mock_info_with_ust("taker", trade_amount);
+
+    //Treasury rotations apply to trades in progress
+    let (_, mut deps) = create_trade(trade_amount, seller.clone(), Some(small_buy_offer()));
+    deps.querier.with_treasury("treasury-b");
+    let res = release_trade(deps.as_mut(), seller).unwrap();
+    assert_eq!(
+        bank_sends(&res)[0],
+        ("treasury-b".to_string(), Uint128::new(10))
+    );
+}
+
+#[test]
+fn test_fee_burn_split() {
+    let trade_amount = Uint128::new(1_000);
+    let release = |fee_burn_bps: u64| {
+        let seller = mock_info_with_ust("taker", trade_amount);
+        let (_, mut deps) = create_trade(trade_amount, seller.clone(), Some(small_buy_offer()));
+        deps.querier.with_factory_fee(100, 0, fee_burn_bps);
+        release_trade(deps.as_mut(), seller).unwrap()
+    };
+    let attr = |res: &Response, key: &str| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .map(|attr| attr.value.clone())
+            .unwrap()
+    };
+
+    //The burn is rounded down, the dust goes to the collector
+    let res = release(3_333);
+    assert_eq!(bank_burns(&res), vec![Uint128::new(3)]);
+    assert_eq!(
+        bank_sends(&res)[0],
+        ("treasury".to_string(), Uint128::new(7))
+    );
+    assert_eq!(attr(&res, "burn_amount"), "3");
+    assert_eq!(attr(&res, "collected_amount"), "7");
+
+    //Burning all or none of the fee takes a single message
+    let res = release(10_000);
+    assert_eq!(bank_burns(&res), vec![Uint128::new(10)]);
+    assert_eq!(
+        bank_sends(&res),
+        vec![("offer-owner".to_string(), Uint128::new(990))]
+    );
+    let res = release(0);
+    assert!(bank_burns(&res).is_empty());
+    assert_eq!(
+        bank_sends(&res)[0],
+        ("treasury".to_string(), Uint128::new(10))
+    );
+}
+
+#[test]
+fn test_maker_taker_fees() {
+    let trade_amount = Uint128::new(10_000);
+
+    //Makers of buy offers receive the escrow and pay both fees out of it
+    let seller = mock_info_with_ust("taker", trade_amount);
+    let (_, mut deps) = create_trade(trade_amount, seller.clone(), Some(small_buy_offer()));
+    deps.querier.with_factory_fee(0, 60, 0);
+    let res = release_trade(deps.as_mut(), seller).unwrap();
+    assert_eq!(
+        bank_sends(&res),
+        vec![
+            ("treasury".to_string(), Uint128::new(60)),
+            ("offer-owner".to_string(), Uint128::new(9_940)),
+        ]
+    );
+    assert!(res
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "taker_fee_amount" && attr.value == "60"));
+
+    //Makers of sell offers escrow their fee, the buyer pays the taker fee
+    let offer = create_offer_struct(
+        Uint128::new(1),
+        Uint128::new(1_000_000),
+        Some(OfferType::Sell),
+        None,
+    );
+    let (_, mut deps) = create_trade(trade_amount, mock_info("taker", &[]), Some(offer));
+    deps.querier.with_factory_fee(40, 60, 0);
+    let seller = mock_info_with_ust("offer-owner", Uint128::new(10_040));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        seller.clone(),
+        ExecuteMsg::FundEscrow {},
+    )
+    .unwrap();
+    let res = release_trade(deps.as_mut(), seller).unwrap();
+    assert_eq!(
+        bank_sends(&res),
+        vec![
+            ("treasury".to_string(), Uint128::new(100)),
+            ("taker".to_string(), Uint128::new(9_940)),
+        ]
+    );
+}
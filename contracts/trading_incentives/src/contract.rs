@@ -2,8 +2,8 @@ use crate::errors::TradingIncentivesError;
 use crate::math::DECIMAL_FRACTIONAL;
 use crate::state::{CONFIG, TOTAL_VOLUME, TRADER_VOLUME};
 use cosmwasm_std::{
-    entry_point, to_binary, Binary, CosmosMsg, Decimal, Deps,  StdError, StdResult,
-    Storage, SubMsg, WasmMsg, 
+    entry_point, to_binary, Binary, CosmosMsg, Decimal, Deps, StdError, StdResult, Storage, SubMsg,
+    WasmMsg,
 };
 use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, Uint128};
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
@@ -14,7 +14,7 @@ use localterra_protocol::trading_incentives::{
     Config, Distribution, ExecuteMsg, InstantiateMsg, QueryMsg,
 };
 use std::cmp;
-use std::ops::{ Mul};
+use std::ops::Mul;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -77,7 +77,7 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
 
 fn get_distribution_info(env: Env, storage: &dyn Storage) -> StdResult<Distribution> {
     let cfg = CONFIG.load(storage).unwrap();
-    let block_time = env.block.time.clone();
+    let block_time = env.block.time;
     let period = (block_time.seconds() - cfg.distribution_start) / cfg.distribution_period_duration;
     let period = cmp::min(period as u8, cfg.distribution_periods);
 
@@ -137,11 +137,11 @@ fn register_trade(
         }))
         .unwrap();
 
-    if trade_info.trade.state != TradeTradeState::Closed {
+    if trade_info.trade.state != TradeTradeState::Released {
         return Err(TradingIncentivesError::Unauthorized {});
     }
 
-    let ust_amount = trade_info.trade.ust_amount.clone();
+    let ust_amount = trade_info.trade.amount.clone();
     let distribution_info = get_distribution_info(env.clone(), deps.storage).unwrap();
     let period = distribution_info.current_period;
 
@@ -180,7 +180,7 @@ fn claim(
 ) -> Result<Response, TradingIncentivesError> {
     let cfg = CONFIG.load(deps.storage).unwrap();
     let distribution_info = get_distribution_info(env.clone(), deps.storage).unwrap();
-    let current_period = distribution_info.current_period.clone();
+    let current_period = distribution_info.current_period;
 
     if cfg.distribution_start.eq(&0u64) {
         return Err(TradingIncentivesError::Std(StdError::generic_err(
@@ -72,6 +72,7 @@ fn distribution_happy_path() {
 
 //TODO
 // #[test]
+#[allow(dead_code)]
 fn test_claim() {
     let mut deps = mock_dependencies(&[], None);
     let mut env = mock_env();
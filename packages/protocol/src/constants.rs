@@ -1 +1,54 @@
+/// Stable denom offers and trades used before the denom became configurable.
+pub const DEFAULT_DENOM: &str = "uusd";
 pub const OFFERS_KEY: &str = "offers";
+/// Raw key of the offer contract's `State` singleton.
+pub const OFFER_STATE_KEY: &[u8] = b"state";
+pub const OFFER_COUNTS_KEY: &str = "offer_counts";
+pub const OFFER_FIATS_KEY: &str = "offer_fiats";
+pub const OFFER_SUMMARIES_KEY: &str = "offer_summaries";
+pub const OWNER_OFFER_COUNTS_KEY: &str = "owner_offer_counts";
+pub const STORED_OFFERS_KEY: &str = "stored_offers";
+/// Raw key of the `TradeData` singleton, kept stable for clients reading the
+/// trade contract storage directly (length prefixed, as `cosmwasm_storage`
+/// singletons are). `QueryMsg::State` is the supported way to read it.
+pub const TRADE_STATE_KEY: &[u8] = b"state";
+/// Raw key of the trade's `MessageCommitment` list singleton.
+pub const TRADE_COMMITMENTS_KEY: &[u8] = b"message_commitments";
+pub const NEW_TRADE_REPLY_ID: u64 = 0;
+
+pub const MAX_PAYMENT_METHODS: usize = 5;
+pub const MAX_PAYMENT_METHOD_LEN: usize = 32;
+pub const MAX_DESCRIPTION_LEN: usize = 280;
+pub const MAX_MAKER_CONTACT_LEN: usize = 1024;
+/// Highest margin of floating price offers, +100% of the oracle rate.
+pub const MAX_PRICE_MARGIN_BPS: i32 = 10_000;
+pub const MAX_BATCH_SIZE: usize = 20;
+/// Offers `PauseAll` and `ActivateAll` change per call.
+pub const MAX_BULK_STATE_CHANGES: usize = 50;
+/// Page size of paginated queries called with a `limit` of `0`.
+pub const DEFAULT_PAGE_LIMIT: u32 = 10;
+/// Larger `limit`s of paginated queries are lowered to this.
+pub const MAX_PAGE_LIMIT: u32 = 30;
+/// Random ids `OffersSample` probes per requested offer before giving up.
+pub const SAMPLE_PROBES_PER_OFFER: usize = 4;
+/// Entries filtered pages visit per requested offer before coming back short.
+pub const SCANNED_ENTRIES_PER_OFFER: usize = 4;
+/// Most recent offers listed by a `Profile` query.
+pub const PROFILE_OFFERS_PREVIEW: usize = 10;
+/// Bank denoms of IBC vouchers start with this, followed by the hash of the
+/// voucher's trace.
+pub const IBC_DENOM_PREFIX: &str = "ibc/";
+/// Evidence hashes each party of a disputed trade can submit.
+pub const MAX_EVIDENCE_PER_PARTY: usize = 5;
+/// Message hashes both parties of a trade can commit in total.
+pub const MAX_MESSAGE_COMMITMENTS: usize = 50;
+/// Seconds a trade stays open before its escrow can be refunded.
+pub const DEFAULT_TRADE_DURATION: u64 = 3600;
+/// Seconds past the expiration of a trade its parties can still dispute it.
+pub const DEFAULT_DISPUTE_WINDOW: u64 = 86400;
+/// Seconds of the windows `Config::new_trade_limit` counts new trades in.
+pub const DEFAULT_NEW_TRADE_WINDOW: u64 = 3600;
+/// Seconds an oracle rate can be old and still price new trades.
+pub const DEFAULT_MAX_PRICE_AGE: u64 = 3600;
+/// Protocol fee taken on release, the 1% charged before it was configurable.
+pub const DEFAULT_FEE_BPS: u64 = 100;
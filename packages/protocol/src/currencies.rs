@@ -1,169 +1,197 @@
+use crate::errors::OfferError;
+use cosmwasm_std::{Decimal, Uint128};
 use schemars::JsonSchema;
+use serde::de::{self, Deserializer};
 use serde::{Deserialize, Serialize};
 use std::fmt::{self};
+use std::str::FromStr;
 
+/// Codes `FiatCurrency` accepted while it was an enum, the allowlist new
+/// configs start from.
+pub const LEGACY_FIAT_CURRENCIES: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BRL", "BSD", "BTN", "BWP", "BYR", "BZD", "CAD",
+    "CDF", "CHF", "CLP", "CNY", "COP", "COU", "CRC", "CUC", "CUP", "CVE", "CZK", "DJF", "DKK",
+    "DOP", "DZD", "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP", "GEL", "GHS", "GIP", "GMD",
+    "GNF", "GTQ", "GYD", "HKD", "HNL", "HRK", "HTG", "HUF", "IDR", "ILS", "INR", "IQD", "IRR",
+    "ISK", "JMD", "JOD", "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW", "KWD", "KYD", "KZT",
+    "LAK", "LBP", "LKR", "LRD", "LSL", "LTL", "LVL", "LYD", "MAD", "MDL", "MGA", "MKD", "MMK",
+    "MNT", "MOP", "MRO", "MUR", "MVR", "MWK", "MXN", "MYR", "MZN", "NAD", "NGN", "NIO", "NOK",
+    "NPR", "NZD", "OMR", "PAB", "PEN", "PGK", "PHP", "PKR", "PLN", "PYG", "QAR", "RON", "RSD",
+    "RUB", "RWF", "SAR", "SBD", "SCR", "SDG", "SEK", "SGD", "SHP", "SLL", "SOS", "SRD", "SSP",
+    "STD", "SYP", "SZL", "THB", "TJS", "TMT", "TND", "TOP", "TRY", "TTD", "TWD", "TZS", "UAH",
+    "UGX", "USD", "UYU", "UZS", "VES", "VND", "VUV", "WST", "XCD", "YER", "ZAR", "ZMW",
+];
+
+/// Names accepted in place of a code, matched after uppercasing. Ambiguous
+/// names like "peso" or "dollar" are left out on purpose.
+pub const FIAT_CURRENCY_ALIASES: &[(&str, &str)] = &[
+    ("USDOLLAR", "USD"),
+    ("EURO", "EUR"),
+    ("STERLING", "GBP"),
+    ("YEN", "JPY"),
+    ("REAL", "BRL"),
+    ("NAIRA", "NGN"),
+    ("BOLIVAR", "VES"),
+    ("VEF", "VES"),
+    ("RUPIAH", "IDR"),
+];
+
+/// ISO-4217 codes without minor units.
+const ZERO_DECIMALS: &[&str] = &[
+    "BIF", "CLP", "DJF", "GNF", "ISK", "JPY", "KMF", "KRW", "PYG", "RWF", "UGX", "VND", "VUV",
+];
+
+/// ISO-4217 codes with thousandths as minor units.
+const THREE_DECIMALS: &[&str] = &["BHD", "IQD", "JOD", "KWD", "LYD", "OMR", "TND"];
+
+/// Fractional digits of `Decimal`.
+const DECIMAL_PLACES: u32 = 18;
+
+/// Three letter ISO-4217 code such as `BRL`. Serialized as the bare code,
+/// exactly like the enum it replaces, so stored offers keep deserializing.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, JsonSchema)]
+#[serde(transparent)]
+pub struct FiatCurrency(String);
+
+impl FiatCurrency {
+    /// Skips the format check, meant for constants and tests.
+    pub fn unchecked(code: &str) -> Self {
+        FiatCurrency(code.to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Digits of the minor unit, `2` for codes ISO-4217 doesn't list otherwise.
+    pub fn decimals(&self) -> u32 {
+        if ZERO_DECIMALS.contains(&self.as_str()) {
+            0
+        } else if THREE_DECIMALS.contains(&self.as_str()) {
+            3
+        } else {
+            2
+        }
+    }
+}
+
+/// Entry of the `SupportedCurrencies` query.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FiatCurrencyInfo {
+    pub fiat_currency: FiatCurrency,
+    pub decimals: u32,
+}
+
+impl From<FiatCurrency> for FiatCurrencyInfo {
+    fn from(fiat_currency: FiatCurrency) -> Self {
+        FiatCurrencyInfo {
+            decimals: fiat_currency.decimals(),
+            fiat_currency,
+        }
+    }
+}
+
+/// Converts `amount` of `fiat_currency` into minor units, e.g. `12.34` USD
+/// into `1234` cents. Nothing is rounded, amounts with more decimals than the
+/// currency has are rejected.
+pub fn to_minor_units(
+    amount: Decimal,
+    fiat_currency: &FiatCurrency,
+) -> Result<Uint128, OfferError> {
+    let atomics = (Uint128::new(10u128.pow(DECIMAL_PLACES)) * amount).u128();
+    let unit = 10u128.pow(DECIMAL_PLACES - fiat_currency.decimals());
+    if atomics.checked_rem(unit) != Some(0) {
+        return Err(OfferError::FiatPrecisionLoss {
+            amount,
+            fiat_currency: fiat_currency.clone(),
+        });
+    }
+    Ok(Uint128::new(atomics / unit))
+}
+
+/// Converts `amount` minor units of `fiat_currency` back into a `Decimal`,
+/// always exact but bounded by the range of `Decimal`.
+pub fn from_minor_units(
+    amount: Uint128,
+    fiat_currency: &FiatCurrency,
+) -> Result<Decimal, OfferError> {
+    let unit = 10u128.pow(DECIMAL_PLACES - fiat_currency.decimals());
+    amount
+        .u128()
+        .checked_mul(unit)
+        .ok_or_else(|| OfferError::FiatAmountOverflow {
+            amount,
+            fiat_currency: fiat_currency.clone(),
+        })?;
+    Ok(Decimal::from_ratio(
+        amount,
+        10u128.pow(fiat_currency.decimals()),
+    ))
+}
 
-pub enum FiatCurrency {
-    AED,
-    AFN,
-    ALL,
-    AMD,
-    ANG,
-    AOA,
-    ARS,
-    AUD,
-    AWG,
-    AZN,
-    BAM,
-    BBD,
-    BDT,
-    BGN,
-    BHD,
-    BIF,
-    BMD,
-    BND,
-    BOB,
-    BRL,
-    BSD,
-    BTN,
-    BWP,
-    BYR,
-    BZD,
-    CAD,
-    CDF,
-    CHF,
-    CLP,
-    CNY,
-    COP,
-    COU,
-    CRC,
-    CUC,
-    CUP,
-    CVE,
-    CZK,
-    DJF,
-    DKK,
-    DOP,
-    DZD,
-    EGP,
-    ERN,
-    ETB,
-    EUR,
-    FJD,
-    FKP,
-    GBP,
-    GEL,
-    GHS,
-    GIP,
-    GMD,
-    GNF,
-    GTQ,
-    GYD,
-    HKD,
-    HNL,
-    HRK,
-    HTG,
-    HUF,
-    IDR,
-    ILS,
-    INR,
-    IQD,
-    IRR,
-    ISK,
-    JMD,
-    JOD,
-    JPY,
-    KES,
-    KGS,
-    KHR,
-    KMF,
-    KPW,
-    KRW,
-    KWD,
-    KYD,
-    KZT,
-    LAK,
-    LBP,
-    LKR,
-    LRD,
-    LSL,
-    LTL,
-    LVL,
-    LYD,
-    MAD,
-    MDL,
-    MGA,
-    MKD,
-    MMK,
-    MNT,
-    MOP,
-    MRO,
-    MUR,
-    MVR,
-    MWK,
-    MXN,
-    MYR,
-    MZN,
-    NAD,
-    NGN,
-    NIO,
-    NOK,
-    NPR,
-    NZD,
-    OMR,
-    PAB,
-    PEN,
-    PGK,
-    PHP,
-    PKR,
-    PLN,
-    PYG,
-    QAR,
-    RON,
-    RSD,
-    RUB,
-    RWF,
-    SAR,
-    SBD,
-    SCR,
-    SDG,
-    SEK,
-    SGD,
-    SHP,
-    SLL,
-    SOS,
-    SRD,
-    SSP,
-    STD,
-    SYP,
-    SZL,
-    THB,
-    TJS,
-    TMT,
-    TND,
-    TOP,
-    TRY,
-    TTD,
-    TWD,
-    TZS,
-    UAH,
-    UGX,
-    USD,
-    UYU,
-    UZS,
-    VES,
-    VND,
-    VUV,
-    WST,
-    XCD,
-    YER,
-    ZAR,
-    ZMW,
+/// Units of an asset priced at `price` `fiat_currency` per unit that `amount`
+/// minor units buy. The asset has no fractions, so whatever is left worth
+/// less than one unit is dropped: 1099 cents at a price of 10 USD buy 1 unit.
+/// Amounts `from_minor_units` refuses, or buying less than one unit, are
+/// rejected.
+pub fn minor_units_to_denom(
+    amount: Uint128,
+    fiat_currency: &FiatCurrency,
+    price: Decimal,
+) -> Result<Uint128, OfferError> {
+    let fiat_amount = from_minor_units(amount, fiat_currency)?;
+    let fiat_atomics = Uint128::new(10u128.pow(DECIMAL_PLACES)) * fiat_amount;
+    let price_atomics = Uint128::new(10u128.pow(DECIMAL_PLACES)) * price;
+    let converted = fiat_atomics.checked_div(price_atomics).unwrap_or_default();
+    if converted.is_zero() {
+        return Err(OfferError::FiatAmountTooSmall {
+            amount,
+            fiat_currency: fiat_currency.clone(),
+        });
+    }
+    Ok(converted)
+}
+
+impl FromStr for FiatCurrency {
+    type Err = String;
+
+    /// Case and surrounding whitespace are ignored and aliases resolve to
+    /// their code, so `" cop "` and `"UsDollar"` parse as `COP` and `USD`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_uppercase();
+        let code = FIAT_CURRENCY_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == normalized)
+            .map_or(normalized.as_str(), |(_, code)| code);
+        if code.len() == 3 && code.bytes().all(|b| b.is_ascii_uppercase()) {
+            Ok(FiatCurrency(code.to_string()))
+        } else {
+            Err(format!(
+                "Invalid fiat currency `{}`, expected a three letter ISO-4217 code or an alias ({}). The `SupportedCurrencies` query lists the codes offers accept.",
+                s,
+                FIAT_CURRENCY_ALIASES
+                    .iter()
+                    .map(|(alias, _)| *alias)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FiatCurrency {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let currency = String::deserialize(deserializer)?;
+        FiatCurrency::from_str(&currency).map_err(de::Error::custom)
+    }
 }
 
 impl fmt::Display for FiatCurrency {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        f.write_str(&self.0)
     }
 }
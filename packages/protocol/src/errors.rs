@@ -1,5 +1,7 @@
+use crate::currencies::FiatCurrency;
 use crate::offer::OfferState;
-use cosmwasm_std::{Addr, StdError};
+use crate::trade::{TradeRole, TradeState};
+use cosmwasm_std::{Addr, Decimal, StdError, Timestamp, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -8,10 +10,232 @@ pub enum OfferError {
     Std(#[from] StdError),
     #[error("Invalid state change.")]
     InvalidStateChange { from: OfferState, to: OfferState },
-    #[error("Unauthorized.")]
-    Unauthorized { owner: Addr, caller: Addr },
+    #[error("Unauthorized, {caller} is not the {role}.")]
+    Unauthorized {
+        role: String,
+        expected: Addr,
+        caller: Addr,
+    },
     #[error("Governance not found.")]
     GovernanceNotFound { gov_addr: Addr },
     #[error("Invalid reply message id.")]
     InvalidReply {},
+    #[error("Cannot migrate from {contract} {version}.")]
+    InvalidMigration { contract: String, version: String },
+    #[error("Trade instantiation did not report a contract address.")]
+    MissingTradeAddr {},
+    #[error("No admin handover is pending.")]
+    NoPendingAdmin {},
+    #[error("Offer contract is paused.")]
+    ContractPaused {},
+    #[error("Offers in {fiat_currency} are not enabled, the configured fiat_currencies are {supported}.")]
+    UnsupportedFiatCurrency {
+        fiat_currency: FiatCurrency,
+        supported: String,
+    },
+    #[error("Address is not allowed to trade.")]
+    Blocked {},
+    #[error("Offer {id} not found.")]
+    NotFound { id: u64 },
+    #[error("Offer expiration must be in the future.")]
+    InvalidExpiration {
+        expires_at: Timestamp,
+        block_time: Timestamp,
+    },
+    #[error("{field} is not a valid address: {addr:?}.")]
+    InvalidAddress { field: String, addr: String },
+    #[error("{owner} can't take their own offer.")]
+    SelfTradeNotAllowed { owner: Addr },
+    #[error("Offer {id} is {state}, only active offers can be traded.")]
+    OfferNotActive { id: u64, state: OfferState },
+    #[error("Trade amount {amount} is outside of the offer range {min_amount} to {max_amount}.")]
+    TradeAmountOutOfRange {
+        amount: Uint128,
+        min_amount: Uint128,
+        max_amount: Uint128,
+    },
+    #[error("Amount {amount} exceeds the maximum trade amount of {max_trade_amount}.")]
+    MaxTradeAmountExceeded {
+        amount: Uint128,
+        max_trade_amount: Uint128,
+    },
+    #[error("Offer {id} has expired.")]
+    Expired { id: u64, expired_at: Timestamp },
+    #[error("Offer limit of {max} per owner reached.")]
+    OfferMaxReached { owner: Addr, max: u32 },
+    #[error("Min amount must be greater than 0 and at most max amount, got {min} and {max}.")]
+    InvalidAmountRange { min: Uint128, max: Uint128 },
+    #[error("Max amount {max} exceeds {max_ratio} times the min amount {min}.")]
+    AmountRatioExceeded {
+        min: Uint128,
+        max: Uint128,
+        max_ratio: u64,
+    },
+    #[error("Price margin must be greater than -10000 and at most 10000 basis points.")]
+    InvalidPriceMargin { price_margin_bps: i32 },
+    #[error("Price for {fiat_currency} is unavailable.")]
+    PriceUnavailable { fiat_currency: FiatCurrency },
+    #[error("{amount} {fiat_currency} is more precise than its minor unit.")]
+    FiatPrecisionLoss {
+        amount: Decimal,
+        fiat_currency: FiatCurrency,
+    },
+    #[error("{amount} minor units of {fiat_currency} are out of range.")]
+    FiatAmountOverflow {
+        amount: Uint128,
+        fiat_currency: FiatCurrency,
+    },
+    #[error("{amount} minor units of {fiat_currency} buy less than one unit of the asset.")]
+    FiatAmountTooSmall {
+        amount: Uint128,
+        fiat_currency: FiatCurrency,
+    },
+    #[error("Up to {max_count} payment methods of 1 to {max_len} characters are allowed.")]
+    InvalidPaymentMethods { max_count: usize, max_len: usize },
+    #[error("Description exceeds {max_len} bytes.")]
+    DescriptionTooLong { len: usize, max_len: usize },
+    #[error("Description contains control characters.")]
+    InvalidDescription {},
+    #[error("Maker contact exceeds {max_len} bytes.")]
+    MakerContactTooLong { len: usize, max_len: usize },
+    #[error("Denom {denom} is not supported.")]
+    UnsupportedDenom { denom: String },
+    #[error("{denom} is not a trusted IBC denom.")]
+    UntrustedIbcDenom { denom: String },
+    #[error("{contract_addr} is not a valid CW20 token.")]
+    InvalidCw20 { contract_addr: String },
+    #[error("Offer has {count} trades in progress.")]
+    HasActiveTrades { count: u64 },
+    #[error("Offer {offer_id} already has the maximum of {max} open trades.")]
+    TooManyOpenTrades { offer_id: u64, max: u8 },
+    #[error("Offer {offer_id} only has {available} left once its open trades are served.")]
+    InsufficientLiquidity { offer_id: u64, available: Uint128 },
+    #[error("Trade is not registered or already closed.")]
+    TradeNotOpen { trade: Addr },
+    #[error("Offer requires {required} completed trades, {taker} has {actual}.")]
+    NotEnoughCompletedTrades {
+        taker: Addr,
+        required: u64,
+        actual: u64,
+    },
+    #[error("Batches are limited to {max} entries.")]
+    BatchTooLarge { len: usize, max: usize },
+    #[error("Batch entry {index} failed: {error}")]
+    BatchEntryFailed {
+        index: usize,
+        error: Box<OfferError>,
+    },
+    #[error("Offer {id} is archived and can't be cloned.")]
+    CloneArchived { id: u64 },
+    #[error("{sender} opened {limit} trades in the current window, retry at {}.", .retry_at.seconds())]
+    TradeRateLimited {
+        sender: Addr,
+        limit: u32,
+        retry_at: Timestamp,
+    },
+    #[error("Offer {id} has trades and can't be deleted, archive it instead.")]
+    HasTradeHistory { id: u64 },
+    #[error("Offer schedule must pause after it activates.")]
+    InvalidSchedule {
+        activate_at: Timestamp,
+        pause_at: Timestamp,
+    },
+    #[error("Trading hours must be two different UTC hours from 0 to 23.")]
+    InvalidTradingHours {
+        available_from_hour: Option<u8>,
+        available_to_hour: Option<u8>,
+    },
+    #[error("Offer {id} takes trades from {from_hour}:00 to {to_hour}:00 UTC.")]
+    OutsideTradingHours { id: u64, from_hour: u8, to_hour: u8 },
+}
+
+#[derive(Error, Debug)]
+pub enum TradeError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+    #[error("Cannot migrate from {contract} {version}.")]
+    InvalidMigration { contract: String, version: String },
+    #[error("Amount is outside of offer amount range.")]
+    AmountError {
+        amount: Uint128,
+        min_amount: Uint128,
+        max_amount: Uint128,
+    },
+    #[error("Failed to execute contract.")]
+    ExecutionError { message: String },
+    #[error("This trade has expired.")]
+    Expired {
+        expires_at: Timestamp,
+        block_time: Timestamp,
+    },
+    #[error("Failed to instantiate contract.")]
+    InstantiationError { message: String },
+    #[error("Offer not found.")]
+    OfferNotFound { offer_id: u64 },
+    #[error("Offer is not active.")]
+    OfferNotActive { offer_id: u64, state: OfferState },
+    #[error(
+        "No arbitrator is registered for {fiat_currency} nor configured on the offer contract."
+    )]
+    NoArbitrator { fiat_currency: FiatCurrency },
+    #[error("Trades are only opened by the offer contract.")]
+    NotOfferContract { caller: Addr, offer_contract: Addr },
+    #[error("Refund error.")]
+    RefundError { message: String, trade: String },
+    #[error("Release error.")]
+    ReleaseError { message: String },
+    #[error("Swap error.")]
+    SwapError {
+        required_amount: Uint128,
+        returned_amount: Uint128,
+    },
+    #[error("Escrow requires {required_amount}, received {sent_amount}.")]
+    FundEscrowError {
+        required_amount: Uint128,
+        sent_amount: Uint128,
+    },
+    #[error("Invalid state change.")]
+    InvalidStateChange { from: TradeState, to: TradeState },
+    #[error("Escrow already released.")]
+    AlreadyReleased {},
+    #[error("Escrow already funded.")]
+    AlreadyFundedError {},
+    #[error("Escrow must be funded in {expected}.")]
+    InvalidDenom { expected: String, received: String },
+    #[error("{denom} is not a trusted IBC denom.")]
+    UntrustedIbcDenom { denom: String },
+    #[error("Cw20 sends must embed a trade Cw20HookMsg.")]
+    InvalidHookMsg {},
+    #[error("Unauthorized, {caller} is not one of {allowed:?}.")]
+    Unauthorized {
+        caller: Addr,
+        allowed: Vec<TradeRole>,
+    },
+    #[error("Escrow is funded, it can only be refunded.")]
+    CancelFunded { state: TradeState },
+    #[error("The dispute window of this trade is closed.")]
+    DisputeWindowClosed {
+        ends_at: Timestamp,
+        block_time: Timestamp,
+    },
+    #[error("Trade is disputed, only the arbitrator can resolve it.")]
+    Disputed { arbitrator: Addr },
+    #[error("Trade is not disputed.")]
+    NotDisputed { state: TradeState },
+    #[error("Winner must be the buyer or the seller of the trade.")]
+    InvalidWinner { winner: String },
+    #[error("Trade isn't settled by release approvals.")]
+    MultisigReleaseDisabled {},
+    #[error("Trade is settled by two matching release approvals, not by the arbitrator alone.")]
+    ApprovalsRequired {},
+    #[error("Evidence must be a sha256 digest of 64 hex characters.")]
+    InvalidEvidenceHash { sha256: String },
+    #[error("At most {max} evidence hashes per party.")]
+    TooMuchEvidence { max: usize },
+    #[error("Messages must be committed as a sha256 digest of 64 hex characters.")]
+    InvalidMessageHash { sha256: String },
+    #[error("At most {max} message commitments per trade.")]
+    TooManyCommitments { max: usize },
+    #[error("Trade is {state}, nothing can be committed to it anymore.")]
+    TradeClosed { state: TradeState },
 }
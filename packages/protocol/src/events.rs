@@ -0,0 +1,48 @@
+//! Custom events emitted on every offer and trade state change, the chain
+//! reports them prefixed with `wasm-`. Event types and attribute names are a
+//! stable interface for indexers: attributes may be added, existing ones are
+//! never renamed or dropped.
+//!
+//! `p2p-offer`, from the offer contract:
+//! * `action`, `offer_id`, `owner`, `fiat`, `to_state`;
+//! * `from_state`, except when the offer is created. `delete_offer` reports
+//!   the state the offer was deleted in as both states;
+//! * `trade_addr` and `amount` when a trade opens or closes on the offer.
+//!
+//! `p2p-trade`, from the trade contract:
+//! * `action`, `trade_addr`, `offer_id`, `to_state`, `amount`;
+//! * `from_state`, except when the trade is created;
+//! * `fiat` when the trade is created.
+use cosmwasm_std::Event;
+
+use crate::offer::{Offer, OfferState};
+use crate::trade::{TradeData, TradeState};
+
+pub const OFFER_EVENT: &str = "p2p-offer";
+pub const TRADE_EVENT: &str = "p2p-trade";
+
+pub fn offer_event(action: &str, offer: &Offer, from_state: Option<&OfferState>) -> Event {
+    let event = Event::new(OFFER_EVENT)
+        .add_attribute("action", action)
+        .add_attribute("offer_id", offer.id.to_string())
+        .add_attribute("owner", offer.owner.to_string())
+        .add_attribute("fiat", offer.fiat_currency.to_string());
+    match from_state {
+        Some(from_state) => event.add_attribute("from_state", from_state.to_string()),
+        None => event,
+    }
+    .add_attribute("to_state", offer.state.to_string())
+}
+
+pub fn trade_event(action: &str, trade: &TradeData, from_state: Option<&TradeState>) -> Event {
+    let event = Event::new(TRADE_EVENT)
+        .add_attribute("action", action)
+        .add_attribute("trade_addr", trade.addr.to_string())
+        .add_attribute("offer_id", trade.offer_id.to_string());
+    match from_state {
+        Some(from_state) => event.add_attribute("from_state", from_state.to_string()),
+        None => event,
+    }
+    .add_attribute("to_state", trade.state.to_string())
+    .add_attribute("amount", trade.amount.to_string())
+}
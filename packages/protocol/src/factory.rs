@@ -1,6 +1,11 @@
+use crate::constants::{DEFAULT_FEE_BPS, IBC_DENOM_PREFIX};
+use crate::currencies::FiatCurrency;
+use crate::offer::default_addr;
 use cosmwasm_std::{Addr, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::{self};
 
 ///Messages
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -13,20 +18,127 @@ pub struct InstantiateMsg {
     pub trade_code_id: u64,
     pub fee_collector_threshold: Uint128,
     pub local_ust_pool_addr: String,
+    /// Forwarded to the offer contract, `0` means unlimited.
+    #[serde(default)]
+    pub max_offers_per_owner: u32,
+    /// Receives the protocol fees, see `Config::treasury_addr`.
+    pub treasury_addr: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
-pub enum ExecuteMsg {}
+pub enum ExecuteMsg {
+    /// Admin only, fields left as `None` are kept. The maker and taker fees
+    /// can't exceed 10000 together and the burn share can't exceed 10000.
+    UpdateConfig {
+        maker_fee_bps: Option<u64>,
+        taker_fee_bps: Option<u64>,
+        fee_burn_bps: Option<u64>,
+        fee_collector_addr: Option<String>,
+    },
+    /// Admin only, replaces any pending proposal. Fees keep going to the
+    /// current treasury until `treasury_addr` sends `AcceptTreasury`.
+    ProposeTreasury { treasury_addr: String },
+    /// Admin only, drops the pending proposal.
+    CancelTreasuryProposal {},
+    /// Restricted to the proposed treasury, makes it `treasury_addr`.
+    AcceptTreasury {},
+    /// Admin only, trades created afterwards are instantiated from
+    /// `code_id`, open trades keep running on their code.
+    UpdateTradeCodeId { code_id: u64 },
+    /// Restricted to the offer contract, records a trade it instantiated.
+    /// The trade's `arbitrator`, when registered for its currency, becomes
+    /// the last one assigned for `NextArbitrator`.
+    RegisterTrade {
+        addr: String,
+        #[serde(default)]
+        arbitrator: Option<Arbitrator>,
+    },
+    /// Admin only, registers `addr` to settle disputes of trades in `fiat`.
+    AddArbitrator { addr: String, fiat: FiatCurrency },
+    /// Admin only, the last arbitrator of a currency can't be removed.
+    RemoveArbitrator { addr: String, fiat: FiatCurrency },
+    /// Admin only, trusts the voucher of `base_denom` received through the
+    /// transfer `channel`, e.g. `channel-1` and `uatom`.
+    AddIbcDenom { channel: String, base_denom: String },
+    /// Admin only, `denom` is the `ibc/…` denom of a trusted voucher. Open
+    /// trades escrowing it keep running.
+    RemoveIbcDenom { denom: String },
+}
+
+/// Sent by chain governance through the `sudo` entry point, applied like the
+/// admin's `ExecuteMsg::UpdateConfig` without checking a sender.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SudoMsg {
+    UpdateConfig {
+        maker_fee_bps: Option<u64>,
+        taker_fee_bps: Option<u64>,
+        fee_burn_bps: Option<u64>,
+        fee_collector_addr: Option<String>,
+    },
+}
 
+/// Paginated queries return at most `MAX_PAGE_LIMIT` entries per page and
+/// `DEFAULT_PAGE_LIMIT` when `limit` is `0`, see `pagination::page_limit`.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     Config {},
+    /// Ordered by address, returned as an `ArbitratorsResponse`.
+    /// `last_value` is the last address of the previous page.
+    Arbitrators {
+        fiat_currency: FiatCurrency,
+        last_value: Option<String>,
+        limit: u32,
+    },
+    /// `Option<Arbitrator>`, the one new trades in `fiat_currency` get: the
+    /// registered arbitrator after the last assigned one in address order,
+    /// starting over after the last. `None` without registered arbitrators.
+    NextArbitrator {
+        fiat_currency: FiatCurrency,
+    },
+    /// Every contract deployed by the factory or the offer contract, ordered
+    /// by address and returned as a `ContractsResponse`. `last_value` is the
+    /// last address of the previous page.
+    Contracts {
+        kind: Option<ContractKind>,
+        last_value: Option<String>,
+        limit: u32,
+    },
+    /// `Option<IbcDenom>`, `None` when the `ibc/…` denom isn't trusted.
+    IbcDenom {
+        denom: String,
+    },
+    /// Trusted IBC vouchers ordered by denom, returned as an
+    /// `IbcDenomsResponse`. `last_value` is the last denom of the previous
+    /// page.
+    IbcDenoms {
+        last_value: Option<String>,
+        limit: u32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    /// Required by configs stored before `Config::admin` existed and ignored
+    /// by the others, whose admin is the instantiating address.
+    #[serde(default)]
+    pub admin: Option<String>,
+    /// Required by configs stored before `Config::treasury_addr` existed and
+    /// ignored by the others, whose treasury only changes through
+    /// `ProposeTreasury` and `AcceptTreasury`.
+    #[serde(default)]
+    pub treasury_addr: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
+    /// Manages the arbitrator registry. Empty in configs stored before the
+    /// field existed until `MigrateMsg` sets it.
+    #[serde(default = "default_addr")]
+    pub admin: Addr,
+    /// Queried by the offer contract for every new trade.
     pub trade_code_id: u64,
     pub token_addr: Addr,
     pub local_ust_pool_addr: Addr,
@@ -34,4 +146,128 @@ pub struct Config {
     pub offers_addr: Addr,
     pub fee_collector_addr: Addr,
     pub trading_incentives_addr: Addr,
+    /// Fee of the offer owner in basis points of the trade amount, read by
+    /// trades when they are released so changes apply to trades in progress.
+    /// Sell offers escrow it at the rate of the funding. Was the single
+    /// `fee_bps` of every trade.
+    #[serde(alias = "fee_bps", default = "default_fee_bps")]
+    pub maker_fee_bps: u64,
+    /// Fee of the trade's taker in basis points of the trade amount, read by
+    /// trades when they are released.
+    #[serde(default)]
+    pub taker_fee_bps: u64,
+    /// Share of the fees burned, in basis points of the fees. Rounded down,
+    /// the rest goes to the treasury. Trade assets are all burnable, native
+    /// denoms through `BankMsg::Burn` and cw20s through their `Burn`. The
+    /// `fee_collector_bps` of configs stored along with it is ignored.
+    #[serde(default)]
+    pub fee_burn_bps: u64,
+    /// Receives the collected share of the fees, read by trades when they
+    /// are released so rotations apply to trades in progress. Only changed
+    /// by the admin's `ProposeTreasury` accepted by the new treasury. Empty
+    /// in configs stored before the field existed until `MigrateMsg` sets it.
+    #[serde(default = "default_addr")]
+    pub treasury_addr: Addr,
+    #[serde(default)]
+    pub pending_treasury_addr: Option<Addr>,
+}
+
+pub(crate) fn default_fee_bps() -> u64 {
+    DEFAULT_FEE_BPS
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Arbitrator {
+    pub addr: Addr,
+    pub fiat: FiatCurrency,
+}
+
+/// IBC voucher offers and trades can escrow. Look-alike vouchers of the same
+/// `base_denom` received through other channels get a different `denom`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IbcDenom {
+    pub channel: String,
+    pub base_denom: String,
+    /// Bank denom of the voucher on this chain.
+    pub denom: String,
+}
+
+impl IbcDenom {
+    pub fn new(channel: &str, base_denom: &str) -> IbcDenom {
+        IbcDenom {
+            channel: channel.to_string(),
+            base_denom: base_denom.to_string(),
+            denom: ibc_voucher_denom(channel, base_denom),
+        }
+    }
+}
+
+/// `ibc/` followed by the upper case hex sha256 of the voucher's trace,
+/// `transfer/{channel}/{base_denom}`.
+pub fn ibc_voucher_denom(channel: &str, base_denom: &str) -> String {
+    let trace = format!("transfer/{}/{}", channel, base_denom);
+    let hash: String = Sha256::digest(trace.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect();
+    format!("{}{}", IBC_DENOM_PREFIX, hash)
+}
+
+pub fn is_ibc_denom(denom: &str) -> bool {
+    denom.starts_with(IBC_DENOM_PREFIX)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractKind {
+    Token,
+    Gov,
+    FeeCollector,
+    Offer,
+    TradingIncentives,
+    Trade,
+}
+
+impl fmt::Display for ContractKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractRecord {
+    pub kind: ContractKind,
+    pub addr: Addr,
+    /// Block height of the instantiation reply.
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ArbitratorsResponse {
+    pub arbitrators: Vec<Arbitrator>,
+    /// `last_value` of the following page, `None` once the listing is
+    /// exhausted.
+    pub next: Option<Addr>,
+    /// `page_limit` of the requested `limit`, the most entries a page holds.
+    pub limit: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractsResponse {
+    pub contracts: Vec<ContractRecord>,
+    /// `last_value` of the following page, `None` once the listing is
+    /// exhausted.
+    pub next: Option<Addr>,
+    /// `page_limit` of the requested `limit`, the most entries a page holds.
+    pub limit: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IbcDenomsResponse {
+    pub ibc_denoms: Vec<IbcDenom>,
+    /// `last_value` of the following page, `None` once the listing is
+    /// exhausted.
+    pub next: Option<String>,
+    /// `page_limit` of the requested `limit`, the most entries a page holds.
+    pub limit: u32,
 }
@@ -1,6 +1,9 @@
-use cosmwasm_std::{to_binary, QuerierWrapper, QueryRequest, WasmQuery};
+use cosmwasm_std::{
+    to_binary, QuerierWrapper, QueryRequest, StdResult, SubMsgExecutionResponse, WasmQuery,
+};
 
-use crate::factory::{Config, QueryMsg};
+use crate::currencies::FiatCurrency;
+use crate::factory::{Arbitrator, Config, IbcDenom, QueryMsg};
 
 pub fn get_factory_config(querier: &QuerierWrapper, factory_addr: String) -> Config {
     querier
@@ -10,3 +13,74 @@ pub fn get_factory_config(querier: &QuerierWrapper, factory_addr: String) -> Con
         }))
         .unwrap()
 }
+
+/// Registered arbitrator the next trade in `fiat_currency` gets, `None` when
+/// the currency has none.
+pub fn get_next_arbitrator(
+    querier: &QuerierWrapper,
+    factory_addr: String,
+    fiat_currency: FiatCurrency,
+) -> StdResult<Option<Arbitrator>> {
+    querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: factory_addr,
+        msg: to_binary(&QueryMsg::NextArbitrator { fiat_currency })?,
+    }))
+}
+
+/// Address of the contract created by a `WasmMsg::Instantiate` sub message,
+/// read from the `MsgInstantiateContractResponse` in the reply data or else
+/// from Terra's `instantiate_contract` event.
+pub fn instantiated_contract_addr(response: &SubMsgExecutionResponse) -> Option<String> {
+    response
+        .data
+        .as_ref()
+        .and_then(|data| response_contract_addr(data.as_slice()))
+        .or_else(|| {
+            response
+                .events
+                .iter()
+                .find(|e| e.ty == "instantiate_contract")
+                .and_then(|ev| {
+                    ev.attributes
+                        .iter()
+                        .find(|attr| attr.key == "contract_address")
+                })
+                .map(|attr| attr.value.clone())
+        })
+}
+
+/// Field 1 of the protobuf encoded response, a length delimited string.
+fn response_contract_addr(data: &[u8]) -> Option<String> {
+    let (tag, rest) = data.split_first()?;
+    if *tag != 0x0a {
+        return None;
+    }
+    let mut len = 0usize;
+    let mut read = 0usize;
+    loop {
+        let byte = *rest.get(read)?;
+        len |= ((byte & 0x7f) as usize) << (7 * read);
+        read += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if read == 4 {
+            return None;
+        }
+    }
+    let addr = rest.get(read..read + len)?;
+    String::from_utf8(addr.to_vec()).ok()
+}
+
+/// Registration of the `ibc/…` `denom`, `None` when the factory doesn't trust
+/// it.
+pub fn get_ibc_denom(
+    querier: &QuerierWrapper,
+    factory_addr: String,
+    denom: String,
+) -> StdResult<Option<IbcDenom>> {
+    querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: factory_addr,
+        msg: to_binary(&QueryMsg::IbcDenom { denom })?,
+    }))
+}
@@ -0,0 +1,103 @@
+//! Protocol fee math, shared by trades charging the fees on funding and
+//! release and the offer contract's `SimulateTrade` query so both always
+//! agree.
+use cosmwasm_std::{QuerierWrapper, StdResult, Uint128};
+
+use crate::offer::{OfferAsset, OfferType};
+use crate::taxation::asset_tax;
+
+/// Protocol fee on `amount`, rounded down.
+pub fn localterra_fee(amount: Uint128, fee_bps: u64) -> StdResult<Uint128> {
+    let fee = amount.checked_mul(Uint128::from(fee_bps))?;
+    Ok(fee.checked_div(Uint128::new(10_000u128))?)
+}
+
+/// Part of `fee_amount` burned, rounded down, the rest goes to the treasury.
+pub fn fee_burn_amount(fee_amount: Uint128, fee_burn_bps: u64) -> Uint128 {
+    fee_amount.multiply_ratio(fee_burn_bps, 10_000u128)
+}
+
+/// Maker and taker fees of a trade, each rounded down. The buyer receives
+/// the escrow minus the taker fee, makers of buy offers receive the escrow
+/// and pay their fee out of it too, makers of sell offers escrow it on top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TradeFees {
+    pub maker_fee_amount: Uint128,
+    pub taker_fee_amount: Uint128,
+}
+
+impl TradeFees {
+    pub fn new(amount: Uint128, maker_fee_bps: u64, taker_fee_bps: u64) -> StdResult<TradeFees> {
+        Ok(TradeFees {
+            maker_fee_amount: localterra_fee(amount, maker_fee_bps)?,
+            taker_fee_amount: localterra_fee(amount, taker_fee_bps)?,
+        })
+    }
+
+    pub fn total(&self) -> Uint128 {
+        self.maker_fee_amount + self.taker_fee_amount
+    }
+
+    /// Funded by the seller on top of the trade amount.
+    pub fn escrowed(&self, offer_type: &OfferType) -> Uint128 {
+        match offer_type {
+            OfferType::Buy => Uint128::zero(),
+            OfferType::Sell => self.maker_fee_amount,
+        }
+    }
+
+    /// Deducted from what the buyer receives.
+    pub fn deducted(&self, offer_type: &OfferType) -> Uint128 {
+        match offer_type {
+            OfferType::Buy => self.total(),
+            OfferType::Sell => self.taker_fee_amount,
+        }
+    }
+
+    /// Amount the seller has to escrow, sell offers add the maker fee and the
+    /// taxes it takes to release the fee and the trade amount.
+    pub fn escrow_amount(
+        &self,
+        querier: &QuerierWrapper,
+        asset: &OfferAsset,
+        offer_type: &OfferType,
+        amount: Uint128,
+    ) -> StdResult<Uint128> {
+        match offer_type {
+            OfferType::Sell => {
+                let ltfee = self.escrowed(offer_type);
+                let ltfee_tax = asset_tax(querier, asset, ltfee)?;
+                let release_tax = asset_tax(querier, asset, amount)?;
+                Ok(amount + ltfee + ltfee_tax + release_tax)
+            }
+            OfferType::Buy => Ok(amount),
+        }
+    }
+
+    /// Released to the buyer out of an escrowed `amount`, minus the deducted
+    /// fees and their tax. Buy offers also pay the tax on the release itself,
+    /// after `arbitration_amount` and its tax left the escrow.
+    pub fn release_amount(
+        &self,
+        querier: &QuerierWrapper,
+        asset: &OfferAsset,
+        offer_type: &OfferType,
+        amount: Uint128,
+        arbitration_amount: Uint128,
+    ) -> StdResult<Uint128> {
+        let ltfee = self.deducted(offer_type);
+        let ltfee_tax = asset_tax(querier, asset, ltfee)?;
+        match offer_type {
+            OfferType::Buy => {
+                let arbitration_fee_inc_tax =
+                    arbitration_amount + asset_tax(querier, asset, arbitration_amount)?;
+                let release_amount = amount
+                    .checked_sub(ltfee + ltfee_tax)?
+                    .checked_sub(arbitration_fee_inc_tax)?;
+                let release_tax = asset_tax(querier, asset, release_amount)?;
+                Ok(amount.checked_sub(ltfee + ltfee_tax + release_tax)?)
+            }
+            OfferType::Sell => Ok(amount.checked_sub(ltfee + ltfee_tax)?),
+        }
+    }
+}
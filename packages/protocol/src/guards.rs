@@ -1,20 +1,235 @@
-use crate::errors::OfferError;
-use cosmwasm_std::{Addr, StdError, Uint128};
+use crate::constants::{
+    MAX_DESCRIPTION_LEN, MAX_MAKER_CONTACT_LEN, MAX_PAYMENT_METHODS, MAX_PAYMENT_METHOD_LEN,
+    MAX_PRICE_MARGIN_BPS,
+};
+use crate::currencies::FiatCurrency;
+use crate::errors::{OfferError, TradeError};
+use crate::trade::{TradeData, TradeRole, TradeState};
+use cosmwasm_std::{Addr, Api, Timestamp, Uint128};
 
-pub fn assert_ownership(caller: Addr, owner: Addr) -> Result<(), OfferError> {
-    if caller.eq(&owner) {
+/// `role` names `expected` in the error, e.g. "factory" or "offer owner".
+pub fn assert_sender_is(caller: &Addr, expected: &Addr, role: &str) -> Result<(), OfferError> {
+    if caller == expected {
         Ok(())
     } else {
-        Err(OfferError::Unauthorized { owner, caller })
+        Err(OfferError::Unauthorized {
+            role: role.to_string(),
+            expected: expected.clone(),
+            caller: caller.clone(),
+        })
     }
 }
 
+/// Validates an address taken from a message, `field` names it in the error.
+/// Bech32 addresses are stored lowercase, mixed case ones are refused here
+/// rather than stored as a second spelling of the same account.
+pub fn validate_addr(api: &dyn Api, field: &str, addr: &str) -> Result<Addr, OfferError> {
+    let invalid = || OfferError::InvalidAddress {
+        field: field.to_string(),
+        addr: addr.to_string(),
+    };
+    if addr.chars().any(|c| c.is_ascii_uppercase()) {
+        return Err(invalid());
+    }
+    api.addr_validate(addr).map_err(|_| invalid())
+}
+
+/// Compares validated addresses, the taker is one of the trade's two parties
+/// and the owner the other, a sender acting as both is caught here too.
+pub fn assert_not_self_trade(owner: &Addr, taker: &Addr) -> Result<(), OfferError> {
+    if owner == taker {
+        return Err(OfferError::SelfTradeNotAllowed {
+            owner: owner.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// `min == max` is allowed for fixed amount offers.
 pub fn assert_min_g_max(min: Uint128, max: Uint128) -> Result<(), OfferError> {
-    if min >= max {
-        Err(OfferError::Std(StdError::generic_err(
-            "Min amount must be greater than Max amount.",
-        )))
+    if min.is_zero() || min > max {
+        Err(OfferError::InvalidAmountRange { min, max })
+    } else {
+        Ok(())
+    }
+}
+
+/// `max_ratio` of `0` leaves the spread between the bounds unchecked.
+pub fn assert_amount_ratio(min: Uint128, max: Uint128, max_ratio: u64) -> Result<(), OfferError> {
+    if max_ratio == 0 {
+        return Ok(());
+    }
+    match min.checked_mul(Uint128::from(max_ratio)) {
+        Ok(bound) if max > bound => Err(OfferError::AmountRatioExceeded {
+            min,
+            max,
+            max_ratio,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// A margin of -100% or lower would price the offer at zero or below.
+pub fn assert_price_margin(price_margin_bps: Option<i32>) -> Result<(), OfferError> {
+    match price_margin_bps {
+        Some(price_margin_bps)
+            if price_margin_bps <= -10_000 || price_margin_bps > MAX_PRICE_MARGIN_BPS =>
+        {
+            Err(OfferError::InvalidPriceMargin { price_margin_bps })
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn assert_payment_methods(payment_methods: &[String]) -> Result<(), OfferError> {
+    let invalid_method = payment_methods
+        .iter()
+        .any(|method| method.is_empty() || method.len() > MAX_PAYMENT_METHOD_LEN);
+    if payment_methods.len() > MAX_PAYMENT_METHODS || invalid_method {
+        Err(OfferError::InvalidPaymentMethods {
+            max_count: MAX_PAYMENT_METHODS,
+            max_len: MAX_PAYMENT_METHOD_LEN,
+        })
     } else {
         Ok(())
     }
 }
+
+/// The limit is in bytes, multi-byte characters count for their UTF-8 length.
+pub fn assert_description(description: &Option<String>) -> Result<(), OfferError> {
+    match description {
+        Some(description) if description.len() > MAX_DESCRIPTION_LEN => {
+            Err(OfferError::DescriptionTooLong {
+                len: description.len(),
+                max_len: MAX_DESCRIPTION_LEN,
+            })
+        }
+        Some(description) if description.chars().any(char::is_control) => {
+            Err(OfferError::InvalidDescription {})
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn assert_maker_contact(maker_contact: &Option<String>) -> Result<(), OfferError> {
+    match maker_contact {
+        Some(maker_contact) if maker_contact.len() > MAX_MAKER_CONTACT_LEN => {
+            Err(OfferError::MakerContactTooLong {
+                len: maker_contact.len(),
+                max_len: MAX_MAKER_CONTACT_LEN,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn assert_denom(denoms: &[String], denom: &str) -> Result<(), OfferError> {
+    if denoms.iter().any(|allowed| allowed == denom) {
+        Ok(())
+    } else {
+        Err(OfferError::UnsupportedDenom {
+            denom: denom.to_string(),
+        })
+    }
+}
+
+pub fn assert_fiat_currency(
+    fiat_currencies: &[FiatCurrency],
+    fiat_currency: &FiatCurrency,
+) -> Result<(), OfferError> {
+    if fiat_currencies.contains(fiat_currency) {
+        Ok(())
+    } else {
+        Err(OfferError::UnsupportedFiatCurrency {
+            fiat_currency: fiat_currency.clone(),
+            supported: fiat_currencies
+                .iter()
+                .map(FiatCurrency::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+        })
+    }
+}
+
+pub fn assert_expiration(
+    expires_at: Option<Timestamp>,
+    block_time: Timestamp,
+) -> Result<(), OfferError> {
+    match expires_at {
+        Some(expires_at) if expires_at <= block_time => Err(OfferError::InvalidExpiration {
+            expires_at,
+            block_time,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// A trading window has to open before it closes.
+pub fn assert_schedule(
+    activate_at: Option<Timestamp>,
+    pause_at: Option<Timestamp>,
+) -> Result<(), OfferError> {
+    match (activate_at, pause_at) {
+        (Some(activate_at), Some(pause_at)) if pause_at <= activate_at => {
+            Err(OfferError::InvalidSchedule {
+                activate_at,
+                pause_at,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Trading hours are set together as two different UTC hours.
+pub fn assert_trading_hours(
+    available_from_hour: Option<u8>,
+    available_to_hour: Option<u8>,
+) -> Result<(), OfferError> {
+    match (available_from_hour, available_to_hour) {
+        (None, None) => Ok(()),
+        (Some(from), Some(to)) if from < 24 && to < 24 && from != to => Ok(()),
+        _ => Err(OfferError::InvalidTradingHours {
+            available_from_hour,
+            available_to_hour,
+        }),
+    }
+}
+
+/// Checks `to` against the trade's transition table.
+pub fn assert_trade_state_change(from: &TradeState, to: &TradeState) -> Result<(), TradeError> {
+    if from.can_transition_to(to) {
+        Ok(())
+    } else {
+        Err(TradeError::InvalidStateChange {
+            from: from.clone(),
+            to: to.clone(),
+        })
+    }
+}
+
+/// Hex encoded sha256 digest, either case.
+pub fn assert_sha256_hex(sha256: &str) -> Result<(), TradeError> {
+    if sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(TradeError::InvalidEvidenceHash {
+            sha256: sha256.to_string(),
+        })
+    }
+}
+
+/// Returns the first of `allowed_roles` held by `caller` in `trade`.
+pub fn assert_caller_role(
+    caller: &Addr,
+    trade: &TradeData,
+    allowed_roles: &[TradeRole],
+) -> Result<TradeRole, TradeError> {
+    allowed_roles
+        .iter()
+        .find(|role| role.addr(trade) == caller)
+        .cloned()
+        .ok_or_else(|| TradeError::Unauthorized {
+            caller: caller.clone(),
+            allowed: allowed_roles.to_vec(),
+        })
+}
@@ -1,11 +1,17 @@
 pub mod constants;
 pub mod currencies;
 pub mod errors;
+pub mod events;
 pub mod factory;
 pub mod factory_util;
 pub mod fee_collector;
+pub mod fees;
 pub mod governance;
 pub mod guards;
+pub mod migration;
 pub mod offer;
+pub mod pagination;
+pub mod price_oracle;
+pub mod taxation;
 pub mod trade;
 pub mod trading_incentives;
@@ -0,0 +1,21 @@
+use cw2::ContractVersion;
+
+/// Whether a contract stored as `stored` can be migrated to `name` at
+/// `version`, its name has to match and its version can't be newer.
+pub fn is_valid_migration(stored: &ContractVersion, name: &str, version: &str) -> bool {
+    stored.contract == name && parse_version(&stored.version) <= parse_version(version)
+}
+
+/// `major.minor.patch` as comparable numbers, pre-release suffixes and
+/// unparsable parts read as `0`.
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .split('.')
+        .map(|part| {
+            part.split('-')
+                .next()
+                .and_then(|number| number.parse().ok())
+                .unwrap_or_default()
+        })
+        .collect()
+}
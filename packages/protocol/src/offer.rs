@@ -1,48 +1,89 @@
-use super::constants::OFFERS_KEY;
-use crate::currencies::FiatCurrency;
+use super::constants::{
+    DEFAULT_DENOM, DEFAULT_DISPUTE_WINDOW, DEFAULT_MAX_PRICE_AGE, DEFAULT_NEW_TRADE_WINDOW,
+    DEFAULT_TRADE_DURATION, MAX_PAGE_LIMIT, OFFERS_KEY, OFFER_COUNTS_KEY, OFFER_FIATS_KEY,
+    OFFER_STATE_KEY, OFFER_SUMMARIES_KEY, OWNER_OFFER_COUNTS_KEY, PROFILE_OFFERS_PREVIEW,
+    SAMPLE_PROBES_PER_OFFER, SCANNED_ENTRIES_PER_OFFER, STORED_OFFERS_KEY,
+};
+use crate::currencies::{FiatCurrency, LEGACY_FIAT_CURRENCIES};
 use crate::errors::OfferError;
+use crate::pagination::{page_limit, page_next};
 use crate::trade::{TradeData, TradeState};
-use cosmwasm_std::{Addr, Deps, Order, StdResult, Storage, Uint128};
-use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, MultiIndex};
+use cosmwasm_std::{
+    Addr, Decimal, Deps, Order, OverflowError, OverflowOperation, StdResult, Storage, Timestamp,
+    Uint128,
+};
+use cosmwasm_storage::singleton;
+use cw_storage_plus::{
+    Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex, PrimaryKey, U64Key,
+};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::fmt::{self};
 
 pub static CONFIG_KEY: &[u8] = b"config";
+static DECIMAL_FRACTION: Uint128 = Uint128::new(1_000_000_000_000_000_000u128);
 // pub const OFFERS: Map<&[u8], Offer> = Map::new(OFFERS_KEY);
 pub struct OfferIndexes<'a> {
-    // pk goes to second tuple element
-    pub owner: MultiIndex<'a, (Addr, Vec<u8>), Offer>,
-    pub offer_type: MultiIndex<'a, (String, Vec<u8>), Offer>,
-    pub fiat: MultiIndex<'a, (String, Vec<u8>), Offer>,
+    // pk goes to the last tuple element, the id before it keeps owner and
+    // type prefixes in id order across currencies
+    pub owner: MultiIndex<'a, (Addr, U64Key, Vec<u8>), Offer>,
+    pub offer_type: MultiIndex<'a, (String, U64Key, Vec<u8>), Offer>,
     pub filter: MultiIndex<'a, (String, String, Vec<u8>), Offer>,
 }
 
 impl<'a> IndexList<Offer> for OfferIndexes<'a> {
     fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Offer>> + '_> {
-        let v: Vec<&dyn Index<Offer>> =
-            vec![&self.owner, &self.offer_type, &self.fiat, &self.filter];
+        let v: Vec<&dyn Index<Offer>> = vec![&self.owner, &self.offer_type, &self.filter];
         Box::new(v.into_iter())
     }
 }
 
-pub fn offers<'a>() -> IndexedMap<'a, &'a str, Offer, OfferIndexes<'a>> {
-    let indexes = OfferIndexes {
+/// Offers keyed by `(fiat_currency, id)`, so that single currency listings
+/// only walk their own prefix. Use `OfferModel::load` to read one by id.
+pub fn offers<'a>() -> IndexedMap<'a, (&'a str, U64Key), Offer, OfferIndexes<'a>> {
+    IndexedMap::new(OFFERS_KEY, offer_indexes())
+}
+
+/// Currency of every stored offer by id, the rest of its primary key.
+pub fn offer_fiat_currencies<'a>() -> Map<'a, U64Key, FiatCurrency> {
+    Map::new(OFFER_FIATS_KEY)
+}
+
+/// Offer counters keyed by fiat currency, only currencies with at least one
+/// active or paused offer have an entry.
+pub fn offer_counts<'a>() -> Map<'a, &'a str, FiatOfferCounts> {
+    Map::new(OFFER_COUNTS_KEY)
+}
+
+/// Active offers per side keyed by `(fiat_currency, denom)`, only pairs with
+/// at least one active offer have an entry.
+pub fn offer_summaries<'a>() -> Map<'a, (&'a str, &'a str), FiatOffersSummary> {
+    Map::new(OFFER_SUMMARIES_KEY)
+}
+
+/// Active and paused offers of each owner, archived offers free their slot.
+pub fn owner_offer_counts<'a>() -> Map<'a, &'a Addr, u64> {
+    Map::new(OWNER_OFFER_COUNTS_KEY)
+}
+
+/// Stored offers of every currency and state, deleted offers leave it.
+pub fn stored_offers<'a>() -> Item<'a, u64> {
+    Item::new(STORED_OFFERS_KEY)
+}
+
+pub fn offer_indexes<'a>() -> OfferIndexes<'a> {
+    OfferIndexes {
         owner: MultiIndex::new(
-            |d: &Offer, k: Vec<u8>| (d.owner.clone(), k),
+            |d: &Offer, k: Vec<u8>| (d.owner.clone(), U64Key::new(d.id), k),
             "offers",        // TODO replace with OFFERS_KEY
             "offers__owner", // TODO replace with OFFERS_KEY and concat
         ),
         offer_type: MultiIndex::new(
-            |d: &Offer, k: Vec<u8>| (d.offer_type.to_string(), k),
+            |d: &Offer, k: Vec<u8>| (d.offer_type.to_string(), U64Key::new(d.id), k),
             "offers",             // TODO replace with OFFERS_KEY
             "offers__offer_type", // TODO replace with OFFERS_KEY and concat
         ),
-        fiat: MultiIndex::new(
-            |d: &Offer, k: Vec<u8>| (d.fiat_currency.to_string(), k),
-            "offers",       // TODO replace with OFFERS_KEY
-            "offers__fiat", // TODO replace with OFFERS_KEY and concat
-        ),
         filter: MultiIndex::new(
             |d: &Offer, k: Vec<u8>| {
                 (
@@ -54,28 +95,167 @@ pub fn offers<'a>() -> IndexedMap<'a, &'a str, Offer, OfferIndexes<'a>> {
             "offers",         // TODO replace with OFFERS_KEY
             "offers__filter", // TODO replace with OFFERS_KEY and concat
         ),
-    };
-    IndexedMap::new(OFFERS_KEY, indexes)
+    }
 }
 
 // pub const OFFERS : IndexedMap<&str, Offer, OfferIndexes> = create_offers_indexedmap();
 
+pub fn default_denom() -> String {
+    DEFAULT_DENOM.to_string()
+}
+
+fn default_denoms() -> Vec<String> {
+    vec![default_denom()]
+}
+
+pub fn default_fiat_currencies() -> Vec<FiatCurrency> {
+    LEGACY_FIAT_CURRENCIES
+        .iter()
+        .map(|code| FiatCurrency::unchecked(code))
+        .collect()
+}
+
+fn default_trade_duration() -> u64 {
+    DEFAULT_TRADE_DURATION
+}
+
+fn default_dispute_window() -> u64 {
+    DEFAULT_DISPUTE_WINDOW
+}
+
+fn default_max_price_age() -> u64 {
+    DEFAULT_MAX_PRICE_AGE
+}
+
+fn default_new_trade_window() -> u64 {
+    DEFAULT_NEW_TRADE_WINDOW
+}
+
+pub(crate) fn default_addr() -> Addr {
+    Addr::unchecked("")
+}
+
 ///Messages
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct InstantiateMsg {}
+pub struct InstantiateMsg {
+    /// `0` means unlimited.
+    #[serde(default)]
+    pub max_offers_per_owner: u32,
+    /// Ceiling on offer max amounts and trades, `0` means unlimited.
+    #[serde(default)]
+    pub max_trade_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
 
+/// Amounts are `Uint128` and therefore travel as JSON strings
+/// (`"min_amount": "1000000"`), clients still sending plain JSON numbers
+/// must quote them.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct OfferMsg {
     pub offer_type: OfferType,
     pub fiat_currency: FiatCurrency,
     pub min_amount: Uint128,
     pub max_amount: Uint128,
-    pub maker_contact: String,
+    /// Meant to hold ciphertext, up to `MAX_MAKER_CONTACT_LEN` bytes.
+    pub maker_contact: Option<String>,
+    /// Offers without an expiration stay listed until paused or archived.
+    pub expires_at: Option<Timestamp>,
+    /// Floating price as a margin in basis points over the oracle rate, can
+    /// be negative. Offers without a margin have no price attached.
+    pub price_margin_bps: Option<i32>,
+    /// Free form labels such as `"pix"` or `"bank_transfer"`, see
+    /// `MAX_PAYMENT_METHODS` and `MAX_PAYMENT_METHOD_LEN` for the bounds.
+    #[serde(default)]
+    pub payment_methods: Vec<String>,
+    /// Short terms shown with the offer, `None` on `Update` clears them.
+    pub description: Option<String>,
+    /// Asset escrowed by trades of this offer, native denoms must be
+    /// whitelisted in `Config::denoms`.
+    #[serde(default)]
+    pub asset: OfferAsset,
+    /// Takers need at least this many closed trades to trade the offer.
+    pub min_trades_completed: Option<u64>,
+    /// Total the maker is willing to trade across all trades, `0` keeps the
+    /// offer tradable up to `max_amount` per trade indefinitely. `Update`
+    /// starts the count over.
+    #[serde(default)]
+    pub total_available: Uint128,
+    /// Trading window, the offer counts as active from `activate_at` and as
+    /// paused from `pause_at` whatever its stored state, see
+    /// `Offer::state_at`. Either end can be left open, `pause_at` must come
+    /// after `activate_at`.
+    #[serde(default)]
+    pub activate_at: Option<Timestamp>,
+    #[serde(default)]
+    pub pause_at: Option<Timestamp>,
+    /// `min_amount`, `max_amount` and `total_available` are minor units of
+    /// `fiat_currency` instead of units of the asset. Trades still request
+    /// and reserve fiat, their escrow is converted at the oracle rate of the
+    /// block they are opened in.
+    #[serde(default)]
+    pub amounts_in_fiat: bool,
+    /// UTC hours the maker takes trades in, from `available_from_hour`
+    /// included to `available_to_hour` excluded. `22` to `6` wraps around
+    /// midnight, offers without them take trades at any hour.
+    #[serde(default)]
+    pub available_from_hour: Option<u8>,
+    #[serde(default)]
+    pub available_to_hour: Option<u8>,
+}
+
+/// Fields replaced on the copy made by `CloneOffer`, those left as `None` are
+/// taken from the source offer. Optional terms can only be replaced, not
+/// cleared, an `Update` of the copy clears them.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct OfferOverrides {
+    pub offer_type: Option<OfferType>,
+    pub fiat_currency: Option<FiatCurrency>,
+    pub min_amount: Option<Uint128>,
+    pub max_amount: Option<Uint128>,
+    pub maker_contact: Option<String>,
+    pub expires_at: Option<Timestamp>,
+    pub price_margin_bps: Option<i32>,
+    pub payment_methods: Option<Vec<String>>,
+    pub description: Option<String>,
+    pub asset: Option<OfferAsset>,
+    pub min_trades_completed: Option<u64>,
+    pub total_available: Option<Uint128>,
+    pub activate_at: Option<Timestamp>,
+    pub pause_at: Option<Timestamp>,
+    pub available_from_hour: Option<u8>,
+    pub available_to_hour: Option<u8>,
+}
+
+impl OfferOverrides {
+    pub fn apply(self, msg: OfferMsg) -> OfferMsg {
+        OfferMsg {
+            offer_type: self.offer_type.unwrap_or(msg.offer_type),
+            fiat_currency: self.fiat_currency.unwrap_or(msg.fiat_currency),
+            min_amount: self.min_amount.unwrap_or(msg.min_amount),
+            max_amount: self.max_amount.unwrap_or(msg.max_amount),
+            maker_contact: self.maker_contact.or(msg.maker_contact),
+            expires_at: self.expires_at.or(msg.expires_at),
+            price_margin_bps: self.price_margin_bps.or(msg.price_margin_bps),
+            payment_methods: self.payment_methods.unwrap_or(msg.payment_methods),
+            description: self.description.or(msg.description),
+            asset: self.asset.unwrap_or(msg.asset),
+            min_trades_completed: self.min_trades_completed.or(msg.min_trades_completed),
+            total_available: self.total_available.unwrap_or(msg.total_available),
+            activate_at: self.activate_at.or(msg.activate_at),
+            pause_at: self.pause_at.or(msg.pause_at),
+            amounts_in_fiat: msg.amounts_in_fiat,
+            available_from_hour: self.available_from_hour.or(msg.available_from_hour),
+            available_to_hour: self.available_to_hour.or(msg.available_to_hour),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
+    /// The created `Offer` is the response data.
     Create {
         offer: OfferMsg,
     },
@@ -85,41 +265,189 @@ pub enum ExecuteMsg {
     Activate {
         id: u64,
     },
+    Archive {
+        id: u64,
+    },
+    /// Pauses the caller's active offers, up to `MAX_BULK_STATE_CHANGES` per
+    /// call. The `continue` attribute is `true` while some are left, so the
+    /// call can be repeated until it is `false`.
+    PauseAll {},
+    /// Activates the caller's paused offers, paginated like `PauseAll`.
+    ActivateAll {},
+    /// Restricted to the admin, fields left as `None` are kept.
+    UpdateConfig(ConfigUpdate),
+    /// Restricted to the admin, replaces any pending proposal. The admin
+    /// only changes once `new_admin` sends `AcceptAdmin`.
+    ProposeNewAdmin {
+        new_admin: String,
+    },
+    /// Restricted to the admin, drops the pending proposal.
+    CancelAdminProposal {},
+    /// Restricted to the pending admin, makes it the new `admin`.
+    AcceptAdmin {},
+    /// Restricted to the admin. While paused, offers can't be created or
+    /// updated and no trades can be opened, pausing and activating single
+    /// offers keeps working.
+    SetPause {
+        paused: bool,
+    },
+    /// Restricted to the admin. Blocked addresses can't create or update
+    /// offers nor take part in new trades, their offers stay queryable.
+    SetBlacklist {
+        addr: String,
+        blocked: bool,
+    },
+    /// The updated `Offer` is the response data.
     Update {
         id: u64,
         offer: OfferMsg,
     },
+    /// Up to `MAX_BATCH_SIZE` offers, created ids are returned in the `ids`
+    /// attribute.
+    CreateMany {
+        offers: Vec<OfferMsg>,
+    },
+    /// Up to `MAX_BATCH_SIZE` `(id, offer)` pairs.
+    UpdateMany {
+        updates: Vec<(u64, OfferMsg)>,
+    },
+    /// Lets `operator` update, pause, activate and archive the caller's
+    /// offers, `enabled: false` revokes the grant. Operators only ever
+    /// manage their own grants through this message.
+    SetOperator {
+        operator: String,
+        enabled: bool,
+    },
+    /// Restricted to the owner, removes an offer without open trades. Offers
+    /// that had trades can only be archived, unless `allow_delete_traded` is
+    /// set. The id is never reused.
+    Delete {
+        id: u64,
+    },
+    /// Creates an active copy of one of the caller's offers that isn't
+    /// archived, validated like `Create`. The new offer is the response data.
+    CloneOffer {
+        id: u64,
+        overrides: Option<OfferOverrides>,
+    },
+    /// Instantiates the trade contract, the reply registering it sets a
+    /// `NewTradeResponse` as the response data.
     NewTrade {
         offer_id: u64,
-        ust_amount: String,
+        /// Amount of the offer's denom, or fiat minor units for offers with
+        /// `amounts_in_fiat`. `ust_amount` is still accepted. Encoded as a
+        /// string of digits like before.
+        #[serde(alias = "ust_amount")]
+        amount: Uint128,
         taker: String, // TODO should be Addr
         taker_contact: String,
-        arbitrator: String, // TODO should be Addr
+        /// See the trade's `InstantiateMsg::multisig_release`.
+        #[serde(default)]
+        multisig_release: bool,
+    },
+    /// Sent by a trade reaching a final state, only accepted from trades
+    /// created by this contract.
+    TradeClosed {
+        state: TradeState,
+    },
+    /// Sent by a trade moving to a state that isn't final yet, keeps the
+    /// trades index by state current.
+    TradeStateChanged {
+        state: TradeState,
     },
 }
 
+/// Config changes of `ExecuteMsg::UpdateConfig` and `SudoMsg::UpdateConfig`,
+/// fields left as `None` are kept.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct ConfigUpdate {
+    pub factory_addr: Option<String>,
+    pub max_offers_per_owner: Option<u32>,
+    pub price_oracle_addr: Option<String>,
+    pub denoms: Option<Vec<String>>,
+    pub trade_duration: Option<u64>,
+    pub arbitrator: Option<String>,
+    pub max_trade_amount: Option<Uint128>,
+    pub fiat_currencies: Option<Vec<FiatCurrency>>,
+    pub max_concurrent_trades: Option<u8>,
+    pub dispute_window: Option<u64>,
+    pub new_trade_limit: Option<u32>,
+    pub new_trade_window: Option<u64>,
+    pub allow_delete_traded: Option<bool>,
+    pub max_amount_ratio: Option<u64>,
+    pub max_price_age: Option<u64>,
+}
+
+/// Sent by chain governance through the `sudo` entry point, applied like the
+/// admin's `ExecuteMsg` counterparts without checking a sender.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
+pub enum SudoMsg {
+    SetPause { paused: bool },
+    UpdateConfig(ConfigUpdate),
+    RemoveBlacklisted { addr: String },
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum TradesIndex {
     Seller,
     Buyer,
+    Taker,
     ArbitratorState,
 }
 
+/// Paginated queries return at most `MAX_PAGE_LIMIT` entries per page and
+/// `DEFAULT_PAGE_LIMIT` when `limit` is `0`, see `pagination::page_limit`.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     Config {},
+    /// Returns a `StateResponse`.
     State {},
+    /// Deprecated, the first `MAX_PAGE_LIMIT` listed offers in
+    /// `fiat_currency` as a `Vec<Offer>`, `OffersByFiat` pages through all of
+    /// them.
     Offers {
-        // TODO deprecated, remove
         fiat_currency: FiatCurrency,
+        offer_type: Option<OfferType>,
+        payment_method: Option<String>,
+        /// Native denom or cw20 address the offers trade.
+        denom: Option<String>,
+        order: Option<QueryOrder>,
+        /// See `OfferFilter::amount`.
+        amount: Option<Uint128>,
+        /// See `OfferFilter::min_amount`.
+        min_amount: Option<Uint128>,
+        /// See `OfferFilter::max_amount`.
+        max_amount: Option<Uint128>,
     },
+    /// Listed offers by id, returned as an `OffersPageResponse` like every
+    /// `Offers*` page below.
     OffersQuery {
         owner: Option<Addr>,
+        /// Lets indexers fetch only what changed since their last sync.
+        updated_since: Option<Timestamp>,
         last_value: Option<u64>,
         limit: u32,
     },
+    /// Listed offers of every currency by id, newest first unless `order` is
+    /// `asc`, returned as an `OffersPageResponse`. Descending pages continue
+    /// below `start_before` and ascending ones above `last_value`, offers
+    /// created meanwhile never show up behind the cursor.
+    OffersPage {
+        order: Option<QueryOrder>,
+        start_before: Option<u64>,
+        last_value: Option<u64>,
+        limit: u32,
+        /// See `OfferFilter::amount`.
+        amount: Option<Uint128>,
+        /// See `OfferFilter::min_amount`.
+        min_amount: Option<Uint128>,
+        /// See `OfferFilter::max_amount`.
+        max_amount: Option<Uint128>,
+    },
     OffersByType {
         offer_type: OfferType,
         last_value: Option<u64>,
@@ -127,6 +455,11 @@ pub enum QueryMsg {
     },
     OffersByFiat {
         fiat_currency: FiatCurrency,
+        offer_type: Option<OfferType>,
+        payment_method: Option<String>,
+        /// Native denom or cw20 address the offers trade.
+        denom: Option<String>,
+        order: Option<QueryOrder>,
         last_value: Option<u64>,
         limit: u32,
     },
@@ -136,9 +469,54 @@ pub enum QueryMsg {
         last_value: Option<u64>,
         limit: u32,
     },
+    OffersByOwner {
+        owner: String,
+        last_value: Option<u64>,
+        limit: u32,
+    },
+    /// Stored offers matching every given filter in id order, archived and
+    /// expired ones included. `state` is the stored state, not the
+    /// `effective_state`. Returned as an `OffersFilteredResponse`.
+    OffersFiltered {
+        owner: Option<String>,
+        fiat_currency: Option<FiatCurrency>,
+        offer_type: Option<OfferType>,
+        state: Option<OfferState>,
+        last_value: Option<u64>,
+        limit: u32,
+    },
     Offer {
         id: u64,
     },
+    /// Addresses allowed to manage `owner`'s offers, returned as a `Vec<Addr>`.
+    Operators {
+        owner: String,
+    },
+    /// Number of trades `address` took part in that were closed by a
+    /// release, returned as a `u64`.
+    CompletedTrades {
+        address: String,
+    },
+    /// Released trades of `addr` per role and its disputes, returned as
+    /// `TraderStats`, zeroed for addresses without any.
+    TraderStats {
+        addr: String,
+    },
+    /// Everything a profile page shows about `addr`, returned as a
+    /// `ProfileResponse`, zeroed for addresses without any activity.
+    Profile {
+        addr: String,
+    },
+    /// Fees a trade of `amount` on the offer would pay at the current
+    /// factory config, returned as a `TradeSimulation`. Fails like `NewTrade`
+    /// for amounts outside the offer's bounds. `amount` is in the offer's
+    /// denomination, the simulation is in units of the asset.
+    SimulateTrade {
+        offer_id: u64,
+        amount: Uint128,
+    },
+    /// Trades of `user` in the role of `index`, paginated by trade address
+    /// and returned as a `TradesResponse`.
     TradesQuery {
         user: Addr,
         state: Option<TradeState>,
@@ -146,30 +524,603 @@ pub enum QueryMsg {
         last_value: Option<Addr>,
         limit: u32,
     },
+    /// Trades on `maker`'s offers newest first, returned as a
+    /// `TradesPageResponse`. `last_value` is the `TradeInfo::page_cursor` of
+    /// the last trade of the previous page, trades created meanwhile don't
+    /// shift pages.
+    TradesPage {
+        maker: String,
+        last_value: Option<Vec<u8>>,
+        limit: u32,
+    },
+    /// Trades `taker` opened on other users' offers, paginated by trade
+    /// address and returned as a `TradesResponse` like `TradesQuery`.
+    TradesAsTaker {
+        taker: String,
+        last_value: Option<Addr>,
+        limit: u32,
+    },
+    /// Trades across every offer currently in `state`, paginated by trade
+    /// address and returned as a `TradesResponse`.
+    TradesByState {
+        state: TradeState,
+        last_value: Option<Addr>,
+        limit: u32,
+    },
+    /// Active offers and liquidity per pair and side, returned as a
+    /// `Vec<FiatOffersSummary>` ordered by currency and denom.
+    OffersSummary {},
+    /// Up to `count` listed active offers picked by random id probes derived
+    /// from `seed`, the same seed always returns the same offers while
+    /// storage is unchanged. Returned as a `Vec<Offer>`, never more than
+    /// `MAX_PAGE_LIMIT`.
+    OffersSample {
+        fiat_currency: Option<FiatCurrency>,
+        count: u8,
+        seed: u64,
+    },
+    /// The `fiat_currencies` allowlist of the config with the decimals of
+    /// each currency, returned as a `Vec<FiatCurrencyInfo>`.
+    SupportedCurrencies {},
+    /// Blocked addresses ordered by address, returned as a
+    /// `BlacklistResponse`.
+    Blacklist {
+        last_value: Option<Addr>,
+        limit: u32,
+    },
+    /// Price of the last released trade in `fiat_currency`, returned as an
+    /// `Option<LastTradedPrice>`. Only floating price offers agree on a
+    /// price, refunded and settled trades never update it.
+    LastTradedPrice {
+        fiat_currency: FiatCurrency,
+    },
+    /// Every stored offer as is, archived ones and maker contacts included,
+    /// in id order past `start_after`. Returned as an `OffersExport` for
+    /// tooling dumping the whole store, not for listings.
+    ExportOffers {
+        start_after: Option<u64>,
+        limit: u32,
+    },
+    /// Every registered trade as is in trade address order past
+    /// `start_after`, returned as a `TradesExport`.
+    ExportTrades {
+        start_after: Option<Addr>,
+        limit: u32,
+    },
 }
 
 ///Data
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
+    /// Queried for the factory config, trade code and IBC denoms.
     pub factory_addr: Addr,
+    /// Changes the config, pauses the contract and blocks addresses, handed
+    /// over with `ProposeNewAdmin` and `AcceptAdmin`. Configs stored before
+    /// it existed get `factory_addr` when migrated.
+    #[serde(default = "default_addr")]
+    pub admin: Addr,
+    /// Maximum of active and paused offers per owner, `0` means unlimited.
+    #[serde(default)]
+    pub max_offers_per_owner: u32,
+    /// Queried for the fiat rate of offers with a `price_margin_bps`.
+    pub price_oracle_addr: Option<Addr>,
+    /// Native denoms offers can be created for.
+    #[serde(default = "default_denoms")]
+    pub denoms: Vec<String>,
+    /// Seconds new trades stay open before their escrow can be refunded.
+    #[serde(default = "default_trade_duration")]
+    pub trade_duration: u64,
+    /// Assigned to new trades in currencies without arbitrators registered
+    /// on the factory, settles their disputes. Without either new trades are
+    /// refused.
+    #[serde(default)]
+    pub arbitrator: Option<Addr>,
+    /// Proposed by the current admin, becomes `admin` once it accepts.
+    #[serde(default)]
+    pub pending_admin: Option<Addr>,
+    /// Ceiling on offer max amounts and trades, `0` means unlimited.
+    #[serde(default)]
+    pub max_trade_amount: Uint128,
+    /// Currencies offers can be created in, configs stored before the list
+    /// existed get every code of the former `FiatCurrency` enum.
+    #[serde(default = "default_fiat_currencies")]
+    pub fiat_currencies: Vec<FiatCurrency>,
+    /// Trades an offer can have open at once, `0` means unlimited.
+    #[serde(default)]
+    pub max_concurrent_trades: u8,
+    /// Seconds past their expiration new trades can still be disputed.
+    #[serde(default = "default_dispute_window")]
+    pub dispute_window: u64,
+    /// Trades an address can open within one `new_trade_window`, trades closing
+    /// early don't give any back. `0` for either means unlimited.
+    #[serde(default)]
+    pub new_trade_limit: u32,
+    /// Seconds of the block time buckets new trades are counted in.
+    #[serde(default = "default_new_trade_window")]
+    pub new_trade_window: u64,
+    /// Lets owners `Delete` offers that had trades, once those are closed.
+    /// Off by default so that trades keep pointing to an existing offer.
+    #[serde(default)]
+    pub allow_delete_traded: bool,
+    /// Bound on how many times `min_amount` an offer's `max_amount` can be,
+    /// `0` means unbounded.
+    #[serde(default)]
+    pub max_amount_ratio: u64,
+    /// Seconds since the oracle's `updated_at` past which its rate no longer
+    /// prices new trades or simulations.
+    #[serde(default = "default_max_price_age")]
+    pub max_price_age: u64,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
 pub struct State {
     pub offers_count: u64,
+    /// Set by the factory to halt offer writes and new trades.
+    #[serde(default)]
+    pub paused: bool,
+}
+
+/// Mints the id of a new offer, the only place `State::offers_count` is
+/// bumped. A missing `State` is initialized as if no offer was created yet.
+pub fn next_offer_id(storage: &mut dyn Storage) -> StdResult<u64> {
+    let mut store = singleton::<State>(storage, OFFER_STATE_KEY);
+    let mut state = store.may_load()?.unwrap_or_default();
+    state.offers_count = state
+        .offers_count
+        .checked_add(1)
+        .ok_or_else(|| OverflowError::new(OverflowOperation::Add, state.offers_count, 1))?;
+    store.save(&state)?;
+    Ok(state.offers_count)
+}
+
+/// Counters are maintained on every offer write, expiration is time based and
+/// is not reflected in them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FiatOfferCounts {
+    pub fiat_currency: FiatCurrency,
+    pub active: u64,
+    pub paused: u64,
+}
+
+/// Entry of the `OffersSummary` query, the liquidity of a side is the sum of
+/// its offers' `max_amount`s. Maintained like `FiatOfferCounts`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FiatOffersSummary {
+    pub fiat_currency: FiatCurrency,
+    pub denom: String,
+    pub buy_count: u64,
+    pub sell_count: u64,
+    pub buy_liquidity: Uint128,
+    pub sell_liquidity: Uint128,
+}
+
+/// Only trades closed by a release count as trades, refunds and disputes
+/// don't.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct TraderStats {
+    pub trades_as_maker: u64,
+    pub trades_as_taker: u64,
+    /// Block time of the last release.
+    pub last_trade_at: Timestamp,
+    /// Disputed trades the address was a party to, counted as the dispute
+    /// is opened.
+    #[serde(default)]
+    pub disputes_opened: u64,
+    /// Disputes settled by the arbitrator against the address.
+    #[serde(default)]
+    pub disputes_lost: u64,
+    #[serde(default)]
+    pub disputes_won: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProfileResponse {
+    pub stats: TraderStats,
+    /// Active and paused offers, see `owner_offer_counts`.
+    pub offers_count: u64,
+    /// Up to `PROFILE_OFFERS_PREVIEW` offers newest first, archived ones
+    /// included.
+    pub recent_offers: Vec<Offer>,
+    /// Open trades the address is the maker or taker of, counted as trades
+    /// are registered and closed. Trades without a `TradeAddr::maker` only
+    /// count for their taker.
+    pub open_trades: u64,
+}
+
+/// Breakdown of a trade's protocol fees, Terra's stability tax on `uusd`
+/// transfers comes on top of them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TradeSimulation {
+    pub offer_id: u64,
+    pub asset: OfferAsset,
+    pub amount: Uint128,
+    pub maker_fee_bps: u64,
+    pub taker_fee_bps: u64,
+    pub fee_burn_bps: u64,
+    pub maker_fee_amount: Uint128,
+    pub taker_fee_amount: Uint128,
+    /// Maker and taker fees together.
+    pub fee_amount: Uint128,
+    /// Part of `fee_amount` burned, the rest goes to the treasury.
+    pub burn_amount: Uint128,
+    /// Funded by the seller, sell offers escrow the maker fee on top of
+    /// `amount` along with the tax on releasing both.
+    pub escrow_amount: Uint128,
+    /// Released to the buyer, `amount` minus the taker fee, the maker fee of
+    /// buy offers and the tax on the transfers.
+    pub receive_amount: Uint128,
+}
+
+/// `total` counts every stored offer, kept up to date as offers are stored
+/// and deleted.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OffersExport {
+    pub offers: Vec<(u64, Offer)>,
+    /// `start_after` of the following page, `None` once the export is done.
+    pub next: Option<u64>,
+    /// `page_limit` of the requested `limit`, the most entries a page holds.
+    pub limit: u32,
+    pub total: u64,
+}
+
+/// `total` counts every registered trade, kept up to date as trades are
+/// registered.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TradesExport {
+    pub trades: Vec<TradeAddr>,
+    /// `start_after` of the following page, `None` once the export is done.
+    pub next: Option<Addr>,
+    /// `page_limit` of the requested `limit`, the most entries a page holds.
+    pub limit: u32,
+    pub total: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OffersPageResponse {
+    pub offers: Vec<Offer>,
+    /// `last_value` of the following page, or `start_before` for descending
+    /// `OffersPage` queries, `None` once the listing is exhausted. Sparse
+    /// matches can leave pages short or empty before that.
+    pub next: Option<u64>,
+    /// `page_limit` of the requested `limit`, the most entries a page holds.
+    pub limit: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TradesPageResponse {
+    pub trades: Vec<TradeInfo>,
+    /// `last_value` of the following page, the `TradeInfo::page_cursor` of
+    /// the last trade, `None` once the listing is exhausted.
+    pub next: Option<Vec<u8>>,
+    /// `page_limit` of the requested `limit`, the most entries a page holds.
+    pub limit: u32,
+}
+
+/// Page of the trades queries paginated by trade address.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TradesResponse {
+    pub trades: Vec<TradeInfo>,
+    /// `last_value` of the following page, `None` once the listing is
+    /// exhausted.
+    pub next: Option<Addr>,
+    /// `page_limit` of the requested `limit`, the most entries a page holds.
+    pub limit: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BlacklistResponse {
+    pub addrs: Vec<Addr>,
+    /// `last_value` of the following page, `None` once the listing is
+    /// exhausted.
+    pub next: Option<Addr>,
+    /// `page_limit` of the requested `limit`, the most entries a page holds.
+    pub limit: u32,
+}
+
+/// Filters of the `OffersFiltered` query, `None` matches any value.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct OffersFilters {
+    pub owner: Option<Addr>,
+    pub fiat_currency: Option<FiatCurrency>,
+    pub offer_type: Option<OfferType>,
+    pub state: Option<OfferState>,
+}
+
+impl OffersFilters {
+    pub fn matches(&self, offer: &Offer) -> bool {
+        self.owner
+            .as_ref()
+            .is_none_or(|owner| &offer.owner == owner)
+            && self
+                .fiat_currency
+                .as_ref()
+                .is_none_or(|fiat| &offer.fiat_currency == fiat)
+            && self
+                .offer_type
+                .as_ref()
+                .is_none_or(|offer_type| &offer.offer_type == offer_type)
+            && self
+                .state
+                .as_ref()
+                .is_none_or(|state| &offer.state == state)
+    }
+}
+
+/// Storage walked by an `OffersFiltered` query, the remaining filters are
+/// checked on each offer read.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OffersFilterIndex {
+    /// Type, currency and state are all given.
+    Filter,
+    Owner,
+    FiatCurrency,
+    OfferType,
+    /// Every offer by id.
+    Id,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OffersFilteredResponse {
+    pub offers: Vec<Offer>,
+    /// `last_value` of the next page, `None` once storage is exhausted. Sparse
+    /// matches can leave pages short or empty before that.
+    pub next: Option<u64>,
+    /// `page_limit` of the requested `limit`, the most entries a page holds.
+    pub limit: u32,
+    /// The filters as applied, with the owner validated.
+    pub filters: OffersFilters,
+    pub index: OffersFilterIndex,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StateResponse {
+    pub offers_count: u64,
+    /// Id of the newest offer, `None` until the first one is created.
+    pub last_offer_id: Option<u64>,
+    pub paused: bool,
+    pub active_offers_count: u64,
+    pub paused_offers_count: u64,
+    pub fiat_offers_counts: Vec<FiatOfferCounts>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Offer {
     pub id: u64,
     pub owner: Addr,
-    pub maker_contact: String,
+    /// Only returned by the `Offer { id }` query and copied into the trades
+    /// spawned from the offer, listing queries leave it out.
+    pub maker_contact: Option<String>,
     pub offer_type: OfferType,
     pub fiat_currency: FiatCurrency,
     pub min_amount: Uint128,
     pub max_amount: Uint128,
     pub state: OfferState,
     pub timestamp: u64,
+    pub expires_at: Option<Timestamp>,
+    pub price_margin_bps: Option<i32>,
+    #[serde(default)]
+    pub payment_methods: Vec<String>,
+    pub description: Option<String>,
+    /// Offers stored before the field existed read as native `DEFAULT_DENOM`.
+    #[serde(default)]
+    pub asset: OfferAsset,
+    /// Trades spawned from the offer that did not reach a final state yet.
+    #[serde(default)]
+    pub active_trades: u64,
+    /// Sum of the amounts of those trades, taken out of `max_amount`.
+    #[serde(default)]
+    pub reserved_amount: Uint128,
+    /// See `OfferMsg::total_available`, `remaining` is what is left of it
+    /// after released trades.
+    #[serde(default)]
+    pub total_available: Uint128,
+    #[serde(default)]
+    pub remaining: Uint128,
+    /// Both read as `0` for offers stored before the fields existed, until
+    /// the migration backfills them.
+    #[serde(default)]
+    pub created_at: Timestamp,
+    /// Bumped by every maker update and state change.
+    #[serde(default)]
+    pub last_updated: Timestamp,
+    pub min_trades_completed: Option<u64>,
+    /// See `OfferMsg::activate_at`.
+    #[serde(default)]
+    pub activate_at: Option<Timestamp>,
+    #[serde(default)]
+    pub pause_at: Option<Timestamp>,
+    /// See `OfferMsg::amounts_in_fiat`, amounts of offers stored before the
+    /// field existed are in units of the asset.
+    #[serde(default)]
+    pub amounts_in_fiat: bool,
+    /// See `OfferMsg::available_from_hour`.
+    #[serde(default)]
+    pub available_from_hour: Option<u8>,
+    #[serde(default)]
+    pub available_to_hour: Option<u8>,
+    /// `state_at` the block time of the query returning the offer, `None` in
+    /// storage. Offer counts and the type and currency index keep following
+    /// the stored `state`.
+    #[serde(default)]
+    pub effective_state: Option<OfferState>,
+    /// `is_available_at` the block time of the query returning the offer,
+    /// `None` in storage.
+    #[serde(default)]
+    pub currently_available: Option<bool>,
+}
+
+impl Offer {
+    pub fn is_expired(&self, block_time: Timestamp) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= block_time)
+    }
+
+    /// What the offer trades, its asset against its fiat currency.
+    pub fn pair(&self) -> OfferPair {
+        OfferPair {
+            denom: self.asset.to_string(),
+            fiat: self.fiat_currency.clone(),
+        }
+    }
+
+    /// Applies the offer's margin to the oracle `rate`, `None` for offers
+    /// without a floating price. Margins stored before they were bounded can
+    /// fall outside of `assert_price_margin` and fail instead.
+    pub fn price(&self, rate: Decimal) -> Result<Option<Decimal>, OverflowError> {
+        let price_margin_bps = match self.price_margin_bps {
+            Some(price_margin_bps) => price_margin_bps,
+            None => return Ok(None),
+        };
+        let factor = 10_000i32
+            .checked_add(price_margin_bps)
+            .and_then(|factor| u128::try_from(factor).ok())
+            .ok_or_else(|| OverflowError::new(OverflowOperation::Add, 10_000, price_margin_bps))?;
+        let scaled = (DECIMAL_FRACTION * rate).multiply_ratio(factor, 10_000u128);
+        Ok(Some(Decimal::from_ratio(scaled, DECIMAL_FRACTION)))
+    }
+
+    /// Terms of the offer as they were created or last updated, `total_available`
+    /// is the original total rather than what remains of it.
+    pub fn to_msg(&self) -> OfferMsg {
+        OfferMsg {
+            offer_type: self.offer_type.clone(),
+            fiat_currency: self.fiat_currency.clone(),
+            min_amount: self.min_amount,
+            max_amount: self.max_amount,
+            maker_contact: self.maker_contact.clone(),
+            expires_at: self.expires_at,
+            price_margin_bps: self.price_margin_bps,
+            payment_methods: self.payment_methods.clone(),
+            description: self.description.clone(),
+            asset: self.asset.clone(),
+            min_trades_completed: self.min_trades_completed,
+            total_available: self.total_available,
+            activate_at: self.activate_at,
+            pause_at: self.pause_at,
+            amounts_in_fiat: self.amounts_in_fiat,
+            available_from_hour: self.available_from_hour,
+            available_to_hour: self.available_to_hour,
+        }
+    }
+
+    /// State the offer is in at `block_time` given its schedule: active from
+    /// `activate_at`, paused before it and from `pause_at`. Offers without a
+    /// schedule, or before `pause_at` without an `activate_at`, are in their
+    /// stored state, archived offers stay archived.
+    pub fn state_at(&self, block_time: Timestamp) -> OfferState {
+        if self.state == OfferState::Archived {
+            return OfferState::Archived;
+        }
+        if self.pause_at.is_some_and(|pause_at| pause_at <= block_time) {
+            return OfferState::Paused;
+        }
+        match self.activate_at {
+            Some(activate_at) if activate_at <= block_time => OfferState::Active,
+            Some(_) => OfferState::Paused,
+            None => self.state.clone(),
+        }
+    }
+
+    /// Whether `block_time` falls in the offer's trading hours, see
+    /// `OfferMsg::available_from_hour`.
+    pub fn is_available_at(&self, block_time: Timestamp) -> bool {
+        let hour = (block_time.seconds() / 3600 % 24) as u8;
+        match (self.available_from_hour, self.available_to_hour) {
+            (Some(from), Some(to)) if from < to => from <= hour && hour < to,
+            (Some(from), Some(to)) => from <= hour || hour < to,
+            _ => true,
+        }
+    }
+
+    /// Fills in `effective_state` and `currently_available` for a query
+    /// response.
+    pub fn with_effective_state(mut self, block_time: Timestamp) -> Offer {
+        self.effective_state = Some(self.state_at(block_time));
+        self.currently_available = Some(self.is_available_at(block_time));
+        self
+    }
+
+    fn into_listing(mut self, block_time: Timestamp) -> Offer {
+        self.maker_contact = None;
+        self.with_effective_state(block_time)
+    }
+
+    /// Part of `max_amount`, and of `remaining` for offers with a
+    /// `total_available`, not reserved by open trades.
+    pub fn available_amount(&self) -> Uint128 {
+        let ceiling = if self.total_available.is_zero() {
+            self.max_amount
+        } else {
+            self.max_amount.min(self.remaining)
+        };
+        ceiling.saturating_sub(self.reserved_amount)
+    }
+
+    /// Whether the offer can still take a trade of at least `min_amount`.
+    pub fn has_liquidity(&self) -> bool {
+        self.available_amount() >= self.min_amount
+    }
+
+    /// Whether the offer should show up in listing queries, offers whose
+    /// liquidity is reserved by open trades are left out until it frees up.
+    pub fn is_listed(&self, block_time: Timestamp) -> bool {
+        self.state != OfferState::Archived && !self.is_expired(block_time) && self.has_liquidity()
+    }
+}
+
+/// Crypto/fiat pair of an offer, `denom` is a native denom or a cw20 address.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OfferPair {
+    pub denom: String,
+    pub fiat: FiatCurrency,
+}
+
+/// Optional predicates of the listing queries, applied before the page limit
+/// so that a page is only short once storage is exhausted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OfferFilter {
+    pub offer_type: Option<OfferType>,
+    pub payment_method: Option<String>,
+    /// Only offers updated at or after this time.
+    pub updated_since: Option<Timestamp>,
+    /// Only offers of this native denom or cw20 address.
+    pub denom: Option<String>,
+    /// Only offers that can take a trade of exactly this amount, within
+    /// their bounds and what's still available.
+    pub amount: Option<Uint128>,
+    /// Only offers taking a trade somewhere between `min_amount` and
+    /// `max_amount`, either bound can be left open.
+    pub min_amount: Option<Uint128>,
+    pub max_amount: Option<Uint128>,
+}
+
+impl OfferFilter {
+    pub fn matches(&self, offer: &Offer) -> bool {
+        self.offer_type
+            .as_ref()
+            .is_none_or(|offer_type| &offer.offer_type == offer_type)
+            && self
+                .payment_method
+                .as_ref()
+                .is_none_or(|method| offer.payment_methods.contains(method))
+            && self
+                .updated_since
+                .is_none_or(|since| offer.last_updated >= since)
+            && self
+                .denom
+                .as_ref()
+                .is_none_or(|denom| &offer.asset.to_string() == denom)
+            && self.amount.is_none_or(|amount| {
+                offer.min_amount <= amount && amount <= offer.available_amount()
+            })
+            && self
+                .min_amount
+                .is_none_or(|min_amount| offer.available_amount() >= min_amount)
+            && self
+                .max_amount
+                .is_none_or(|max_amount| offer.min_amount <= max_amount)
+    }
 }
 
 pub struct OfferModel<'a> {
@@ -178,218 +1129,838 @@ pub struct OfferModel<'a> {
 }
 
 impl OfferModel<'_> {
+    /// Offers moving to another currency are moved to its prefix.
     pub fn store(storage: &mut dyn Storage, offer: &Offer) -> StdResult<()> {
-        offers().save(storage, &offer.id.to_string(), &offer)
+        match OfferModel::load(storage, offer.id)? {
+            Some(previous) => {
+                OfferModel::count(storage, &previous, false)?;
+                if previous.fiat_currency != offer.fiat_currency {
+                    let previous_fiat = previous.fiat_currency.to_string();
+                    offers().remove(storage, (previous_fiat.as_str(), U64Key::new(offer.id)))?;
+                }
+            }
+            None => {
+                let stored = stored_offers().may_load(storage)?.unwrap_or_default();
+                stored_offers().save(storage, &(stored + 1))?;
+            }
+        }
+        OfferModel::count(storage, offer, true)?;
+        let fiat = offer.fiat_currency.to_string();
+        offer_fiat_currencies().save(storage, U64Key::new(offer.id), &offer.fiat_currency)?;
+        offers().save(storage, (fiat.as_str(), U64Key::new(offer.id)), offer)
     }
 
-    pub fn from_store(storage: &mut dyn Storage, id: &u64) -> Offer {
-        offers()
-            .may_load(storage, &id.to_string())
-            .unwrap_or_default()
-            .unwrap()
+    /// Reads an offer by id through its recorded currency.
+    pub fn load(storage: &dyn Storage, id: u64) -> StdResult<Option<Offer>> {
+        match offer_fiat_currencies().may_load(storage, U64Key::new(id))? {
+            Some(fiat) => offers().may_load(storage, (fiat.as_str(), U64Key::new(id))),
+            None => Ok(None),
+        }
+    }
+
+    /// Adds or removes `offer` from the counters of its currency and state.
+    fn count(storage: &mut dyn Storage, offer: &Offer, add: bool) -> StdResult<()> {
+        if offer.state == OfferState::Active {
+            OfferModel::summarize(storage, offer, add)?;
+        }
+        if offer.state != OfferState::Archived {
+            let owner_count = owner_offer_counts()
+                .may_load(storage, &offer.owner)?
+                .unwrap_or(0);
+            let owner_count = if add {
+                owner_count + 1
+            } else {
+                owner_count.saturating_sub(1)
+            };
+            if owner_count == 0 {
+                owner_offer_counts().remove(storage, &offer.owner);
+            } else {
+                owner_offer_counts().save(storage, &offer.owner, &owner_count)?;
+            }
+        }
+
+        let key = offer.fiat_currency.to_string();
+        let mut counts = offer_counts()
+            .may_load(storage, &key)?
+            .unwrap_or(FiatOfferCounts {
+                fiat_currency: offer.fiat_currency.clone(),
+                active: 0,
+                paused: 0,
+            });
+        let count = match offer.state {
+            OfferState::Active => &mut counts.active,
+            OfferState::Paused => &mut counts.paused,
+            OfferState::Archived => return Ok(()),
+        };
+        if add {
+            *count += 1;
+        } else {
+            *count = count.saturating_sub(1);
+        }
+
+        if counts.active == 0 && counts.paused == 0 {
+            offer_counts().remove(storage, &key);
+            Ok(())
+        } else {
+            offer_counts().save(storage, &key, &counts)
+        }
+    }
+
+    /// Adds or removes the active `offer` from the summary of its pair.
+    fn summarize(storage: &mut dyn Storage, offer: &Offer, add: bool) -> StdResult<()> {
+        let pair = offer.pair();
+        let fiat_key = pair.fiat.to_string();
+        let key = (fiat_key.as_str(), pair.denom.as_str());
+        let mut summary = offer_summaries()
+            .may_load(storage, key)?
+            .unwrap_or(FiatOffersSummary {
+                fiat_currency: pair.fiat.clone(),
+                denom: pair.denom.clone(),
+                buy_count: 0,
+                sell_count: 0,
+                buy_liquidity: Uint128::zero(),
+                sell_liquidity: Uint128::zero(),
+            });
+        let (count, liquidity) = match offer.offer_type {
+            OfferType::Buy => (&mut summary.buy_count, &mut summary.buy_liquidity),
+            OfferType::Sell => (&mut summary.sell_count, &mut summary.sell_liquidity),
+        };
+        if add {
+            *count += 1;
+            *liquidity = liquidity.checked_add(offer.max_amount)?;
+        } else {
+            *count = count.saturating_sub(1);
+            *liquidity = Uint128::new(liquidity.u128().saturating_sub(offer.max_amount.u128()));
+        }
+
+        if summary.buy_count == 0 && summary.sell_count == 0 {
+            offer_summaries().remove(storage, key);
+            Ok(())
+        } else {
+            offer_summaries().save(storage, key, &summary)
+        }
     }
 
-    pub fn create(storage: &mut dyn Storage, offer: Offer) -> OfferModel {
+    /// Recomputes every counter from the stored offers, used on migration since
+    /// offers written before the counters existed are not accounted for.
+    pub fn recount(storage: &mut dyn Storage) -> StdResult<()> {
+        let stale: Vec<FiatOfferCounts> = offer_counts()
+            .range(storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, counts)| counts))
+            .collect::<StdResult<Vec<_>>>()?;
+        for counts in stale.iter() {
+            offer_counts().remove(storage, &counts.fiat_currency.to_string());
+        }
+        let stale_summaries: Vec<FiatOffersSummary> = offer_summaries()
+            .range(storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, summary)| summary))
+            .collect::<StdResult<Vec<_>>>()?;
+        for summary in stale_summaries.iter() {
+            let fiat_key = summary.fiat_currency.to_string();
+            offer_summaries().remove(storage, (fiat_key.as_str(), summary.denom.as_str()));
+        }
+        let stale_owners: Vec<Addr> = owner_offer_counts()
+            .keys(storage, None, None, Order::Ascending)
+            .map(|key| Addr::unchecked(String::from_utf8_lossy(&key)))
+            .collect();
+        for owner in stale_owners.iter() {
+            owner_offer_counts().remove(storage, owner);
+        }
+
+        let all: Vec<Offer> = offers()
+            .range(storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, offer)| offer))
+            .collect::<StdResult<Vec<_>>>()?;
+        for offer in all.iter() {
+            OfferModel::count(storage, offer, true)?;
+        }
+        stored_offers().save(storage, &(all.len() as u64))
+    }
+
+    pub fn owner_count(storage: &dyn Storage, owner: &Addr) -> StdResult<u64> {
+        Ok(owner_offer_counts().may_load(storage, owner)?.unwrap_or(0))
+    }
+
+    pub fn query_counts(storage: &dyn Storage) -> StdResult<Vec<FiatOfferCounts>> {
+        offer_counts()
+            .range(storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, counts)| counts))
+            .collect()
+    }
+
+    pub fn query_summaries(storage: &dyn Storage) -> StdResult<Vec<FiatOffersSummary>> {
+        offer_summaries()
+            .range(storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, summary)| summary))
+            .collect()
+    }
+
+    pub fn from_store(storage: &dyn Storage, id: &u64) -> Result<Offer, OfferError> {
+        OfferModel::load(storage, *id)?.ok_or(OfferError::NotFound { id: *id })
+    }
+
+    pub fn create(storage: &mut dyn Storage, offer: Offer) -> OfferModel<'_> {
         OfferModel::store(storage, &offer).unwrap();
         OfferModel { offer, storage }
     }
 
-    pub fn save<'a>(self) -> Offer {
+    /// Removes the offer and takes it out of its counters, its id is never
+    /// handed out again.
+    pub fn delete(self) -> Result<Offer, OfferError> {
+        self.assert_no_active_trades()?;
+        OfferModel::count(self.storage, &self.offer, false)?;
+        let fiat = self.offer.fiat_currency.to_string();
+        offers().remove(self.storage, (fiat.as_str(), U64Key::new(self.offer.id)))?;
+        offer_fiat_currencies().remove(self.storage, U64Key::new(self.offer.id));
+        let stored = stored_offers().may_load(self.storage)?.unwrap_or_default();
+        stored_offers().save(self.storage, &stored.saturating_sub(1))?;
+        Ok(self.offer)
+    }
+
+    pub fn save(self) -> Offer {
         OfferModel::store(self.storage, &self.offer).unwrap();
         self.offer
     }
 
-    pub fn may_load<'a>(storage: &'a mut dyn Storage, id: &u64) -> OfferModel<'a> {
+    pub fn may_load<'a>(
+        storage: &'a mut dyn Storage,
+        id: &u64,
+    ) -> Result<OfferModel<'a>, OfferError> {
         let offer_model = OfferModel {
-            offer: OfferModel::from_store(storage, &id),
+            offer: OfferModel::from_store(storage, id)?,
             storage,
         };
-        return offer_model;
+        Ok(offer_model)
     }
 
-    pub fn activate(&mut self) -> Result<&Offer, OfferError> {
+    pub fn activate(&mut self, block_time: Timestamp) -> Result<&Offer, OfferError> {
         match self.offer.state {
             OfferState::Paused => {
                 self.offer.state = OfferState::Active;
+                self.offer.last_updated = block_time;
                 OfferModel::store(self.storage, &self.offer).unwrap();
                 Ok(&self.offer)
             }
-            OfferState::Active => Err(OfferError::InvalidStateChange {
+            OfferState::Active | OfferState::Archived => Err(OfferError::InvalidStateChange {
                 from: self.offer.state.clone(),
                 to: OfferState::Active,
             }),
         }
     }
 
-    pub fn pause(&mut self) -> Result<&Offer, OfferError> {
+    pub fn pause(&mut self, block_time: Timestamp) -> Result<&Offer, OfferError> {
         match self.offer.state {
             OfferState::Active => {
                 self.offer.state = OfferState::Paused;
+                self.offer.last_updated = block_time;
                 OfferModel::store(self.storage, &self.offer).unwrap();
                 Ok(&self.offer)
             }
-            OfferState::Paused => Err(OfferError::InvalidStateChange {
+            OfferState::Paused | OfferState::Archived => Err(OfferError::InvalidStateChange {
                 from: self.offer.state.clone(),
                 to: OfferState::Paused,
             }),
         }
     }
 
-    pub fn update(&mut self, msg: OfferMsg) -> &Offer {
+    /// Archived offers are retired for good, there is no transition out of it.
+    pub fn archive(&mut self, block_time: Timestamp) -> Result<&Offer, OfferError> {
+        self.assert_no_active_trades()?;
+        match self.offer.state {
+            OfferState::Active | OfferState::Paused => {
+                self.offer.state = OfferState::Archived;
+                self.offer.last_updated = block_time;
+                OfferModel::store(self.storage, &self.offer).unwrap();
+                Ok(&self.offer)
+            }
+            OfferState::Archived => Err(OfferError::InvalidStateChange {
+                from: self.offer.state.clone(),
+                to: OfferState::Archived,
+            }),
+        }
+    }
+
+    /// Trades in progress were agreed against the current terms, so those
+    /// can't change under them.
+    pub fn update(&mut self, msg: OfferMsg, block_time: Timestamp) -> Result<&Offer, OfferError> {
+        self.assert_no_active_trades()?;
         self.offer.offer_type = msg.offer_type;
         self.offer.fiat_currency = msg.fiat_currency;
         self.offer.min_amount = msg.min_amount;
         self.offer.max_amount = msg.max_amount;
+        self.offer.expires_at = msg.expires_at;
+        self.offer.price_margin_bps = msg.price_margin_bps;
+        self.offer.payment_methods = msg.payment_methods;
+        self.offer.description = msg.description;
+        self.offer.maker_contact = msg.maker_contact;
+        self.offer.asset = msg.asset;
+        self.offer.min_trades_completed = msg.min_trades_completed;
+        self.offer.total_available = msg.total_available;
+        self.offer.remaining = msg.total_available;
+        self.offer.activate_at = msg.activate_at;
+        self.offer.pause_at = msg.pause_at;
+        self.offer.amounts_in_fiat = msg.amounts_in_fiat;
+        self.offer.available_from_hour = msg.available_from_hour;
+        self.offer.available_to_hour = msg.available_to_hour;
+        self.offer.last_updated = block_time;
         OfferModel::store(self.storage, &self.offer).unwrap();
-        &self.offer
+        Ok(&self.offer)
         // self.save()
         //     ^^^^ move occurs because `*self` has type `OfferModel<'_>`, which does not implement the `Copy` trait
     }
 
+    pub fn open_trade(&mut self, amount: Uint128) -> &Offer {
+        self.offer.active_trades += 1;
+        self.offer.reserved_amount += amount;
+        OfferModel::store(self.storage, &self.offer).unwrap();
+        &self.offer
+    }
+
+    /// Trades whose escrow went to the buyer, released or settled for them,
+    /// are taken out of `remaining`. The reservation of refunded, canceled
+    /// and seller settled trades returns to it untouched.
+    pub fn close_trade(&mut self, trade: &TradeAddr) -> &Offer {
+        self.offer.active_trades = self.offer.active_trades.saturating_sub(1);
+        let amount = trade.offer_amount();
+        self.offer.reserved_amount = self.offer.reserved_amount.saturating_sub(amount);
+        //Makers are the buyers of buy offers, takers of sell offers
+        let paid_to_buyer = match trade.state {
+            TradeState::Released => true,
+            TradeState::SettledForMaker => self.offer.offer_type == OfferType::Buy,
+            TradeState::SettledForTaker => self.offer.offer_type == OfferType::Sell,
+            _ => false,
+        };
+        if paid_to_buyer {
+            self.offer.remaining = self.offer.remaining.saturating_sub(amount);
+        }
+        OfferModel::store(self.storage, &self.offer).unwrap();
+        &self.offer
+    }
+
+    fn assert_no_active_trades(&self) -> Result<(), OfferError> {
+        match self.offer.active_trades {
+            0 => Ok(()),
+            count => Err(OfferError::HasActiveTrades { count }),
+        }
+    }
+
+    /// First page of `query_by_fiat` at the largest limit, without a cursor.
     pub fn query_all_offers(
         storage: &dyn Storage,
         fiat_currency: FiatCurrency,
+        filter: &OfferFilter,
+        order: QueryOrder,
+        block_time: Timestamp,
     ) -> StdResult<Vec<Offer>> {
-        let result: Vec<Offer> = offers()
-            .range(storage, None, None, Order::Ascending)
-            .flat_map(|item| item.and_then(|(_, offer)| Ok(offer)))
-            .filter(|offer| offer.fiat_currency == fiat_currency)
-            .collect();
+        let fiat = fiat_currency.to_string();
+        let range = offers()
+            .prefix(fiat.as_str())
+            .range(storage, None, None, order.into())
+            .map(|item| item.map(|(_, offer)| offer));
+        let (page, _) = scan_page(range, MAX_PAGE_LIMIT as usize, |offer| {
+            offer.is_listed(block_time) && filter.matches(offer)
+        })?;
+
+        Ok(page
+            .into_iter()
+            .map(|offer| offer.into_listing(block_time))
+            .collect())
+    }
+
+    /// Listed offers of every currency in id order, past `cursor`.
+    pub fn query_page(
+        storage: &dyn Storage,
+        block_time: Timestamp,
+        filter: &OfferFilter,
+        order: QueryOrder,
+        cursor: Option<u64>,
+        limit: u32,
+    ) -> StdResult<OffersPageResponse> {
+        let (min, max) = order.bounds_after(cursor);
+        let limit = page_limit(limit);
+
+        let range = offer_fiat_currencies()
+            .range(storage, min, max, order.into())
+            .map(|item| {
+                let (id, fiat) = item?;
+                offers().load(storage, (fiat.as_str(), U64Key::from(id)))
+            });
+        let page = scan_page(range, limit, |offer| {
+            offer.is_listed(block_time) && filter.matches(offer)
+        })?;
+
+        Ok(listing_page(page, limit, block_time))
+    }
+
+    /// Stored offers in id order past `start_after`, unfiltered.
+    pub fn query_export(
+        storage: &dyn Storage,
+        start_after: Option<u64>,
+        limit: u32,
+    ) -> StdResult<OffersExport> {
+        let limit = page_limit(limit);
+        let offers = offer_fiat_currencies()
+            .range(
+                storage,
+                start_after.map(Bound::exclusive_int),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .map(|item| {
+                let (id, fiat) = item?;
+                let offer = offers().load(storage, (fiat.as_str(), U64Key::from(id)))?;
+                Ok((offer.id, offer))
+            })
+            .collect::<StdResult<Vec<(u64, Offer)>>>()?;
+        let next = page_next(&offers, limit, |(id, _)| *id);
+        let total = stored_offers().may_load(storage)?.unwrap_or_default();
 
-        Ok(result)
+        Ok(OffersExport {
+            offers,
+            next,
+            limit: limit as u32,
+            total,
+        })
+    }
+
+    /// Probes `count * SAMPLE_PROBES_PER_OFFER` random ids at most, each
+    /// picking the first offer at or after the probed id, so that sampling
+    /// never walks the whole map. `max_id` is the highest id handed out.
+    pub fn query_sample(
+        storage: &dyn Storage,
+        block_time: Timestamp,
+        fiat_currency: Option<FiatCurrency>,
+        count: u8,
+        seed: u64,
+        max_id: u64,
+    ) -> StdResult<Vec<Offer>> {
+        let count = (count as u32).min(MAX_PAGE_LIMIT) as usize;
+        let fiat = fiat_currency.map(|fiat_currency| fiat_currency.to_string());
+        let mut sample: Vec<Offer> = vec![];
+        let mut state = seed;
+
+        for _ in 0..count * SAMPLE_PROBES_PER_OFFER {
+            if sample.len() == count || max_id == 0 {
+                break;
+            }
+            let id = next_random(&mut state) % max_id + 1;
+            let offer = match OfferModel::probe(storage, fiat.as_deref(), id)? {
+                Some(offer) => offer,
+                None => break,
+            };
+            if offer.state_at(block_time) == OfferState::Active
+                && offer.is_listed(block_time)
+                && !sample.iter().any(|sampled| sampled.id == offer.id)
+            {
+                sample.push(offer);
+            }
+        }
+
+        Ok(sample
+            .into_iter()
+            .map(|offer| offer.into_listing(block_time))
+            .collect())
+    }
+
+    /// First offer at or after `id`, wrapping around to the lowest id.
+    fn probe(storage: &dyn Storage, fiat: Option<&str>, id: u64) -> StdResult<Option<Offer>> {
+        let first_from = |min: Option<Bound>| -> StdResult<Option<Offer>> {
+            match fiat {
+                Some(fiat) => offers()
+                    .prefix(fiat)
+                    .range(storage, min, None, Order::Ascending)
+                    .next()
+                    .transpose()
+                    .map(|item| item.map(|(_, offer)| offer)),
+                None => match offer_fiat_currencies()
+                    .range(storage, min, None, Order::Ascending)
+                    .next()
+                    .transpose()?
+                {
+                    Some((key, stored)) => {
+                        offers().may_load(storage, (stored.as_str(), U64Key::from(key)))
+                    }
+                    None => Ok(None),
+                },
+            }
+        };
+        match first_from(Some(Bound::inclusive_int(id)))? {
+            Some(offer) => Ok(Some(offer)),
+            None => first_from(None),
+        }
     }
 
     pub fn query_by_type(
         deps: Deps,
+        block_time: Timestamp,
         offer_type: OfferType,
         last_value: Option<u64>,
         limit: u32,
-    ) -> StdResult<Vec<Offer>> {
+    ) -> StdResult<OffersPageResponse> {
         let storage = deps.storage;
+        let limit = page_limit(limit);
 
-        let range_from = match last_value {
-            Some(thing) => Some(Bound::Exclusive(Vec::from(thing.to_string()))),
-            None => None,
-        };
-
-        let result = offers()
+        let range = offers()
             .idx
             .offer_type
-            .prefix(offer_type.to_string())
-            .range(storage, range_from, None, Order::Ascending)
-            .take(limit as usize)
-            .flat_map(|item| item.and_then(|(_, offer)| Ok(offer)))
-            .collect();
+            .sub_prefix(offer_type.to_string())
+            .range(
+                storage,
+                index_bound_after(last_value),
+                None,
+                Order::Ascending,
+            )
+            .map(|item| item.map(|(_, offer)| offer));
+        let page = scan_page(range, limit, |offer| offer.is_listed(block_time))?;
 
-        Ok(result)
+        Ok(listing_page(page, limit, block_time))
     }
 
     pub fn query_by_type_fiat(
         deps: Deps,
+        block_time: Timestamp,
         offer_type: OfferType,
         fiat_currency: FiatCurrency,
         last_value: Option<u64>,
         limit: u32,
-    ) -> StdResult<Vec<Offer>> {
+    ) -> StdResult<OffersPageResponse> {
         let storage = deps.storage;
+        let limit = page_limit(limit);
 
-        let range_from = match last_value {
-            Some(thing) => Some(Bound::Exclusive(Vec::from(thing.to_string()))),
-            None => None,
-        };
+        // The prefix fixes the currency, so primary keys follow the ids
+        let fiat = fiat_currency.to_string();
+        let range_from =
+            last_value.map(|id| Bound::Exclusive((fiat.as_str(), U64Key::new(id)).joined_key()));
 
-        let result = offers()
+        let range = offers()
             .idx
             .filter
             .prefix((
                 offer_type.to_string(),
-                fiat_currency.to_string() + &*OfferState::Active.to_string(),
+                fiat.clone() + &*OfferState::Active.to_string(),
             ))
             .range(storage, range_from, None, Order::Ascending)
-            .take(limit as usize)
-            .flat_map(|item| item.and_then(|(_, offer)| Ok(offer)))
-            .collect();
+            .map(|item| item.map(|(_, offer)| offer));
+        let page = scan_page(range, limit, |offer| {
+            offer.state_at(block_time) == OfferState::Active && offer.is_listed(block_time)
+        })?;
 
-        Ok(result)
+        Ok(listing_page(page, limit, block_time))
     }
 
     pub fn query_by_fiat(
         deps: Deps,
+        block_time: Timestamp,
         fiat_currency: FiatCurrency,
+        filter: &OfferFilter,
+        order: QueryOrder,
         last_value: Option<u64>,
         limit: u32,
-    ) -> StdResult<Vec<Offer>> {
+    ) -> StdResult<OffersPageResponse> {
         let storage = deps.storage;
+        let limit = page_limit(limit);
+
+        let (min, max) = order.bounds_after(last_value);
+
+        let fiat = fiat_currency.to_string();
+        let range = offers()
+            .prefix(fiat.as_str())
+            .range(storage, min, max, order.into())
+            .map(|item| item.map(|(_, offer)| offer));
+        let page = scan_page(range, limit, |offer| {
+            offer.is_listed(block_time) && filter.matches(offer)
+        })?;
+
+        Ok(listing_page(page, limit, block_time))
+    }
 
-        let range_from = match last_value {
-            Some(thing) => Some(Bound::Exclusive(Vec::from(thing.to_string()))),
-            None => None,
+    /// Walks the most selective index the filters allow: the filter index
+    /// when type, currency and state are given, then the owner, currency and
+    /// type ones.
+    pub fn query_filtered(
+        storage: &dyn Storage,
+        block_time: Timestamp,
+        filters: OffersFilters,
+        last_value: Option<u64>,
+        limit: u32,
+    ) -> StdResult<OffersFilteredResponse> {
+        let fiat = filters.fiat_currency.as_ref().map(|fiat| fiat.to_string());
+        let (index, range): (
+            OffersFilterIndex,
+            Box<dyn Iterator<Item = StdResult<Offer>>>,
+        ) = match (&filters.owner, &fiat, &filters.offer_type, &filters.state) {
+            (_, Some(fiat), Some(offer_type), Some(state)) => {
+                // The prefix fixes the currency, so primary keys follow the ids
+                let range_from = last_value
+                    .map(|id| Bound::Exclusive((fiat.as_str(), U64Key::new(id)).joined_key()));
+                let range = offers()
+                    .idx
+                    .filter
+                    .prefix((offer_type.to_string(), fiat.clone() + &*state.to_string()))
+                    .range(storage, range_from, None, Order::Ascending)
+                    .map(|item| item.map(|(_, offer)| offer));
+                (OffersFilterIndex::Filter, Box::new(range))
+            }
+            (Some(owner), ..) => {
+                let range = offers()
+                    .idx
+                    .owner
+                    .sub_prefix(owner.clone())
+                    .range(
+                        storage,
+                        index_bound_after(last_value),
+                        None,
+                        Order::Ascending,
+                    )
+                    .map(|item| item.map(|(_, offer)| offer));
+                (OffersFilterIndex::Owner, Box::new(range))
+            }
+            (None, Some(fiat), ..) => {
+                let range = offers()
+                    .prefix(fiat.as_str())
+                    .range(
+                        storage,
+                        last_value.map(Bound::exclusive_int),
+                        None,
+                        Order::Ascending,
+                    )
+                    .map(|item| item.map(|(_, offer)| offer));
+                (OffersFilterIndex::FiatCurrency, Box::new(range))
+            }
+            (None, None, Some(offer_type), _) => {
+                let range = offers()
+                    .idx
+                    .offer_type
+                    .sub_prefix(offer_type.to_string())
+                    .range(
+                        storage,
+                        index_bound_after(last_value),
+                        None,
+                        Order::Ascending,
+                    )
+                    .map(|item| item.map(|(_, offer)| offer));
+                (OffersFilterIndex::OfferType, Box::new(range))
+            }
+            (None, None, None, _) => {
+                let range = offer_fiat_currencies()
+                    .range(
+                        storage,
+                        last_value.map(Bound::exclusive_int),
+                        None,
+                        Order::Ascending,
+                    )
+                    .map(move |item| {
+                        let (id, fiat) = item?;
+                        offers().load(storage, (fiat.as_str(), U64Key::from(id)))
+                    });
+                (OffersFilterIndex::Id, Box::new(range))
+            }
         };
 
-        let result = offers()
-            .idx
-            .fiat
-            .prefix(fiat_currency.to_string())
-            .range(storage, range_from, None, Order::Ascending)
-            .take(limit as usize)
-            .flat_map(|item| item.and_then(|(_, offer)| Ok(offer)))
+        let limit = page_limit(limit);
+        let (page, next) = scan_page(range, limit, |offer| filters.matches(offer))?;
+        let page = page
+            .into_iter()
+            .map(|offer| offer.into_listing(block_time))
             .collect();
 
-        Ok(result)
+        Ok(OffersFilteredResponse {
+            offers: page,
+            next,
+            limit: limit as u32,
+            filters,
+            index,
+        })
+    }
+
+    /// Every offer created by `owner`, regardless of its state or currency.
+    pub fn query_by_owner(
+        deps: Deps,
+        block_time: Timestamp,
+        owner: Addr,
+        last_value: Option<u64>,
+        limit: u32,
+    ) -> StdResult<OffersPageResponse> {
+        let storage = deps.storage;
+        let limit = page_limit(limit);
+
+        let range = offers()
+            .idx
+            .owner
+            .sub_prefix(owner)
+            .range(
+                storage,
+                index_bound_after(last_value),
+                None,
+                Order::Ascending,
+            )
+            .map(|item| item.map(|(_, offer)| offer));
+        let page = scan_page(range, limit, |_| true)?;
+
+        Ok(listing_page(page, limit, block_time))
+    }
+
+    /// The `PROFILE_OFFERS_PREVIEW` newest offers of `owner`.
+    pub fn query_recent_by_owner(
+        storage: &dyn Storage,
+        block_time: Timestamp,
+        owner: Addr,
+    ) -> StdResult<Vec<Offer>> {
+        offers()
+            .idx
+            .owner
+            .sub_prefix(owner)
+            .range(storage, None, None, Order::Descending)
+            .take(PROFILE_OFFERS_PREVIEW)
+            .map(|item| item.map(|(_, offer)| offer.into_listing(block_time)))
+            .collect()
     }
 
     pub fn query(
         deps: Deps,
+        block_time: Timestamp,
         owner: Option<Addr>,
+        filter: &OfferFilter,
         last_value: Option<u64>,
         limit: u32,
-    ) -> StdResult<Vec<Offer>> {
+    ) -> StdResult<OffersPageResponse> {
         let storage = deps.storage;
-        // let range: Box<dyn Iterator<Item = StdResult<Pair<Offer>>>>;
-
-        let range_from = match last_value {
-            Some(thing) => Some(Bound::Exclusive(Vec::from(thing.to_string()))),
-            None => None,
-        };
+        let limit = page_limit(limit);
 
-        // Handle optional owner address query parameter
-        let range = match owner {
-            None => offers().range(storage, range_from, None, Order::Ascending),
+        // Handle optional owner address query parameter, offers of every
+        // currency are walked in id order through their lookup entries
+        let range: Box<dyn Iterator<Item = StdResult<Offer>>> = match owner {
+            None => {
+                let range_from = last_value.map(Bound::exclusive_int);
+                Box::new(
+                    offer_fiat_currencies()
+                        .range(storage, range_from, None, Order::Ascending)
+                        .map(move |item| {
+                            let (id, fiat) = item?;
+                            offers().load(storage, (fiat.as_str(), U64Key::from(id)))
+                        }),
+                )
+            }
             Some(unchecked_addr) => {
                 let owner_addr = deps.api.addr_validate(unchecked_addr.as_str()).unwrap();
 
-                offers().idx.owner.prefix(owner_addr).range(
-                    storage,
-                    range_from,
-                    None,
-                    Order::Ascending,
+                Box::new(
+                    offers()
+                        .idx
+                        .owner
+                        .sub_prefix(owner_addr)
+                        .range(
+                            storage,
+                            index_bound_after(last_value),
+                            None,
+                            Order::Ascending,
+                        )
+                        .map(|item| item.map(|(_, offer)| offer)),
                 )
             }
         };
 
-        let result = range
-            .take(limit as usize)
-            .flat_map(|item| item.and_then(|(_, offer)| Ok(offer)))
-            .collect();
+        let page = scan_page(range, limit, |offer| {
+            offer.is_listed(block_time) && filter.matches(offer)
+        })?;
 
-        Ok(result)
+        Ok(listing_page(page, limit, block_time))
     }
 }
 
+/// Latest released trade of a currency with an agreed fiat price, see
+/// `QueryMsg::LastTradedPrice`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LastTradedPrice {
+    pub fiat_currency: FiatCurrency,
+    /// Fiat per unit of `denom`.
+    pub price: Decimal,
+    pub denom: String,
+    pub amount: Uint128,
+    /// Block time of the release report.
+    pub traded_at: Timestamp,
+}
+
+/// Response data of `NewTrade`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NewTradeResponse {
+    pub trade_addr: Addr,
+    pub offer_id: u64,
+    pub amount: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TradeInfo {
+    /// Contract to send the trade's execute messages to.
+    pub trade_addr: Addr,
+    pub offer_id: u64,
+    /// Block time the trade was registered at, see `TradeAddr::created_at`.
+    pub created_at: u64,
     pub trade: TradeData,
-    pub offer: Offer,
+    /// `None` once the offer was deleted, see `Config::allow_delete_traded`.
+    pub offer: Option<Offer>,
     pub expired: bool,
+    /// Escrowed amount, in the smallest unit of `denom`.
+    pub amount: Uint128,
+    /// Native denom or cw20 contract of the escrowed asset.
+    pub denom: String,
+    /// Counterparty of the offer owner, the trade's taker.
+    pub counterparty: Addr,
+    /// From then on the escrow can be refunded, see `TradeData::is_expired`.
+    pub expires_at: Timestamp,
+}
+
+impl TradeInfo {
+    /// `last_value` of the `TradesPage` following this trade, its
+    /// `created_at` as big-endian bytes followed by its address.
+    pub fn page_cursor(&self) -> Vec<u8> {
+        let mut cursor = self.created_at.to_be_bytes().to_vec();
+        cursor.extend_from_slice(self.trade_addr.as_bytes());
+        cursor
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TradeAddr {
     pub trade: Addr,
+    /// Trades registered before the field existed read as `0`.
+    #[serde(default)]
+    pub offer_id: u64,
+    /// Owner of the offer, empty for trades registered before the field
+    /// existed.
+    #[serde(default = "default_addr")]
+    pub maker: Addr,
     pub seller: Addr,
     pub buyer: Addr,
+    /// Counterparty of the offer owner, backfilled on migration for trades
+    /// registered before the field existed.
+    #[serde(default = "default_addr")]
+    pub taker: Addr,
     pub arbitrator: Addr,
     pub state: TradeState,
+    /// Block time of the instantiation reply, zero for trades registered
+    /// before the field existed.
+    #[serde(default)]
+    pub created_at: u64,
+    /// Reserved on the offer while the trade is open, zero for trades
+    /// registered before the field existed.
+    #[serde(default)]
+    pub amount: Uint128,
+    /// See `TradeData::fiat_price`, unset for trades registered before the
+    /// field existed.
+    #[serde(default)]
+    pub fiat_price: Option<Decimal>,
+    /// See `TradeData::fiat_amount`.
+    #[serde(default)]
+    pub fiat_amount: Option<Uint128>,
+}
+
+impl TradeAddr {
+    /// What the trade reserves on its offer, in the offer's denomination.
+    pub fn offer_amount(&self) -> Uint128 {
+        self.fiat_amount.unwrap_or(self.amount)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -409,9 +1980,127 @@ impl fmt::Display for OfferState {
     }
 }
 
+/// Asset traded by an offer and held in escrow by its trades.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OfferAsset {
+    Native { denom: String },
+    Cw20 { contract_addr: Addr },
+}
+
+impl Default for OfferAsset {
+    fn default() -> Self {
+        OfferAsset::Native {
+            denom: default_denom(),
+        }
+    }
+}
+
+impl fmt::Display for OfferAsset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OfferAsset::Native { denom } => write!(f, "{}", denom),
+            OfferAsset::Cw20 { contract_addr } => write!(f, "{}", contract_addr),
+        }
+    }
+}
+
+/// Direction in which listing queries walk offer ids.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// splitmix64 step, spreads the sample probes of a seed over the id space.
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Takes up to `limit` offers matching `matches`, visiting at most
+/// `limit * SCANNED_ENTRIES_PER_OFFER` entries of `range`. The cursor returned
+/// is the id of the last visited offer unless `range` ran out first, so pages
+/// cut short by sparse matches still move forward.
+fn scan_page(
+    range: impl Iterator<Item = StdResult<Offer>>,
+    limit: usize,
+    matches: impl Fn(&Offer) -> bool,
+) -> StdResult<(Vec<Offer>, Option<u64>)> {
+    let mut page = vec![];
+    let mut visited = 0;
+    for offer in range {
+        let offer = offer?;
+        let id = offer.id;
+        visited += 1;
+        if matches(&offer) {
+            page.push(offer);
+        }
+        if page.len() == limit || visited == limit * SCANNED_ENTRIES_PER_OFFER {
+            return Ok((page, Some(id)));
+        }
+    }
+    Ok((page, None))
+}
+
+/// `scan_page` result as listed offers.
+fn listing_page(
+    (page, next): (Vec<Offer>, Option<u64>),
+    limit: usize,
+    block_time: Timestamp,
+) -> OffersPageResponse {
+    OffersPageResponse {
+        offers: page
+            .into_iter()
+            .map(|offer| offer.into_listing(block_time))
+            .collect(),
+        next,
+        limit: limit as u32,
+    }
+}
+
+/// Start of the entries after offer `last_value` in the owner and type
+/// indexes, whose keys continue with the length prefixed id and then the
+/// primary key. Past the last possible id the bound is a length prefix no
+/// key has, which sorts after all of them.
+fn index_bound_after(last_value: Option<u64>) -> Option<Bound> {
+    last_value.map(|id| match id.checked_add(1) {
+        Some(next) => Bound::Inclusive([&[0u8, 8], &next.to_be_bytes()[..]].concat()),
+        None => Bound::Inclusive(vec![0u8, 9]),
+    })
+}
+
+impl QueryOrder {
+    /// Range bounds for the page following the `last_value` cursor: it is an
+    /// exclusive lower bound when ascending and an exclusive upper bound when
+    /// descending.
+    pub fn bounds_after(&self, last_value: Option<u64>) -> (Option<Bound>, Option<Bound>) {
+        let cursor = last_value.map(Bound::exclusive_int);
+        match self {
+            QueryOrder::Asc => (cursor, None),
+            QueryOrder::Desc => (None, cursor),
+        }
+    }
+}
+
+impl From<QueryOrder> for Order {
+    fn from(order: QueryOrder) -> Self {
+        match order {
+            QueryOrder::Asc => Order::Ascending,
+            QueryOrder::Desc => Order::Descending,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum OfferState {
     Active,
     Paused,
+    Archived,
 }
@@ -0,0 +1,21 @@
+use crate::constants::{DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT};
+
+/// Number of entries a paginated query returns for the requested `limit`,
+/// `DEFAULT_PAGE_LIMIT` for `0` and never more than `MAX_PAGE_LIMIT`. Page
+/// responses with a `next` cursor report it as their `limit`, that cursor
+/// rather than a short page tells whether more entries follow.
+pub fn page_limit(limit: u32) -> usize {
+    match limit {
+        0 => DEFAULT_PAGE_LIMIT as usize,
+        limit => limit.min(MAX_PAGE_LIMIT) as usize,
+    }
+}
+
+/// `next` cursor of a page taken with `page_limit`, the cursor of its last
+/// entry when the page is full and `None` otherwise.
+pub fn page_next<T, C>(page: &[T], limit: usize, cursor: impl Fn(&T) -> C) -> Option<C> {
+    match page.len() == limit {
+        true => page.last().map(cursor),
+        false => None,
+    }
+}
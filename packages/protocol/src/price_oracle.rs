@@ -0,0 +1,35 @@
+use crate::currencies::FiatCurrency;
+use cosmwasm_std::{
+    to_binary, Addr, Decimal, QuerierWrapper, QueryRequest, StdResult, Timestamp, WasmQuery,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Query interface the offer contract expects from its configured price oracle.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    /// `denom` is a native denom or the address of a cw20 contract.
+    Price { fiat: FiatCurrency, denom: String },
+}
+
+/// Amount of `fiat` paid for one unit of `denom`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceResponse {
+    pub price: Decimal,
+    /// Block time the oracle last updated the rate at.
+    pub updated_at: Timestamp,
+}
+
+/// Current rate of `denom` in `fiat` according to the oracle at `oracle_addr`.
+pub fn query_price(
+    querier: &QuerierWrapper,
+    oracle_addr: &Addr,
+    fiat: FiatCurrency,
+    denom: String,
+) -> StdResult<PriceResponse> {
+    querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: oracle_addr.to_string(),
+        msg: to_binary(&QueryMsg::Price { fiat, denom })?,
+    }))
+}
@@ -1,12 +1,15 @@
 use cosmwasm_std::{Coin, Decimal, QuerierWrapper, StdResult, Uint128};
 
+use crate::factory::is_ibc_denom;
+use crate::offer::OfferAsset;
 use terra_cosmwasm::TerraQuerier;
 
 static DECIMAL_FRACTION: Uint128 = Uint128::new(1_000_000_000_000_000_000u128);
 
 pub fn compute_tax(querier: &QuerierWrapper, coin: &Coin) -> StdResult<Uint128> {
     let amount = coin.amount;
-    if coin.denom == "uluna" {
+    //Only Terra's own stable denoms are taxed
+    if coin.denom == "uluna" || is_ibc_denom(&coin.denom) {
         Ok(Uint128::zero())
     } else {
         let terra_querier = TerraQuerier::new(querier);
@@ -22,6 +25,20 @@ pub fn compute_tax(querier: &QuerierWrapper, coin: &Coin) -> StdResult<Uint128>
     }
 }
 
+/// Terra tax due when moving `amount` of the asset, tokens pay none.
+pub fn asset_tax(
+    querier: &QuerierWrapper,
+    asset: &OfferAsset,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    match asset {
+        OfferAsset::Native { denom } => {
+            compute_tax(querier, &Coin::new(amount.u128(), denom.clone()))
+        }
+        OfferAsset::Cw20 { .. } => Ok(Uint128::zero()),
+    }
+}
+
 pub fn deduct_tax(querier: &QuerierWrapper, coin: Coin) -> StdResult<Coin> {
     let tax_amount = compute_tax(querier, &coin)?;
     Ok(Coin {
@@ -1,4 +1,11 @@
-use cosmwasm_std::{Addr, Uint128};
+use crate::constants::{TRADE_COMMITMENTS_KEY, TRADE_STATE_KEY};
+use crate::errors::TradeError;
+use crate::factory::default_fee_bps;
+use crate::guards::assert_trade_state_change;
+use crate::offer::OfferAsset;
+use cosmwasm_std::{Addr, Decimal, StdResult, Storage, Timestamp, Uint128};
+use cosmwasm_storage::{singleton, singleton_read};
+use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt::{self};
@@ -6,12 +13,25 @@ use std::fmt::{self};
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub offer_id: u64,
-    pub ust_amount: String,
+    #[serde(alias = "ust_amount")]
+    pub amount: Uint128,
+    #[serde(default)]
+    pub asset: OfferAsset,
     pub taker: String,
     pub offers_addr: String,
-    pub arbitrator: String,
     pub taker_contact: String,
     pub timestamp: u64,
+    /// Fiat per unit of the asset agreed at trade creation, only set for
+    /// floating price and fiat denominated offers.
+    pub fiat_price: Option<Decimal>,
+    /// Minor units of fiat requested on a fiat denominated offer, `amount`
+    /// being what they converted to at `fiat_price`.
+    #[serde(default)]
+    pub fiat_amount: Option<Uint128>,
+    /// Disputes are settled by two matching `ApproveRelease` of the buyer,
+    /// the seller and the arbitrator instead of the arbitrator's `Resolve`.
+    #[serde(default)]
+    pub multisig_release: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -20,13 +40,63 @@ pub enum ExecuteMsg {
     FundEscrow {},
     Refund {},
     Release {},
+    /// Either party can dispute once the buyer marked fiat as deposited,
+    /// locking the trade until the arbitrator resolves it.
     Dispute {},
+    /// Arbitrator only, releases the escrow to the buyer or refunds it to
+    /// the seller depending on `winner`.
+    Resolve {
+        winner: String,
+    },
+    /// Buyer, seller or arbitrator of a disputed `multisig_release` trade
+    /// naming who gets the escrow, the buyer or the seller. Approving again
+    /// replaces the caller's approval, the second party approving the same
+    /// recipient settles the trade.
+    ApproveRelease {
+        to: String,
+    },
+    /// Buyer or seller of a disputed trade committing to an off-chain
+    /// document by its hex encoded sha256, up to `MAX_EVIDENCE_PER_PARTY`
+    /// each.
+    SubmitEvidence {
+        sha256: String,
+    },
+    /// Buyer or seller of a trade that isn't final committing to an
+    /// off-chain chat message by its hex encoded sha256, disputed or not.
+    /// Unlike evidence, commitments are shared by both parties, up to
+    /// `MAX_MESSAGE_COMMITMENTS` per trade.
+    CommitMessage {
+        sha256: String,
+    },
+    /// Sent by the buyer once the fiat payment is made, protects the escrow
+    /// from being refunded on expiry.
+    FiatDeposited {},
+    /// Buyer or seller backing out of a trade whose escrow isn't funded yet,
+    /// funded trades go through `Refund`.
+    Cancel {},
+    /// Sent by the trade's cw20 token on `Send`, the embedded message has to
+    /// be a `Cw20HookMsg`, anything else is refused and the tokens stay put.
+    Receive(Cw20ReceiveMsg),
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20HookMsg {
+    /// Funding from the seller of exactly the escrow amount, like
+    /// `ExecuteMsg::FundEscrow` with native funds.
+    FundEscrow {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
+    /// Full `TradeData`: state, parties, amount, asset, deadlines and offer id.
     State {},
+    /// `Vec<MessageCommitment>` of the trade, in commitment order.
+    MessageCommitments {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -36,28 +106,261 @@ pub struct TradeData {
     pub buyer: Addr,
     pub seller: Addr,
     pub taker_contact: String,
+    /// Copied from the offer when the trade is created.
+    pub maker_contact: Option<String>,
     pub arbitrator: Addr,
     pub offer_contract: Addr,
     pub offer_id: u64,
-    pub expire_height: u64,
-    pub ust_amount: Uint128,
+    /// Block time of the instantiation, zero for trades created before the
+    /// field existed.
+    #[serde(default)]
+    pub created_at: Timestamp,
+    /// Set from the offer contract's `trade_duration`, once past it the
+    /// escrow can be refunded unless fiat was marked as deposited or the
+    /// trade is disputed.
+    #[serde(default)]
+    pub expires_at: Timestamp,
+    /// `expires_at` plus the offer contract's `dispute_window`, disputes are
+    /// refused from then on. Zero for trades created before the field
+    /// existed, which can be disputed at any time.
+    #[serde(default)]
+    pub dispute_window_ends_at: Timestamp,
+    /// Factory maker fee when the escrow was funded. Sell offers escrow the
+    /// maker fee on top of the amount and pay this rate, buy offers pay the
+    /// factory maker fee at release. Takers pay the factory taker fee at
+    /// release either way.
+    #[serde(default = "default_fee_bps")]
+    pub fee_bps: u64,
+    #[serde(alias = "ust_amount")]
+    pub amount: Uint128,
+    /// Escrow funding has to be made in exactly this asset, either as native
+    /// funds on `FundEscrow` or through a cw20 `Send` hitting `Receive`.
+    #[serde(default)]
+    pub asset: OfferAsset,
     pub state: TradeState,
+    pub fiat_price: Option<Decimal>,
+    /// See `InstantiateMsg::fiat_amount`.
+    #[serde(default)]
+    pub fiat_amount: Option<Uint128>,
+    #[serde(default)]
+    pub multisig_release: bool,
+    /// Approvals of a disputed `multisig_release` trade, at most one per party.
+    #[serde(default)]
+    pub release_approvals: Vec<ReleaseApproval>,
+    /// Set while two parties approved different recipients, nothing is
+    /// released until one of them changes their approval or the third party
+    /// approves.
+    #[serde(default)]
+    pub approvals_conflict: bool,
+    /// Evidence submitted during the dispute, in submission order.
+    #[serde(default)]
+    pub evidence: Vec<Evidence>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Evidence {
+    pub submitter: Addr,
+    /// Lowercase hex.
+    pub sha256: String,
+    pub submitted_at: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MessageCommitment {
+    pub sender: Addr,
+    /// Lowercase hex.
+    pub sha256: String,
+    pub committed_at: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReleaseApproval {
+    pub approver: Addr,
+    pub to: Addr,
+}
+
+impl TradeData {
+    pub fn is_expired(&self, block_time: Timestamp) -> bool {
+        block_time >= self.expires_at
+    }
+
+    pub fn is_dispute_window_closed(&self, block_time: Timestamp) -> bool {
+        self.dispute_window_ends_at != Timestamp::default()
+            && block_time >= self.dispute_window_ends_at
+    }
+
+    /// Records the approval of `approver`, replacing a previous one, and
+    /// returns the recipient once two parties agree on it.
+    pub fn approve_release(&mut self, approver: Addr, to: Addr) -> Option<Addr> {
+        self.release_approvals
+            .retain(|approval| approval.approver != approver);
+        self.release_approvals
+            .push(ReleaseApproval { approver, to });
+        let agreed = self.release_approvals.iter().find(|approval| {
+            self.release_approvals
+                .iter()
+                .filter(|other| other.to == approval.to)
+                .count()
+                >= 2
+        });
+        let agreed = agreed.map(|approval| approval.to.clone());
+        self.approvals_conflict = agreed.is_none() && self.release_approvals.len() > 1;
+        agreed
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum TradeState {
+    /// Backed out of by a party before the escrow was funded.
     Canceled,
-    Closed,
+    /// Escrow returned to the seller before fiat was marked as deposited.
+    Refunded,
+    /// Escrow released to the buyer by the seller, stored as `closed` before.
+    #[serde(alias = "closed")]
+    Released,
     Created,
     EscrowFunded,
+    FiatDeposited,
     Disputed,
     SettledForMaker,
     SettledForTaker,
 }
 
+impl TradeState {
+    /// Final states, the offer stops counting the trade as in progress.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TradeState::Canceled
+                | TradeState::Refunded
+                | TradeState::Released
+                | TradeState::SettledForMaker
+                | TradeState::SettledForTaker
+        )
+    }
+
+    /// Transition table of the trade, final states have no way out.
+    pub fn can_transition_to(&self, to: &TradeState) -> bool {
+        matches!(
+            (self, to),
+            (TradeState::Created, TradeState::EscrowFunded)
+                | (TradeState::Created, TradeState::Canceled)
+                | (TradeState::EscrowFunded, TradeState::FiatDeposited)
+                | (TradeState::EscrowFunded, TradeState::Released)
+                | (TradeState::EscrowFunded, TradeState::Refunded)
+                | (TradeState::FiatDeposited, TradeState::Released)
+                | (TradeState::FiatDeposited, TradeState::Disputed)
+                | (TradeState::Disputed, TradeState::SettledForMaker)
+                | (TradeState::Disputed, TradeState::SettledForTaker)
+        )
+    }
+}
+
 impl fmt::Display for TradeState {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
     }
 }
+
+/// Who a trade handler accepts as caller.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeRole {
+    Buyer,
+    Seller,
+    Arbitrator,
+}
+
+impl TradeRole {
+    pub fn addr<'a>(&self, trade: &'a TradeData) -> &'a Addr {
+        match self {
+            TradeRole::Buyer => &trade.buyer,
+            TradeRole::Seller => &trade.seller,
+            TradeRole::Arbitrator => &trade.arbitrator,
+        }
+    }
+}
+
+pub struct TradeModel<'a> {
+    pub trade: TradeData,
+    pub storage: &'a mut dyn Storage,
+}
+
+impl TradeModel<'_> {
+    pub fn store(storage: &mut dyn Storage, trade: &TradeData) -> StdResult<()> {
+        singleton(storage, TRADE_STATE_KEY).save(trade)
+    }
+
+    pub fn from_store(storage: &dyn Storage) -> StdResult<TradeData> {
+        singleton_read(storage, TRADE_STATE_KEY).load()
+    }
+
+    /// Kept apart from `TradeData` so state reads don't load the whole list.
+    pub fn commitments(storage: &dyn Storage) -> StdResult<Vec<MessageCommitment>> {
+        Ok(singleton_read(storage, TRADE_COMMITMENTS_KEY)
+            .may_load()?
+            .unwrap_or_default())
+    }
+
+    pub fn store_commitments(
+        storage: &mut dyn Storage,
+        commitments: Vec<MessageCommitment>,
+    ) -> StdResult<()> {
+        singleton(storage, TRADE_COMMITMENTS_KEY).save(&commitments)
+    }
+
+    pub fn create(storage: &mut dyn Storage, trade: TradeData) -> StdResult<TradeModel<'_>> {
+        TradeModel::store(storage, &trade)?;
+        Ok(TradeModel { trade, storage })
+    }
+
+    pub fn may_load(storage: &mut dyn Storage) -> StdResult<TradeModel<'_>> {
+        Ok(TradeModel {
+            trade: TradeModel::from_store(storage)?,
+            storage,
+        })
+    }
+
+    pub fn fund(&mut self) -> Result<&TradeData, TradeError> {
+        if self.trade.state == TradeState::EscrowFunded {
+            return Err(TradeError::AlreadyFundedError {});
+        }
+        self.transition(TradeState::EscrowFunded)
+    }
+
+    pub fn mark_fiat_deposited(&mut self) -> Result<&TradeData, TradeError> {
+        self.transition(TradeState::FiatDeposited)
+    }
+
+    pub fn release(&mut self) -> Result<&TradeData, TradeError> {
+        if self.trade.state == TradeState::Released {
+            return Err(TradeError::AlreadyReleased {});
+        }
+        self.transition(TradeState::Released)
+    }
+
+    pub fn refund(&mut self) -> Result<&TradeData, TradeError> {
+        self.transition(TradeState::Refunded)
+    }
+
+    pub fn cancel(&mut self) -> Result<&TradeData, TradeError> {
+        self.transition(TradeState::Canceled)
+    }
+
+    pub fn dispute(&mut self) -> Result<&TradeData, TradeError> {
+        self.transition(TradeState::Disputed)
+    }
+
+    /// Arbitrator's outcome of a dispute, either `SettledForMaker` or `SettledForTaker`.
+    pub fn settle(&mut self, state: TradeState) -> Result<&TradeData, TradeError> {
+        self.transition(state)
+    }
+
+    fn transition(&mut self, to: TradeState) -> Result<&TradeData, TradeError> {
+        assert_trade_state_change(&self.trade.state, &to)?;
+        self.trade.state = to;
+        TradeModel::store(self.storage, &self.trade)?;
+        Ok(&self.trade)
+    }
+}